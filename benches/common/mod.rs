@@ -0,0 +1,61 @@
+//! Sample blocks and blk files shared by the benchmarks.
+#![allow(dead_code)]
+use bitcoin_block_finder::util::types::CompactSize;
+use std::path::PathBuf;
+
+/// The genesis block, whose header fronts every sample block.
+pub const GENESIS_BLOCK_HEX: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+/// A P2WPKH spend with two outputs, shaped like most transactions in a recent block.
+fn segwit_tx() -> Vec<u8> {
+    hex::decode(
+        [
+            "02000000",
+            "0001",
+            "01",
+            "11".repeat(32).as_str(),
+            "00000000",
+            "00",
+            "fdffffff",
+            "02",
+            "e803000000000000",
+            "160014",
+            "22".repeat(20).as_str(),
+            "d007000000000000",
+            "160014",
+            "33".repeat(20).as_str(),
+            "02",
+            "47",
+            "44".repeat(71).as_str(),
+            "21",
+            "55".repeat(33).as_str(),
+            "00000000",
+        ]
+        .concat(),
+    )
+    .expect("expected valid hex")
+}
+
+/// A block of the genesis header followed by `tx_count` copies of a segwit transaction.
+/// The merkle root doesn't match, which parsing never checks.
+pub fn sample_block(tx_count: u64) -> Vec<u8> {
+    let mut block = hex::decode(GENESIS_BLOCK_HEX).expect("expected valid hex")[..80].to_vec();
+    block.extend(CompactSize::encode(tx_count));
+    let tx = segwit_tx();
+    for _ in 0..tx_count {
+        block.extend(&tx);
+    }
+    block
+}
+
+/// A data directory holding a single blk file of `count` copies of `block`.
+pub fn sample_datadir(name: &str, block: &[u8], count: usize) -> PathBuf {
+    let datadir = std::env::temp_dir().join(format!("bbf-bench-{name}"));
+    std::fs::create_dir_all(&datadir).expect("failed to create the sample datadir");
+    let mut record = vec![0xf9, 0xbe, 0xb4, 0xd9];
+    record.extend((block.len() as u32).to_le_bytes());
+    record.extend(block);
+    std::fs::write(datadir.join("blk00000.dat"), record.repeat(count))
+        .expect("failed to write the sample blk file");
+    datadir
+}
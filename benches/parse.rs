@@ -0,0 +1,50 @@
+//! Parsing throughput of headers and whole blocks, owned and borrowed.
+use bitcoin_block_finder::{
+    util::{types::ByteCursor, view::BlockView},
+    Block, BlockHeader,
+};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+use std::hint::black_box;
+
+mod common;
+
+fn parse_header(c: &mut Criterion) {
+    let block = common::sample_block(1);
+    let header = &block[..80];
+    let mut group = c.benchmark_group("header");
+    group.throughput(Throughput::Bytes(header.len() as u64));
+    group.bench_function("owned", |b| {
+        b.iter(|| BlockHeader::from_raw_bytes(&mut ByteCursor::new(black_box(header))).unwrap())
+    });
+    group.bench_function("hash", |b| {
+        let header = BlockHeader::from_raw_bytes(&mut ByteCursor::new(header)).unwrap();
+        b.iter(|| black_box(&header).hash())
+    });
+    group.finish();
+}
+
+fn parse_block(c: &mut Criterion) {
+    // Around the size of a full block of simple segwit spends.
+    let block = common::sample_block(4000);
+    let mut group = c.benchmark_group("block");
+    group.throughput(Throughput::Bytes(block.len() as u64));
+    group.bench_function("owned", |b| {
+        b.iter(|| Block::from_raw_bytes(&mut ByteCursor::new(black_box(block.as_slice()))).unwrap())
+    });
+    group.bench_function("view", |b| {
+        b.iter(|| BlockView::from_slice(black_box(&block)).unwrap())
+    });
+    group.bench_function("view_txids", |b| {
+        b.iter(|| {
+            BlockView::from_slice(black_box(&block))
+                .unwrap()
+                .transactions()
+                .map(|tx| tx.txid())
+                .last()
+        })
+    });
+    group.finish();
+}
+
+criterion_group!(benches, parse_header, parse_block);
+criterion_main!(benches);
@@ -0,0 +1,48 @@
+//! End to end scans of a sample blk file, comparing the ways of reading it.
+use bitcoin_block_finder::{reader::FileChain, BlockFileReader};
+use criterion::{criterion_group, criterion_main, Criterion, Throughput};
+
+mod common;
+
+fn scan(c: &mut Criterion) {
+    let block = common::sample_block(500);
+    let datadir = common::sample_datadir("scan", &block, 200);
+    let reader =
+        |mmap: bool| BlockFileReader::new(FileChain::open(&datadir).unwrap().with_mmap(mmap), None);
+    let mut group = c.benchmark_group("scan");
+    group.sample_size(10);
+    group.throughput(Throughput::Bytes(
+        std::fs::metadata(datadir.join("blk00000.dat"))
+            .unwrap()
+            .len(),
+    ));
+    group.bench_function("blocks", |b| {
+        b.iter(|| reader(false).blocks().map(Result::unwrap).count())
+    });
+    group.bench_function("blocks_mmap", |b| {
+        b.iter(|| reader(true).blocks().map(Result::unwrap).count())
+    });
+    group.bench_function("headers", |b| {
+        b.iter(|| reader(false).headers().map(Result::unwrap).count())
+    });
+    group.bench_function("views_mmap", |b| {
+        b.iter(|| {
+            let mut count = 0;
+            reader(true)
+                .for_each_view(|_, _| {
+                    count += 1;
+                    Ok(())
+                })
+                .unwrap();
+            count
+        })
+    });
+    group.bench_function("par_map", |b| {
+        b.iter(|| reader(false).par_map(|_, block| block.tx_count()).unwrap())
+    });
+    group.finish();
+    std::fs::remove_dir_all(&datadir).unwrap();
+}
+
+criterion_group!(benches, scan);
+criterion_main!(benches);
@@ -0,0 +1,43 @@
+//! Generates the service of the `grpc` feature, described here rather than in a .proto file
+//! so building it doesn't need protoc.
+fn main() {
+    println!("cargo:rerun-if-changed=build.rs");
+    #[cfg(feature = "grpc")]
+    grpc();
+}
+
+#[cfg(feature = "grpc")]
+fn grpc() {
+    use tonic_build::manual::{Builder, Method, Service};
+
+    let method = |name: &str, route_name: &str, input_type: &str| {
+        Method::builder()
+            .name(name)
+            .route_name(route_name)
+            .input_type(format!("crate::grpc::{input_type}"))
+            .output_type("crate::grpc::BlockReply")
+            .codec_path("tonic_prost::ProstCodec")
+    };
+    let service = Service::builder()
+        .name("BlockFinder")
+        .package("bitcoin_block_finder")
+        .comment("Blocks on the best chain through the blk files.")
+        .method(
+            method("get_block_by_height", "GetBlockByHeight", "HeightRequest")
+                .comment("The block at a height.")
+                .build(),
+        )
+        .method(
+            method("get_block_by_hash", "GetBlockByHash", "HashRequest")
+                .comment("The block with a hash.")
+                .build(),
+        )
+        .method(
+            method("stream_blocks", "StreamBlocks", "RangeRequest")
+                .comment("The blocks from one height to another, in order.")
+                .server_streaming()
+                .build(),
+        )
+        .build();
+    Builder::new().compile(&[service]);
+}
@@ -0,0 +1,20 @@
+//! Whole blocks, checking the owned parser and the borrowed view accept the same input.
+#![no_main]
+use bitcoin_block_finder::{
+    util::{types::ByteCursor, view::BlockView},
+    Block,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let owned = Block::from_raw_bytes(&mut ByteCursor::new(data));
+    let view = BlockView::from_slice(data);
+    assert_eq!(owned.is_ok(), view.is_ok());
+    if let (Ok(block), Ok(view)) = (owned, view) {
+        assert_eq!(block.block_header().hash(), view.block_header().hash());
+        assert_eq!(block.tx_count(), view.tx_count());
+        let _ = block.merkle_root();
+        let _ = block.block_header().difficulty();
+        let _ = block.block_header().expanded_target();
+    }
+});
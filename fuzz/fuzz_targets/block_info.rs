@@ -0,0 +1,11 @@
+//! The magic bytes and size that start every blk file record.
+#![no_main]
+use bitcoin_block_finder::{util::types::ByteCursor, BlockInfo};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(block_info) = BlockInfo::from_raw_bytes(&mut ByteCursor::new(data), 0) {
+        let _ = block_info.network();
+        let _ = block_info.size_as_u32();
+    }
+});
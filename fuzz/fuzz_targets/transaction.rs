@@ -0,0 +1,30 @@
+//! Single transactions in either serialization.
+#![no_main]
+use bitcoin_block_finder::{
+    util::{types::ByteCursor, view::TransactionView},
+    Transaction,
+};
+use libfuzzer_sys::fuzz_target;
+
+fuzz_target!(|data: &[u8]| {
+    let mut cursor = ByteCursor::new(data);
+    let owned = Transaction::from_raw_bytes(&mut cursor);
+    let view = TransactionView::from_slice(data);
+    assert_eq!(owned.is_ok(), view.is_ok());
+    if let (Ok(tx), Ok(view)) = (owned, view) {
+        // Compact sizes are re-encoded in their shortest form, so only a
+        // canonically encoded transaction serializes back to the bytes it came from.
+        let consumed = &data[..cursor.position() as usize];
+        if tx.raw_bytes() == consumed {
+            assert_eq!(tx.txid(), view.txid());
+            assert_eq!(tx.total_size(), view.total_size());
+        }
+        for output in tx.outputs() {
+            let _ = output.address();
+            let _ = output.script().script_type();
+        }
+        for input in tx.inputs() {
+            let _ = input.taproot_spend();
+        }
+    }
+});
@@ -0,0 +1,138 @@
+#ifndef BITCOIN_BLOCK_FINDER_H
+#define BITCOIN_BLOCK_FINDER_H
+
+/* Generated by cbindgen from src/ffi.rs, don't edit it by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+typedef struct BbfBlock BbfBlock;
+
+typedef struct BbfBlockHeader BbfBlockHeader;
+
+// The message of the last failure on the calling thread, or null if nothing has failed.
+// It stays valid until the next failure on the thread.
+const char *bbf_last_error(void);
+
+// Parse the serialized block of `len` bytes at `data`, returning a handle to free with
+// [bbf_block_free], or null if they aren't exactly a block.
+//
+// # Safety
+//
+// `data` must be null or point to `len` readable bytes.
+struct BbfBlock *bbf_block_parse(const uint8_t *data, size_t len);
+
+// Free a block from [bbf_block_parse], doing nothing for null.
+//
+// # Safety
+//
+// `block` must be null or a handle from [bbf_block_parse] not yet freed.
+void bbf_block_free(struct BbfBlock *block);
+
+// The header of `block`, borrowed for as long as the block isn't freed.
+//
+// # Safety
+//
+// `block` must be a live handle from [bbf_block_parse].
+const struct BbfBlockHeader *bbf_block_header(const struct BbfBlock *block);
+
+// The number of transactions in `block`.
+//
+// # Safety
+//
+// `block` must be a live handle from [bbf_block_parse].
+uint64_t bbf_block_tx_count(const struct BbfBlock *block);
+
+// Write the txid of the transaction at `index` in `block` to the 32 bytes at `out`,
+// returning false if there's none at `index`.
+//
+// # Safety
+//
+// `block` must be a live handle from [bbf_block_parse] and `out` must be null or point to
+// 32 writable bytes.
+bool bbf_block_txid(const struct BbfBlock *block, size_t index, uint8_t *out);
+
+// `block` as the JSON `decode --format json` prints, a string to free with
+// [bbf_string_free], or null if it can't be serialized.
+//
+// # Safety
+//
+// `block` must be a live handle from [bbf_block_parse].
+char *bbf_block_to_json(const struct BbfBlock *block);
+
+// Free a string from [bbf_block_to_json], doing nothing for null.
+//
+// # Safety
+//
+// `string` must be null or a string from [bbf_block_to_json] not yet freed.
+void bbf_string_free(char *string);
+
+// Parse the 80 byte serialized header at `data`, returning a handle to free with
+// [bbf_header_free], or null if `len` bytes aren't exactly a header.
+//
+// # Safety
+//
+// `data` must be null or point to `len` readable bytes.
+struct BbfBlockHeader *bbf_header_parse(const uint8_t *data, size_t len);
+
+// Free a header from [bbf_header_parse], doing nothing for null. Headers from
+// [bbf_block_header] are freed with their block instead.
+//
+// # Safety
+//
+// `header` must be null or a handle from [bbf_header_parse] not yet freed.
+void bbf_header_free(struct BbfBlockHeader *header);
+
+// Write the block hash of `header` to the 32 bytes at `out`.
+//
+// # Safety
+//
+// `header` must be a live header and `out` must be null or point to 32 writable bytes.
+bool bbf_header_hash(const struct BbfBlockHeader *header, uint8_t *out);
+
+// Write the hash of the parent block of `header` to the 32 bytes at `out`.
+//
+// # Safety
+//
+// `header` must be a live header and `out` must be null or point to 32 writable bytes.
+bool bbf_header_previous_hash(const struct BbfBlockHeader *header, uint8_t *out);
+
+// Write the merkle root of `header` to the 32 bytes at `out`.
+//
+// # Safety
+//
+// `header` must be a live header and `out` must be null or point to 32 writable bytes.
+bool bbf_header_merkle_root(const struct BbfBlockHeader *header, uint8_t *out);
+
+// The version of `header`.
+//
+// # Safety
+//
+// `header` must be a live header.
+uint32_t bbf_header_version(const struct BbfBlockHeader *header);
+
+// The unix epoch time of `header`.
+//
+// # Safety
+//
+// `header` must be a live header.
+uint32_t bbf_header_time(const struct BbfBlockHeader *header);
+
+// The compact target of `header`, its nBits.
+//
+// # Safety
+//
+// `header` must be a live header.
+uint32_t bbf_header_bits(const struct BbfBlockHeader *header);
+
+// The nonce of `header`.
+//
+// # Safety
+//
+// `header` must be a live header.
+uint32_t bbf_header_nonce(const struct BbfBlockHeader *header);
+
+#endif  /* BITCOIN_BLOCK_FINDER_H */
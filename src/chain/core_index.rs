@@ -0,0 +1,231 @@
+//! Reading the block index a Bitcoin Core node keeps in `blocks/index`, so a chain can be
+//! resolved from its LevelDB database rather than by linking every blk file.
+//!
+//! Each block is stored under the key `b` followed by its hash. The value is a serialized
+//! `CDiskBlockIndex`: Core varints for the client version, height, status, transaction count,
+//! file number and data and undo positions, followed by the 80 byte block header.
+use super::{Chain, ChainEntry};
+use crate::util::{
+    constant::{
+        BLOCK_HEADER, BLOCK_HEADER_VERSION, BLOCK_SIZE, MAGIC_BYTES, PREVIOUS_BLOCK_HEADER_HASH,
+    },
+    error::ErrorKind,
+    types::BlockLocation,
+};
+use anyhow::{bail, Result};
+use rusty_leveldb::{LdbIterator, Options, DB};
+use std::{collections::HashMap, path::Path};
+
+/// The block's data is stored in a blk file.
+const BLOCK_HAVE_DATA: u64 = 8;
+/// The block's undo data is stored in a rev file.
+const BLOCK_HAVE_UNDO: u64 = 16;
+/// The block, or one of its ancestors, failed validation.
+const BLOCK_FAILED_MASK: u64 = 32 | 64;
+
+/// A single record of Core's block index.
+#[derive(Debug, PartialEq, Eq)]
+pub struct DiskBlockIndex {
+    pub height: u64,
+    pub status: u64,
+    pub tx_count: u64,
+    /// Where the block data is kept, when the node has it.
+    pub location: Option<BlockLocation>,
+    pub previous_hash: [u8; 32],
+}
+impl DiskBlockIndex {
+    /// Parse the value stored under a block's `b` key.
+    pub fn from_raw_bytes(bytes: &[u8]) -> Result<Self> {
+        let mut bytes = bytes;
+        let _client_version = read_varint(&mut bytes)?;
+        let height = read_varint(&mut bytes)?;
+        let status = read_varint(&mut bytes)?;
+        let tx_count = read_varint(&mut bytes)?;
+        let file = if status & (BLOCK_HAVE_DATA | BLOCK_HAVE_UNDO) != 0 {
+            Some(read_varint(&mut bytes)?)
+        } else {
+            None
+        };
+        let data_position = if status & BLOCK_HAVE_DATA != 0 {
+            Some(read_varint(&mut bytes)?)
+        } else {
+            None
+        };
+        if status & BLOCK_HAVE_UNDO != 0 {
+            read_varint(&mut bytes)?;
+        }
+        if bytes.len() < BLOCK_HEADER as usize {
+            bail!("expected a block header in the block index record")
+        }
+        let previous_hash = bytes[BLOCK_HEADER_VERSION as usize..]
+            [..PREVIOUS_BLOCK_HEADER_HASH as usize]
+            .try_into()
+            .expect("expected 32 bytes for previous_hash");
+
+        // Core records the position of the block itself, just past its magic bytes and size.
+        let location = file
+            .zip(data_position)
+            .map(|(file, data_position)| BlockLocation {
+                file: file as usize,
+                offset: data_position.saturating_sub((MAGIC_BYTES + BLOCK_SIZE) as u64),
+            });
+        Ok(Self {
+            height,
+            status,
+            tx_count,
+            location,
+            previous_hash,
+        })
+    }
+}
+
+/// Read a varint in Core's own encoding, as opposed to the CompactSize used on the wire.
+///
+/// Each byte carries 7 bits, most significant first, and every continuation adds one
+/// so each value has a single encoding.
+fn read_varint(bytes: &mut &[u8]) -> Result<u64> {
+    let mut n: u64 = 0;
+    loop {
+        let Some((&byte, rest)) = bytes.split_first() else {
+            bail!("expected a varint in the block index record")
+        };
+        *bytes = rest;
+        n = n
+            .checked_mul(128)
+            .ok_or_else(|| anyhow::anyhow!("varint in the block index record overflows"))?
+            | u64::from(byte & 0x7f);
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+        n += 1;
+    }
+}
+
+/// Build the best chain from the Core block index database at `path`.
+///
+/// The node must not be running, since LevelDB allows a single process to open the database.
+/// The tip is the highest block with data that hasn't failed validation, and every block
+/// below it must still be on disk, so pruned nodes can't be read this way.
+pub fn load(path: &Path) -> Result<Chain> {
+    let options = Options {
+        create_if_missing: false,
+        ..Options::default()
+    };
+    let mut db = DB::open(path, options).map_err(|e| {
+        ErrorKind::Io.error(format!(
+            "failed to open the block index at {}: {e}",
+            path.display()
+        ))
+    })?;
+    let mut iter = db.new_iter()?;
+
+    let mut blocks: HashMap<[u8; 32], DiskBlockIndex> = HashMap::new();
+    let mut tip: Option<([u8; 32], u64)> = None;
+    iter.seek(b"b");
+    while let Some((key, value)) = iter.current() {
+        let Some(hash) = key
+            .strip_prefix(b"b")
+            .and_then(|hash| <[u8; 32]>::try_from(hash).ok())
+        else {
+            break;
+        };
+        let block = DiskBlockIndex::from_raw_bytes(&value)?;
+        let usable = block.location.is_some() && block.status & BLOCK_FAILED_MASK == 0;
+        if usable && tip.is_none_or(|(_, height)| block.height > height) {
+            tip = Some((hash, block.height));
+        }
+        blocks.insert(hash, block);
+        if !iter.advance() {
+            break;
+        }
+    }
+
+    let Some((tip, _)) = tip else {
+        return Ok(Chain::default());
+    };
+    let mut entries = Vec::new();
+    let mut next = Some(tip);
+    while let Some(hash) = next {
+        let block = blocks
+            .get(&hash)
+            .ok_or_else(|| anyhow::anyhow!("block index is missing an ancestor of the tip"))?;
+        let location = block.location.ok_or_else(|| {
+            anyhow::anyhow!(
+                "block at height {} is not on disk, is the node pruned?",
+                block.height
+            )
+        })?;
+        entries.push(ChainEntry { hash, location });
+        next = (block.height > 0).then_some(block.previous_hash);
+    }
+    entries.reverse();
+
+    Ok(Chain::from_entries(entries))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load, read_varint, DiskBlockIndex};
+    use crate::{
+        util::{fixtures::GENESIS_BLOCK_HEX, hash::sha256d},
+        BlockLocation,
+    };
+    use rusty_leveldb::{Options, DB};
+
+    /// Checks Core's varint encoding, where each continuation byte adds one.
+    #[test]
+    fn test_read_varint() {
+        for (bytes, value) in [
+            (vec![0x00], 0),
+            (vec![0x7f], 127),
+            (vec![0x80, 0x00], 128),
+            (vec![0x80, 0x7f], 255),
+            (vec![0xfe, 0x7f], 16383),
+            (vec![0xff, 0x00], 16384),
+        ] {
+            assert_eq!(read_varint(&mut bytes.as_slice()).unwrap(), value);
+        }
+        assert!(read_varint(&mut [0x80].as_slice()).is_err());
+    }
+
+    /// Checks a record the way Core writes the genesis block, in blk00000.dat at position 8.
+    #[test]
+    fn test_disk_block_index() {
+        let header = hex::decode(&GENESIS_BLOCK_HEX[..160]).unwrap();
+        // version 250000, height 0, status 29 (valid scripts, data and undo), 1 tx,
+        // file 0, data position 8, undo position 8
+        let mut record = vec![0x8e, 0xa0, 0x10, 0x00, 0x1d, 0x01, 0x00, 0x08, 0x08];
+        record.extend_from_slice(&header);
+
+        let block = DiskBlockIndex::from_raw_bytes(&record).unwrap();
+        assert_eq!(block.height, 0);
+        assert_eq!(block.tx_count, 1);
+        assert_eq!(block.location, Some(BlockLocation { file: 0, offset: 0 }));
+        assert_eq!(block.previous_hash, [0; 32]);
+        assert!(DiskBlockIndex::from_raw_bytes(&record[..20]).is_err());
+    }
+
+    /// Checks that a chain is read back from a LevelDB database laid out like Core's.
+    #[test]
+    fn test_load() {
+        let header = hex::decode(&GENESIS_BLOCK_HEX[..160]).unwrap();
+        let mut record = vec![0x8e, 0xa0, 0x10, 0x00, 0x1d, 0x01, 0x00, 0x08, 0x08];
+        record.extend_from_slice(&header);
+        let hash = sha256d(&header);
+
+        let path = std::env::temp_dir().join("bbf-test-core-index");
+        let _ = std::fs::remove_dir_all(&path);
+        {
+            let mut db = DB::open(&path, Options::default()).unwrap();
+            db.put(&[b"b".as_slice(), &hash].concat(), &record).unwrap();
+            db.put(b"F", b"unrelated").unwrap();
+            db.flush().unwrap();
+        }
+        let chain = load(&path).unwrap();
+        std::fs::remove_dir_all(&path).unwrap();
+
+        assert_eq!(chain.len(), 1);
+        assert_eq!(chain.get(0).unwrap().hash, hash);
+        assert_eq!(chain.height_of(&hash), Some(0));
+    }
+}
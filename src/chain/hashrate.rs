@@ -0,0 +1,117 @@
+//! Estimates of the network hashrate over a sliding window of blocks, from the work their
+//! difficulty calls for and how long they actually took to find.
+use serde::Serialize;
+
+/// The hashrate estimated over the window of blocks ending at a height.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HashrateEstimate {
+    /// The height of the last block in the window.
+    pub height: u64,
+    /// The timestamp of the last block in the window.
+    pub time: u32,
+    /// Seconds from the block before the window to its last, so across every block in it.
+    pub timespan: i64,
+    /// The difficulty of the last block in the window.
+    pub difficulty: f64,
+    /// The expected hashes to find every block in the window over its timespan, in hashes
+    /// per second, or `None` when the timestamps don't move forward across it.
+    pub hashrate: Option<f64>,
+}
+impl HashrateEstimate {
+    /// The column names, in the order [HashrateEstimate::to_csv] writes them.
+    pub const CSV_HEADER: &'static str = "height,time,timespan,difficulty,hashrate";
+
+    /// The estimate as a CSV row, with an empty hashrate when there isn't one.
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{}",
+            self.height,
+            self.time,
+            self.timespan,
+            self.difficulty,
+            self.hashrate
+                .map_or(String::new(), |hashrate| format!("{hashrate:.0}"))
+        )
+    }
+}
+
+/// The hashrate over every window of `window` blocks among `headers`, the timestamp and
+/// difficulty of each block from height `start` on, one estimate per block with `window`
+/// blocks before it.
+pub fn estimate_hashrate(
+    start: u64,
+    headers: &[(u32, f64)],
+    window: usize,
+) -> Vec<HashrateEstimate> {
+    if window == 0 {
+        return Vec::new();
+    }
+    // The expected hashes for a block at difficulty 1, and the sums of the blocks' work up
+    // to each height so each window's is a difference.
+    let work_per_difficulty = 2f64.powi(32);
+    let work = std::iter::once(0.0)
+        .chain(headers.iter().scan(0.0, |total, (_, difficulty)| {
+            *total += difficulty * work_per_difficulty;
+            Some(*total)
+        }))
+        .collect::<Vec<_>>();
+
+    (window..headers.len())
+        .map(|index| {
+            let (time, difficulty) = headers[index];
+            let timespan = i64::from(time) - i64::from(headers[index - window].0);
+            let hashes = work[index + 1] - work[index + 1 - window];
+            HashrateEstimate {
+                height: start + index as u64,
+                time,
+                timespan,
+                difficulty,
+                hashrate: (timespan > 0).then(|| hashes / timespan as f64),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::estimate_hashrate;
+    use crate::util::{
+        constant::TARGET_SPACING,
+        fixtures::GENESIS_BLOCK_HEX,
+        types::{BlockHeader, ByteCursor},
+    };
+
+    /// Checks blocks found every ten minutes give the hashrate their difficulty calls for,
+    /// twice as fast gives twice that, and timestamps going backwards give none.
+    #[test]
+    fn test_estimate_hashrate() {
+        let genesis = BlockHeader::from_raw_bytes(&mut ByteCursor::new(
+            hex::decode(GENESIS_BLOCK_HEX).unwrap().as_slice(),
+        ))
+        .unwrap();
+        let spacing = TARGET_SPACING;
+        let headers = [
+            0,
+            spacing,
+            2 * spacing,
+            2 * spacing + spacing / 2,
+            3 * spacing,
+            0,
+        ]
+        .map(|time| (time, genesis.difficulty()));
+
+        let estimates = estimate_hashrate(100, &headers, 2);
+        assert_eq!(
+            estimates.iter().map(|e| e.height).collect::<Vec<_>>(),
+            [102, 103, 104, 105]
+        );
+        assert_eq!(estimates[0].timespan, i64::from(2 * spacing));
+        assert_eq!(estimates[0].hashrate, Some(genesis.hashrate()));
+        assert_eq!(estimates[2].hashrate, Some(genesis.hashrate() * 2.0));
+        assert_eq!(estimates[3].hashrate, None);
+        assert_eq!(estimates[3].to_csv(), "105,0,-1500,1,");
+
+        assert!(estimate_hashrate(0, &headers, 6).is_empty());
+        assert!(estimate_hashrate(0, &headers, 0).is_empty());
+    }
+}
@@ -0,0 +1,297 @@
+//! A compact on-disk copy of a [Chain], so lookups can seek straight to a block
+//! instead of linking every blk file again.
+//!
+//! The file is a small header followed by one fixed size record per height, all little endian:
+//! the magic `BBFI`, a format version, the combined size of the blk files indexed, the number
+//! of entries, then for each entry the block hash, file number and byte offset.
+use super::{Chain, ChainEntry, Neighbour, Neighbours};
+use crate::util::{error::ErrorKind, types::BlockLocation};
+use anyhow::{bail, Result};
+use std::{
+    fs::{File, OpenOptions},
+    io::{BufReader, BufWriter, Read, Seek, SeekFrom, Write},
+    path::{Path, PathBuf},
+};
+
+const MAGIC: &[u8; 4] = b"BBFI";
+const VERSION: u32 = 1;
+/// The size of the header: the magic, version, blk file size and number of entries.
+const HEADER: u64 = 24;
+/// The size of each entry: the block hash, file number and offset.
+const ENTRY: u64 = 44;
+
+/// A [Chain] loaded from an index file, along with the size of the blk files it was built from.
+#[derive(Debug)]
+pub struct Index {
+    pub chain: Chain,
+    /// The combined size of the blk files when they were indexed, for telling when
+    /// a node has appended blocks since.
+    pub blk_size: u64,
+}
+impl Index {
+    /// Whether blk files of `blk_size` bytes have changed since they were indexed.
+    pub fn is_stale(&self, blk_size: u64) -> bool {
+        self.blk_size != blk_size
+    }
+}
+
+/// Write `chain`, built from blk files of `blk_size` bytes, to the index file at `path`.
+pub fn save(path: &Path, chain: &Chain, blk_size: u64) -> Result<()> {
+    let file = File::create(path)
+        .map_err(|e| ErrorKind::Io.error(format!("failed to create {}: {e}", path.display())))?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&blk_size.to_le_bytes())?;
+    writer.write_all(&(chain.entries.len() as u64).to_le_bytes())?;
+    for entry in &chain.entries {
+        write_entry(&mut writer, entry)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Add `entries` to the end of the chain in the index file at `path`, now built from blk
+/// files of `blk_size` bytes, without rewriting the entries already there.
+pub fn append(path: &Path, entries: &[ChainEntry], blk_size: u64) -> Result<()> {
+    let mut file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open(path)
+        .map_err(|e| ErrorKind::Io.error(format!("failed to open {}: {e}", path.display())))?;
+    let (_, len) = read_header(&mut file, path)?;
+    let write_error =
+        |e: std::io::Error| ErrorKind::Io.error(format!("failed to write {}: {e}", path.display()));
+    file.seek(SeekFrom::Start(HEADER + len * ENTRY))
+        .map_err(write_error)?;
+    let mut writer = BufWriter::new(&mut file);
+    for entry in entries {
+        write_entry(&mut writer, entry).map_err(write_error)?;
+    }
+    writer.flush().map_err(write_error)?;
+    drop(writer);
+    // The header is only updated once the entries are written, so an interrupted append
+    // leaves the index as it was.
+    file.seek(SeekFrom::Start(8)).map_err(write_error)?;
+    file.write_all(
+        &[
+            blk_size.to_le_bytes(),
+            (len + entries.len() as u64).to_le_bytes(),
+        ]
+        .concat(),
+    )
+    .map_err(write_error)?;
+    Ok(())
+}
+
+fn write_entry(writer: &mut impl Write, entry: &ChainEntry) -> std::io::Result<()> {
+    writer.write_all(&entry.hash)?;
+    writer.write_all(&(entry.location.file as u32).to_le_bytes())?;
+    writer.write_all(&entry.location.offset.to_le_bytes())
+}
+
+/// Read the index file at `path`.
+pub fn load(path: &Path) -> Result<Index> {
+    let file = File::open(path)
+        .map_err(|e| ErrorKind::Io.error(format!("failed to open {}: {e}", path.display())))?;
+    let mut reader = BufReader::new(file);
+    let (blk_size, count) = read_header(&mut reader, path)?;
+    let entries = (0..count)
+        .map(|_| read_entry(&mut reader, path))
+        .collect::<Result<Vec<_>>>()?;
+
+    Ok(Index {
+        chain: Chain::from_entries(entries),
+        blk_size,
+    })
+}
+
+/// An index file opened to look up single heights, seeking straight to the entry of each
+/// rather than loading the whole chain as [load] does.
+#[derive(Debug)]
+pub struct IndexFile {
+    file: File,
+    path: PathBuf,
+    /// The combined size of the blk files when they were indexed, as for [Index].
+    pub blk_size: u64,
+    len: u64,
+}
+impl IndexFile {
+    /// Open the index file at `path`, reading just its header.
+    pub fn open(path: &Path) -> Result<Self> {
+        let mut file = File::open(path)
+            .map_err(|e| ErrorKind::Io.error(format!("failed to open {}: {e}", path.display())))?;
+        let (blk_size, len) = read_header(&mut file, path)?;
+        Ok(Self {
+            file,
+            path: path.to_path_buf(),
+            blk_size,
+            len,
+        })
+    }
+    /// Whether blk files of `blk_size` bytes have changed since they were indexed.
+    pub fn is_stale(&self, blk_size: u64) -> bool {
+        self.blk_size != blk_size
+    }
+    /// The number of blocks on the indexed chain.
+    pub fn len(&self) -> u64 {
+        self.len
+    }
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+    /// The entry of the block at `height`, if the chain reaches it.
+    pub fn get(&mut self, height: u64) -> Result<Option<ChainEntry>> {
+        if height >= self.len {
+            return Ok(None);
+        }
+        self.file
+            .seek(SeekFrom::Start(HEADER + height * ENTRY))
+            .map_err(|e| {
+                ErrorKind::Io.error(format!("failed to read {}: {e}", self.path.display()))
+            })?;
+        read_entry(&mut self.file, &self.path).map(Some)
+    }
+    /// The blocks either side of the one at `height`, as [Chain::neighbours] finds them.
+    pub fn neighbours(&mut self, height: u64) -> Result<Neighbours> {
+        let mut neighbour = |height: u64| -> Result<Option<Neighbour>> {
+            let entry = self.get(height)?;
+            Ok(entry.map(|entry| Neighbour {
+                height,
+                hash: entry.hash,
+            }))
+        };
+        Ok(Neighbours {
+            previous: match height.checked_sub(1) {
+                Some(previous) => neighbour(previous)?,
+                None => None,
+            },
+            next: neighbour(height + 1)?,
+        })
+    }
+}
+
+/// Read exactly `n` bytes from `reader`, the index file at `path`.
+fn take(reader: &mut impl Read, path: &Path, n: usize) -> Result<Vec<u8>> {
+    let mut bytes = vec![0; n];
+    reader
+        .read_exact(&mut bytes)
+        .map_err(|e| ErrorKind::Io.error(format!("failed to read {}: {e}", path.display())))?;
+    Ok(bytes)
+}
+
+/// Read the header of the index file at `path`, returning the size of the blk files
+/// indexed and the number of entries that follow.
+fn read_header(reader: &mut impl Read, path: &Path) -> Result<(u64, u64)> {
+    if take(reader, path, 4)? != MAGIC {
+        bail!("{} is not a block index", path.display())
+    }
+    let version = u32::from_le_bytes(
+        take(reader, path, 4)?
+            .try_into()
+            .expect("expected 4 bytes for version"),
+    );
+    if version != VERSION {
+        bail!(
+            "unsupported block index version {version} in {}",
+            path.display()
+        )
+    }
+    let blk_size = u64::from_le_bytes(
+        take(reader, path, 8)?
+            .try_into()
+            .expect("expected 8 bytes for size"),
+    );
+    let count = u64::from_le_bytes(
+        take(reader, path, 8)?
+            .try_into()
+            .expect("expected 8 bytes for count"),
+    );
+    Ok((blk_size, count))
+}
+
+fn read_entry(reader: &mut impl Read, path: &Path) -> Result<ChainEntry> {
+    let bytes = take(reader, path, ENTRY as usize)?;
+    let hash = bytes[..32].try_into().expect("expected 32 bytes for hash");
+    let file = u32::from_le_bytes(bytes[32..36].try_into().expect("expected 4 bytes for file"));
+    let offset = u64::from_le_bytes(bytes[36..].try_into().expect("expected 8 bytes for offset"));
+    Ok(ChainEntry {
+        hash,
+        location: BlockLocation {
+            file: file as usize,
+            offset,
+        },
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{append, load, save, IndexFile};
+    use crate::{
+        reader::{blk_file_path, BlockFileReader},
+        util::fixtures::{blk_record, BLOCK_1_HEX, GENESIS_BLOCK_HEX},
+        Chain,
+    };
+
+    /// Checks that a saved chain loads back with the same entries and heights, and that
+    /// single entries are read back the same by seeking to them.
+    #[test]
+    fn test_index_round_trip() {
+        let datadir = std::env::temp_dir().join("bbf-test-index-round-trip");
+        std::fs::create_dir_all(&datadir).unwrap();
+        std::fs::write(blk_file_path(&datadir, 0), blk_record(GENESIS_BLOCK_HEX)).unwrap();
+        std::fs::write(blk_file_path(&datadir, 1), blk_record(BLOCK_1_HEX)).unwrap();
+
+        let mut reader = BlockFileReader::open(&datadir, None).unwrap();
+        let chain = Chain::build(reader.by_ref()).unwrap();
+        let path = datadir.join("index.idx");
+        save(&path, &chain, 1234).unwrap();
+        let index = load(&path).unwrap();
+        let mut file = IndexFile::open(&path).unwrap();
+        let entries = (0..3)
+            .map(|height| file.get(height).unwrap())
+            .collect::<Vec<_>>();
+        let neighbours = file.neighbours(0).unwrap();
+        std::fs::write(&path, b"not an index").unwrap();
+        let corrupt = load(&path);
+        std::fs::remove_dir_all(&datadir).unwrap();
+
+        assert_eq!(index.blk_size, 1234);
+        assert!(!index.is_stale(1234));
+        assert_eq!(index.chain.entries, chain.entries);
+        let tip = index.chain.get(1).unwrap();
+        assert_eq!(index.chain.height_of(&tip.hash), Some(1));
+        assert!(corrupt.is_err());
+
+        assert_eq!(file.len(), 2);
+        assert!(!file.is_stale(1234));
+        assert_eq!(
+            entries,
+            [chain.get(0).copied(), chain.get(1).copied(), None]
+        );
+        assert_eq!(neighbours.previous, None);
+        assert_eq!(neighbours.next.map(|next| next.hash), Some(tip.hash));
+    }
+
+    /// Checks that entries appended to a saved chain load back as the whole chain, with the
+    /// new size of the blk files.
+    #[test]
+    fn test_index_append() {
+        let datadir = std::env::temp_dir().join("bbf-test-index-append");
+        std::fs::create_dir_all(&datadir).unwrap();
+        std::fs::write(blk_file_path(&datadir, 0), blk_record(GENESIS_BLOCK_HEX)).unwrap();
+        std::fs::write(blk_file_path(&datadir, 1), blk_record(BLOCK_1_HEX)).unwrap();
+
+        let mut reader = BlockFileReader::open(&datadir, None).unwrap();
+        let chain = Chain::build(reader.by_ref()).unwrap();
+        let genesis = Chain::from_entries(chain.entries[..1].to_vec());
+        let path = datadir.join("index.idx");
+        save(&path, &genesis, 100).unwrap();
+        append(&path, &chain.entries[1..], 200).unwrap();
+        let index = load(&path).unwrap();
+        std::fs::remove_dir_all(&datadir).unwrap();
+
+        assert_eq!(index.blk_size, 200);
+        assert_eq!(index.chain.entries, chain.entries);
+    }
+}
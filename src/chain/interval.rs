@@ -0,0 +1,90 @@
+//! The longest and shortest intervals between consecutive blocks of a range, going by their
+//! header timestamps: the multi-hour gaps of the early chain and the blocks found seconds,
+//! or apparently less, after their parent.
+use serde::Serialize;
+
+/// The time between a block and its parent.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct BlockInterval {
+    pub height: u64,
+    pub hash: String,
+    pub time: u32,
+    pub parent_time: u32,
+    /// Seconds from the parent's timestamp to the block's, negative when it's dated earlier.
+    pub interval: i64,
+}
+
+/// The extremes of the intervals from height `from` to `to`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct IntervalExtremes {
+    pub from: u64,
+    pub to: u64,
+    /// The longest intervals, longest first.
+    pub longest: Vec<BlockInterval>,
+    /// The shortest intervals, shortest first.
+    pub shortest: Vec<BlockInterval>,
+}
+impl IntervalExtremes {
+    /// The `count` longest and shortest intervals among `headers`, the hash and timestamp of
+    /// each block from height `start` on, each block's measured from the one before it, so
+    /// the first is only a parent. Ties go to the lower height.
+    pub fn new(start: u64, headers: &[(String, u32)], count: usize) -> Self {
+        let mut intervals = headers
+            .windows(2)
+            .zip(start + 1..)
+            .map(|(pair, height)| {
+                let ((_, parent_time), (hash, time)) = (&pair[0], &pair[1]);
+                BlockInterval {
+                    height,
+                    hash: hash.clone(),
+                    time: *time,
+                    parent_time: *parent_time,
+                    interval: i64::from(*time) - i64::from(*parent_time),
+                }
+            })
+            .collect::<Vec<_>>();
+
+        intervals.sort_by_key(|interval| (interval.interval, interval.height));
+        let shortest = intervals.iter().take(count).cloned().collect();
+        intervals.sort_by_key(|interval| (std::cmp::Reverse(interval.interval), interval.height));
+        let longest = intervals.into_iter().take(count).collect();
+        Self {
+            from: start + 1,
+            to: start + headers.len().saturating_sub(1) as u64,
+            longest,
+            shortest,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::IntervalExtremes;
+
+    /// Checks intervals are measured from each block's parent and ordered from the extremes
+    /// in, ties going to the lower height and a block dated before its parent the shortest.
+    #[test]
+    fn test_interval_extremes() {
+        let headers = [0, 600, 7800, 7801, 7700, 8300, 15500]
+            .into_iter()
+            .enumerate()
+            .map(|(height, time)| (height.to_string(), time))
+            .collect::<Vec<_>>();
+        let extremes = IntervalExtremes::new(10, &headers, 2);
+        assert_eq!((extremes.from, extremes.to), (11, 16));
+
+        let heights = |intervals: &[super::BlockInterval]| {
+            intervals
+                .iter()
+                .map(|i| (i.height, i.interval))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(heights(&extremes.longest), [(12, 7200), (16, 7200)]);
+        assert_eq!(heights(&extremes.shortest), [(14, -101), (13, 1)]);
+        assert_eq!(extremes.shortest[0].hash, "4");
+
+        assert!(IntervalExtremes::new(0, &headers[..1], 2)
+            .longest
+            .is_empty());
+    }
+}
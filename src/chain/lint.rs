@@ -0,0 +1,172 @@
+//! Spotting suspicious header timestamps: blocks timestamped before their parent, ones
+//! that consensus should have rejected, ones far ahead of the blocks around them, and
+//! the time warp pattern of a period's first block dated well before the last one's.
+use super::{median_time, retarget::RETARGET_INTERVAL, MEDIAN_TIME_SPAN};
+use crate::util::{display::format_utc, hash::to_display_hex};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+/// How far a timestamp can run ahead of the network's time, and so of honest neighbours.
+const MAX_FUTURE_DRIFT: u32 = 2 * 60 * 60;
+/// How much earlier than the last block of a period the next can be dated before it
+/// looks like a time warp, the limit proposed by the Great Consensus Cleanup.
+const MAX_TIMEWARP: u32 = 2 * 60 * 60;
+
+/// What's suspicious about a header's timestamp.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AnomalyKind {
+    /// Earlier than its parent's, which is allowed but unusual.
+    EarlierThanParent,
+    /// Not after the median time past of its parent, which consensus forbids.
+    NotAfterMedianTimePast,
+    /// More than two hours ahead of both its parent and its child.
+    AheadOfNeighbours,
+    /// The first block of a period, more than two hours earlier than the last of the one before.
+    TimeWarp,
+}
+impl AnomalyKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            AnomalyKind::EarlierThanParent => "earlier_than_parent",
+            AnomalyKind::NotAfterMedianTimePast => "not_after_median_time_past",
+            AnomalyKind::AheadOfNeighbours => "ahead_of_neighbours",
+            AnomalyKind::TimeWarp => "time_warp",
+        }
+    }
+}
+
+/// A header whose timestamp stands out, along with the time it was compared to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HeaderAnomaly {
+    pub height: u64,
+    pub hash: [u8; 32],
+    pub kind: AnomalyKind,
+    pub time: u32,
+    /// The parent's timestamp, its median time past, or the later of its neighbours'.
+    pub reference: u32,
+}
+impl std::fmt::Display for HeaderAnomaly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let (time, reference) = (format_utc(self.time), format_utc(self.reference));
+        match self.kind {
+            AnomalyKind::EarlierThanParent => {
+                write!(f, "timestamp {time} is before its parent's {reference}")
+            }
+            AnomalyKind::NotAfterMedianTimePast => write!(
+                f,
+                "timestamp {time} isn't after its parent's median time past {reference}"
+            ),
+            AnomalyKind::AheadOfNeighbours => write!(
+                f,
+                "timestamp {time} is more than 2 hours ahead of its neighbours, the latest at {reference}"
+            ),
+            AnomalyKind::TimeWarp => write!(
+                f,
+                "timestamp {time} opens a period more than 2 hours before the last block of the previous one at {reference}"
+            ),
+        }
+    }
+}
+
+impl Serialize for HeaderAnomaly {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("HeaderAnomaly", 6)?;
+        state.serialize_field("height", &self.height)?;
+        state.serialize_field("block_hash", &to_display_hex(&self.hash))?;
+        state.serialize_field("kind", self.kind.name())?;
+        state.serialize_field("time", &self.time)?;
+        state.serialize_field("reference", &self.reference)?;
+        state.serialize_field("description", &self.to_string())?;
+        state.end()
+    }
+}
+
+/// Check the timestamps of `headers`, the hash and time of each block from height `start`
+/// on, in height order. Every header is checked, but the last can't be compared to a
+/// child, and those without 11 before them only to the median of the ones there are, so
+/// include the blocks either side of a range of interest.
+pub fn lint_timestamps(start: u64, headers: &[([u8; 32], u32)]) -> Vec<HeaderAnomaly> {
+    let mut anomalies = Vec::new();
+    for (index, &(hash, time)) in headers.iter().enumerate() {
+        let height = start + index as u64;
+        let mut report = |kind, reference| {
+            anomalies.push(HeaderAnomaly {
+                height,
+                hash,
+                kind,
+                time,
+                reference,
+            })
+        };
+        let Some(&(_, parent_time)) = index.checked_sub(1).and_then(|parent| headers.get(parent))
+        else {
+            continue;
+        };
+
+        let span_start = index.saturating_sub(MEDIAN_TIME_SPAN as usize);
+        let median_time_past = median_time(
+            headers[span_start..index]
+                .iter()
+                .map(|(_, time)| *time)
+                .collect(),
+        );
+        if time <= median_time_past {
+            report(AnomalyKind::NotAfterMedianTimePast, median_time_past);
+        } else if time < parent_time {
+            report(AnomalyKind::EarlierThanParent, parent_time);
+        }
+        if height.is_multiple_of(RETARGET_INTERVAL)
+            && time < parent_time.saturating_sub(MAX_TIMEWARP)
+        {
+            report(AnomalyKind::TimeWarp, parent_time);
+        }
+        if let Some(&(_, child_time)) = headers.get(index + 1) {
+            let latest = parent_time.max(child_time);
+            if time > latest.saturating_add(MAX_FUTURE_DRIFT) {
+                report(AnomalyKind::AheadOfNeighbours, latest);
+            }
+        }
+    }
+    anomalies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lint_timestamps, AnomalyKind};
+
+    /// Checks each kind of anomaly is reported at the height it happens, and nothing else.
+    #[test]
+    fn test_lint_timestamps() {
+        let ten_minutes = |blocks: u32| 1_000_000 + blocks * 600;
+        let mut times = (0..2020).map(ten_minutes).collect::<Vec<u32>>();
+        // A block a little behind its parent, one no later than the median of the 11 before
+        // it, and one far ahead of both neighbours.
+        times[5] = ten_minutes(4) - 60;
+        times[50] = ten_minutes(44);
+        times[100] = ten_minutes(100) + 3 * 60 * 60;
+        // A time warp, the last block of the first period dated 4 hours ahead
+        // so the first of the next can fall back to the honest time.
+        times[2015] = ten_minutes(2015) + 4 * 60 * 60;
+        let headers = times
+            .iter()
+            .enumerate()
+            .map(|(height, time)| ([height as u8; 32], *time))
+            .collect::<Vec<_>>();
+
+        let anomalies = lint_timestamps(0, &headers)
+            .into_iter()
+            .map(|anomaly| (anomaly.height, anomaly.kind))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            anomalies,
+            [
+                (5, AnomalyKind::EarlierThanParent),
+                (50, AnomalyKind::NotAfterMedianTimePast),
+                (100, AnomalyKind::AheadOfNeighbours),
+                (101, AnomalyKind::EarlierThanParent),
+                (2015, AnomalyKind::AheadOfNeighbours),
+                (2016, AnomalyKind::EarlierThanParent),
+                (2016, AnomalyKind::TimeWarp),
+            ]
+        );
+    }
+}
@@ -0,0 +1,754 @@
+//! Linking block records into the chain they form, following each header's previous block hash.
+use crate::util::{
+    hash::to_display_hex,
+    types::{Block, BlockHeader, BlockInfo, BlockLocation},
+};
+use anyhow::Result;
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use std::collections::HashMap;
+
+#[cfg(feature = "leveldb")]
+pub mod core_index;
+pub mod hashrate;
+pub mod index;
+pub mod interval;
+pub mod lint;
+pub mod retarget;
+pub mod txoutset;
+pub mod utxo;
+
+/// The number of blocks before a block whose median timestamp is its median time past (BIP113).
+pub(crate) const MEDIAN_TIME_SPAN: u64 = 11;
+
+/// The median of a span of timestamps, the later of the middle two for an even number.
+pub(crate) fn median_time(mut span: Vec<u32>) -> u32 {
+    span.sort_unstable();
+    span[span.len() / 2]
+}
+
+/// A block's place in the chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ChainEntry {
+    pub hash: [u8; 32],
+    pub location: BlockLocation,
+}
+
+/// What linking a block into the chain needs to know about it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockLink {
+    pub hash: [u8; 32],
+    pub previous_hash: [u8; 32],
+    pub location: BlockLocation,
+    /// The block's proof of work, as [BlockHeader::work] counts it.
+    pub work: u128,
+}
+impl BlockLink {
+    pub fn from_block(block_info: &BlockInfo, block: &Block) -> Result<Self> {
+        Self::from_header(block_info, block.block_header())
+    }
+    /// The link of a block read without its transactions, from just its header.
+    pub fn from_header(block_info: &BlockInfo, header: &BlockHeader) -> Result<Self> {
+        Ok(Self {
+            hash: header.hash(),
+            previous_hash: header.previous_hash(),
+            work: header.work(),
+            location: block_info
+                .location()
+                .ok_or_else(|| anyhow::anyhow!("expected a location for every block"))?,
+        })
+    }
+}
+
+/// A block on the best chain next to another, by height and hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Neighbour {
+    pub height: u64,
+    pub hash: [u8; 32],
+}
+impl Serialize for Neighbour {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Neighbour", 2)?;
+        state.serialize_field("height", &self.height)?;
+        state.serialize_field("block_hash", &to_display_hex(&self.hash))?;
+        state.end()
+    }
+}
+
+/// The blocks either side of one on the best chain, for navigating from it.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, PartialOrd, Ord)]
+pub struct Neighbours {
+    /// Its parent, `None` for the genesis block or when the parent isn't on the best chain.
+    pub previous: Option<Neighbour>,
+    /// The block built on it, `None` at the tip or when it isn't on the best chain itself.
+    pub next: Option<Neighbour>,
+}
+
+/// A block found in the blk files that isn't on the best chain, either stale,
+/// having lost a race to another block at its height, or orphaned, its parent unknown.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct StaleBlock {
+    pub hash: [u8; 32],
+    /// The height the block would have, `None` when its branch doesn't reach the best chain.
+    pub height: Option<u64>,
+    pub location: BlockLocation,
+}
+/// Something amiss in how the scanned blocks link together, which linking the best chain
+/// otherwise passes over.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LinkWarning {
+    /// The same block stored twice, only the first of which is linked.
+    Duplicate {
+        hash: [u8; 32],
+        first: BlockLocation,
+        again: BlockLocation,
+    },
+    /// Several blocks building on the same parent, competing tips of which at most one
+    /// can be on the best chain.
+    Fork {
+        parent: [u8; 32],
+        children: Vec<([u8; 32], BlockLocation)>,
+    },
+    /// A block whose parent isn't among those scanned, so the heights before it have a gap.
+    MissingParent {
+        hash: [u8; 32],
+        previous_hash: [u8; 32],
+        location: BlockLocation,
+    },
+}
+impl std::fmt::Display for LinkWarning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let at = |location: &BlockLocation| {
+            format!("offset {} of file {}", location.offset, location.file)
+        };
+        match self {
+            LinkWarning::Duplicate { hash, first, again } => write!(
+                f,
+                "Block {} is stored twice, at {} and again at {}",
+                to_display_hex(hash),
+                at(first),
+                at(again)
+            ),
+            LinkWarning::Fork { parent, children } => write!(
+                f,
+                "{} blocks compete to follow {}: {}",
+                children.len(),
+                to_display_hex(parent),
+                children
+                    .iter()
+                    .map(|(hash, location)| format!("{} at {}", to_display_hex(hash), at(location)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ),
+            LinkWarning::MissingParent {
+                hash,
+                previous_hash,
+                location,
+            } => write!(
+                f,
+                "Block {} at {} follows {}, which isn't in the blk files, leaving a gap before it",
+                to_display_hex(hash),
+                at(location),
+                to_display_hex(previous_hash)
+            ),
+        }
+    }
+}
+
+impl Serialize for StaleBlock {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("StaleBlock", 4)?;
+        state.serialize_field("block_hash", &to_display_hex(&self.hash))?;
+        state.serialize_field("height", &self.height)?;
+        state.serialize_field("file", &self.location.file)?;
+        state.serialize_field("offset", &self.location.offset)?;
+        state.end()
+    }
+}
+
+/// The best chain found in a set of blk files, indexed by height.
+///
+/// Blocks in blk files are written in the order a node received them, which is
+/// not necessarily height order and can include stale blocks. Heights here are
+/// assigned by walking the previous block hash links back from the tip with the
+/// most work, so a long branch of easy blocks can't outweigh the real chain.
+#[derive(Debug, Default)]
+pub struct Chain {
+    entries: Vec<ChainEntry>,
+    heights: HashMap<[u8; 32], u64>,
+}
+impl Chain {
+    /// Link every block read from `blocks` into the best chain.
+    ///
+    /// The chain is rooted at the genesis block, whose previous hash is all zeros.
+    /// When the files don't contain it, the first block whose parent is missing
+    /// is treated as height 0 instead.
+    pub fn build(blocks: impl Iterator<Item = Result<(BlockInfo, Block)>>) -> Result<Self> {
+        let links = blocks
+            .map(|block| {
+                let (block_info, block) = block?;
+                BlockLink::from_block(&block_info, &block)
+            })
+            .collect::<Result<Vec<BlockLink>>>()?;
+        Ok(Self::link(links))
+    }
+    /// Link blocks into the best chain as with [Chain::build], from just their
+    /// hashes and locations, given in the order they were read.
+    pub fn link(links: impl IntoIterator<Item = BlockLink>) -> Self {
+        // Every known block, its parent and where it was read from, in file order.
+        let mut records: Vec<([u8; 32], [u8; 32], BlockLocation, u128)> = Vec::new();
+        let mut seen = HashMap::new();
+        for link in links {
+            if seen.contains_key(&link.hash) {
+                continue;
+            }
+            seen.insert(link.hash, records.len());
+            records.push((link.hash, link.previous_hash, link.location, link.work));
+        }
+
+        let mut children: HashMap<[u8; 32], Vec<usize>> = HashMap::new();
+        for (index, (_, previous_hash, _, _)) in records.iter().enumerate() {
+            children.entry(*previous_hash).or_default().push(index);
+        }
+        let root = records
+            .iter()
+            .position(|(_, previous_hash, _, _)| *previous_hash == [0; 32])
+            .or_else(|| {
+                records
+                    .iter()
+                    .position(|(_, previous_hash, _, _)| !seen.contains_key(previous_hash))
+            });
+        let Some(root) = root else {
+            return Self::default();
+        };
+
+        // Walk the tree breadth first, remembering each block's parent and the work of the
+        // branch up to it, then follow the parents back from the tip with the most work,
+        // the highest of those with as much and the first of those found.
+        let mut parents = vec![None; records.len()];
+        let mut depths = vec![0u64; records.len()];
+        let mut chainwork = vec![0u128; records.len()];
+        chainwork[root] = records[root].3;
+        let mut tip = root;
+        let mut queue = std::collections::VecDeque::from([root]);
+        while let Some(index) = queue.pop_front() {
+            if (chainwork[index], depths[index]) > (chainwork[tip], depths[tip]) {
+                tip = index;
+            }
+            for &child in children.get(&records[index].0).into_iter().flatten() {
+                parents[child] = Some(index);
+                depths[child] = depths[index] + 1;
+                chainwork[child] = chainwork[index].saturating_add(records[child].3);
+                queue.push_back(child);
+            }
+        }
+        let mut entries = Vec::with_capacity(depths[tip] as usize + 1);
+        let mut next = Some(tip);
+        while let Some(index) = next {
+            let (hash, _, location, _) = records[index];
+            entries.push(ChainEntry { hash, location });
+            next = parents[index];
+        }
+        entries.reverse();
+
+        Self::from_entries(entries)
+    }
+    /// A chain of `entries` already in height order.
+    fn from_entries(entries: Vec<ChainEntry>) -> Self {
+        let heights = entries
+            .iter()
+            .enumerate()
+            .map(|(height, entry)| (entry.hash, height as u64))
+            .collect();
+        Self { entries, heights }
+    }
+    /// Add a block to the top of the chain, returning its height,
+    /// or `None` if it doesn't build on the current tip.
+    ///
+    /// Only the tip is extended, so a block on a competing branch is never
+    /// linked in, even once its branch overtakes this one.
+    pub fn extend(&mut self, link: BlockLink) -> Option<u64> {
+        let builds_on_tip = match self.entries.last() {
+            Some(tip) => tip.hash == link.previous_hash,
+            None => link.previous_hash == [0; 32],
+        };
+        if !builds_on_tip || self.heights.contains_key(&link.hash) {
+            return None;
+        }
+        let height = self.entries.len() as u64;
+        self.entries.push(ChainEntry {
+            hash: link.hash,
+            location: link.location,
+        });
+        self.heights.insert(link.hash, height);
+        Some(height)
+    }
+    /// What's amiss in how `links`, those this chain was linked from, fit together:
+    /// duplicates, then forks, then blocks after a gap, each in the order given. The block
+    /// the chain starts from never counts as being after a gap, whatever its parent.
+    pub fn link_warnings(&self, links: &[BlockLink]) -> Vec<LinkWarning> {
+        let mut warnings = Vec::new();
+        let mut first_seen = HashMap::new();
+        let mut parents = Vec::new();
+        let mut children: HashMap<[u8; 32], Vec<&BlockLink>> = HashMap::new();
+        for link in links {
+            if let Some(first) = first_seen.get(&link.hash) {
+                warnings.push(LinkWarning::Duplicate {
+                    hash: link.hash,
+                    first: *first,
+                    again: link.location,
+                });
+                continue;
+            }
+            first_seen.insert(link.hash, link.location);
+            let siblings = children.entry(link.previous_hash).or_default();
+            if siblings.is_empty() {
+                parents.push(link.previous_hash);
+            }
+            siblings.push(link);
+        }
+        for parent in &parents {
+            if let siblings @ [_, _, ..] = children[parent].as_slice() {
+                warnings.push(LinkWarning::Fork {
+                    parent: *parent,
+                    children: siblings
+                        .iter()
+                        .map(|link| (link.hash, link.location))
+                        .collect(),
+                });
+            }
+        }
+        let root = self.entries.first().map(|entry| entry.hash);
+        for parent in parents
+            .iter()
+            .filter(|parent| !first_seen.contains_key(*parent))
+        {
+            for link in children[parent]
+                .iter()
+                .filter(|link| Some(link.hash) != root)
+            {
+                warnings.push(LinkWarning::MissingParent {
+                    hash: link.hash,
+                    previous_hash: link.previous_hash,
+                    location: link.location,
+                });
+            }
+        }
+        warnings
+    }
+    /// Every block in `links` that isn't on this chain, in the order given.
+    pub fn stale(&self, links: &[BlockLink]) -> Vec<StaleBlock> {
+        let by_hash = links
+            .iter()
+            .map(|link| (link.hash, link))
+            .collect::<HashMap<_, _>>();
+        let mut heights: HashMap<[u8; 32], Option<u64>> = HashMap::new();
+        for link in links {
+            if self.heights.contains_key(&link.hash) || heights.contains_key(&link.hash) {
+                continue;
+            }
+            // Follow the parents back to the best chain, or as far as they go,
+            // then count the heights back up the branch.
+            let mut branch = vec![link.hash];
+            let mut base = None;
+            let mut parent = link.previous_hash;
+            loop {
+                if let Some(height) = self.heights.get(&parent) {
+                    base = Some(*height);
+                    break;
+                }
+                if let Some(height) = heights.get(&parent) {
+                    base = *height;
+                    break;
+                }
+                match by_hash.get(&parent) {
+                    Some(previous) if !branch.contains(&previous.hash) => {
+                        branch.push(previous.hash);
+                        parent = previous.previous_hash;
+                    }
+                    _ => break,
+                }
+            }
+            for (depth, hash) in branch.iter().rev().enumerate() {
+                heights.insert(*hash, base.map(|base| base + 1 + depth as u64));
+            }
+        }
+        links
+            .iter()
+            .filter_map(|link| {
+                heights.remove(&link.hash).map(|height| StaleBlock {
+                    hash: link.hash,
+                    height,
+                    location: link.location,
+                })
+            })
+            .collect()
+    }
+    /// The height of the last block at or before `time`, judged by header timestamps or,
+    /// with `median_time_past`, by the median of each block's last 11 timestamps.
+    /// `header_time` gives the timestamp of the block at a height.
+    ///
+    /// Header timestamps can go backwards, but each is later than the median time past
+    /// before it, which never decreases. So a binary search on the median time past finds
+    /// the last block that could qualify, and a short walk back finds the one that does.
+    pub fn height_at_time(
+        &self,
+        time: u32,
+        median_time_past: bool,
+        mut header_time: impl FnMut(u64) -> Result<u32>,
+    ) -> Result<Option<u64>> {
+        let mut times = HashMap::new();
+        let mut cached_time = |height: u64| -> Result<u32> {
+            if let Some(time) = times.get(&height) {
+                return Ok(*time);
+            }
+            let block_time = header_time(height)?;
+            times.insert(height, block_time);
+            Ok(block_time)
+        };
+        let mut median_time = |height: u64| -> Result<u32> {
+            let span = (height.saturating_sub(MEDIAN_TIME_SPAN - 1)..=height)
+                .map(&mut cached_time)
+                .collect::<Result<Vec<u32>>>()?;
+            Ok(median_time(span))
+        };
+
+        // The first height whose median time past is past `time`, or at or past it when
+        // looking at header timestamps, since a block's own timestamp must be later still.
+        let (mut low, mut high) = (0, self.len() as u64);
+        while low < high {
+            let middle = low + (high - low) / 2;
+            let median = median_time(middle)?;
+            let before = match median_time_past {
+                true => median <= time,
+                false => median < time,
+            };
+            match before {
+                true => low = middle + 1,
+                false => high = middle,
+            }
+        }
+        if median_time_past {
+            return Ok(low.checked_sub(1));
+        }
+        // The block at `low` itself can still be early enough, but none after it.
+        let mut height = (low + 1).min(self.len() as u64);
+        while height > 0 {
+            height -= 1;
+            if cached_time(height)? <= time {
+                return Ok(Some(height));
+            }
+        }
+        Ok(None)
+    }
+    /// The block at `height` on the best chain.
+    pub fn get(&self, height: u64) -> Option<&ChainEntry> {
+        self.entries.get(usize::try_from(height).ok()?)
+    }
+    /// The height of the block with `hash`, if it is on the best chain.
+    pub fn height_of(&self, hash: &[u8; 32]) -> Option<u64> {
+        self.heights.get(hash).copied()
+    }
+    /// The blocks either side of the one with `header` on the best chain, the previous
+    /// resolved from its parent's hash so it's found even when the block itself isn't on it.
+    pub fn neighbours(&self, header: &BlockHeader) -> Neighbours {
+        let neighbour = |height: u64| {
+            self.get(height).map(|entry| Neighbour {
+                height,
+                hash: entry.hash,
+            })
+        };
+        Neighbours {
+            previous: self.height_of(&header.previous_hash()).and_then(&neighbour),
+            next: self
+                .height_of(&header.hash())
+                .and_then(|height| neighbour(height + 1)),
+        }
+    }
+    /// The number of blocks on the best chain.
+    pub fn len(&self) -> usize {
+        self.entries.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlockLink, Chain, LinkWarning, Neighbour, StaleBlock};
+    use crate::{
+        reader::{blk_file_path, BlockFileReader},
+        util::fixtures::{blk_record, BLOCK_1_HEX, GENESIS_BLOCK_HEX},
+        util::types::BlockLocation,
+    };
+
+    /// Checks that heights follow the chain rather than the order blocks were written in.
+    #[test]
+    fn test_chain_order() {
+        let datadir = std::env::temp_dir().join("bbf-test-chain-order");
+        std::fs::create_dir_all(&datadir).unwrap();
+        std::fs::write(blk_file_path(&datadir, 0), blk_record(BLOCK_1_HEX)).unwrap();
+        std::fs::write(blk_file_path(&datadir, 1), blk_record(GENESIS_BLOCK_HEX)).unwrap();
+
+        let mut reader = BlockFileReader::open(&datadir, None).unwrap();
+        let chain = Chain::build(reader.by_ref()).unwrap();
+        let genesis = *chain.get(0).unwrap();
+        let (block_info, block) = reader.read_at(genesis.location, 0).unwrap();
+        std::fs::remove_dir_all(&datadir).unwrap();
+
+        assert_eq!(chain.len(), 2);
+        assert_eq!(genesis.location.file, 1);
+        assert_eq!(chain.height_of(&chain.get(1).unwrap().hash), Some(1));
+        assert_eq!(block_info.height, 0);
+        assert_eq!(block.block_header().hash(), genesis.hash);
+    }
+
+    /// Checks that only blocks building on the tip extend the chain.
+    #[test]
+    fn test_chain_extend() {
+        let datadir = std::env::temp_dir().join("bbf-test-chain-extend");
+        std::fs::create_dir_all(&datadir).unwrap();
+        let mut blk = blk_record(GENESIS_BLOCK_HEX);
+        blk.extend(blk_record(BLOCK_1_HEX));
+        std::fs::write(blk_file_path(&datadir, 0), blk).unwrap();
+
+        let links = BlockFileReader::open(&datadir, None)
+            .unwrap()
+            .map(|block| {
+                let (block_info, block) = block.unwrap();
+                BlockLink::from_block(&block_info, &block).unwrap()
+            })
+            .collect::<Vec<_>>();
+        std::fs::remove_dir_all(&datadir).unwrap();
+
+        let mut chain = Chain::default();
+        assert_eq!(chain.extend(links[1]), None);
+        assert_eq!(chain.extend(links[0]), Some(0));
+        assert_eq!(chain.extend(links[0]), None);
+        assert_eq!(chain.extend(links[1]), Some(1));
+        assert_eq!(chain.height_of(&links[1].hash), Some(1));
+        assert_eq!(chain.len(), 2);
+    }
+
+    /// Checks the neighbours of each end of a two block chain.
+    #[test]
+    fn test_chain_neighbours() {
+        let datadir = std::env::temp_dir().join("bbf-test-chain-neighbours");
+        std::fs::create_dir_all(&datadir).unwrap();
+        let mut blk = blk_record(GENESIS_BLOCK_HEX);
+        blk.extend(blk_record(BLOCK_1_HEX));
+        std::fs::write(blk_file_path(&datadir, 0), blk).unwrap();
+
+        let blocks = BlockFileReader::open(&datadir, None)
+            .unwrap()
+            .map(|block| block.unwrap().1)
+            .collect::<Vec<_>>();
+        std::fs::remove_dir_all(&datadir).unwrap();
+        let chain = Chain::link(blocks.iter().enumerate().map(|(offset, block)| BlockLink {
+            hash: block.block_header().hash(),
+            previous_hash: block.block_header().previous_hash(),
+            location: BlockLocation {
+                file: 0,
+                offset: offset as u64,
+            },
+            work: block.block_header().work(),
+        }));
+
+        let genesis = chain.neighbours(blocks[0].block_header());
+        assert_eq!(genesis.previous, None);
+        assert_eq!(
+            genesis.next,
+            Some(Neighbour {
+                height: 1,
+                hash: blocks[1].block_header().hash()
+            })
+        );
+        let tip = chain.neighbours(blocks[1].block_header());
+        assert_eq!(tip.previous.unwrap().height, 0);
+        assert_eq!(tip.next, None);
+    }
+
+    /// Checks that blocks off the best chain get the height their branch would have,
+    /// or none when it doesn't reach the chain.
+    #[test]
+    fn test_chain_stale() {
+        let location = |offset| crate::BlockLocation { file: 0, offset };
+        let link = |hash: u8, previous_hash: u8, offset| BlockLink {
+            hash: [hash; 32],
+            previous_hash: [previous_hash; 32],
+            location: location(offset),
+            work: 1,
+        };
+        // 1 <- 2 <- 3 <- 8 <- 9 is the best chain, 2 <- 4 <- 5 a stale branch
+        // and 6 an orphan, its parent 7 missing.
+        let links = [
+            link(1, 0, 0),
+            link(2, 1, 1),
+            link(5, 4, 2),
+            link(4, 2, 3),
+            link(3, 2, 4),
+            link(6, 7, 5),
+            link(8, 3, 6),
+            link(9, 8, 7),
+        ];
+        let chain = Chain::link(links);
+        assert_eq!(chain.len(), 5);
+
+        let stale = chain.stale(&links);
+        assert_eq!(
+            stale,
+            vec![
+                StaleBlock {
+                    hash: [5; 32],
+                    height: Some(3),
+                    location: location(2)
+                },
+                StaleBlock {
+                    hash: [4; 32],
+                    height: Some(2),
+                    location: location(3)
+                },
+                StaleBlock {
+                    hash: [6; 32],
+                    height: None,
+                    location: location(5)
+                },
+            ]
+        );
+    }
+
+    /// Checks that the best chain is the branch with the most work rather than the longest,
+    /// and that between branches with as much work the longer one wins.
+    #[test]
+    fn test_chain_most_work() {
+        let link = |hash: u8, previous_hash: u8, work| BlockLink {
+            hash: [hash; 32],
+            previous_hash: [previous_hash; 32],
+            location: crate::BlockLocation {
+                file: 0,
+                offset: u64::from(hash),
+            },
+            work,
+        };
+        // 1 <- 2 <- 3 <- 4 <- 5 of easy blocks, longer than 1 <- 6 <- 7 of harder ones.
+        let links = [
+            link(1, 0, 10),
+            link(2, 1, 2),
+            link(3, 2, 2),
+            link(4, 3, 2),
+            link(5, 4, 2),
+            link(6, 1, 5),
+            link(7, 6, 5),
+        ];
+        let chain = Chain::link(links);
+        assert_eq!(chain.len(), 3);
+        assert_eq!(chain.height_of(&[7; 32]), Some(2));
+        assert_eq!(
+            chain
+                .stale(&links)
+                .iter()
+                .map(|stale| (stale.hash[0], stale.height))
+                .collect::<Vec<_>>(),
+            [(2, Some(1)), (3, Some(2)), (4, Some(3)), (5, Some(4))]
+        );
+
+        // One more easy block makes the branches' work equal, and the longer one the best.
+        let chain = Chain::link(links.into_iter().chain([link(8, 5, 2)]));
+        assert_eq!(chain.len(), 6);
+        assert_eq!(chain.height_of(&[8; 32]), Some(5));
+    }
+
+    /// Checks that a duplicate, a fork and a block after a gap are each reported, but not
+    /// the root of the chain.
+    #[test]
+    fn test_link_warnings() {
+        let location = |offset| crate::BlockLocation { file: 0, offset };
+        let link = |hash: u8, previous_hash: u8, offset| BlockLink {
+            hash: [hash; 32],
+            previous_hash: [previous_hash; 32],
+            location: location(offset),
+            work: 1,
+        };
+        // 1 <- 2 <- 3 with 2 <- 4 competing, 2 stored twice and 6 after a gap, its parent 5 missing.
+        let links = [
+            link(1, 0, 0),
+            link(2, 1, 1),
+            link(3, 2, 2),
+            link(2, 1, 3),
+            link(4, 2, 4),
+            link(6, 5, 5),
+        ];
+        let chain = Chain::link(links);
+
+        let warnings = chain.link_warnings(&links);
+        assert_eq!(
+            warnings,
+            vec![
+                LinkWarning::Duplicate {
+                    hash: [2; 32],
+                    first: location(1),
+                    again: location(3)
+                },
+                LinkWarning::Fork {
+                    parent: [2; 32],
+                    children: vec![([3; 32], location(2)), ([4; 32], location(4))]
+                },
+                LinkWarning::MissingParent {
+                    hash: [6; 32],
+                    previous_hash: [5; 32],
+                    location: location(5)
+                },
+            ]
+        );
+        assert!(warnings[1]
+            .to_string()
+            .starts_with("2 blocks compete to follow 0202"));
+        // Without the genesis block, the chain starts from the block after the gap instead.
+        assert_eq!(
+            Chain::link([link(6, 5, 0)]).link_warnings(&[link(6, 5, 0)]),
+            []
+        );
+    }
+
+    /// Checks searching by time against a walk over every height, on timestamps that go
+    /// backwards as often as real ones are allowed to.
+    #[test]
+    fn test_height_at_time() {
+        let times: [u32; 14] = [10, 20, 30, 25, 40, 35, 50, 45, 60, 55, 70, 65, 80, 75];
+        let chain = Chain::link((0..times.len() as u8).map(|height| BlockLink {
+            hash: [height + 1; 32],
+            previous_hash: [height; 32],
+            location: crate::BlockLocation {
+                file: 0,
+                offset: u64::from(height),
+            },
+            work: 1,
+        }));
+        let median_time = |height: usize| {
+            let mut span = times[height.saturating_sub(10)..=height].to_vec();
+            span.sort_unstable();
+            span[span.len() / 2]
+        };
+        for (height, time) in times.iter().enumerate().skip(1) {
+            assert!(*time > median_time(height - 1));
+        }
+
+        for time in 0..=90 {
+            let header = (0..times.len()).rev().find(|&height| times[height] <= time);
+            let median = (0..times.len())
+                .rev()
+                .find(|&height| median_time(height) <= time);
+            let header_time = |height: u64| Ok(times[height as usize]);
+            assert_eq!(
+                chain.height_at_time(time, false, header_time).unwrap(),
+                header.map(|height| height as u64),
+                "header time {time}"
+            );
+            assert_eq!(
+                chain.height_at_time(time, true, header_time).unwrap(),
+                median.map(|height| height as u64),
+                "median time past {time}"
+            );
+        }
+    }
+}
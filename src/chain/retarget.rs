@@ -0,0 +1,106 @@
+//! The difficulty adjustments made every 2016 blocks, comparing how long each period
+//! actually took with the two weeks it was meant to.
+use crate::util::{display::format_utc, types::BlockHeader};
+use serde::Serialize;
+
+/// The number of blocks between difficulty adjustments.
+pub const RETARGET_INTERVAL: u64 = 2016;
+/// How long a period is meant to take, two weeks of ten minute blocks, in seconds.
+pub const TARGET_TIMESPAN: i64 = 14 * 24 * 60 * 60;
+/// The most a single adjustment can change the difficulty by, up or down.
+const MAX_ADJUSTMENT_FACTOR: i64 = 4;
+
+/// The difficulty adjustment at the start of a period.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Retarget {
+    /// The height of the first block at the new difficulty.
+    pub height: u64,
+    /// When the period before it started, the timestamp of its first block.
+    pub period_start: String,
+    /// Seconds from the first block of the previous period to its last, as
+    /// measured by consensus, so one block interval short of the whole period.
+    pub actual_timespan: i64,
+    pub expected_timespan: i64,
+    pub previous_bits: String,
+    pub bits: String,
+    pub previous_difficulty: f64,
+    pub difficulty: f64,
+    /// The change in difficulty the headers actually show, as a percentage.
+    pub adjustment_percent: f64,
+    /// The change the measured timespan calls for, limited to a factor of four either way.
+    pub expected_adjustment_percent: f64,
+}
+impl Retarget {
+    /// The adjustment at `height`, from the first and last headers of the period before it
+    /// and its own header.
+    pub fn new(height: u64, first: &BlockHeader, last: &BlockHeader, header: &BlockHeader) -> Self {
+        let actual_timespan =
+            i64::from(last.unix_epoch_time()) - i64::from(first.unix_epoch_time());
+        let clamped = actual_timespan.clamp(
+            TARGET_TIMESPAN / MAX_ADJUSTMENT_FACTOR,
+            TARGET_TIMESPAN * MAX_ADJUSTMENT_FACTOR,
+        );
+        Self {
+            height,
+            period_start: format_utc(first.unix_epoch_time()),
+            actual_timespan,
+            expected_timespan: TARGET_TIMESPAN,
+            previous_bits: format!("{:08x}", last.target()),
+            bits: format!("{:08x}", header.target()),
+            previous_difficulty: last.difficulty(),
+            difficulty: header.difficulty(),
+            adjustment_percent: (header.difficulty() / last.difficulty() - 1.0) * 100.0,
+            expected_adjustment_percent: (TARGET_TIMESPAN as f64 / clamped as f64 - 1.0) * 100.0,
+        }
+    }
+}
+
+/// The heights of every adjustment on a chain of `len` blocks, first to last.
+pub fn retarget_heights(len: u64) -> impl Iterator<Item = u64> {
+    (RETARGET_INTERVAL..len).step_by(RETARGET_INTERVAL as usize)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{retarget_heights, Retarget, TARGET_TIMESPAN};
+    use crate::util::{
+        fixtures::GENESIS_BLOCK_HEX,
+        types::{BlockHeader, ByteCursor},
+    };
+
+    /// A copy of the genesis header with its time and bits replaced.
+    fn header(time: u32, bits: u32) -> BlockHeader {
+        let mut raw_bytes = hex::decode(GENESIS_BLOCK_HEX).unwrap()[..80].to_vec();
+        raw_bytes[68..72].copy_from_slice(&time.to_le_bytes());
+        raw_bytes[72..76].copy_from_slice(&bits.to_le_bytes());
+        BlockHeader::from_raw_bytes(&mut ByteCursor::new(raw_bytes.as_slice())).unwrap()
+    }
+
+    /// Checks a period that took half as long as it should, against
+    /// the first real adjustment's bits, and the heights walked.
+    #[test]
+    fn test_retarget() {
+        let retarget = Retarget::new(
+            32256,
+            &header(0, 0x1d00ffff),
+            &header((TARGET_TIMESPAN / 2) as u32, 0x1d00ffff),
+            &header((TARGET_TIMESPAN / 2) as u32 + 600, 0x1d00d86a),
+        );
+        assert_eq!(retarget.previous_bits, "1d00ffff");
+        assert_eq!(retarget.bits, "1d00d86a");
+        assert_eq!(retarget.expected_adjustment_percent, 100.0);
+        assert!((retarget.adjustment_percent - 18.29).abs() < 0.01);
+
+        let collapsed = Retarget::new(
+            2016,
+            &header(0, 0x1d00ffff),
+            &header(1, 0x1d00ffff),
+            &header(2, 0x1d00ffff),
+        );
+        assert_eq!(collapsed.expected_adjustment_percent, 300.0);
+        assert_eq!(collapsed.adjustment_percent, 0.0);
+
+        assert_eq!(retarget_heights(2016).count(), 0);
+        assert_eq!(retarget_heights(4033).collect::<Vec<_>>(), [2016, 4032]);
+    }
+}
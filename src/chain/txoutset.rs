@@ -0,0 +1,423 @@
+//! Reading and writing utxo sets in the snapshot format of Bitcoin Core's `dumptxoutset`,
+//! as `loadtxoutset` takes for assumeutxo.
+//!
+//! A snapshot is the magic `utxo\xff`, a 2 byte version, the network's magic bytes, the hash
+//! of the last block applied and the number of coins. The coins follow grouped by txid: the
+//! txid, the number of its coins, then for each its output index and Core's compressed
+//! serialization of the coin.
+use crate::{
+    chain::{
+        utxo::{Coin, UtxoSet},
+        Chain,
+    },
+    util::{
+        constant::Network,
+        error::ErrorKind,
+        hash::to_display_hex,
+        tx::TxOut,
+        types::{ByteCursor, CompactSize},
+    },
+};
+use anyhow::{bail, Result};
+use std::{
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::Path,
+};
+
+const MAGIC: &[u8; 5] = b"utxo\xff";
+const VERSION: u16 = 2;
+/// The number of script types compressed to a fixed size, whose sizes serialized scripts
+/// are offset by.
+const SPECIAL_SCRIPTS: u64 = 6;
+
+/// Write `utxos`, from blocks of `network`, to a snapshot at `path`.
+pub fn write(path: &Path, utxos: &UtxoSet, network: Network) -> Result<()> {
+    let Some((_, hash)) = utxos.tip() else {
+        bail!("failed to write an empty utxo set to {}", path.display())
+    };
+    let mut coins = utxos.iter().collect::<Vec<_>>();
+    coins.sort_unstable_by_key(|(txid, vout, _)| (*txid, *vout));
+
+    let file = File::create(path)
+        .map_err(|e| ErrorKind::Io.error(format!("failed to create {}: {e}", path.display())))?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&network.magic_bytes())?;
+    writer.write_all(&hash)?;
+    writer.write_all(&(coins.len() as u64).to_le_bytes())?;
+    for group in coins.chunk_by(|(a, _, _), (b, _, _)| a == b) {
+        writer.write_all(&group[0].0)?;
+        writer.write_all(&CompactSize::encode(group.len() as u64))?;
+        for (_, vout, coin) in group {
+            writer.write_all(&CompactSize::encode(u64::from(*vout)))?;
+            writer.write_all(&compress_coin(coin))?;
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read the snapshot at `path`, returning the network it's for and its coins, with the
+/// height of its last block found on `chain`.
+pub fn read(path: &Path, chain: &Chain) -> Result<(Network, UtxoSet)> {
+    let file = File::open(path)
+        .map_err(|e| ErrorKind::Io.error(format!("failed to open {}: {e}", path.display())))?;
+    let mut cursor = ByteCursor::new(file);
+    if cursor.take(5, "snapshot magic")? != MAGIC {
+        bail!("{} is not a utxo snapshot", path.display())
+    }
+    let version = u16::from_le_bytes(
+        cursor
+            .take(2, "snapshot version")?
+            .try_into()
+            .expect("expected 2 bytes for version"),
+    );
+    if version != VERSION {
+        bail!(
+            "unsupported utxo snapshot version {version} in {}",
+            path.display()
+        )
+    }
+    let magic_bytes = cursor.take(4, "snapshot network")?;
+    let network = Network::from_magic_bytes(&magic_bytes).ok_or_else(|| {
+        anyhow::anyhow!(
+            "failed to recognise the network {} of {}",
+            hex::encode(&magic_bytes),
+            path.display()
+        )
+    })?;
+    let hash: [u8; 32] = cursor
+        .take(32, "snapshot base block hash")?
+        .try_into()
+        .expect("expected 32 bytes for hash");
+    let height = chain.height_of(&hash).ok_or_else(|| {
+        ErrorKind::NotFound.error(format!(
+            "failed to find the snapshot's last block {} on the best chain",
+            to_display_hex(&hash)
+        ))
+    })?;
+    let count = u64::from_le_bytes(
+        cursor
+            .take(8, "snapshot coin count")?
+            .try_into()
+            .expect("expected 8 bytes for count"),
+    );
+
+    let mut utxos = UtxoSet::default();
+    let mut read = 0;
+    while read < count {
+        let txid: [u8; 32] = cursor
+            .take(32, "snapshot txid")?
+            .try_into()
+            .expect("expected 32 bytes for txid");
+        let coins = CompactSize::from_raw_bytes(&mut cursor)?.to_u64();
+        for _ in 0..coins {
+            let vout = CompactSize::from_raw_bytes(&mut cursor)?.to_u64();
+            let Ok(vout) = u32::try_from(vout) else {
+                bail!(
+                    "failed to read output {vout} of {} from {}, more than a transaction can have",
+                    to_display_hex(&txid),
+                    path.display()
+                )
+            };
+            utxos.insert(&txid, vout, decompress_coin(&mut cursor)?);
+        }
+        read += coins;
+    }
+    utxos.tip = Some((height, hash));
+    Ok((network, utxos))
+}
+
+/// Core's serialization of `coin`: the height it was created at, doubled and plus one for a
+/// coinbase, then the value compressed by [compress_amount] and the script by [compress_script],
+/// the numbers as [varint]s.
+fn compress_coin(coin: &Coin) -> Vec<u8> {
+    let code = u64::from(coin.height) * 2 + u64::from(coin.coinbase);
+    [
+        varint(code),
+        varint(compress_amount(coin.output.value())),
+        compress_script(coin.output.script_pubkey_bytes()),
+    ]
+    .concat()
+}
+
+fn decompress_coin<R: Read>(cursor: &mut ByteCursor<R>) -> Result<Coin> {
+    let code = read_varint(cursor)?;
+    Ok(Coin {
+        output: decompress_txout(cursor)?,
+        height: (code / 2) as u32,
+        coinbase: code % 2 == 1,
+    })
+}
+
+/// An output in Core's compressed serialization, as coins and undo records store them.
+pub(crate) fn decompress_txout<R: Read>(cursor: &mut ByteCursor<R>) -> Result<TxOut> {
+    let value = decompress_amount(read_varint(cursor)?)?;
+    Ok(TxOut::new(value, decompress_script(cursor)?))
+}
+
+/// Core's `VARINT`: base 128 digits, most significant first, each but the last with its high
+/// bit set, and one taken off every digit but the last so each number has a single encoding.
+fn varint(mut n: u64) -> Vec<u8> {
+    let mut digits = vec![(n & 0x7f) as u8];
+    while n > 0x7f {
+        n = (n >> 7) - 1;
+        digits.push((n & 0x7f) as u8 | 0x80);
+    }
+    digits.reverse();
+    digits
+}
+
+pub(crate) fn read_varint<R: Read>(cursor: &mut ByteCursor<R>) -> Result<u64> {
+    let mut n = 0u64;
+    loop {
+        let byte = cursor.take(1, "varint")?[0];
+        n = n
+            .checked_mul(128)
+            .ok_or_else(|| anyhow::anyhow!("failed to read a varint, it overflows 64 bits"))?
+            | u64::from(byte & 0x7f);
+        if byte & 0x80 == 0 {
+            return Ok(n);
+        }
+        n = n
+            .checked_add(1)
+            .ok_or_else(|| anyhow::anyhow!("failed to read a varint, it overflows 64 bits"))?;
+    }
+}
+
+/// Core's compression of an amount in sats, which are mostly round numbers: the trailing
+/// zeros are counted off into the last decimal digit.
+fn compress_amount(mut n: u64) -> u64 {
+    if n == 0 {
+        return 0;
+    }
+    let mut exponent = 0;
+    while n.is_multiple_of(10) && exponent < 9 {
+        n /= 10;
+        exponent += 1;
+    }
+    if exponent < 9 {
+        let digit = n % 10;
+        n /= 10;
+        1 + (n * 9 + digit - 1) * 10 + exponent
+    } else {
+        1 + (n - 1) * 10 + 9
+    }
+}
+
+fn decompress_amount(x: u64) -> Result<u64> {
+    if x == 0 {
+        return Ok(0);
+    }
+    let x = x - 1;
+    let exponent = x % 10;
+    let x = x / 10;
+    let n = match exponent < 9 {
+        true => (x / 9) * 10 + (x % 9) + 1,
+        false => x + 1,
+    };
+    n.checked_mul(10u64.pow(exponent as u32))
+        .ok_or_else(|| anyhow::anyhow!("failed to decompress an amount, it overflows 64 bits"))
+}
+
+/// Core's compression of a script: P2PKH and P2SH scripts to their hash and P2PK scripts
+/// to their key's x coordinate, behind a byte for their type, and anything else as it is
+/// behind its size offset by [SPECIAL_SCRIPTS].
+///
+/// Core only compresses uncompressed keys on the curve, which takes secp256k1 to check, so
+/// without it they're left as they are. Core reads them either way, but only with it do
+/// snapshots match the ones Core writes byte for byte.
+fn compress_script(script: &[u8]) -> Vec<u8> {
+    match script {
+        [0x76, 0xa9, 0x14, hash @ .., 0x88, 0xac] if hash.len() == 20 => [&[0x00], hash].concat(),
+        [0xa9, 0x14, hash @ .., 0x87] if hash.len() == 20 => [&[0x01], hash].concat(),
+        [0x21, key @ .., 0xac] if key.len() == 33 && matches!(key[0], 0x02 | 0x03) => key.to_vec(),
+        [0x41, key @ .., 0xac] if key.len() == 65 && key[0] == 0x04 && is_on_curve(key) => {
+            [&[0x04 | (key[64] & 0x01)], &key[1..33]].concat()
+        }
+        _ => [
+            varint(script.len() as u64 + SPECIAL_SCRIPTS),
+            script.to_vec(),
+        ]
+        .concat(),
+    }
+}
+
+fn decompress_script<R: Read>(cursor: &mut ByteCursor<R>) -> Result<Vec<u8>> {
+    let size = read_varint(cursor)?;
+    let script = match size {
+        0x00 => [
+            &[0x76, 0xa9, 0x14],
+            cursor.take(20, "key hash")?.as_slice(),
+            &[0x88, 0xac],
+        ]
+        .concat(),
+        0x01 => [
+            &[0xa9, 0x14],
+            cursor.take(20, "script hash")?.as_slice(),
+            &[0x87],
+        ]
+        .concat(),
+        0x02 | 0x03 => [
+            &[0x21, size as u8],
+            cursor.take(32, "public key")?.as_slice(),
+            &[0xac],
+        ]
+        .concat(),
+        0x04 | 0x05 => {
+            let x = cursor.take(32, "public key")?;
+            [
+                &[0x41],
+                decompress_key(size as u8 - 2, &x)?.as_slice(),
+                &[0xac],
+            ]
+            .concat()
+        }
+        size => {
+            let length = size - SPECIAL_SCRIPTS;
+            let Ok(length) = u32::try_from(length) else {
+                bail!("failed to decompress a script claiming to be {length} bytes long")
+            };
+            cursor.take(length, "script")?
+        }
+    };
+    Ok(script)
+}
+
+/// Whether the uncompressed `key` is a point on the curve.
+#[cfg(feature = "secp256k1")]
+fn is_on_curve(key: &[u8]) -> bool {
+    secp256k1::PublicKey::from_slice(key).is_ok()
+}
+
+#[cfg(not(feature = "secp256k1"))]
+fn is_on_curve(_key: &[u8]) -> bool {
+    false
+}
+
+/// The uncompressed form of the key with x coordinate `x` and the parity of `prefix`.
+#[cfg(feature = "secp256k1")]
+fn decompress_key(prefix: u8, x: &[u8]) -> Result<[u8; 65]> {
+    let key = secp256k1::PublicKey::from_slice(&[&[prefix], x].concat())?;
+    Ok(key.serialize_uncompressed())
+}
+
+#[cfg(not(feature = "secp256k1"))]
+fn decompress_key(_prefix: u8, _x: &[u8]) -> Result<[u8; 65]> {
+    bail!("failed to decompress an uncompressed public key, which needs the secp256k1 feature")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        compress_amount, decompress_amount, decompress_script, read, read_varint, varint, write,
+    };
+    use crate::{
+        chain::{
+            utxo::{Coin, UtxoSet},
+            BlockLink, Chain,
+        },
+        util::{
+            fixtures::{block, BLOCK_1_HEX, GENESIS_BLOCK_HEX},
+            tx::TxOut,
+            types::{BlockLocation, ByteCursor},
+        },
+        Network,
+    };
+
+    /// Checks Core's compression of amounts and its varints, that a snapshot of the first two
+    /// blocks is the one Core writes and that with each compressed script type it survives a
+    /// round trip, while varints and amounts past 64 bits, scripts past 32 bits of length and
+    /// output indexes past 32 bits fail.
+    #[test]
+    fn test_txoutset() {
+        for (amount, compressed) in [
+            (0, 0x0),
+            (1, 0x1),
+            (1_000_000, 0x7),
+            (100_000_000, 0x9),
+            (5_000_000_000, 0x32),
+            (2_100_000_000_000_000, 0x1406f40),
+            (123_456_789, 0x423a35bd),
+        ] {
+            assert_eq!(compress_amount(amount), compressed);
+            assert_eq!(decompress_amount(compressed).unwrap(), amount);
+        }
+        assert!(decompress_amount(u64::MAX).is_err());
+        assert!(decompress_script(&mut ByteCursor::new(varint(u64::MAX).as_slice())).is_err());
+        assert_eq!(varint(0x7f), [0x7f]);
+        assert_eq!(varint(0x80), [0x80, 0x00]);
+        assert_eq!(varint(0x4000), [0xff, 0x00]);
+        let overflowing = [&[0x80], [0xfe; 8].as_slice(), &[0xff, 0x00]].concat();
+        assert!(read_varint(&mut ByteCursor::new(overflowing.as_slice())).is_err());
+
+        let blocks = [GENESIS_BLOCK_HEX, BLOCK_1_HEX].map(block);
+        let chain = Chain::link(blocks.iter().map(|block| BlockLink {
+            hash: block.block_header().hash(),
+            previous_hash: block.block_header().previous_hash(),
+            location: BlockLocation { file: 0, offset: 0 },
+            work: block.block_header().work(),
+        }));
+        let mut utxos = UtxoSet::default();
+        for (height, mut block) in blocks.into_iter().enumerate() {
+            utxos.apply(height as u64, &mut block).unwrap();
+        }
+        let path = std::env::temp_dir().join("bbf-test-txoutset.dat");
+        write(&path, &utxos, Network::Mainnet).unwrap();
+        let snapshot = std::fs::read(&path).unwrap();
+        // Core's dumptxoutset at height 1: the block 1 coinbase alone, as the genesis
+        // coinbase is never spendable, paying 50 BTC from height 1 to an uncompressed key.
+        let core_prefix = [
+            "7574786fff",
+            "0200",
+            "f9beb4d9",
+            "4860eb18bf1b1620e37e9490fc8a427514416fd75159ab86688e9a8300000000",
+            "0100000000000000",
+            "982051fd1e4ba744bbbe680e1fee14677ba1a3c3540bf7b1cdb606e857233e0e",
+            "01",
+            "00",
+            "03",
+            "32",
+        ]
+        .concat();
+        assert_eq!(hex::encode(&snapshot[..core_prefix.len() / 2]), core_prefix);
+        #[cfg(feature = "secp256k1")]
+        assert_eq!(
+            hex::encode(&snapshot[core_prefix.len() / 2..]),
+            "0496b538e853519c726a2c91e61ec11600ae1390813a627c66fb8be7947be63c52"
+        );
+
+        let mut bad_vout = snapshot[..83].to_vec();
+        bad_vout.extend([1, 0xff, 0, 0, 0, 0, 1, 0, 0, 0]);
+        bad_vout.extend(&snapshot[85..]);
+        std::fs::write(&path, bad_vout).unwrap();
+        let error = read(&path, &chain).unwrap_err().to_string();
+        assert!(error.contains("output 4294967296"), "{error}");
+        for (vout, script) in [
+            "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac",
+            "a914748284390f9e263a4b766a75d0633c50426eb87587",
+            "2102aaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaaac",
+            "6a0b68656c6c6f20776f726c64",
+            "",
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let coin = Coin {
+                output: TxOut::new(vout as u64 * 1000, hex::decode(script).unwrap()),
+                height: 1,
+                coinbase: false,
+            };
+            utxos.insert(&[0x11; 32], vout as u32, coin);
+        }
+
+        write(&path, &utxos, Network::Mainnet).unwrap();
+        let (network, loaded) = read(&path, &chain).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(network, Network::Mainnet);
+        assert_eq!(loaded.tip(), utxos.tip());
+        assert_eq!(loaded.outputs, utxos.outputs);
+    }
+}
@@ -0,0 +1,301 @@
+//! The set of unspent transaction outputs, built by applying blocks in height order,
+//! so the output every input spends is known, for working out transaction fees and
+//! checking signatures.
+//!
+//! The set can be cached on disk between runs: the magic `BBFU`, a format version, the
+//! height and hash of the last block applied and the number of outputs, then for each
+//! output its 36 byte outpoint, the height it was created at, a byte set for coinbase
+//! outputs, its value, script length and script, all little endian.
+//! [txoutset](super::txoutset) reads and writes the snapshots Bitcoin Core's `dumptxoutset`
+//! makes instead.
+use crate::util::{error::ErrorKind, tx::TxOut, types::Block};
+use anyhow::{bail, Result};
+use std::{
+    collections::HashMap,
+    fs::File,
+    io::{BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+const MAGIC: &[u8; 4] = b"BBFU";
+const VERSION: u32 = 4;
+
+/// An unspent output, along with where it was created.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Coin {
+    pub output: TxOut,
+    /// The height of the block that created it.
+    pub height: u32,
+    /// Whether it was created by a coinbase, and so can't be spent for 100 blocks.
+    pub coinbase: bool,
+}
+
+/// Every output not yet spent as of the last block applied.
+#[derive(Debug, Default)]
+pub struct UtxoSet {
+    /// The outputs, keyed by the serialized outpoint that spends them.
+    pub(super) outputs: HashMap<[u8; 36], Coin>,
+    /// The height and hash of the last block applied.
+    pub(super) tip: Option<(u64, [u8; 32])>,
+}
+impl UtxoSet {
+    /// The height and hash of the last block applied, `None` before the genesis block.
+    pub fn tip(&self) -> Option<(u64, [u8; 32])> {
+        self.tip
+    }
+    /// The height of the next block to apply.
+    pub fn next_height(&self) -> u64 {
+        self.tip.map_or(0, |(height, _)| height + 1)
+    }
+    /// The number of unspent outputs.
+    pub fn len(&self) -> usize {
+        self.outputs.len()
+    }
+    pub fn is_empty(&self) -> bool {
+        self.outputs.is_empty()
+    }
+    /// Every unspent output, with the txid, in internal byte order, and output index of
+    /// the outpoint that spends it, in no particular order.
+    pub fn iter(&self) -> impl Iterator<Item = ([u8; 32], u32, &Coin)> {
+        self.outputs.iter().map(|(outpoint, coin)| {
+            let (txid, vout) = outpoint.split_at(32);
+            (
+                txid.try_into().expect("expected 32 bytes for txid"),
+                u32::from_le_bytes(vout.try_into().expect("expected 4 bytes for vout")),
+                coin,
+            )
+        })
+    }
+    /// The unspent outputs paying to `script_pubkey`, as [UtxoSet::iter] gives them.
+    pub fn paying<'a>(
+        &'a self,
+        script_pubkey: &'a [u8],
+    ) -> impl Iterator<Item = ([u8; 32], u32, &'a Coin)> {
+        self.iter()
+            .filter(move |(_, _, coin)| coin.output.script_pubkey_bytes() == script_pubkey)
+    }
+    /// Add an unspent output, spent by the `vout` output of `txid`.
+    pub(super) fn insert(&mut self, txid: &[u8; 32], vout: u32, coin: Coin) {
+        let mut outpoint = [0; 36];
+        outpoint[..32].copy_from_slice(txid);
+        outpoint[32..].copy_from_slice(&vout.to_le_bytes());
+        self.outputs.insert(outpoint, coin);
+    }
+    /// Spend the inputs and add the outputs of every transaction in `block`, the block at
+    /// `height`, recording the fee of each transaction and the block's total on it as it goes.
+    /// Returns the outputs each transaction spent, in the order of its inputs, and none for
+    /// the coinbase.
+    ///
+    /// Transactions can spend outputs from earlier in the same block, so they're applied in order.
+    /// As in Bitcoin Core, the genesis coinbase's output is never added, and nor are outputs
+    /// no input could ever spend.
+    pub fn apply(&mut self, height: u64, block: &mut Block) -> Result<Vec<Vec<TxOut>>> {
+        if height != self.next_height() {
+            bail!(
+                "failed to apply block {} at height {height}, the next expected is {}",
+                block.block_header().block_hash(),
+                self.next_height()
+            )
+        }
+        let mut spent = Vec::with_capacity(block.transactions().len());
+        for tx in block.transactions() {
+            let mut prevouts = Vec::with_capacity(tx.inputs().len());
+            if !tx.is_coinbase() {
+                for input in tx.inputs() {
+                    let outpoint: [u8; 36] = input
+                        .previous_output()
+                        .raw_bytes()
+                        .try_into()
+                        .expect("expected 36 bytes for outpoint");
+                    let coin = self.outputs.remove(&outpoint).ok_or_else(|| {
+                        ErrorKind::NotFound.error(format!(
+                            "failed to find output {} spent in block {}",
+                            input.previous_output(),
+                            block.block_header().block_hash()
+                        ))
+                    })?;
+                    prevouts.push(coin.output);
+                }
+            }
+            spent.push(prevouts);
+            if height == 0 {
+                continue;
+            }
+            let txid = tx.txid();
+            for (vout, output) in tx.outputs().iter().enumerate() {
+                if output.script().is_unspendable() {
+                    continue;
+                }
+                let coin = Coin {
+                    output: output.clone(),
+                    height: height as u32,
+                    coinbase: tx.is_coinbase(),
+                };
+                self.insert(&txid, vout as u32, coin);
+            }
+        }
+        block.set_spent(&spent);
+        self.tip = Some((height, block.block_header().hash()));
+        Ok(spent)
+    }
+}
+
+/// Write `utxos` to the cache file at `path`.
+pub fn save(path: &Path, utxos: &UtxoSet) -> Result<()> {
+    let Some((height, hash)) = utxos.tip else {
+        bail!("failed to save an empty utxo set to {}", path.display())
+    };
+    let file = File::create(path)
+        .map_err(|e| ErrorKind::Io.error(format!("failed to create {}: {e}", path.display())))?;
+    let mut writer = BufWriter::new(file);
+    writer.write_all(MAGIC)?;
+    writer.write_all(&VERSION.to_le_bytes())?;
+    writer.write_all(&height.to_le_bytes())?;
+    writer.write_all(&hash)?;
+    writer.write_all(&(utxos.outputs.len() as u64).to_le_bytes())?;
+    for (outpoint, coin) in &utxos.outputs {
+        writer.write_all(outpoint)?;
+        writer.write_all(&coin.height.to_le_bytes())?;
+        writer.write_all(&[coin.coinbase as u8])?;
+        writer.write_all(&coin.output.value().to_le_bytes())?;
+        let script = coin.output.script_pubkey_bytes();
+        writer.write_all(&(script.len() as u32).to_le_bytes())?;
+        writer.write_all(script)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// Read the cache file at `path`.
+pub fn load(path: &Path) -> Result<UtxoSet> {
+    let file = File::open(path)
+        .map_err(|e| ErrorKind::Io.error(format!("failed to open {}: {e}", path.display())))?;
+    let mut reader = BufReader::new(file);
+    let mut take = |n: usize| -> Result<Vec<u8>> {
+        let mut bytes = vec![0; n];
+        reader
+            .read_exact(&mut bytes)
+            .map_err(|e| ErrorKind::Io.error(format!("failed to read {}: {e}", path.display())))?;
+        Ok(bytes)
+    };
+
+    if take(4)? != MAGIC {
+        bail!("{} is not a utxo set", path.display())
+    }
+    let version = u32::from_le_bytes(take(4)?.try_into().expect("expected 4 bytes for version"));
+    if version != VERSION {
+        bail!(
+            "unsupported utxo set version {version} in {}",
+            path.display()
+        )
+    }
+    let height = u64::from_le_bytes(take(8)?.try_into().expect("expected 8 bytes for height"));
+    let hash = take(32)?.try_into().expect("expected 32 bytes for hash");
+    let count = u64::from_le_bytes(take(8)?.try_into().expect("expected 8 bytes for count"));
+    let outputs = (0..count)
+        .map(|_| {
+            let outpoint = take(36)?
+                .try_into()
+                .expect("expected 36 bytes for outpoint");
+            let height =
+                u32::from_le_bytes(take(4)?.try_into().expect("expected 4 bytes for height"));
+            let coinbase = take(1)?[0] != 0;
+            let value =
+                u64::from_le_bytes(take(8)?.try_into().expect("expected 8 bytes for value"));
+            let script_len = u32::from_le_bytes(
+                take(4)?
+                    .try_into()
+                    .expect("expected 4 bytes for script length"),
+            );
+            let output = TxOut::new(value, take(script_len as usize)?);
+            Ok((
+                outpoint,
+                Coin {
+                    output,
+                    height,
+                    coinbase,
+                },
+            ))
+        })
+        .collect::<Result<HashMap<_, _>>>()?;
+
+    Ok(UtxoSet {
+        outputs,
+        tip: Some((height, hash)),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{load, save, UtxoSet};
+    use crate::util::{
+        fixtures::{block, BLOCK_1_HEX, GENESIS_BLOCK_HEX},
+        types::{Block, ByteCursor},
+    };
+
+    /// Checks that blocks apply in height order, filling in the fees and value flow of each,
+    /// that the genesis coinbase and unspendable outputs are left out, and that the set
+    /// survives a round trip to disk.
+    #[test]
+    fn test_utxo_set() {
+        let (mut genesis, mut block_1) = (block(GENESIS_BLOCK_HEX), block(BLOCK_1_HEX));
+
+        let mut utxos = UtxoSet::default();
+        assert!(utxos.apply(1, &mut block_1).is_err());
+        utxos.apply(0, &mut genesis).unwrap();
+        assert!(utxos.is_empty());
+        assert_eq!(utxos.apply(1, &mut block_1).unwrap(), [[]]);
+        assert_eq!(utxos.len(), 1);
+        assert_eq!(utxos.next_height(), 2);
+        assert_eq!(block_1.total_fees(), Some(0));
+        assert_eq!(block_1.transactions()[0].fee(), None);
+
+        // A block spending the block 1 coinbase, leaving 10,000 sats of its 50 BTC as the fee,
+        // to OP_TRUE and to a script too long to ever run.
+        let raw_bytes = [
+            &hex::decode(BLOCK_1_HEX).unwrap()[..80],
+            &[1],
+            &1u32.to_le_bytes(),
+            &[1],
+            &block_1.transactions()[0].txid(),
+            &0u32.to_le_bytes(),
+            &[0],
+            &u32::MAX.to_le_bytes(),
+            &[2],
+            &4_999_990_000u64.to_le_bytes(),
+            &[1, 0x51],
+            &0u64.to_le_bytes(),
+            &[0xfd, 0x11, 0x27],
+            &[0x51; 10_001],
+            &0u32.to_le_bytes(),
+        ]
+        .concat();
+        let mut spend = Block::from_raw_bytes(&mut ByteCursor::new(raw_bytes.as_slice())).unwrap();
+        let spent = utxos.apply(2, &mut spend).unwrap();
+        assert_eq!(spent[0][0], block_1.transactions()[0].outputs()[0]);
+        assert_eq!(spend.transactions()[0].fee(), Some(10_000));
+        assert_eq!(spend.total_fees(), Some(10_000));
+        let flow = spend.value_flow().unwrap();
+        assert_eq!(
+            (flow.input_value, flow.output_value, flow.fees),
+            (5_000_000_000, 4_999_990_000, 10_000)
+        );
+        assert_eq!((flow.coins_spent, flow.coins_created), (1, 1));
+        assert_eq!(genesis.value_flow().unwrap().input_value, 0);
+        assert_eq!(utxos.len(), 1);
+        assert!(utxos.apply(3, &mut spend).is_err());
+        let genesis_script = genesis.transactions()[0].outputs()[0].script_pubkey_bytes();
+        assert_eq!(utxos.paying(genesis_script).count(), 0);
+        let paying = utxos.paying(&[0x51]).collect::<Vec<_>>();
+        assert_eq!(paying.len(), 1);
+        assert_eq!(paying[0].0, spend.transactions()[0].txid());
+        assert!(!paying[0].2.coinbase && paying[0].2.height == 2);
+
+        let path = std::env::temp_dir().join("bbf-test-utxo-set.utxo");
+        save(&path, &utxos).unwrap();
+        let loaded = load(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.tip(), utxos.tip());
+        assert_eq!(loaded.outputs, utxos.outputs);
+    }
+}
@@ -1,14 +1,673 @@
-use clap::{arg, command, Parser};
+use crate::{command, progress::Progress};
+use anyhow::Result;
+use bitcoin_block_finder::{
+    chain::{index, BlockLink},
+    reader::FileChain,
+    util::{
+        constant::Network,
+        display::{parse_timestamp, ColorChoice},
+        field::BlockField,
+        hash::from_display_hex,
+        top::Ranking,
+        Format,
+    },
+    Block, BlockFileReader, BlockInfo, Chain,
+};
+use clap::{ArgGroup, Parser, Subcommand};
+use std::{io::IsTerminal, path::PathBuf};
+use tracing::level_filters::LevelFilter;
 
 #[derive(Debug, Parser)]
 #[command(
     name = "bitcoin-block-finder",
     about = "A bitcoin block parser API that returns a block at a given height",
-    version
+    version,
+    subcommand_negates_reqs = true,
+    group(
+        ArgGroup::new("query")
+            .required(true)
+            .multiple(true)
+            .args(["block_at_height", "block_hash", "from_height", "to_height", "at_time"])
+    )
 )]
 pub struct Context {
+    #[command(subcommand)]
+    pub command: Option<Command>,
     /// The height of a block to search for.
-    /// Must be a non-negative integer.
-    #[arg(long, short = 'b')]
-    pub block_at_height: u64,
+    /// Must be a non-negative integer. Repeat it or give a comma separated list for several.
+    #[arg(
+        long,
+        short = 'b',
+        value_delimiter = ',',
+        conflicts_with_all = ["from_height", "to_height", "at_time"]
+    )]
+    pub block_at_height: Vec<u64>,
+    /// The hash of a block to search for, in the usual big endian hex form.
+    /// Repeat it or give a comma separated list for several.
+    #[arg(
+        long,
+        visible_alias = "hash",
+        value_parser = from_display_hex,
+        value_delimiter = ',',
+        conflicts_with_all = ["from_height", "to_height", "at_time"]
+    )]
+    pub block_hash: Vec<[u8; 32]>,
+    /// Find the blocks given by --block-at-height by the height their coinbase commits to
+    /// (BIP34) rather than by linking the chain, reading only each block's header and
+    /// coinbase. Only heights from when BIP34 was enforced, block 227,931 on mainnet, are
+    /// trusted, the rest are found by linking the chain as usual. A stale block at the same
+    /// height can be found in place of the one on the best chain.
+    #[arg(
+        long,
+        requires = "block_at_height",
+        conflicts_with_all = ["block_hash", "with_fees", "undo", "context_blocks"]
+    )]
+    pub trust_bip34: bool,
+    /// The first height of a range of blocks to print.
+    /// Defaults to the genesis block when only --to-height is given.
+    #[arg(long)]
+    pub from_height: Option<u64>,
+    /// The last height of a range of blocks to print, inclusive.
+    /// Defaults to the chain tip when only --from-height is given.
+    #[arg(long)]
+    pub to_height: Option<u64>,
+    /// Find the last block timestamped at or before this moment, the chain tip as of then.
+    /// Given as unix seconds or an RFC 3339 datetime such as 2017-08-24T00:00:00Z.
+    #[arg(long, value_parser = parse_timestamp, conflicts_with_all = ["from_height", "to_height"])]
+    pub at_time: Option<u32>,
+    /// Compare --at-time to each block's median time past, the median of its last 11
+    /// timestamps that lock times are judged by, rather than its own timestamp.
+    #[arg(long, requires = "at_time")]
+    pub median_time_past: bool,
+    /// Print each block found as its full serialization in hex, ready for
+    /// submitblock or another decoder, instead of describing it.
+    #[arg(long)]
+    pub raw: bool,
+    /// With --format ndjson, write a line for each transaction of the blocks found
+    /// instead of one per block.
+    #[arg(long, conflicts_with = "raw")]
+    pub per_tx: bool,
+    /// Follow the block found with the headers of the N blocks either side of it, to see
+    /// where it sits and navigate from it. Not for ranges, and only in text output.
+    #[arg(
+        long = "context",
+        value_name = "N",
+        conflicts_with_all = ["from_height", "to_height", "raw", "per_tx"]
+    )]
+    pub context_blocks: Option<u64>,
+    /// Print only these fields of each block found, tab separated on a line of its own,
+    /// for shell pipelines: height, hash, prev_hash, merkle_root, time, version, bits,
+    /// difficulty, nonce, tx_count, size, weight or fees. Only in text output.
+    #[arg(
+        long,
+        value_delimiter = ',',
+        conflicts_with_all = ["raw", "per_tx", "context_blocks"]
+    )]
+    pub fields: Option<Vec<BlockField>>,
+    /// Write the hex from --raw to this file rather than stdout, one block per line.
+    #[arg(long, requires = "raw")]
+    pub out: Option<PathBuf>,
+    /// Once the range has been printed, keep watching the blk files and print each new
+    /// block that extends the chain as a running node writes it, until interrupted.
+    #[arg(long, requires = "from_height", conflicts_with = "to_height")]
+    pub watch: bool,
+    /// Once the range has been printed, subscribe to the rawblock notifications of a node
+    /// run with -zmqpubrawblock at this address and print each block it connects, until
+    /// interrupted.
+    #[cfg(feature = "zmq")]
+    #[arg(
+        long,
+        value_name = "ADDRESS",
+        requires = "from_height",
+        conflicts_with_all = ["to_height", "watch", "with_fees", "undo"]
+    )]
+    pub zmq: Option<String>,
+    /// With --zmq, add each block the node connects to the block index too, once it's
+    /// found in the blk files.
+    #[cfg(feature = "zmq")]
+    #[arg(long, requires = "zmq")]
+    pub zmq_index: bool,
+    /// Work out the fee of every transaction and the total for each block, along with the
+    /// value and coins each block spends and creates, by building the set of unspent outputs
+    /// from the genesis block up. The set is cached alongside the block index, so later runs
+    /// only apply the blocks since.
+    #[arg(long, conflicts_with_all = ["raw", "watch"])]
+    pub with_fees: bool,
+    /// Work out fees as --with-fees does, and show the output each input spends, from the
+    /// undo data Bitcoin Core keeps in the rev*.dat files beside the blk files rather than
+    /// by building the utxo set.
+    #[arg(long, conflicts_with_all = ["raw", "watch", "with_fees"])]
+    pub undo: bool,
+    /// Check the signature of every input spending a P2PK, P2PKH, P2WPKH, P2SH wrapped P2WPKH
+    /// or taproot key path output, failing on the first that doesn't verify. The outputs
+    /// spent are read from the rev files with --undo, fetched with getrawtransaction when
+    /// --rpc-url is given, which needs a node with -txindex, and otherwise found by building
+    /// the utxo set as --with-fees does.
+    #[cfg(feature = "secp256k1")]
+    #[arg(long, conflicts_with_all = ["raw", "watch", "trust_bip34"])]
+    pub verify_signatures: bool,
+    /// The directory containing the blk*.dat files.
+    /// Files are read in order starting from blk00000.dat.
+    #[arg(long, global = true, default_value = ".")]
+    pub datadir: PathBuf,
+    /// The path to a single blk*.dat file, or a directory of them, or - to read a stream
+    /// of blk records from stdin. Takes the place of --datadir when given.
+    #[arg(long, global = true, short = 'p', conflicts_with = "datadir")]
+    pub path: Option<PathBuf>,
+    /// The network the blk files belong to: mainnet, testnet3, testnet4, signet or regtest.
+    /// Detected from the first block when omitted.
+    #[arg(long, global = true)]
+    pub network: Option<Network>,
+    /// When to color text output: auto, always or never. Auto colors only a terminal,
+    /// and json and csv are never colored.
+    #[arg(long, global = true, default_value = "auto")]
+    pub color: ColorChoice,
+    /// The output format: text or json, csv for the export, nonces and hashrate, hex for
+    /// export-headers, or ndjson, a line of json per block, when finding blocks, for export
+    /// and for nonces.
+    #[arg(long, global = true, default_value = "text")]
+    pub format: Format,
+    /// How a failure is reported on stderr: text, or json with its message, causes, kind and
+    /// exit code. Failures exit with 3 when something asked for isn't found, 4 for a corrupt
+    /// file, 5 for blocks of the wrong network, 6 when reading or writing fails and 1 otherwise.
+    #[arg(long, global = true, default_value = "text")]
+    pub error_format: Format,
+    /// Validate the block found before printing it, failing if its hash doesn't meet its
+    /// own difficulty target, its transactions don't match its merkle root or their witness
+    /// data doesn't match the coinbase's witness commitment.
+    #[arg(long, global = true)]
+    pub verify: bool,
+    /// Describe more of each block, repeat for more detail: -v adds the transaction count,
+    /// sizes and coinbase, -vv every transaction and -vvv the raw hex of each field.
+    /// Only affects text output, json always includes everything.
+    #[arg(long, short = 'v', global = true, action = clap::ArgAction::Count)]
+    pub verbose: u8,
+    /// Show block timestamps in the local timezone as well as UTC.
+    #[arg(long, global = true)]
+    pub local_time: bool,
+    /// Show sizes in KB or MB, amounts in BTC as well as sats, and difficulty and hashrate
+    /// with metric prefixes. The default for text written to a terminal.
+    #[arg(long, global = true, overrides_with = "no_human")]
+    pub human: bool,
+    /// Show only the raw numbers in text output, the default when it isn't written to a terminal.
+    #[arg(long, global = true, overrides_with = "human")]
+    pub no_human: bool,
+    /// Name the pool that mined each block, from the markers known pools leave in their
+    /// coinbase. Only affects text output, json always includes it with the coinbase.
+    #[arg(long, global = true)]
+    pub show_miner: bool,
+    /// Don't draw a progress bar or print scan statistics while reading the blk files.
+    #[arg(long, short = 'q', global = true)]
+    pub quiet: bool,
+    /// The most detailed diagnostics to print to stderr: off, error, warn, info, debug or trace.
+    /// Debug traces every field as it's parsed. Defaults to info, or warn with --quiet.
+    #[arg(long, global = true)]
+    pub log_level: Option<LevelFilter>,
+    /// Parse blk files on this many threads at once, one file per thread,
+    /// or 0 for one thread per core. Files are read one block at a time when omitted.
+    #[arg(long, global = true)]
+    pub threads: Option<usize>,
+    /// Link the chain from block headers alone when scanning the blk files, as when building
+    /// the index or finding a block by hash, seeking past each block's transactions rather
+    /// than parsing them. Much faster, but a corrupt transaction goes unnoticed.
+    #[arg(long, global = true)]
+    pub headers_only: bool,
+    /// Memory map the blk files instead of reading them through a buffer,
+    /// leaving the page cache to manage memory across large datadirs.
+    #[arg(long, global = true)]
+    pub mmap: bool,
+    /// Where to keep the block index written by the index subcommand.
+    /// Defaults to bitcoin-block-finder.idx alongside the blk files.
+    #[arg(long, global = true)]
+    pub index_path: Option<PathBuf>,
+    /// Rescan the blk files rather than trusting the block index, rewriting it afterwards.
+    #[arg(long, global = true)]
+    pub reindex: bool,
+    /// Neither read nor write the cache of recently printed blocks, kept under
+    /// ~/.cache/bitcoin-block-finder, which prints a block asked for again by --block-hash
+    /// without linking the chain or reading the blk files.
+    #[arg(long, global = true)]
+    pub no_cache: bool,
+    /// Resolve heights from the LevelDB block index in the index directory next to the
+    /// blk files, as kept by Bitcoin Core, instead of linking the blocks. The node must be stopped.
+    #[cfg(feature = "leveldb")]
+    #[arg(long, global = true, conflicts_with = "reindex")]
+    pub use_core_index: bool,
+    /// The URL of a Bitcoin Core RPC server, such as http://127.0.0.1:8332, to fetch
+    /// blocks from with getblock when they aren't in the blk files.
+    #[cfg(feature = "rpc")]
+    #[arg(long, global = true)]
+    pub rpc_url: Option<String>,
+    /// The rpcuser to authenticate to the RPC server as.
+    #[cfg(feature = "rpc")]
+    #[arg(long, global = true, requires = "rpc_url")]
+    pub rpc_user: Option<String>,
+    /// The rpcpassword to authenticate to the RPC server with.
+    #[cfg(feature = "rpc")]
+    #[arg(long, global = true, requires = "rpc_user")]
+    pub rpc_pass: Option<String>,
+}
+impl Context {
+    /// Open the blk files selected by --path or --datadir.
+    pub fn block_files(&self) -> Result<BlockFileReader> {
+        let files = FileChain::open(self.path.clone().unwrap_or_else(|| self.datadir.clone()))?;
+        Ok(BlockFileReader::new(
+            files.with_mmap(self.mmap),
+            self.network,
+        ))
+    }
+    /// A client for the RPC server given by --rpc-url, if there is one.
+    #[cfg(feature = "rpc")]
+    pub fn rpc(&self) -> Option<bitcoin_block_finder::rpc::RpcClient> {
+        let url = self.rpc_url.as_ref()?;
+        Some(bitcoin_block_finder::rpc::RpcClient::new(
+            url,
+            self.rpc_user.as_deref(),
+            self.rpc_pass.as_deref(),
+        ))
+    }
+    /// The index file selected by --index-path, or the default for the blk files.
+    pub fn index_path(&self) -> PathBuf {
+        if let Some(index_path) = &self.index_path {
+            return index_path.clone();
+        }
+        match &self.path {
+            Some(path) if path.is_file() => path.with_extension("idx"),
+            Some(path) => path.join("bitcoin-block-finder.idx"),
+            None => self.datadir.join("bitcoin-block-finder.idx"),
+        }
+    }
+    /// The utxo set cache kept by --with-fees, beside the block index.
+    pub fn utxo_path(&self) -> PathBuf {
+        self.index_path().with_extension("utxo")
+    }
+    /// Whether blocks are printed past the range as a node adds them, with --watch or --zmq.
+    pub fn follows(&self) -> bool {
+        #[cfg(feature = "zmq")]
+        if self.zmq.is_some() {
+            return true;
+        }
+        self.watch
+    }
+    /// Whether the utxo set needs building, for --with-fees or for the outputs
+    /// --verify-signatures checks against when there's no --undo or RPC server to find them.
+    pub fn needs_utxos(&self) -> bool {
+        #[cfg(feature = "secp256k1")]
+        if self.verify_signatures && !self.undo && !command::signatures::from_rpc(self) {
+            return true;
+        }
+        self.with_fees
+    }
+    /// Whether the blocks asked for can be read on their own, straight from where the block
+    /// index puts them, with nothing needing the rest of the chain: no fees, signatures to
+    /// check or surrounding blocks, and not another index in place of this tool's.
+    pub fn reads_single_blocks(&self) -> bool {
+        #[cfg(feature = "secp256k1")]
+        if self.verify_signatures {
+            return false;
+        }
+        #[cfg(feature = "leveldb")]
+        if self.use_core_index {
+            return false;
+        }
+        !self.needs_utxos() && !self.undo && self.context_blocks.is_none()
+    }
+    /// The best chain through `blocks`, from the block index when it's up to date
+    /// and otherwise by scanning every block, saving the index again with --reindex.
+    pub fn chain(&self, blocks: &mut BlockFileReader) -> Result<Chain> {
+        #[cfg(feature = "leveldb")]
+        if self.use_core_index {
+            let blocks_dir = self.path.as_ref().unwrap_or(&self.datadir);
+            return bitcoin_block_finder::chain::core_index::load(&blocks_dir.join("index"));
+        }
+        let index_path = self.index_path();
+        if self.reindex {
+            return command::index::build(self, blocks);
+        }
+        if index_path.is_file() {
+            let index = index::load(&index_path)?;
+            if !index.is_stale(blocks.total_size()?) {
+                return Ok(index.chain);
+            }
+            tracing::warn!(
+                "{} is out of date, scanning the blk files instead (use --reindex to update it)",
+                index_path.display()
+            );
+        }
+        Ok(command::index::link(&self.links(blocks)?))
+    }
+    /// Read every block's link into the chain, from just its header with --headers-only.
+    pub fn links(&self, blocks: &mut BlockFileReader) -> Result<Vec<BlockLink>> {
+        let links = match self.headers_only {
+            true => {
+                let progress = self.scan(blocks)?;
+                match self.threads {
+                    Some(_) => progress.par_map_headers(BlockLink::from_header)?,
+                    None => progress.map_headers(BlockLink::from_header)?,
+                }
+            }
+            false => self.scan_map(blocks, BlockLink::from_block)?,
+        };
+        links.into_iter().collect()
+    }
+    /// Whether text written to stdout is colored, never for json or csv.
+    pub fn stdout_color(&self) -> bool {
+        self.format == Format::Text && self.color.enabled(std::io::stdout().is_terminal())
+    }
+    /// Whether text output shows human units, from --human, --no-human and whether stdout
+    /// is a terminal. JSON and CSV always carry the raw numbers.
+    pub fn human(&self) -> bool {
+        match (self.human, self.no_human) {
+            (true, _) => true,
+            (_, true) => false,
+            _ => self.format == Format::Text && std::io::stdout().is_terminal(),
+        }
+    }
+    /// The level diagnostics are filtered to, from --log-level and --quiet.
+    pub fn log_level(&self) -> LevelFilter {
+        self.log_level.unwrap_or(match self.quiet {
+            true => LevelFilter::WARN,
+            false => LevelFilter::INFO,
+        })
+    }
+    /// Iterate the blocks of `blocks`, reporting progress unless --quiet is given.
+    pub fn scan<'a>(&self, blocks: &'a mut BlockFileReader) -> Result<Progress<'a>> {
+        Progress::new(blocks, self.quiet)
+    }
+    /// Call `f` on every block of `blocks`, in file order, across threads when --threads is given.
+    pub fn scan_map<T: Send>(
+        &self,
+        blocks: &mut BlockFileReader,
+        f: impl Fn(&BlockInfo, &Block) -> T + Sync,
+    ) -> Result<Vec<T>> {
+        let progress = self.scan(blocks)?;
+        match self.threads {
+            Some(_) => progress.par_map(f),
+            None => progress
+                .map(|block| block.map(|(block_info, block)| f(&block_info, &block)))
+                .collect(),
+        }
+    }
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Find a transaction by its txid, printing it along with the block that contains it.
+    FindTx {
+        /// The txid to search for, in the usual big endian hex form.
+        #[arg(value_parser = from_display_hex)]
+        txid: [u8; 32],
+    },
+    /// Find every output paying to an address, printing the block, txid, output index and value of each.
+    FindAddress {
+        /// A base58check (legacy or p2sh) or bech32 (segwit) address.
+        address: String,
+    },
+    /// List every transaction on the best chain paying to or spending from a script, by its
+    /// Electrum script hash, as an Electrum server's blockchain.scripthash.get_history does.
+    History {
+        /// The SHA256 of the output script, reversed as Electrum shows it.
+        #[arg(value_parser = from_display_hex)]
+        script_hash: [u8; 32],
+    },
+    /// Report the balance of an address and list its unspent outputs, from the utxo set
+    /// cached by the utxo build subcommand, --with-fees or --verify-signatures.
+    Balance {
+        /// A base58check (legacy or p2sh) or bech32 (segwit) address.
+        address: String,
+    },
+    /// Scan every blk file once and write the block index, so later lookups can seek
+    /// straight to a block. Rebuilds the index if one already exists.
+    Index,
+    /// Write a summary row for each block in a height range, as a table, json or csv
+    /// with --format.
+    Export {
+        /// The first height to export. Defaults to the genesis block.
+        #[arg(long = "from", alias = "from-height")]
+        from_height: Option<u64>,
+        /// The last height to export, inclusive. Defaults to the chain tip.
+        #[arg(long = "to", alias = "to-height")]
+        to_height: Option<u64>,
+    },
+    /// Write the raw 80 byte header of each block in a height range, concatenated for
+    /// header sync tools and SPV clients, or one per line in hex with --format hex.
+    ExportHeaders {
+        /// The first height to export. Defaults to the genesis block.
+        #[arg(long = "from", alias = "from-height")]
+        from_height: Option<u64>,
+        /// The last height to export, inclusive. Defaults to the chain tip.
+        #[arg(long = "to", alias = "to-height")]
+        to_height: Option<u64>,
+        /// The file to write the headers to, rather than stdout.
+        #[arg(long)]
+        out: Option<PathBuf>,
+    },
+    /// Write the blocks in a height range and their transactions as Parquet tables,
+    /// blocks.parquet and transactions.parquet, for Spark, DuckDB and the like.
+    #[cfg(feature = "parquet")]
+    ExportParquet {
+        /// The first height to export. Defaults to the genesis block.
+        #[arg(long = "from", alias = "from-height")]
+        from_height: Option<u64>,
+        /// The last height to export, inclusive. Defaults to the chain tip.
+        #[arg(long = "to", alias = "to-height")]
+        to_height: Option<u64>,
+        /// The directory to write the tables to, created if it doesn't exist.
+        #[arg(long)]
+        out_dir: PathBuf,
+        /// Fill in the fee columns, building the utxo set as --with-fees does.
+        #[arg(long)]
+        with_fees: bool,
+    },
+    /// Aggregate a height range: block sizes, transaction counts, the time between blocks,
+    /// fees and segwit adoption, as a summary or json with --format.
+    Stats {
+        /// The first height to include. Defaults to the genesis block.
+        #[arg(long, alias = "from-height")]
+        from: Option<u64>,
+        /// The last height to include. Defaults to the chain tip.
+        #[arg(long, alias = "to-height")]
+        to: Option<u64>,
+        /// Work out the total fees too, building the utxo set as --with-fees does.
+        #[arg(long)]
+        with_fees: bool,
+    },
+    /// List the biggest blocks and transactions of a height range, with their heights and
+    /// hashes, read in a single pass.
+    Top {
+        /// What to rank by: size, txcount or fee. Transactions are only ranked by size or
+        /// fee, and ranking by fee builds the utxo set as --with-fees does, or reads the
+        /// undo data with --undo.
+        #[arg(long, default_value = "size")]
+        by: Ranking,
+        /// How many blocks and transactions to list.
+        #[arg(long, default_value_t = 10)]
+        limit: usize,
+        /// List the smallest instead of the biggest.
+        #[arg(long)]
+        smallest: bool,
+        /// The first height to include. Defaults to the genesis block.
+        #[arg(long, alias = "from-height")]
+        from: Option<u64>,
+        /// The last height to include. Defaults to the chain tip.
+        #[arg(long, alias = "to-height")]
+        to: Option<u64>,
+    },
+    /// Count the blocks of a height range each known pool mined, naming it from the markers
+    /// in the coinbase as --show-miner does.
+    Miners {
+        /// The first height to include. Defaults to the genesis block.
+        #[arg(long, alias = "from-height")]
+        from: Option<u64>,
+        /// The last height to include. Defaults to the chain tip.
+        #[arg(long, alias = "to-height")]
+        to: Option<u64>,
+    },
+    /// Count and list the blocks of a height range holding only their coinbase, for
+    /// studying empty block mining.
+    EmptyBlocks {
+        /// The first height to include. Defaults to the genesis block.
+        #[arg(long, alias = "from-height")]
+        from: Option<u64>,
+        /// The last height to include. Defaults to the chain tip.
+        #[arg(long, alias = "to-height")]
+        to: Option<u64>,
+        /// Group the empty blocks by the miner tag in their coinbase.
+        #[arg(long)]
+        by_miner: bool,
+    },
+    /// Report how the header nonces and coinbase extraNonces of a height range are
+    /// distributed, or each block's as csv or ndjson, for studying early mining patterns.
+    Nonces {
+        /// The first height to include. Defaults to the genesis block.
+        #[arg(long, alias = "from-height")]
+        from: Option<u64>,
+        /// The last height to include. Defaults to the chain tip.
+        #[arg(long, alias = "to-height")]
+        to: Option<u64>,
+    },
+    /// Check every record of every blk file: its magic bytes, that its declared size matches
+    /// the block it holds and that the block's hash meets its target. Reports each corrupt
+    /// region with its file and byte offsets, failing if there are any.
+    VerifyFiles,
+    /// Walk the difficulty adjustments every 2016 blocks, comparing how long each period
+    /// took with the two weeks expected and showing the change in difficulty that followed.
+    Retargets,
+    /// Check the header timestamps of a height range, reporting blocks dated before their
+    /// parent or its median time past, far ahead of their neighbours, or in a time warp
+    /// pattern at the start of a difficulty period.
+    Lint {
+        /// The first height to check. Defaults to the genesis block.
+        #[arg(long, alias = "from-height")]
+        from: Option<u64>,
+        /// The last height to check. Defaults to the chain tip.
+        #[arg(long, alias = "to-height")]
+        to: Option<u64>,
+    },
+    /// List the longest and shortest intervals between consecutive blocks of a height range,
+    /// going by their header timestamps.
+    Intervals {
+        /// The first height to measure, from its parent. Defaults to the first block after
+        /// the genesis block.
+        #[arg(long, alias = "from-height")]
+        from: Option<u64>,
+        /// The last height to measure. Defaults to the chain tip.
+        #[arg(long, alias = "to-height")]
+        to: Option<u64>,
+        /// How many of the longest and of the shortest intervals to list.
+        #[arg(long, default_value_t = 10)]
+        count: usize,
+    },
+    /// Estimate the network hashrate over a sliding window of blocks ending at each height
+    /// of a range, from the work their difficulty calls for and how long they took, as a
+    /// table or, with `--format csv`, for charting.
+    Hashrate {
+        /// The number of blocks each estimate is made over.
+        #[arg(long, default_value_t = 144)]
+        window: u64,
+        /// The first height to estimate at. Defaults to the genesis block.
+        #[arg(long, alias = "from-height")]
+        from: Option<u64>,
+        /// The last height to estimate at. Defaults to the chain tip.
+        #[arg(long, alias = "to-height")]
+        to: Option<u64>,
+    },
+    /// Check the transaction outputs of a height range, reporting dust below Bitcoin Core's
+    /// default threshold, scripts matching no standard template and value burned in an
+    /// OP_RETURN.
+    LintTx {
+        /// The first height to check. Defaults to the genesis block.
+        #[arg(long, alias = "from-height")]
+        from: Option<u64>,
+        /// The last height to check. Defaults to the chain tip.
+        #[arg(long, alias = "to-height")]
+        to: Option<u64>,
+    },
+    /// List the blocks in the blk files that aren't on the best chain, stale blocks that
+    /// lost out to another at the same height and orphans whose parent is missing.
+    Orphans,
+    /// Decode a hex encoded block, header or transaction, as a node's RPC or a block
+    /// explorer gives them, printing it as if it had been found in the blk files.
+    Decode {
+        /// The hex to decode, or - to read it from stdin, which is also where it's read
+        /// from when neither this nor --file is given.
+        #[arg(conflicts_with = "file")]
+        hex: Option<String>,
+        /// A file holding the hex to decode.
+        #[arg(long)]
+        file: Option<PathBuf>,
+    },
+    /// Disassemble a hex encoded script into its opcodes and pushes, along with its template
+    /// and address or the signatures and keys it pushes.
+    DecodeScript {
+        /// The script to decode.
+        hex: String,
+    },
+    /// Preview the block a getblocktemplate result proposes, its fees, sigops and weight,
+    /// and report anything amiss with it, such as a coinbase value other than the subsidy
+    /// and fees.
+    Template {
+        /// A file holding the template as JSON, either the result or the whole RPC
+        /// response, read from stdin when not given or -.
+        file: Option<PathBuf>,
+    },
+    /// Build the set of unspent outputs, as --with-fees and --verify-signatures do, or
+    /// import one from a Bitcoin Core snapshot.
+    Utxo {
+        #[command(subcommand)]
+        command: UtxoCommand,
+    },
+    /// Answer HTTP requests for blocks and transactions with JSON, as --format json prints
+    /// them: GET /block/height/{height}, /block/hash/{hash} or /tx/{txid}. Each /tx request
+    /// scans every blk file, as find-tx does.
+    Serve {
+        /// The address to listen on, as host:port.
+        #[arg(long, default_value = "127.0.0.1:8339")]
+        listen: String,
+    },
+    /// Answer gRPC requests for blocks, by height or hash or streamed over a range of
+    /// heights, with the BlockFinder service.
+    #[cfg(feature = "grpc")]
+    ServeGrpc {
+        /// The address to listen on, as host:port.
+        #[arg(long, default_value = "127.0.0.1:50051")]
+        listen: std::net::SocketAddr,
+    },
+    /// Download a block from a peer over the P2P network rather than the blk files,
+    /// printing it as if it had been found in them.
+    #[cfg(feature = "p2p")]
+    Fetch {
+        /// The hash of the block to download, in the usual big endian hex form.
+        #[arg(value_parser = from_display_hex)]
+        hash: [u8; 32],
+        /// The peer to connect to, as host:port. The port defaults to the network's.
+        #[arg(long)]
+        peer: String,
+    },
+    /// Browse the best chain interactively: a list of the blocks beside the selected one's
+    /// header and transactions. Press / to jump to a height, block hash or txid.
+    #[cfg(feature = "tui")]
+    Tui,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum UtxoCommand {
+    /// Apply every block from the genesis block up to a height, caching the set alongside
+    /// the block index for later runs. A set cached already is built on rather than rebuilt.
+    Build {
+        /// The last height to apply. Defaults to the chain tip.
+        #[arg(long = "to", alias = "to-height")]
+        to_height: Option<u64>,
+        /// Also write the set to this file as a snapshot in the format of Bitcoin Core's
+        /// dumptxoutset, for loadtxoutset and other tools that read them.
+        #[arg(long)]
+        txoutset: Option<PathBuf>,
+    },
+    /// Replace the cached set with a snapshot written by Bitcoin Core's dumptxoutset, whose
+    /// last block must be on the chain in the blk files. Reading the uncompressed public keys
+    /// of early outputs needs the secp256k1 feature.
+    Import {
+        /// The snapshot to read.
+        file: PathBuf,
+    },
 }
@@ -0,0 +1,35 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{
+    chain::utxo,
+    util::{address, log_balance, UnspentOutput},
+    ErrorKind,
+};
+
+/// Sum the outputs in the cached utxo set paying to `address`.
+///
+/// Nothing is scanned, so the balance is as of the last block applied to the set rather
+/// than the chain tip.
+pub(crate) fn run(context: &Context, address: &str) -> Result<()> {
+    let script_pubkey = address::to_script_pubkey(address)?;
+    let path = context.utxo_path();
+    if !path.is_file() {
+        return Err(ErrorKind::NotFound.error(format!(
+            "failed to find a utxo set at {}, build one with the utxo build subcommand",
+            path.display()
+        )));
+    }
+    let utxos = utxo::load(&path)?;
+    let (height, _) = utxos.tip().expect("expected a tip on a saved utxo set");
+    let mut outputs = utxos
+        .paying(&script_pubkey)
+        .map(|(txid, vout, coin)| UnspentOutput {
+            txid,
+            vout,
+            value: coin.output.value(),
+            height: coin.height,
+        })
+        .collect::<Vec<_>>();
+    outputs.sort_by_key(|output| (output.height, output.txid, output.vout));
+    log_balance(address, height, &outputs, context.format)
+}
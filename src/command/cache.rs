@@ -0,0 +1,129 @@
+//! A cache of the blocks printed recently, so asking for one again by its hash skips
+//! linking the chain and reading the blk files.
+//!
+//! Each block is kept in its own file named by its hash: the magic `BBFC`, a format
+//! version, the block's height and the hash of the next block on the best chain, then its
+//! blk record. Only the most recently written [MAX_ENTRIES] are kept.
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{
+    chain::{Neighbour, Neighbours},
+    reader::FileChain,
+    util::hash::to_display_hex,
+    Block, BlockFileReader, BlockInfo, BlockLocation, ErrorKind,
+};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"BBFC";
+const VERSION: u32 = 1;
+/// The size of the header before the record: the magic, version, height and next hash.
+const HEADER: usize = 48;
+/// The number of blocks kept, the oldest removed once there are more.
+const MAX_ENTRIES: usize = 100;
+
+/// The directory the cache is kept in, under `$XDG_CACHE_HOME` or else `~/.cache`.
+pub(crate) fn dir() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CACHE_HOME").filter(|dir| !dir.is_empty()) {
+        Some(dir) => PathBuf::from(dir),
+        None => PathBuf::from(std::env::var_os("HOME")?).join(".cache"),
+    };
+    Some(base.join("bitcoin-block-finder"))
+}
+
+fn entry_path(dir: &Path, hash: &[u8; 32]) -> PathBuf {
+    dir.join(format!("{}.blk", to_display_hex(hash)))
+}
+
+/// The block with `hash` if it's been cached, along with its info as it was printed.
+pub(crate) fn get(context: &Context, hash: &[u8; 32]) -> Result<Option<(BlockInfo, Block)>> {
+    let Some(dir) = dir() else {
+        return Ok(None);
+    };
+    let path = entry_path(&dir, hash);
+    let Ok(bytes) = std::fs::read(&path) else {
+        return Ok(None);
+    };
+    if bytes.len() < HEADER || &bytes[..4] != MAGIC || bytes[4..8] != VERSION.to_le_bytes() {
+        tracing::warn!("{} is not a cached block, ignoring it", path.display());
+        return Ok(None);
+    }
+    let height = u64::from_le_bytes(
+        bytes[8..16]
+            .try_into()
+            .expect("expected 8 bytes for height"),
+    );
+    let next: [u8; 32] = bytes[16..HEADER]
+        .try_into()
+        .expect("expected 32 bytes for hash");
+    let files = FileChain::from_bytes(&path, bytes[HEADER..].to_vec());
+    let (block_info, block) = BlockFileReader::new(files, context.network)
+        .read_at(BlockLocation { file: 0, offset: 0 }, height)?;
+    if block.block_header().hash() != *hash {
+        tracing::warn!("{} holds another block, ignoring it", path.display());
+        return Ok(None);
+    }
+    let neighbours = Neighbours {
+        previous: height.checked_sub(1).map(|previous| Neighbour {
+            height: previous,
+            hash: block.block_header().previous_hash(),
+        }),
+        next: Some(Neighbour {
+            height: height + 1,
+            hash: next,
+        }),
+    };
+    Ok(Some((block_info.with_neighbours(neighbours), block)))
+}
+
+/// Cache `block`, removing the oldest blocks cached past [MAX_ENTRIES].
+///
+/// Only blocks on the best chain with one built on them are cached, since whether a block
+/// at the tip has a next one can change as soon as another is mined.
+pub(crate) fn put(block_info: &BlockInfo, block: &Block) -> Result<()> {
+    let Some(next) = block_info
+        .neighbours()
+        .and_then(|neighbours| neighbours.next)
+    else {
+        return Ok(());
+    };
+    let Some(dir) = dir() else {
+        return Ok(());
+    };
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| ErrorKind::Io.error(format!("failed to create {}: {e}", dir.display())))?;
+    let raw_bytes = block.raw_bytes();
+    let entry = [
+        MAGIC.as_slice(),
+        &VERSION.to_le_bytes(),
+        &block_info.height.to_le_bytes(),
+        &next.hash,
+        block_info.magic_bytes(),
+        &(raw_bytes.len() as u32).to_le_bytes(),
+        &raw_bytes,
+    ]
+    .concat();
+    let path = entry_path(&dir, &block.block_header().hash());
+    std::fs::write(&path, entry)
+        .map_err(|e| ErrorKind::Io.error(format!("failed to write {}: {e}", path.display())))?;
+    prune(&dir)
+}
+
+/// Remove the oldest entries in `dir` until only [MAX_ENTRIES] are left.
+fn prune(dir: &Path) -> Result<()> {
+    let mut entries = std::fs::read_dir(dir)
+        .map_err(|e| ErrorKind::Io.error(format!("failed to read {}: {e}", dir.display())))?
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "blk"))
+        .filter_map(|entry| Some((entry.metadata().ok()?.modified().ok()?, entry.path())))
+        .collect::<Vec<_>>();
+    if entries.len() <= MAX_ENTRIES {
+        return Ok(());
+    }
+    entries.sort();
+    for (_, path) in &entries[..entries.len() - MAX_ENTRIES] {
+        std::fs::remove_file(path).map_err(|e| {
+            ErrorKind::Io.error(format!("failed to remove {}: {e}", path.display()))
+        })?;
+    }
+    Ok(())
+}
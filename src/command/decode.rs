@@ -0,0 +1,38 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{
+    reader::STDIN_PATH,
+    util::{decode::Decoded, log_decoded},
+    ErrorKind,
+};
+use std::{io::Read, path::Path};
+
+/// Decode the `hex` given, or the contents of `file`, or else stdin, checking a block's
+/// or header's proof of work with --verify.
+pub(crate) fn run(context: &Context, hex: Option<&str>, file: Option<&Path>) -> Result<()> {
+    let hex = match (hex, file) {
+        (Some(hex), _) if hex != STDIN_PATH => hex.to_string(),
+        (_, Some(file)) => std::fs::read_to_string(file)
+            .map_err(|e| ErrorKind::Io.error(format!("failed to read {}: {e}", file.display())))?,
+        _ => {
+            let mut hex = String::new();
+            std::io::stdin()
+                .read_to_string(&mut hex)
+                .map_err(|e| ErrorKind::Io.error(format!("failed to read stdin: {e}")))?;
+            hex
+        }
+    };
+    let decoded = Decoded::from_hex(&hex)?;
+    if context.verify {
+        match &decoded {
+            Decoded::Block(block) => {
+                block.block_header().validate_pow()?;
+                block.verify_merkle_root()?;
+                block.verify_witness_commitment()?;
+            }
+            Decoded::Header(header) => header.validate_pow()?,
+            Decoded::Transaction(_) => {}
+        }
+    }
+    log_decoded(&decoded, context.format)
+}
@@ -0,0 +1,10 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::util::{log_script, script::Script};
+
+/// Disassemble the script given in `hex`.
+pub(crate) fn run(context: &Context, hex: &str) -> Result<()> {
+    let bytes =
+        hex::decode(hex.trim()).map_err(|e| anyhow::anyhow!("failed to decode hex: {e}"))?;
+    log_script(Script::new(&bytes), context.format)
+}
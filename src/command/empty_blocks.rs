@@ -0,0 +1,32 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{
+    util::{empty::EmptyBlockCollector, log_empty_blocks},
+    ErrorKind,
+};
+
+/// List the empty blocks from height `from` to `to`, or the chain tip when omitted, grouped
+/// by miner tag when `by_miner`.
+pub(crate) fn run(context: &Context, from: u64, to: Option<u64>, by_miner: bool) -> Result<()> {
+    let mut blocks = context.block_files()?;
+    let chain = context.chain(&mut blocks)?;
+    let tip = (chain.len() as u64)
+        .checked_sub(1)
+        .ok_or_else(|| anyhow::anyhow!("failed to find empty blocks, the chain is empty"))?;
+    let to = to.unwrap_or(tip);
+    if from > to || to > tip {
+        anyhow::bail!(
+            "failed to find empty blocks between heights {from} and {to}, the chain tip is at {tip}"
+        )
+    }
+
+    let mut collector = EmptyBlockCollector::default();
+    for height in from..=to {
+        let entry = chain.get(height).ok_or_else(|| {
+            ErrorKind::NotFound.error(format!("failed to find block at height {height}"))
+        })?;
+        let (_, block) = blocks.read_at(entry.location, height)?;
+        collector.add(height, &block);
+    }
+    log_empty_blocks(&collector.finish(from, to, by_miner), context.format)
+}
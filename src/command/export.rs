@@ -0,0 +1,35 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{
+    util::{display::set_network, log_block_summaries, BlockSummary, Format},
+    ErrorKind,
+};
+
+/// Export a summary of each block from height `from` to `to`, or the chain tip when omitted.
+/// Summaries are gathered to print together, except as ndjson where each is printed once read.
+pub(crate) fn run(context: &Context, from: u64, to: Option<u64>) -> Result<()> {
+    let mut blocks = context.block_files()?;
+    let chain = context.chain(&mut blocks)?;
+    let tip = (chain.len() as u64)
+        .checked_sub(1)
+        .ok_or_else(|| anyhow::anyhow!("failed to export blocks, the chain is empty"))?;
+    let to = to.unwrap_or(tip);
+    if from > to {
+        anyhow::bail!("failed to export blocks between heights {from} and {to}, the range is empty")
+    }
+
+    let mut summaries = Vec::new();
+    for height in from..=to {
+        let entry = chain.get(height).ok_or_else(|| {
+            ErrorKind::NotFound.error(format!("failed to find block at height {height}"))
+        })?;
+        let (block_info, block) = blocks.read_at(entry.location, height)?;
+        set_network(block_info.network()?);
+        let summary = BlockSummary::new(height, &block);
+        match context.format {
+            Format::Ndjson => log_block_summaries(&[summary], context.format)?,
+            _ => summaries.push(summary),
+        }
+    }
+    log_block_summaries(&summaries, context.format)
+}
@@ -0,0 +1,57 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{util::Format, ErrorKind};
+use std::{
+    fs::File,
+    io::{BufWriter, IsTerminal, Write},
+    path::Path,
+};
+
+/// Write the 80 byte header of each block from height `from` to `to`, or the chain tip when
+/// omitted, to `out` or stdout. Headers are concatenated as header sync tools expect, or
+/// written one per line in hex with `--format hex`.
+pub(crate) fn run(context: &Context, from: u64, to: Option<u64>, out: Option<&Path>) -> Result<()> {
+    let hex = match context.format {
+        Format::Text => false,
+        Format::Hex => true,
+        Format::Json | Format::Csv | Format::Ndjson => anyhow::bail!(
+            "failed to export headers, only the binary default and --format hex are supported"
+        ),
+    };
+    if !hex && out.is_none() && std::io::stdout().is_terminal() {
+        anyhow::bail!(
+            "failed to export headers, refusing to write binary to a terminal, pass --out or --format hex"
+        )
+    }
+
+    let mut blocks = context.block_files()?;
+    let chain = context.chain(&mut blocks)?;
+    let tip = (chain.len() as u64)
+        .checked_sub(1)
+        .ok_or_else(|| anyhow::anyhow!("failed to export headers, the chain is empty"))?;
+    let to = to.unwrap_or(tip);
+    if from > to {
+        anyhow::bail!(
+            "failed to export headers between heights {from} and {to}, the range is empty"
+        )
+    }
+
+    let mut writer: BufWriter<Box<dyn Write>> = BufWriter::new(match out {
+        Some(path) => Box::new(File::create(path).map_err(|e| {
+            ErrorKind::Io.error(format!("failed to create {}: {e}", path.display()))
+        })?),
+        None => Box::new(std::io::stdout()),
+    });
+    for height in from..=to {
+        let entry = chain.get(height).ok_or_else(|| {
+            ErrorKind::NotFound.error(format!("failed to find block at height {height}"))
+        })?;
+        let raw_bytes = blocks.read_header_at(entry.location)?.raw_bytes();
+        match hex {
+            true => writeln!(writer, "{}", hex::encode(raw_bytes))?,
+            false => writer.write_all(&raw_bytes)?,
+        }
+    }
+    writer.flush()?;
+    Ok(())
+}
@@ -0,0 +1,48 @@
+use crate::{cli::Context, command};
+use anyhow::Result;
+use bitcoin_block_finder::{chain::utxo, parquet::ParquetWriter, ErrorKind};
+use std::path::Path;
+
+/// Write the blocks from height `from` to `to`, or the chain tip when omitted, and their
+/// transactions as Parquet tables in `out_dir`, with fees when `with_fees`.
+pub(crate) fn run(
+    context: &Context,
+    from: u64,
+    to: Option<u64>,
+    out_dir: &Path,
+    with_fees: bool,
+) -> Result<()> {
+    let mut blocks = context.block_files()?;
+    let chain = context.chain(&mut blocks)?;
+    let tip = (chain.len() as u64)
+        .checked_sub(1)
+        .ok_or_else(|| anyhow::anyhow!("failed to export blocks, the chain is empty"))?;
+    let to = to.unwrap_or(tip);
+    if from > to {
+        anyhow::bail!("failed to export blocks between heights {from} and {to}, the range is empty")
+    }
+    std::fs::create_dir_all(out_dir)
+        .map_err(|e| ErrorKind::Io.error(format!("failed to create {}: {e}", out_dir.display())))?;
+
+    let mut utxos = match with_fees {
+        true => Some(command::fees::load(context, &chain)?),
+        false => None,
+    };
+    let mut writer = ParquetWriter::create(out_dir)?;
+    for height in from..=to {
+        let entry = chain.get(height).ok_or_else(|| {
+            ErrorKind::NotFound.error(format!("failed to find block at height {height}"))
+        })?;
+        let (_, mut block) = blocks.read_at(entry.location, height)?;
+        if let Some(utxos) = utxos.as_mut() {
+            command::fees::apply(context, &mut blocks, &chain, utxos, height, &mut block)?;
+        }
+        writer.add(height, &block)?;
+    }
+    if let Some(utxos) = &utxos {
+        utxo::save(&context.utxo_path(), utxos)?;
+    }
+    writer.finish()?;
+    tracing::info!("Wrote blocks {from} to {to} to {}", out_dir.display());
+    Ok(())
+}
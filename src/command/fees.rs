@@ -0,0 +1,80 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{
+    chain::utxo::{self, UtxoSet},
+    util::tx::TxOut,
+    Block, BlockFileReader, Chain, ErrorKind,
+};
+use indicatif::{ProgressBar, ProgressStyle};
+
+/// The utxo set cached by an earlier run with --with-fees, if it's still on `chain`,
+/// otherwise an empty one to build from the genesis block.
+pub(crate) fn load(context: &Context, chain: &Chain) -> Result<UtxoSet> {
+    let path = context.utxo_path();
+    if !path.is_file() {
+        return Ok(UtxoSet::default());
+    }
+    // A set cached in an older format is rebuilt rather than refused.
+    let utxos = match utxo::load(&path) {
+        Ok(utxos) => utxos,
+        Err(e) => {
+            tracing::warn!("{e}, rebuilding it");
+            return Ok(UtxoSet::default());
+        }
+    };
+    match utxos.tip() {
+        Some((height, hash)) if chain.get(height).is_some_and(|entry| entry.hash == hash) => {
+            Ok(utxos)
+        }
+        _ => {
+            tracing::warn!(
+                "The utxo set in {} is no longer on the best chain, rebuilding it",
+                path.display()
+            );
+            Ok(UtxoSet::default())
+        }
+    }
+}
+
+/// Apply every block before `height` that `utxos` hasn't seen yet, then `block` itself,
+/// the block at `height`, filling in its fees and returning the outputs it spent.
+///
+/// A set already past `height` can't be unwound, so it's built again from the genesis block.
+pub(crate) fn apply(
+    context: &Context,
+    blocks: &mut BlockFileReader,
+    chain: &Chain,
+    utxos: &mut UtxoSet,
+    height: u64,
+    block: &mut Block,
+) -> Result<Vec<Vec<TxOut>>> {
+    if utxos.next_height() > height {
+        *utxos = UtxoSet::default();
+    }
+    let behind = height - utxos.next_height();
+    let bar = (!context.quiet && behind > 0).then(|| {
+        let bar = ProgressBar::new(behind);
+        bar.set_style(
+            ProgressStyle::with_template(
+                "[{elapsed_precise}] {wide_bar} {pos}/{len} blocks applied to the utxo set ({eta})",
+            )
+            .expect("expected a valid template")
+            .progress_chars("=> "),
+        );
+        bar
+    });
+    for previous in utxos.next_height()..height {
+        let entry = chain.get(previous).ok_or_else(|| {
+            ErrorKind::NotFound.error(format!("failed to find block at height {previous}"))
+        })?;
+        let (_, mut previous_block) = blocks.read_at(entry.location, previous)?;
+        utxos.apply(previous, &mut previous_block)?;
+        if let Some(bar) = &bar {
+            bar.inc(1);
+        }
+    }
+    if let Some(bar) = bar {
+        bar.finish_and_clear();
+    }
+    utxos.apply(height, block)
+}
@@ -0,0 +1,25 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{
+    p2p::Peer,
+    util::{display::set_network, log},
+    Network,
+};
+use std::net::ToSocketAddrs;
+
+/// Download the block with `hash` from `peer`, on the network given by --network or mainnet.
+pub(crate) fn run(context: &Context, hash: [u8; 32], peer: &str) -> Result<()> {
+    let network = context.network.unwrap_or(Network::Mainnet);
+    let mut peer = match peer.to_socket_addrs() {
+        Ok(address) => Peer::connect(address.as_slice(), network)?,
+        Err(_) => Peer::connect((peer, network.default_port()), network)?,
+    };
+    let (block_info, block) = peer.fetch(&hash)?;
+    set_network(network);
+    if context.verify {
+        block.block_header().validate_pow()?;
+        block.verify_merkle_root()?;
+        block.verify_witness_commitment()?;
+    }
+    log(block_info, block, context.format)
+}
@@ -0,0 +1,51 @@
+use crate::{cli::Context, command};
+use anyhow::Result;
+use bitcoin_block_finder::{
+    chain::BlockLink,
+    util::{address, log_address_outputs, AddressOutput},
+    ErrorKind,
+};
+
+/// Scan every block for outputs paying to `address`.
+///
+/// As with [find_tx](super::find_tx), the chain is linked in the same pass
+/// so each output's block height is known once the scan finishes.
+pub(crate) fn run(context: &Context, address: &str) -> Result<()> {
+    let script_pubkey = address::to_script_pubkey(address)?;
+    let mut blocks = context.block_files()?;
+    let scanned = context.scan_map(&mut blocks, |block_info, block| {
+        let block_hash = block.block_header().hash();
+        let mut found = Vec::new();
+        for tx in block.transactions() {
+            for (vout, output) in tx.outputs().iter().enumerate() {
+                if output.script_pubkey_bytes() == script_pubkey {
+                    found.push(AddressOutput {
+                        block_height: None,
+                        block_hash,
+                        txid: tx.txid(),
+                        vout: vout as u32,
+                        value: output.value(),
+                    });
+                }
+            }
+        }
+        BlockLink::from_block(block_info, block).map(|link| (link, found))
+    })?;
+    let scanned = scanned.into_iter().collect::<Result<Vec<_>>>()?;
+    let links = scanned.iter().map(|(link, _)| *link).collect::<Vec<_>>();
+    let chain = command::index::link(&links);
+    let mut found = scanned
+        .into_iter()
+        .flat_map(|(_, found)| found)
+        .collect::<Vec<_>>();
+
+    if found.is_empty() {
+        return Err(ErrorKind::NotFound.error(format!("failed to find outputs paying {address}")));
+    }
+    for output in &mut found {
+        output.block_height = chain.height_of(&output.block_hash);
+    }
+    // Blocks are read in file order, so put the outputs back in chain order.
+    found.sort_by_key(|output| output.block_height.unwrap_or(u64::MAX));
+    log_address_outputs(address, &found, context.format)
+}
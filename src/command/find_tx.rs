@@ -0,0 +1,37 @@
+use crate::{cli::Context, command};
+use anyhow::Result;
+use bitcoin_block_finder::{
+    chain::BlockLink,
+    util::{display::set_network, hash::to_display_hex, log_transaction},
+    ErrorKind,
+};
+
+/// Scan every block for the transaction with `txid`.
+///
+/// The chain is linked in the same pass, so the height of the
+/// containing block is known without reading the files twice.
+pub(crate) fn run(context: &Context, txid: [u8; 32]) -> Result<()> {
+    let mut blocks = context.block_files()?;
+    let scanned = context.scan_map(&mut blocks, |block_info, block| {
+        let index = block.transactions().iter().position(|tx| tx.txid() == txid);
+        BlockLink::from_block(block_info, block).map(|link| (link, index))
+    })?;
+    let scanned = scanned.into_iter().collect::<Result<Vec<_>>>()?;
+    let found = scanned
+        .iter()
+        .find_map(|(link, index)| index.map(|index| (link.location, index)));
+    let links = scanned.iter().map(|(link, _)| *link).collect::<Vec<_>>();
+    let chain = command::index::link(&links);
+
+    let (location, index) = found.ok_or_else(|| {
+        ErrorKind::NotFound.error(format!(
+            "failed to find transaction {}",
+            to_display_hex(&txid)
+        ))
+    })?;
+    let (block_info, block) = blocks.read_at(location, 0)?;
+    set_network(block_info.network()?);
+    let block_hash = block.block_header().hash();
+    let tx = &block.transactions()[index];
+    log_transaction(chain.height_of(&block_hash), block_hash, tx, context.format)
+}
@@ -0,0 +1,39 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{chain::hashrate::estimate_hashrate, util::log_hashrate, ErrorKind};
+
+/// Estimate the hashrate over the `window` blocks ending at each height from `from` to `to`,
+/// or the chain tip when omitted.
+pub(crate) fn run(context: &Context, window: u64, from: u64, to: Option<u64>) -> Result<()> {
+    if window == 0 {
+        anyhow::bail!("failed to estimate the hashrate, the window must be at least one block")
+    }
+    let mut blocks = context.block_files()?;
+    let chain = context.chain(&mut blocks)?;
+    let tip = (chain.len() as u64)
+        .checked_sub(1)
+        .ok_or_else(|| anyhow::anyhow!("failed to estimate the hashrate, the chain is empty"))?;
+    let to = to.unwrap_or(tip);
+    if from > to || to > tip {
+        anyhow::bail!(
+            "failed to estimate the hashrate between heights {from} and {to}, the chain tip is at {tip}"
+        )
+    }
+
+    // Read the window before the range too, so its first height has an estimate.
+    let start = from.saturating_sub(window);
+    let headers = (start..=to)
+        .map(|height| {
+            let entry = chain.get(height).ok_or_else(|| {
+                ErrorKind::NotFound.error(format!("failed to find block at height {height}"))
+            })?;
+            let header = blocks.read_header_at(entry.location)?;
+            Ok((header.unix_epoch_time(), header.difficulty()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let estimates = estimate_hashrate(start, &headers, window as usize);
+    if estimates.is_empty() {
+        tracing::warn!("The range ends at height {to}, short of a whole window of {window} blocks");
+    }
+    log_hashrate(&estimates, window, context.format)
+}
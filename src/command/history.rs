@@ -0,0 +1,85 @@
+use crate::{cli::Context, command};
+use anyhow::Result;
+use bitcoin_block_finder::{
+    chain::BlockLink,
+    util::{
+        hash::{self, to_display_hex},
+        log_script_history, HistoryEntry,
+    },
+    ErrorKind,
+};
+use std::collections::{BTreeSet, HashSet};
+
+/// List every transaction on the best chain paying to or spending from an output whose
+/// script hashes to `script_hash`, as Electrum's `blockchain.scripthash.get_history` does.
+///
+/// The blocks are scanned twice: first for the outputs paying the script, linking the chain
+/// in the same pass, then for the inputs spending them, since the files needn't store a
+/// spend after the output it spends.
+pub(crate) fn run(context: &Context, script_hash: [u8; 32]) -> Result<()> {
+    let mut blocks = context.block_files()?;
+    let scanned = context.scan_map(&mut blocks, |block_info, block| {
+        let mut funding = Vec::new();
+        for (position, tx) in block.transactions().iter().enumerate() {
+            for (vout, output) in tx.outputs().iter().enumerate() {
+                if hash::script_hash(output.script_pubkey_bytes()) == script_hash {
+                    funding.push((position, tx.txid(), vout as u32));
+                }
+            }
+        }
+        BlockLink::from_block(block_info, block).map(|link| (link, funding))
+    })?;
+    let scanned = scanned.into_iter().collect::<Result<Vec<_>>>()?;
+    let links = scanned.iter().map(|(link, _)| *link).collect::<Vec<_>>();
+    let chain = command::index::link(&links);
+
+    let mut history = BTreeSet::new();
+    let mut outpoints = HashSet::new();
+    for (link, funding) in scanned {
+        // Outputs of stale blocks were never confirmed, so they have no history.
+        let Some(height) = chain.height_of(&link.hash) else {
+            continue;
+        };
+        for (position, txid, vout) in funding {
+            history.insert(HistoryEntry {
+                height,
+                position,
+                txid,
+            });
+            outpoints.insert([txid.as_slice(), &vout.to_le_bytes()].concat());
+        }
+    }
+    if outpoints.is_empty() {
+        return Err(ErrorKind::NotFound.error(format!(
+            "failed to find outputs paying script hash {}",
+            to_display_hex(&script_hash)
+        )));
+    }
+
+    let spending = context.scan_map(&mut context.block_files()?, |_, block| {
+        let spends = block
+            .transactions()
+            .iter()
+            .enumerate()
+            .filter(|(_, tx)| {
+                tx.inputs()
+                    .iter()
+                    .any(|input| outpoints.contains(&input.previous_output().raw_bytes()))
+            })
+            .map(|(position, tx)| (position, tx.txid()))
+            .collect::<Vec<_>>();
+        (block.block_header().hash(), spends)
+    })?;
+    for (hash, spends) in spending {
+        let Some(height) = chain.height_of(&hash) else {
+            continue;
+        };
+        history.extend(spends.into_iter().map(|(position, txid)| HistoryEntry {
+            height,
+            position,
+            txid,
+        }));
+    }
+    let history = history.into_iter().collect::<Vec<_>>();
+    log_script_history(&script_hash, &history, context.format)
+}
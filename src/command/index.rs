@@ -0,0 +1,73 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{
+    chain::{
+        index::{self, IndexFile},
+        BlockLink,
+    },
+    Block, BlockFileReader, BlockInfo, Chain,
+};
+
+/// Write the block index for the selected blk files.
+pub(crate) fn run(context: &Context) -> Result<()> {
+    let mut blocks = context.block_files()?;
+    let chain = build(context, &mut blocks)?;
+    println!(
+        "Indexed {} blocks to {}",
+        chain.len(),
+        context.index_path().display()
+    );
+    Ok(())
+}
+
+/// Link every block in `blocks` into the chain and save it as the block index.
+pub(crate) fn build(context: &Context, blocks: &mut BlockFileReader) -> Result<Chain> {
+    let chain = link(&context.links(blocks)?);
+    index::save(&context.index_path(), &chain, blocks.total_size()?)?;
+    Ok(chain)
+}
+
+/// Link the blocks scanned into the chain, warning of anything amiss in how they fit
+/// together with where each block involved is stored.
+pub(crate) fn link(links: &[BlockLink]) -> Chain {
+    let chain = Chain::link(links.iter().copied());
+    for warning in chain.link_warnings(links) {
+        tracing::warn!("{warning}");
+    }
+    chain
+}
+
+/// The blocks at `heights`, read by seeking straight to where the block index says each one
+/// is without loading the rest of it, or `None` for the chain to be loaded or linked instead
+/// when there's no up to date index, it doesn't reach a height or a block read doesn't match.
+pub(crate) fn lookup(
+    context: &Context,
+    blocks: &mut BlockFileReader,
+    heights: &[u64],
+) -> Result<Option<Vec<(BlockInfo, Block)>>> {
+    let path = context.index_path();
+    if context.reindex || !path.is_file() {
+        return Ok(None);
+    }
+    let mut index = IndexFile::open(&path)?;
+    if index.is_stale(blocks.total_size()?) {
+        return Ok(None);
+    }
+    let mut found = Vec::with_capacity(heights.len());
+    for &height in heights {
+        let Some(entry) = index.get(height)? else {
+            return Ok(None);
+        };
+        let (block_info, block) = blocks.read_at(entry.location, height)?;
+        if block.block_header().hash() != entry.hash {
+            tracing::warn!(
+                "{} doesn't match the blk files, scanning them instead (use --reindex to update it)",
+                path.display()
+            );
+            return Ok(None);
+        }
+        let neighbours = index.neighbours(height)?;
+        found.push((block_info.with_neighbours(neighbours), block));
+    }
+    Ok(Some(found))
+}
@@ -0,0 +1,38 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{
+    chain::interval::IntervalExtremes, util::log_interval_extremes, ErrorKind,
+};
+
+/// List the `count` longest and shortest block intervals from height `from` to `to`, or the
+/// chain tip when omitted.
+pub(crate) fn run(context: &Context, from: u64, to: Option<u64>, count: usize) -> Result<()> {
+    let mut blocks = context.block_files()?;
+    let chain = context.chain(&mut blocks)?;
+    let tip = (chain.len() as u64)
+        .checked_sub(1)
+        .ok_or_else(|| anyhow::anyhow!("failed to measure intervals, the chain is empty"))?;
+    let to = to.unwrap_or(tip);
+    // The genesis block has no parent to measure from.
+    let from = from.max(1);
+    if from > to || to > tip {
+        anyhow::bail!(
+            "failed to measure intervals between heights {from} and {to}, the chain tip is at {tip}"
+        )
+    }
+
+    // Read the parent of the first block too, which its interval is measured from.
+    let headers = (from - 1..=to)
+        .map(|height| {
+            let entry = chain.get(height).ok_or_else(|| {
+                ErrorKind::NotFound.error(format!("failed to find block at height {height}"))
+            })?;
+            let header = blocks.read_header_at(entry.location)?;
+            Ok((header.block_hash(), header.unix_epoch_time()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    log_interval_extremes(
+        &IntervalExtremes::new(from - 1, &headers, count),
+        context.format,
+    )
+}
@@ -0,0 +1,38 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{chain::lint::lint_timestamps, util::log_header_anomalies, ErrorKind};
+
+/// The blocks before a range whose timestamps its first blocks are judged against.
+const CONTEXT_BEFORE: u64 = 11;
+
+/// Report the suspicious header timestamps from height `from` to `to`, or the chain tip when omitted.
+pub(crate) fn run(context: &Context, from: u64, to: Option<u64>) -> Result<()> {
+    let mut blocks = context.block_files()?;
+    let chain = context.chain(&mut blocks)?;
+    let tip = (chain.len() as u64)
+        .checked_sub(1)
+        .ok_or_else(|| anyhow::anyhow!("failed to lint headers, the chain is empty"))?;
+    let to = to.unwrap_or(tip);
+    if from > to || to > tip {
+        anyhow::bail!(
+            "failed to lint headers between heights {from} and {to}, the chain tip is at {tip}"
+        )
+    }
+
+    // Read the blocks either side of the range too, so its ends are checked like the rest.
+    let start = from.saturating_sub(CONTEXT_BEFORE);
+    let headers = (start..=(to + 1).min(tip))
+        .map(|height| {
+            let entry = chain.get(height).ok_or_else(|| {
+                ErrorKind::NotFound.error(format!("failed to find block at height {height}"))
+            })?;
+            let header = blocks.read_header_at(entry.location)?;
+            Ok((entry.hash, header.unix_epoch_time()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let anomalies = lint_timestamps(start, &headers)
+        .into_iter()
+        .filter(|anomaly| (from..=to).contains(&anomaly.height))
+        .collect::<Vec<_>>();
+    log_header_anomalies(&anomalies, from, to, context.format)
+}
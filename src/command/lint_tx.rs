@@ -0,0 +1,31 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{
+    util::{lint_tx::lint_outputs, log_output_anomalies},
+    ErrorKind,
+};
+
+/// Report the unusual transaction outputs from height `from` to `to`, or the chain tip when omitted.
+pub(crate) fn run(context: &Context, from: u64, to: Option<u64>) -> Result<()> {
+    let mut blocks = context.block_files()?;
+    let chain = context.chain(&mut blocks)?;
+    let tip = (chain.len() as u64)
+        .checked_sub(1)
+        .ok_or_else(|| anyhow::anyhow!("failed to lint outputs, the chain is empty"))?;
+    let to = to.unwrap_or(tip);
+    if from > to || to > tip {
+        anyhow::bail!(
+            "failed to lint outputs between heights {from} and {to}, the chain tip is at {tip}"
+        )
+    }
+
+    let mut anomalies = Vec::new();
+    for height in from..=to {
+        let entry = chain.get(height).ok_or_else(|| {
+            ErrorKind::NotFound.error(format!("failed to find block at height {height}"))
+        })?;
+        let (_, block) = blocks.read_at(entry.location, height)?;
+        anomalies.extend(lint_outputs(height, &block));
+    }
+    log_output_anomalies(&anomalies, from, to, context.format)
+}
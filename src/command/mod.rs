@@ -0,0 +1,40 @@
+//! Implementations of the subcommands in [Command](crate::cli::Command).
+pub(crate) mod balance;
+pub(crate) mod cache;
+pub(crate) mod decode;
+pub(crate) mod decode_script;
+pub(crate) mod empty_blocks;
+pub(crate) mod export;
+pub(crate) mod export_headers;
+#[cfg(feature = "parquet")]
+pub(crate) mod export_parquet;
+pub(crate) mod fees;
+#[cfg(feature = "p2p")]
+pub(crate) mod fetch;
+pub(crate) mod find_address;
+pub(crate) mod find_tx;
+pub(crate) mod hashrate;
+pub(crate) mod history;
+pub(crate) mod index;
+pub(crate) mod intervals;
+pub(crate) mod lint;
+pub(crate) mod lint_tx;
+pub(crate) mod miners;
+pub(crate) mod nonces;
+pub(crate) mod orphans;
+pub(crate) mod retargets;
+pub(crate) mod serve;
+#[cfg(feature = "grpc")]
+pub(crate) mod serve_grpc;
+#[cfg(feature = "secp256k1")]
+pub(crate) mod signatures;
+pub(crate) mod stats;
+pub(crate) mod template;
+pub(crate) mod top;
+#[cfg(feature = "tui")]
+pub(crate) mod tui;
+pub(crate) mod utxo;
+pub(crate) mod verify_files;
+pub(crate) mod watch;
+#[cfg(feature = "zmq")]
+pub(crate) mod zmq;
@@ -0,0 +1,38 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{
+    util::{
+        log_nonce_stats,
+        nonce::{NonceCollector, NonceSample},
+    },
+    ErrorKind,
+};
+
+/// Report the nonces of the blocks from height `from` to `to`, or the chain tip when omitted.
+pub(crate) fn run(context: &Context, from: u64, to: Option<u64>) -> Result<()> {
+    let mut blocks = context.block_files()?;
+    let chain = context.chain(&mut blocks)?;
+    let tip = (chain.len() as u64)
+        .checked_sub(1)
+        .ok_or_else(|| anyhow::anyhow!("failed to gather nonces, the chain is empty"))?;
+    let to = to.unwrap_or(tip);
+    if from > to {
+        anyhow::bail!("failed to gather nonces between heights {from} and {to}, the range is empty")
+    }
+
+    let mut collector = NonceCollector::default();
+    let mut samples = Vec::new();
+    for height in from..=to {
+        let entry = chain.get(height).ok_or_else(|| {
+            ErrorKind::NotFound.error(format!("failed to find block at height {height}"))
+        })?;
+        let (_, block) = blocks.read_at(entry.location, height)?;
+        let sample = NonceSample::new(height, &block);
+        collector.add(&sample);
+        samples.push(sample);
+    }
+    let stats = collector
+        .finish(from, to)
+        .expect("expected at least one block in a non-empty range");
+    log_nonce_stats(&stats, &samples, context.format)
+}
@@ -0,0 +1,16 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{chain::BlockLink, util::log_stale_blocks, Chain};
+
+/// Link every block into the chain, then list those left off it.
+///
+/// The index only records the best chain, so the blk files are always scanned.
+pub(crate) fn run(context: &Context) -> Result<()> {
+    let mut blocks = context.block_files()?;
+    let links = context
+        .scan_map(&mut blocks, BlockLink::from_block)?
+        .into_iter()
+        .collect::<Result<Vec<_>>>()?;
+    let chain = Chain::link(links.iter().copied());
+    log_stale_blocks(&chain.stale(&links), context.format)
+}
@@ -0,0 +1,37 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{
+    chain::retarget::{retarget_heights, Retarget, RETARGET_INTERVAL},
+    util::{log_retargets, types::BlockHeader},
+    ErrorKind,
+};
+
+/// Print every difficulty adjustment on the chain, reading only the headers either side of each.
+pub(crate) fn run(context: &Context) -> Result<()> {
+    let mut blocks = context.block_files()?;
+    let chain = context.chain(&mut blocks)?;
+    let mut header = |height: u64| -> Result<BlockHeader> {
+        let entry = chain.get(height).ok_or_else(|| {
+            ErrorKind::NotFound.error(format!("failed to find block at height {height}"))
+        })?;
+        blocks.read_header_at(entry.location)
+    };
+
+    let retargets = retarget_heights(chain.len() as u64)
+        .map(|height| {
+            Ok(Retarget::new(
+                height,
+                &header(height - RETARGET_INTERVAL)?,
+                &header(height - 1)?,
+                &header(height)?,
+            ))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    if retargets.is_empty() {
+        tracing::warn!(
+            "The chain is {} blocks long, short of the first adjustment at height {RETARGET_INTERVAL}",
+            chain.len()
+        );
+    }
+    log_retargets(&retargets, context.format)
+}
@@ -0,0 +1,218 @@
+//! A local HTTP server answering with the blocks and transactions of the blk files as JSON,
+//! in the same shape `--format json` prints them.
+//!
+//! The chain is linked once on start, so blocks written to the files afterwards aren't
+//! served until it's restarted. Each connection is answered on a thread of its own and
+//! closed after the response, and has [TIMEOUT] to send a request line and headers of at
+//! most [MAX_HEAD] bytes, so a slow or oversized request only holds up its own.
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{
+    util::{
+        display::set_network,
+        hash::{from_display_hex, to_display_hex},
+    },
+    BlockFileReader, Chain, ErrorKind,
+};
+use serde_json::{json, Value};
+use std::{
+    io::{BufRead, BufReader, Read, Write},
+    net::{TcpListener, TcpStream},
+    time::Duration,
+};
+
+/// How long a connection may take to send its request, or to take the response.
+const TIMEOUT: Duration = Duration::from_secs(10);
+/// The most bytes read of a request's line and headers together.
+const MAX_HEAD: u64 = 8192;
+
+/// Serve the best chain through the selected blk files on `listen` until killed.
+pub(crate) fn run(context: &Context, listen: &str) -> Result<()> {
+    let mut blocks = context.block_files()?;
+    let chain = context.chain(&mut blocks)?;
+    let listener = TcpListener::bind(listen)
+        .map_err(|e| ErrorKind::Io.error(format!("failed to listen on {listen}: {e}")))?;
+    tracing::info!(
+        "Serving {} blocks on http://{}",
+        chain.len(),
+        listener.local_addr()?
+    );
+    std::thread::scope(|scope| {
+        for stream in listener.incoming() {
+            let stream = match stream {
+                Ok(stream) => stream,
+                Err(e) => {
+                    tracing::warn!("Failed to accept a connection: {e}");
+                    continue;
+                }
+            };
+            let mut blocks = blocks.reopen();
+            let chain = &chain;
+            scope.spawn(move || {
+                if let Err(e) = respond(stream, &mut blocks, chain) {
+                    tracing::warn!("Failed to answer a request: {e}");
+                }
+            });
+        }
+    });
+    Ok(())
+}
+
+/// Read the request on `stream` and write back its answer.
+fn respond(stream: TcpStream, blocks: &mut BlockFileReader, chain: &Chain) -> Result<()> {
+    stream.set_read_timeout(Some(TIMEOUT))?;
+    stream.set_write_timeout(Some(TIMEOUT))?;
+    let mut reader = BufReader::new((&stream).take(MAX_HEAD));
+    let mut request_line = String::new();
+    reader.read_line(&mut request_line)?;
+    // The headers are read so the client isn't cut off mid-request, but none are used.
+    let mut header = String::new();
+    while reader.read_line(&mut header)? > 2 {
+        header.clear();
+    }
+    let too_large = reader.get_ref().limit() == 0;
+    let mut parts = request_line.split_whitespace();
+    let (method, target) = (parts.next().unwrap_or(""), parts.next().unwrap_or(""));
+    let path = target.split('?').next().unwrap_or("").trim_end_matches('/');
+
+    let (status, body) = match method {
+        _ if too_large => (
+            431,
+            error_body(
+                &format!("the request line and headers are over {MAX_HEAD} bytes"),
+                "other",
+            ),
+        ),
+        "GET" => match route(path, blocks, chain) {
+            Ok(Some(body)) => (200, body),
+            Ok(None) => (
+                404,
+                error_body(&format!("no endpoint at {path}"), "not_found"),
+            ),
+            Err(e) => {
+                let kind = ErrorKind::of(&e);
+                let status = match kind {
+                    ErrorKind::NotFound => 404,
+                    _ if e.is::<BadRequest>() => 400,
+                    _ => 500,
+                };
+                (status, error_body(&format!("{e:#}"), kind.name()))
+            }
+        },
+        _ => (
+            405,
+            error_body(&format!("{method} isn't supported, only GET"), "other"),
+        ),
+    };
+    tracing::info!("{method} {target} {status}");
+
+    let body = serde_json::to_string_pretty(&body)?;
+    let mut stream = &stream;
+    write!(
+        stream,
+        "HTTP/1.1 {status} {}\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+        reason(status),
+        body.len(),
+    )?;
+    stream.flush()?;
+    Ok(())
+}
+
+/// A request naming a height, hash or txid that can't be parsed.
+#[derive(Debug)]
+struct BadRequest(String);
+impl std::fmt::Display for BadRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+impl std::error::Error for BadRequest {}
+
+/// The answer to a GET of `path`, or none if there's no endpoint there.
+fn route(path: &str, blocks: &mut BlockFileReader, chain: &Chain) -> Result<Option<Value>> {
+    let segments = path.trim_start_matches('/').split('/').collect::<Vec<_>>();
+    match segments.as_slice() {
+        ["block", "height", height] => {
+            let height = height
+                .parse::<u64>()
+                .map_err(|e| BadRequest(format!("invalid height {height}: {e}")))?;
+            block(height, blocks, chain)
+        }
+        ["block", "hash", hash] => {
+            let hash = parse_hash(hash)?;
+            let height = chain.height_of(&hash).ok_or_else(|| {
+                ErrorKind::NotFound.error(format!("failed to find block {}", to_display_hex(&hash)))
+            })?;
+            block(height, blocks, chain)
+        }
+        ["tx", txid] => transaction(parse_hash(txid)?, blocks, chain),
+        _ => return Ok(None),
+    }
+    .map(Some)
+}
+
+fn parse_hash(hex: &str) -> Result<[u8; 32]> {
+    from_display_hex(hex).map_err(|e| BadRequest(format!("invalid hash {hex}: {e}")).into())
+}
+
+/// The block at `height` on the best chain, as `--format json` prints it.
+fn block(height: u64, blocks: &mut BlockFileReader, chain: &Chain) -> Result<Value> {
+    let entry = chain
+        .get(height)
+        .ok_or_else(|| ErrorKind::NotFound.error(format!("failed to find block {height}")))?;
+    let (block_info, block) = blocks.read_at(entry.location, height)?;
+    set_network(block_info.network()?);
+    let block = block.with_height(height);
+    let block_info = block_info.with_neighbours(chain.neighbours(block.block_header()));
+    Ok(json!({ "block_info": block_info, "block": block }))
+}
+
+/// The transaction with `txid` and the block it's in, as `find-tx --format json` prints
+/// it, scanning every block for it.
+///
+/// There's no transaction index to look it up in, so each request reads every blk file
+/// on the rayon thread pool, taking as long as a full scan. Other connections are still
+/// answered meanwhile, each on its own thread, though sharing the pool's threads.
+fn transaction(txid: [u8; 32], blocks: &mut BlockFileReader, chain: &Chain) -> Result<Value> {
+    let found = blocks
+        .par_map(|block_info, block| {
+            let index = block
+                .transactions()
+                .iter()
+                .position(|tx| tx.txid() == txid)?;
+            Some((block_info.location()?, block.block_header().hash(), index))
+        })?
+        .into_iter()
+        .flatten()
+        .min_by_key(|(_, hash, _)| chain.height_of(hash).is_none());
+    let (location, block_hash, index) = found.ok_or_else(|| {
+        ErrorKind::NotFound.error(format!(
+            "failed to find transaction {}",
+            to_display_hex(&txid)
+        ))
+    })?;
+    let (block_info, block) = blocks.read_at(location, 0)?;
+    set_network(block_info.network()?);
+    Ok(json!({
+        "block_height": chain.height_of(&block_hash),
+        "block_hash": to_display_hex(&block_hash),
+        "txid": to_display_hex(&txid),
+        "transaction": &block.transactions()[index],
+    }))
+}
+
+/// The body of an error response, shaped like `--error-format json` writes errors.
+fn error_body(error: &str, kind: &str) -> Value {
+    json!({ "error": error, "kind": kind })
+}
+
+fn reason(status: u16) -> &'static str {
+    match status {
+        200 => "OK",
+        400 => "Bad Request",
+        404 => "Not Found",
+        405 => "Method Not Allowed",
+        431 => "Request Header Fields Too Large",
+        _ => "Internal Server Error",
+    }
+}
@@ -0,0 +1,20 @@
+//! Serving the blocks of the blk files over gRPC, as [BlockFinderService] answers them.
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{
+    grpc::{block_finder_server::BlockFinderServer, BlockFinderService},
+    ErrorKind,
+};
+use std::net::SocketAddr;
+use tonic::transport::Server;
+
+/// Serve the best chain through the selected blk files on `listen` until killed.
+pub(crate) fn run(context: &Context, listen: SocketAddr) -> Result<()> {
+    let mut blocks = context.block_files()?;
+    let chain = context.chain(&mut blocks)?;
+    tracing::info!("Serving {} blocks over gRPC on {listen}", chain.len());
+    let service = BlockFinderServer::new(BlockFinderService::new(blocks, chain));
+    tokio::runtime::Runtime::new()?
+        .block_on(Server::builder().add_service(service).serve(listen))
+        .map_err(|e| ErrorKind::Io.error(format!("failed to serve on {listen}: {e}")))
+}
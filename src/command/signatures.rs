@@ -0,0 +1,49 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{util::tx::TxOut, Block};
+
+/// Whether the outputs --verify-signatures checks against are fetched from the RPC server
+/// given by --rpc-url rather than found in the utxo set.
+#[cfg_attr(not(feature = "rpc"), allow(unused_variables))]
+pub(crate) fn from_rpc(context: &Context) -> bool {
+    #[cfg(feature = "rpc")]
+    return context.rpc_url.is_some();
+    #[cfg(not(feature = "rpc"))]
+    false
+}
+
+/// Check the signatures of `block`, the block at `height`, against `spent`, the outputs its
+/// transactions spend from the utxo set, or those fetched from the RPC server without it.
+#[cfg_attr(not(feature = "rpc"), allow(unused_variables))]
+pub(crate) fn verify(
+    context: &Context,
+    height: u64,
+    block: &Block,
+    spent: Option<Vec<Vec<TxOut>>>,
+) -> Result<()> {
+    let spent = match spent {
+        Some(spent) => spent,
+        #[cfg(feature = "rpc")]
+        None => {
+            let rpc = context
+                .rpc()
+                .ok_or_else(|| anyhow::anyhow!("failed to find the outputs spent, no --rpc-url"))?;
+            block
+                .transactions()
+                .iter()
+                .map(|transaction| rpc.prevouts(transaction))
+                .collect::<Result<Vec<_>>>()?
+        }
+        #[cfg(not(feature = "rpc"))]
+        None => anyhow::bail!("failed to find the outputs spent, the utxo set wasn't built"),
+    };
+    let counts = block
+        .verify_signatures(&spent)
+        .map_err(|e| anyhow::anyhow!("failed to verify block {height}: {e}"))?;
+    tracing::info!(
+        "Verified {} signatures in block {height}, skipped {} inputs spending other scripts",
+        counts.verified,
+        counts.skipped
+    );
+    Ok(())
+}
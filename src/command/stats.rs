@@ -0,0 +1,47 @@
+use crate::{cli::Context, command};
+use anyhow::Result;
+use bitcoin_block_finder::{
+    chain::utxo,
+    util::{display::set_network, log_block_stats, stats::StatsCollector},
+    ErrorKind,
+};
+
+/// Print statistics over the blocks from height `from` to `to`, or the chain tip when omitted,
+/// with their total fees when `with_fees`.
+pub(crate) fn run(context: &Context, from: u64, to: Option<u64>, with_fees: bool) -> Result<()> {
+    let mut blocks = context.block_files()?;
+    let chain = context.chain(&mut blocks)?;
+    let tip = (chain.len() as u64)
+        .checked_sub(1)
+        .ok_or_else(|| anyhow::anyhow!("failed to gather statistics, the chain is empty"))?;
+    let to = to.unwrap_or(tip);
+    if from > to {
+        anyhow::bail!(
+            "failed to gather statistics between heights {from} and {to}, the range is empty"
+        )
+    }
+
+    let mut utxos = match with_fees {
+        true => Some(command::fees::load(context, &chain)?),
+        false => None,
+    };
+    let mut collector = StatsCollector::default();
+    for height in from..=to {
+        let entry = chain.get(height).ok_or_else(|| {
+            ErrorKind::NotFound.error(format!("failed to find block at height {height}"))
+        })?;
+        let (block_info, mut block) = blocks.read_at(entry.location, height)?;
+        set_network(block_info.network()?);
+        if let Some(utxos) = utxos.as_mut() {
+            command::fees::apply(context, &mut blocks, &chain, utxos, height, &mut block)?;
+        }
+        collector.add(&block);
+    }
+    if let Some(utxos) = &utxos {
+        utxo::save(&context.utxo_path(), utxos)?;
+    }
+    let stats = collector
+        .finish(from, to)
+        .expect("expected at least one block in a non-empty range");
+    log_block_stats(&stats, context.format)
+}
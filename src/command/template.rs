@@ -0,0 +1,28 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{
+    reader::STDIN_PATH,
+    util::{display::set_network, log_template, template::BlockTemplate},
+    ErrorKind, Network,
+};
+use std::{io::Read, path::Path};
+
+/// Preview the template in `file`, or else read from stdin, on the network given or
+/// mainnet.
+pub(crate) fn run(context: &Context, file: Option<&Path>) -> Result<()> {
+    let json = match file {
+        Some(file) if file != Path::new(STDIN_PATH) => std::fs::read_to_string(file)
+            .map_err(|e| ErrorKind::Io.error(format!("failed to read {}: {e}", file.display())))?,
+        _ => {
+            let mut json = String::new();
+            std::io::stdin()
+                .read_to_string(&mut json)
+                .map_err(|e| ErrorKind::Io.error(format!("failed to read stdin: {e}")))?;
+            json
+        }
+    };
+    let template = BlockTemplate::from_json(&json)?;
+    let network = context.network.unwrap_or(Network::Mainnet);
+    set_network(network);
+    log_template(&template, network, context.format)
+}
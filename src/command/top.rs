@@ -0,0 +1,59 @@
+use crate::{cli::Context, command};
+use anyhow::Result;
+use bitcoin_block_finder::{
+    chain::utxo,
+    util::{
+        display::set_network,
+        log_leaderboard,
+        top::{LeaderboardCollector, Ranking},
+    },
+    ErrorKind,
+};
+
+/// List the `limit` biggest blocks and transactions `by` a ranking from height `from` to
+/// `to`, or the chain tip when omitted, or the smallest when `smallest`.
+pub(crate) fn run(
+    context: &Context,
+    by: Ranking,
+    limit: usize,
+    smallest: bool,
+    from: u64,
+    to: Option<u64>,
+) -> Result<()> {
+    let mut blocks = context.block_files()?;
+    let chain = context.chain(&mut blocks)?;
+    let tip = (chain.len() as u64)
+        .checked_sub(1)
+        .ok_or_else(|| anyhow::anyhow!("failed to rank blocks, the chain is empty"))?;
+    let to = to.unwrap_or(tip);
+    if from > to || to > tip {
+        anyhow::bail!(
+            "failed to rank blocks between heights {from} and {to}, the chain tip is at {tip}"
+        )
+    }
+
+    // Fees come from the undo data when asked, and otherwise from a utxo set.
+    let mut utxos = match by == Ranking::Fee && !context.undo {
+        true => Some(command::fees::load(context, &chain)?),
+        false => None,
+    };
+    let mut collector = LeaderboardCollector::new(by, limit, smallest);
+    for height in from..=to {
+        let entry = chain.get(height).ok_or_else(|| {
+            ErrorKind::NotFound.error(format!("failed to find block at height {height}"))
+        })?;
+        let (block_info, mut block) = blocks.read_at(entry.location, height)?;
+        set_network(block_info.network()?);
+        if let Some(utxos) = utxos.as_mut() {
+            command::fees::apply(context, &mut blocks, &chain, utxos, height, &mut block)?;
+        } else if by == Ranking::Fee {
+            let spent = blocks.read_undo(entry.location, &block)?.prevouts();
+            block.set_spent(&spent);
+        }
+        collector.add(height, &block);
+    }
+    if let Some(utxos) = &utxos {
+        utxo::save(&context.utxo_path(), utxos)?;
+    }
+    log_leaderboard(&collector.finish(from, to), context.format)
+}
@@ -0,0 +1,340 @@
+//! An interactive browser of the best chain through the blk files: a list of the blocks by
+//! height beside the selected block's header and transactions, with search by height, block
+//! hash or txid.
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{
+    util::{
+        display::{format_amount, format_utc, set_color, set_network},
+        hash::{from_display_hex, to_display_hex},
+        tx::TxOut,
+    },
+    Block, BlockFileReader, Chain,
+};
+use ratatui::{
+    crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind},
+    layout::{Constraint, Layout, Rect},
+    style::{Style, Stylize},
+    text::Line,
+    widgets::{Block as Pane, List, ListItem, ListState, Paragraph},
+    DefaultTerminal, Frame,
+};
+use std::collections::HashMap;
+
+/// The shortest block hash prefix searched for as it's typed, since shorter ones match
+/// too many blocks to be worth jumping to the first.
+const MIN_HASH_PREFIX: usize = 6;
+
+/// Browse the best chain through the selected blk files until quit.
+pub(crate) fn run(context: &Context) -> Result<()> {
+    let mut blocks = context.block_files()?;
+    let chain = context.chain(&mut blocks)?;
+    if chain.is_empty() {
+        anyhow::bail!("failed to browse the blk files, the chain is empty")
+    }
+    // Escapes can't be drawn in a pane, so text is rendered plain.
+    set_color(false);
+    let mut browser = Browser::new(&mut blocks, &chain);
+    let mut terminal = ratatui::init();
+    let result = browser.run(&mut terminal);
+    ratatui::restore();
+    result
+}
+
+/// What the keys typed are going to.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Mode {
+    Browse,
+    /// Typing a height, block hash or txid to search for.
+    Search(String),
+}
+
+struct Browser<'a> {
+    blocks: &'a mut BlockFileReader,
+    chain: &'a Chain,
+    selected: u64,
+    /// The first height shown in the list.
+    top: u64,
+    /// The lines of the detail pane for the block at the height they were rendered for.
+    detail: Option<(u64, Vec<String>)>,
+    /// The line of the detail pane where the list of transactions starts.
+    transactions_line: usize,
+    scroll: usize,
+    /// The timestamps of blocks listed so far, read from their headers as they're shown.
+    times: HashMap<u64, u32>,
+    mode: Mode,
+    status: String,
+}
+impl<'a> Browser<'a> {
+    fn new(blocks: &'a mut BlockFileReader, chain: &'a Chain) -> Self {
+        Self {
+            blocks,
+            chain,
+            selected: 0,
+            top: 0,
+            detail: None,
+            transactions_line: 0,
+            scroll: 0,
+            times: HashMap::new(),
+            mode: Mode::Browse,
+            status: String::new(),
+        }
+    }
+    fn tip(&self) -> u64 {
+        self.chain.len() as u64 - 1
+    }
+    fn run(&mut self, terminal: &mut DefaultTerminal) -> Result<()> {
+        loop {
+            self.load()?;
+            let mut drawn = Ok(());
+            terminal.draw(|frame| drawn = self.draw(frame))?;
+            drawn?;
+            let Event::Key(key) = event::read()? else {
+                continue;
+            };
+            if key.kind != KeyEventKind::Press {
+                continue;
+            }
+            let page = terminal.size()?.height.saturating_sub(3).max(1);
+            if !self.handle(key, page.into())? {
+                return Ok(());
+            }
+        }
+    }
+    /// Act on `key`, returning whether to carry on browsing.
+    fn handle(&mut self, key: KeyEvent, page: u64) -> Result<bool> {
+        if let Mode::Search(query) = &mut self.mode {
+            match key.code {
+                KeyCode::Esc => self.mode = Mode::Browse,
+                KeyCode::Enter => {
+                    let query = std::mem::take(query);
+                    self.mode = Mode::Browse;
+                    self.search(&query, true)?;
+                }
+                KeyCode::Backspace => {
+                    query.pop();
+                }
+                KeyCode::Char(c) => {
+                    query.push(c);
+                    let query = query.clone();
+                    self.search(&query, false)?;
+                }
+                _ => {}
+            }
+            return Ok(true);
+        }
+        match key.code {
+            KeyCode::Char('q') | KeyCode::Esc => return Ok(false),
+            KeyCode::Char('/') => {
+                self.mode = Mode::Search(String::new());
+                self.status.clear();
+            }
+            KeyCode::Down | KeyCode::Char('j') => self.select(self.selected.saturating_add(1)),
+            KeyCode::Up | KeyCode::Char('k') => self.select(self.selected.saturating_sub(1)),
+            KeyCode::PageDown => self.select(self.selected.saturating_add(page)),
+            KeyCode::PageUp => self.select(self.selected.saturating_sub(page)),
+            KeyCode::Home | KeyCode::Char('g') => self.select(0),
+            KeyCode::End | KeyCode::Char('G') => self.select(self.tip()),
+            KeyCode::Char('J') => self.scroll = self.scroll.saturating_add(1),
+            KeyCode::Char('K') => self.scroll = self.scroll.saturating_sub(1),
+            KeyCode::Char('d') => self.scroll = self.scroll.saturating_add(page as usize / 2),
+            KeyCode::Char('u') => self.scroll = self.scroll.saturating_sub(page as usize / 2),
+            _ => {}
+        }
+        Ok(true)
+    }
+    fn select(&mut self, height: u64) {
+        let height = height.min(self.tip());
+        if height != self.selected {
+            self.selected = height;
+            self.scroll = 0;
+        }
+    }
+    /// Jump to the block `query` names: a height, or a block hash or the start of one as it's
+    /// typed. Once `submitted`, a whole hash that isn't a block's is searched for as a txid,
+    /// scanning every block.
+    fn search(&mut self, query: &str, submitted: bool) -> Result<()> {
+        let query = query.trim().to_ascii_lowercase();
+        self.status.clear();
+        if query.is_empty() {
+            return Ok(());
+        }
+        if let Ok(height) = query.parse::<u64>() {
+            match height <= self.tip() {
+                true => self.select(height),
+                false => {
+                    self.status = format!("No block at height {height}, the tip is {}", self.tip())
+                }
+            }
+            return Ok(());
+        }
+        if !query.chars().all(|c| c.is_ascii_hexdigit()) {
+            self.status = format!("Expected a height, block hash or txid, not {query}");
+            return Ok(());
+        }
+        if query.len() < MIN_HASH_PREFIX && !submitted {
+            return Ok(());
+        }
+        let found = (0..self.chain.len() as u64).find(|height| {
+            let entry = self
+                .chain
+                .get(*height)
+                .expect("expected a height on the chain");
+            to_display_hex(&entry.hash).starts_with(&query)
+        });
+        if let Some(height) = found {
+            self.select(height);
+            return Ok(());
+        }
+        match (query.len(), submitted) {
+            (64, true) => self.find_transaction(&query)?,
+            (64, false) => {
+                self.status = "No block has that hash, press enter to search for a txid".into()
+            }
+            _ => self.status = format!("No block hash starts with {query}"),
+        }
+        Ok(())
+    }
+    /// Select the block with the transaction `txid`, scrolling the detail pane to it.
+    fn find_transaction(&mut self, txid: &str) -> Result<()> {
+        let txid = from_display_hex(txid)?;
+        let found = self
+            .blocks
+            .par_map(|_, block| {
+                let index = block
+                    .transactions()
+                    .iter()
+                    .position(|tx| tx.txid() == txid)?;
+                Some((block.block_header().hash(), index))
+            })?
+            .into_iter()
+            .flatten()
+            .find_map(|(hash, index)| Some((self.chain.height_of(&hash)?, index)));
+        let Some((height, index)) = found else {
+            self.status = format!(
+                "No block on the best chain has transaction {}",
+                to_display_hex(&txid)
+            );
+            return Ok(());
+        };
+        self.select(height);
+        self.load()?;
+        self.scroll = self.transactions_line + 1 + index;
+        self.status = format!("Transaction {index} of block {height}");
+        Ok(())
+    }
+    /// Render the detail pane for the selected block, if it isn't already.
+    fn load(&mut self) -> Result<()> {
+        if self
+            .detail
+            .as_ref()
+            .is_some_and(|(height, _)| *height == self.selected)
+        {
+            return Ok(());
+        }
+        let entry = self
+            .chain
+            .get(self.selected)
+            .expect("expected the selected height on the chain");
+        let (block_info, block) = self.blocks.read_at(entry.location, self.selected)?;
+        set_network(block_info.network()?);
+        let block = block.with_height(self.selected);
+        let neighbours = self.chain.neighbours(block.block_header());
+        let text = format!("{}\n{block}", block_info.with_neighbours(neighbours));
+        let mut lines = text.lines().map(str::to_string).collect::<Vec<_>>();
+        lines.push(String::new());
+        self.transactions_line = lines.len();
+        lines.push("Transactions:".to_string());
+        lines.extend(transaction_lines(&block));
+        self.detail = Some((self.selected, lines));
+        Ok(())
+    }
+    fn draw(&mut self, frame: &mut Frame) -> Result<()> {
+        let [main, footer] =
+            Layout::vertical([Constraint::Min(0), Constraint::Length(1)]).areas(frame.area());
+        let [list, detail] =
+            Layout::horizontal([Constraint::Length(97), Constraint::Min(0)]).areas(main);
+        self.draw_list(frame, list)?;
+
+        let lines = self
+            .detail
+            .as_ref()
+            .map_or(&[][..], |(_, lines)| lines.as_slice());
+        self.scroll = self.scroll.min(lines.len().saturating_sub(1));
+        let title = format!(" Block {} ", self.selected);
+        let paragraph = Paragraph::new(
+            lines
+                .iter()
+                .map(|line| Line::raw(line.as_str()))
+                .collect::<Vec<_>>(),
+        )
+        .block(Pane::bordered().title(title))
+        .scroll((self.scroll.try_into().unwrap_or(u16::MAX), 0));
+        frame.render_widget(paragraph, detail);
+
+        let footer_line = match &self.mode {
+            Mode::Search(query) => Line::raw(format!("/{query}")),
+            Mode::Browse if !self.status.is_empty() => Line::raw(self.status.as_str()).yellow(),
+            Mode::Browse => Line::raw(
+                "j/k move  PgUp/PgDn page  g/G first/tip  J/K d/u scroll  / search  q quit",
+            )
+            .dim(),
+        };
+        frame.render_widget(Paragraph::new(footer_line), footer);
+        Ok(())
+    }
+    /// The heights around the selected one that fit in `area`, with their hashes and times.
+    fn draw_list(&mut self, frame: &mut Frame, area: Rect) -> Result<()> {
+        let rows = u64::from(area.height.saturating_sub(2)).max(1);
+        if self.selected < self.top {
+            self.top = self.selected;
+        } else if self.selected >= self.top + rows {
+            self.top = self.selected + 1 - rows;
+        }
+        let heights = self.top..(self.top + rows).min(self.tip() + 1);
+        let mut items = Vec::with_capacity(rows as usize);
+        for height in heights {
+            let entry = *self
+                .chain
+                .get(height)
+                .expect("expected a height on the chain");
+            let time = match self.times.get(&height) {
+                Some(time) => *time,
+                None => {
+                    let time = self
+                        .blocks
+                        .read_header_at(entry.location)?
+                        .unix_epoch_time();
+                    *self.times.entry(height).or_insert(time)
+                }
+            };
+            items.push(ListItem::new(format!(
+                "{height:>7}  {}  {}",
+                to_display_hex(&entry.hash),
+                format_utc(time)
+            )));
+        }
+        let mut state =
+            ListState::default().with_selected(Some((self.selected - self.top) as usize));
+        let list = List::new(items)
+            .block(Pane::bordered().title(format!(" Blocks 0-{} ", self.tip())))
+            .highlight_style(Style::new().reversed());
+        frame.render_stateful_widget(list, area, &mut state);
+        Ok(())
+    }
+}
+
+/// A line for each transaction of `block`: its index, txid, the number of inputs and
+/// outputs and the value it sends.
+fn transaction_lines(block: &Block) -> impl Iterator<Item = String> + '_ {
+    block.transactions().iter().enumerate().map(|(index, tx)| {
+        let value = tx.outputs().iter().map(TxOut::value).sum::<u64>();
+        format!(
+            "{index:>5}  {}  {} in, {} out, {}",
+            to_display_hex(&tx.txid()),
+            tx.inputs().len(),
+            tx.outputs().len(),
+            format_amount(value)
+        )
+    })
+}
@@ -0,0 +1,61 @@
+use crate::{cli::Context, command};
+use anyhow::Result;
+use bitcoin_block_finder::{
+    chain::{txoutset, utxo},
+    ErrorKind,
+};
+use std::path::Path;
+
+/// Build the utxo set as of height `to`, or the chain tip when omitted, saving it beside the
+/// block index and as a Bitcoin Core snapshot at `txoutset` when given.
+pub(crate) fn build(context: &Context, to: Option<u64>, txoutset: Option<&Path>) -> Result<()> {
+    let mut blocks = context.block_files()?;
+    let chain = context.chain(&mut blocks)?;
+    let tip = (chain.len() as u64)
+        .checked_sub(1)
+        .ok_or_else(|| anyhow::anyhow!("failed to build the utxo set, the chain is empty"))?;
+    let to = to.unwrap_or(tip);
+    let entry = chain
+        .get(to)
+        .ok_or_else(|| ErrorKind::NotFound.error(format!("failed to find block at height {to}")))?;
+    let (block_info, mut block) = blocks.read_at(entry.location, to)?;
+
+    let mut utxos = command::fees::load(context, &chain)?;
+    if utxos.tip() != Some((to, entry.hash)) {
+        command::fees::apply(context, &mut blocks, &chain, &mut utxos, to, &mut block)?;
+        utxo::save(&context.utxo_path(), &utxos)?;
+    }
+    tracing::info!(
+        "Built the utxo set as of height {to}, {} unspent outputs in {}",
+        utxos.len(),
+        context.utxo_path().display()
+    );
+    if let Some(path) = txoutset {
+        txoutset::write(path, &utxos, block_info.network()?)?;
+        tracing::info!("Wrote the utxo set to {}", path.display());
+    }
+    Ok(())
+}
+
+/// Replace the saved utxo set with the Bitcoin Core snapshot at `path`.
+pub(crate) fn import(context: &Context, path: &Path) -> Result<()> {
+    let mut blocks = context.block_files()?;
+    let chain = context.chain(&mut blocks)?;
+    let (network, utxos) = txoutset::read(path, &chain)?;
+    if let Some(expected) = context.network.filter(|expected| *expected != network) {
+        return Err(ErrorKind::WrongNetwork.error(format!(
+            "failed to import {}, it's a {} snapshot rather than {}",
+            path.display(),
+            network.name(),
+            expected.name()
+        )));
+    }
+    utxo::save(&context.utxo_path(), &utxos)?;
+    let (height, _) = utxos.tip().expect("expected a tip on an imported utxo set");
+    tracing::info!(
+        "Imported the utxo set as of height {height}, {} unspent outputs in {}",
+        utxos.len(),
+        context.utxo_path().display()
+    );
+    Ok(())
+}
@@ -0,0 +1,16 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::util::log_verify_report;
+
+/// Check the integrity of every blk file, failing once they're reported if any are corrupt.
+pub(crate) fn run(context: &Context) -> Result<()> {
+    let report = context.block_files()?.verify()?;
+    log_verify_report(&report, context.format)?;
+    if !report.corrupt.is_empty() {
+        anyhow::bail!(
+            "found {} corrupt regions in the blk files",
+            report.corrupt.len()
+        )
+    }
+    Ok(())
+}
@@ -0,0 +1,82 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{
+    chain::BlockLink, reader::STDIN_PATH, util::hash::to_display_hex, Block, BlockFileReader,
+    BlockInfo, BlockLocation, Chain,
+};
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::sync::mpsc;
+
+/// Follow the blk files as a node writes to them, calling `print` on each new block
+/// that extends `chain`, with its height filled in.
+///
+/// Blocks already on the chain are passed over, and blocks on a competing branch are
+/// only noted on stderr, since the chain is never reorganised.
+pub(crate) fn run(
+    context: &Context,
+    blocks: &mut BlockFileReader,
+    mut chain: Chain,
+    mut print: impl FnMut(BlockInfo, Block) -> Result<()>,
+) -> Result<()> {
+    let watched = context.path.as_ref().unwrap_or(&context.datadir);
+    if watched.as_os_str() == STDIN_PATH {
+        anyhow::bail!("failed to watch stdin, --watch needs blk files to follow")
+    }
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender)
+        .map_err(|e| anyhow::anyhow!("failed to watch {}: {e}", watched.display()))?;
+    watcher
+        .watch(watched, RecursiveMode::NonRecursive)
+        .map_err(|e| anyhow::anyhow!("failed to watch {}: {e}", watched.display()))?;
+    tracing::info!("Watching {} for new blocks", watched.display());
+
+    // Catch up on anything written since the scan, now the watcher is in place not to miss
+    // any more. Blocks are written as they're received, so new ones come after the tip.
+    let tip = chain
+        .get((chain.len() as u64).saturating_sub(1))
+        .map_or(BlockLocation { file: 0, offset: 0 }, |tip| tip.location);
+    let mut resume = follow(blocks, &mut chain, tip, &mut print)?;
+    for event in &receiver {
+        let event =
+            event.map_err(|e| anyhow::anyhow!("failed to watch {}: {e}", watched.display()))?;
+        let touches_blk_file = event.paths.iter().any(|path| {
+            path.file_name()
+                .and_then(|name| name.to_str())
+                .is_some_and(|name| name.starts_with("blk") && name.ends_with(".dat"))
+        });
+        if !touches_blk_file || !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+            continue;
+        }
+        // A single block write can raise several events, so read once for all that are waiting.
+        while receiver.try_recv().is_ok() {}
+        resume = follow(blocks, &mut chain, resume, &mut print)?;
+    }
+    Ok(())
+}
+
+/// Print the blocks written from `from` on, returning where to read from next.
+fn follow(
+    blocks: &mut BlockFileReader,
+    chain: &mut Chain,
+    from: BlockLocation,
+    print: &mut impl FnMut(BlockInfo, Block) -> Result<()>,
+) -> Result<BlockLocation> {
+    let (records, resume) = blocks.read_from(from)?;
+    for (mut block_info, block) in records {
+        let link = BlockLink::from_block(&block_info, &block)?;
+        if chain.height_of(&link.hash).is_some() {
+            continue;
+        }
+        match chain.extend(link) {
+            Some(height) => {
+                block_info.height = height;
+                print(block_info, block)?;
+            }
+            None => tracing::warn!(
+                "Skipping block {}, it doesn't build on the chain tip",
+                to_display_hex(&link.hash)
+            ),
+        }
+    }
+    Ok(resume)
+}
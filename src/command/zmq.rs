@@ -0,0 +1,124 @@
+use crate::cli::Context;
+use anyhow::Result;
+use bitcoin_block_finder::{
+    chain::{
+        index::{self, IndexFile},
+        BlockLink,
+    },
+    util::hash::to_display_hex,
+    zmq::{Subscriber, RAWBLOCK},
+    Block, BlockFileReader, BlockInfo, BlockLocation, Chain, Network,
+};
+
+/// Subscribe to the rawblock notifications of the node publishing at `address`, calling
+/// `print` on each block it connects, with its height filled in.
+///
+/// Heights are counted on from the tip of `chain`. A block that doesn't build on the last
+/// one, as after a reorganisation, takes its height from its coinbase instead. With
+/// --zmq-index, each block is also added to the block index once it's found in the blk
+/// files, which the node writes before it notifies.
+pub(crate) fn run(
+    context: &Context,
+    address: &str,
+    blocks: &mut BlockFileReader,
+    mut chain: Chain,
+    mut print: impl FnMut(BlockInfo, Block) -> Result<()>,
+) -> Result<()> {
+    let address = address.strip_prefix("tcp://").unwrap_or(address);
+    let mut subscriber = Subscriber::connect(address, RAWBLOCK)?;
+    tracing::info!("Subscribed to rawblock notifications from {address}");
+    let network = blocks
+        .network()
+        .or(context.network)
+        .unwrap_or(Network::Mainnet);
+    let last = (chain.len() as u64).checked_sub(1);
+    let mut tip = last.and_then(|height| Some((height, chain.get(height)?.hash)));
+    let mut resume = last
+        .and_then(|height| chain.get(height))
+        .map_or(BlockLocation { file: 0, offset: 0 }, |tip| tip.location);
+    if context.zmq_index {
+        let path = context.index_path();
+        // Only a copy of this chain can be added to, so anything else is replaced first.
+        if !path.is_file() || IndexFile::open(&path)?.len() != chain.len() as u64 {
+            index::save(&path, &chain, blocks.total_size()?)?;
+        }
+    }
+
+    let mut sequence = None;
+    loop {
+        let notification = subscriber.receive()?;
+        if notification.topic != RAWBLOCK {
+            continue;
+        }
+        if let (Some(last), Some(next)) = (sequence, notification.sequence) {
+            let missed = next.wrapping_sub(last).wrapping_sub(1);
+            if missed > 0 {
+                tracing::warn!("Missed {missed} rawblock notifications");
+            }
+        }
+        sequence = notification.sequence;
+        let block = notification.block()?;
+        let header = block.block_header();
+        let hash = header.hash();
+        let height = match tip {
+            Some((_, tip_hash)) if tip_hash == hash => continue,
+            Some((height, tip_hash)) if header.previous_hash() == tip_hash => height + 1,
+            None if header.is_genesis() => 0,
+            _ => match block
+                .coinbase()
+                .and_then(|coinbase| coinbase.bip34_height())
+            {
+                Some(height) => {
+                    tracing::warn!(
+                        "Block {} doesn't build on the last one, taking its height {height} from its coinbase",
+                        to_display_hex(&hash)
+                    );
+                    height
+                }
+                None => {
+                    tracing::warn!(
+                        "Skipping block {}, it doesn't build on the last one or commit to its height",
+                        to_display_hex(&hash)
+                    );
+                    continue;
+                }
+            },
+        };
+        tip = Some((height, hash));
+        if context.zmq_index {
+            resume = update_index(context, blocks, &mut chain, resume)?;
+        }
+        let size = (notification.body.len() as u32).to_le_bytes().to_vec();
+        let block_info =
+            BlockInfo::new(height, network.magic_bytes().to_vec(), size).with_block_hash(hash);
+        print(block_info, block)?;
+    }
+}
+
+/// Add the blocks written to the blk files from `from` on that extend `chain` to the block
+/// index, returning where to read from next.
+fn update_index(
+    context: &Context,
+    blocks: &mut BlockFileReader,
+    chain: &mut Chain,
+    from: BlockLocation,
+) -> Result<BlockLocation> {
+    let (records, resume) = blocks.read_from(from)?;
+    let indexed = chain.len() as u64;
+    for (block_info, block) in &records {
+        let link = BlockLink::from_block(block_info, block)?;
+        if chain.height_of(&link.hash).is_none() && chain.extend(link).is_none() {
+            tracing::warn!(
+                "Not indexing block {}, it doesn't build on the indexed tip (use --reindex to update it)",
+                to_display_hex(&link.hash)
+            );
+        }
+    }
+    let entries = (indexed..chain.len() as u64)
+        .filter_map(|height| chain.get(height).copied())
+        .collect::<Vec<_>>();
+    if !entries.is_empty() {
+        index::append(&context.index_path(), &entries, blocks.total_size()?)?;
+    }
+    Ok(resume)
+}
@@ -0,0 +1,318 @@
+//! C bindings for parsing blocks and headers, for C, C++ or Python's ctypes to reuse the
+//! parser rather than rewrite it.
+//!
+//! The declarations are in `include/bitcoin_block_finder.h`, generated by cbindgen from
+//! this module with `cbindgen --config cbindgen.toml --output include/bitcoin_block_finder.h`.
+//! Blocks and headers are opaque handles, each freed by its own function. Functions failing
+//! return null or false and leave a message for [bbf_last_error] on the calling thread.
+//! Hashes are written as their 32 bytes in serialized order, the reverse of how they're
+//! shown.
+use crate::util::{
+    decode::whole,
+    types::{Block, BlockHeader},
+};
+use std::{
+    cell::RefCell,
+    ffi::{c_char, CString},
+    ptr,
+};
+
+thread_local! {
+    /// The message of the last failure on this thread.
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    // A message can't hold a nul byte for C, so any are dropped.
+    let message = CString::new(message.replace('\0', "")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Box `result` into a handle, or note its error and return null.
+fn into_handle<T>(result: anyhow::Result<T>) -> *mut T {
+    match result {
+        Ok(value) => Box::into_raw(Box::new(value)),
+        Err(e) => {
+            set_last_error(format!("{e:#}"));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// The bytes at `data`, or an error for a null pointer.
+///
+/// # Safety
+///
+/// `data` must be null or point to `len` readable bytes.
+unsafe fn bytes<'a>(data: *const u8, len: usize) -> anyhow::Result<&'a [u8]> {
+    if data.is_null() {
+        anyhow::bail!("failed to parse, the data pointer is null");
+    }
+    Ok(std::slice::from_raw_parts(data, len))
+}
+
+/// Copy `hash` to the 32 bytes at `out`.
+///
+/// # Safety
+///
+/// `out` must be null or point to 32 writable bytes.
+unsafe fn write_hash(hash: [u8; 32], out: *mut u8) -> bool {
+    if out.is_null() {
+        set_last_error("failed to write the hash, the output pointer is null".to_string());
+        return false;
+    }
+    ptr::copy_nonoverlapping(hash.as_ptr(), out, hash.len());
+    true
+}
+
+/// The message of the last failure on the calling thread, or null if nothing has failed.
+/// It stays valid until the next failure on the thread.
+#[no_mangle]
+pub extern "C" fn bbf_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| last.borrow().as_ref().map_or(ptr::null(), |e| e.as_ptr()))
+}
+
+/// Parse the serialized block of `len` bytes at `data`, returning a handle to free with
+/// [bbf_block_free], or null if they aren't exactly a block.
+///
+/// # Safety
+///
+/// `data` must be null or point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bbf_block_parse(data: *const u8, len: usize) -> *mut Block {
+    into_handle(bytes(data, len).and_then(|bytes| whole(bytes, Block::from_raw_bytes)))
+}
+
+/// Free a block from [bbf_block_parse], doing nothing for null.
+///
+/// # Safety
+///
+/// `block` must be null or a handle from [bbf_block_parse] not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn bbf_block_free(block: *mut Block) {
+    if !block.is_null() {
+        drop(Box::from_raw(block));
+    }
+}
+
+/// The header of `block`, borrowed for as long as the block isn't freed.
+///
+/// # Safety
+///
+/// `block` must be a live handle from [bbf_block_parse].
+#[no_mangle]
+pub unsafe extern "C" fn bbf_block_header(block: *const Block) -> *const BlockHeader {
+    (*block).block_header()
+}
+
+/// The number of transactions in `block`.
+///
+/// # Safety
+///
+/// `block` must be a live handle from [bbf_block_parse].
+#[no_mangle]
+pub unsafe extern "C" fn bbf_block_tx_count(block: *const Block) -> u64 {
+    (*block).tx_count()
+}
+
+/// Write the txid of the transaction at `index` in `block` to the 32 bytes at `out`,
+/// returning false if there's none at `index`.
+///
+/// # Safety
+///
+/// `block` must be a live handle from [bbf_block_parse] and `out` must be null or point to
+/// 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bbf_block_txid(block: *const Block, index: usize, out: *mut u8) -> bool {
+    match (*block).transactions().get(index) {
+        Some(tx) => write_hash(tx.txid(), out),
+        None => {
+            set_last_error(format!(
+                "failed to find transaction {index}, the block has {}",
+                (*block).tx_count()
+            ));
+            false
+        }
+    }
+}
+
+/// `block` as the JSON `decode --format json` prints, a string to free with
+/// [bbf_string_free], or null if it can't be serialized.
+///
+/// # Safety
+///
+/// `block` must be a live handle from [bbf_block_parse].
+#[no_mangle]
+pub unsafe extern "C" fn bbf_block_to_json(block: *const Block) -> *mut c_char {
+    let json = serde_json::to_string(&*block)
+        .map_err(anyhow::Error::from)
+        .and_then(|json| Ok(CString::new(json)?));
+    match json {
+        Ok(json) => json.into_raw(),
+        Err(e) => {
+            set_last_error(format!("failed to serialize the block: {e}"));
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Free a string from [bbf_block_to_json], doing nothing for null.
+///
+/// # Safety
+///
+/// `string` must be null or a string from [bbf_block_to_json] not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn bbf_string_free(string: *mut c_char) {
+    if !string.is_null() {
+        drop(CString::from_raw(string));
+    }
+}
+
+/// Parse the 80 byte serialized header at `data`, returning a handle to free with
+/// [bbf_header_free], or null if `len` bytes aren't exactly a header.
+///
+/// # Safety
+///
+/// `data` must be null or point to `len` readable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bbf_header_parse(data: *const u8, len: usize) -> *mut BlockHeader {
+    into_handle(bytes(data, len).and_then(|bytes| whole(bytes, BlockHeader::from_raw_bytes)))
+}
+
+/// Free a header from [bbf_header_parse], doing nothing for null. Headers from
+/// [bbf_block_header] are freed with their block instead.
+///
+/// # Safety
+///
+/// `header` must be null or a handle from [bbf_header_parse] not yet freed.
+#[no_mangle]
+pub unsafe extern "C" fn bbf_header_free(header: *mut BlockHeader) {
+    if !header.is_null() {
+        drop(Box::from_raw(header));
+    }
+}
+
+/// Write the block hash of `header` to the 32 bytes at `out`.
+///
+/// # Safety
+///
+/// `header` must be a live header and `out` must be null or point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bbf_header_hash(header: *const BlockHeader, out: *mut u8) -> bool {
+    write_hash((*header).hash(), out)
+}
+
+/// Write the hash of the parent block of `header` to the 32 bytes at `out`.
+///
+/// # Safety
+///
+/// `header` must be a live header and `out` must be null or point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bbf_header_previous_hash(
+    header: *const BlockHeader,
+    out: *mut u8,
+) -> bool {
+    write_hash((*header).previous_hash(), out)
+}
+
+/// Write the merkle root of `header` to the 32 bytes at `out`.
+///
+/// # Safety
+///
+/// `header` must be a live header and `out` must be null or point to 32 writable bytes.
+#[no_mangle]
+pub unsafe extern "C" fn bbf_header_merkle_root(header: *const BlockHeader, out: *mut u8) -> bool {
+    write_hash((*header).merkle_root(), out)
+}
+
+/// The version of `header`.
+///
+/// # Safety
+///
+/// `header` must be a live header.
+#[no_mangle]
+pub unsafe extern "C" fn bbf_header_version(header: *const BlockHeader) -> u32 {
+    (*header).version()
+}
+
+/// The unix epoch time of `header`.
+///
+/// # Safety
+///
+/// `header` must be a live header.
+#[no_mangle]
+pub unsafe extern "C" fn bbf_header_time(header: *const BlockHeader) -> u32 {
+    (*header).unix_epoch_time()
+}
+
+/// The compact target of `header`, its nBits.
+///
+/// # Safety
+///
+/// `header` must be a live header.
+#[no_mangle]
+pub unsafe extern "C" fn bbf_header_bits(header: *const BlockHeader) -> u32 {
+    (*header).target()
+}
+
+/// The nonce of `header`.
+///
+/// # Safety
+///
+/// `header` must be a live header.
+#[no_mangle]
+pub unsafe extern "C" fn bbf_header_nonce(header: *const BlockHeader) -> u32 {
+    (*header).nonce()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{fixtures::GENESIS_BLOCK_HEX, hash::to_display_hex};
+    use std::ffi::CStr;
+
+    /// Checks the genesis block parses into a handle its header fields, txid and JSON are
+    /// read through, and that bytes left over fail with a message.
+    #[test]
+    fn test_ffi() {
+        let raw_bytes = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        unsafe {
+            let block = bbf_block_parse(raw_bytes.as_ptr(), raw_bytes.len());
+            assert!(!block.is_null());
+            let header = bbf_block_header(block);
+            let mut hash = [0; 32];
+            assert!(bbf_header_hash(header, hash.as_mut_ptr()));
+            assert_eq!(
+                to_display_hex(&hash),
+                "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+            );
+            assert_eq!(
+                (bbf_header_version(header), bbf_header_bits(header)),
+                (1, 0x1d00ffff)
+            );
+            assert_eq!(bbf_block_tx_count(block), 1);
+            let mut txid = [0; 32];
+            assert!(bbf_block_txid(block, 0, txid.as_mut_ptr()));
+            assert_eq!(txid, (*block).transactions()[0].txid());
+            assert!(!bbf_block_txid(block, 1, txid.as_mut_ptr()));
+
+            let json = bbf_block_to_json(block);
+            let value: serde_json::Value =
+                serde_json::from_str(CStr::from_ptr(json).to_str().unwrap()).unwrap();
+            assert_eq!(value["tx_count"], 1);
+            bbf_string_free(json);
+            bbf_block_free(block);
+
+            let header = bbf_header_parse(raw_bytes.as_ptr(), 80);
+            assert_eq!(bbf_header_nonce(header), 2_083_236_893);
+            bbf_header_free(header);
+
+            let padded = [raw_bytes.as_slice(), &[0]].concat();
+            assert!(bbf_block_parse(padded.as_ptr(), padded.len()).is_null());
+            assert_eq!(
+                CStr::from_ptr(bbf_last_error()).to_str().unwrap(),
+                "1 bytes left over"
+            );
+        }
+    }
+}
@@ -0,0 +1,269 @@
+//! A gRPC service answering with the blocks of the best chain through the blk files, for
+//! services preferring typed RPC to parsing the CLI's JSON.
+//!
+//! The service, `bitcoin_block_finder.BlockFinder`, is generated by the build script from
+//! the messages here rather than from a .proto file: GetBlockByHeight, GetBlockByHash and
+//! StreamBlocks, streaming a range of heights in order.
+use crate::{
+    chain::Chain,
+    reader::{async_reader::AsyncBlockFileReader, BlockFileReader},
+    util::{
+        error::ErrorKind,
+        hash::{from_display_hex, to_display_hex},
+    },
+};
+use std::sync::Arc;
+use tokio_stream::wrappers::ReceiverStream;
+use tonic::{Request, Response, Status};
+
+include!(concat!(
+    env!("OUT_DIR"),
+    "/bitcoin_block_finder.BlockFinder.rs"
+));
+
+/// The number of blocks read ahead of a client slower to receive them.
+const STREAM_BUFFER: usize = 16;
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct HeightRequest {
+    #[prost(uint64, tag = "1")]
+    pub height: u64,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct HashRequest {
+    /// The hash of the block in the usual big endian hex form.
+    #[prost(string, tag = "1")]
+    pub hash: String,
+}
+
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct RangeRequest {
+    #[prost(uint64, tag = "1")]
+    pub from_height: u64,
+    /// The last height to stream, the tip when not given.
+    #[prost(uint64, optional, tag = "2")]
+    pub to_height: Option<u64>,
+}
+
+/// A block's header fields and position on the chain, with its txids and the block itself
+/// as serialized.
+#[derive(Clone, PartialEq, prost::Message)]
+pub struct BlockReply {
+    #[prost(uint64, tag = "1")]
+    pub height: u64,
+    #[prost(string, tag = "2")]
+    pub hash: String,
+    #[prost(string, tag = "3")]
+    pub previous_hash: String,
+    /// The hash of the block built on this one, none at the tip.
+    #[prost(string, optional, tag = "4")]
+    pub next_hash: Option<String>,
+    #[prost(uint32, tag = "5")]
+    pub version: u32,
+    #[prost(string, tag = "6")]
+    pub merkle_root: String,
+    #[prost(uint32, tag = "7")]
+    pub time: u32,
+    #[prost(uint32, tag = "8")]
+    pub bits: u32,
+    #[prost(uint32, tag = "9")]
+    pub nonce: u32,
+    /// The size of the serialized block in bytes, witnesses included.
+    #[prost(uint32, tag = "10")]
+    pub size: u32,
+    #[prost(string, repeated, tag = "11")]
+    pub txids: Vec<String>,
+    #[prost(bytes = "vec", tag = "12")]
+    pub raw: Vec<u8>,
+}
+
+/// The [BlockFinder](block_finder_server::BlockFinder) service over a reader of the blk
+/// files and the chain linked through them.
+///
+/// Blocks are read with an [AsyncBlockFileReader], so requests are answered concurrently
+/// without holding up the runtime's worker threads.
+#[derive(Debug, Clone)]
+pub struct BlockFinderService {
+    blocks: Arc<AsyncBlockFileReader>,
+    chain: Arc<Chain>,
+}
+impl BlockFinderService {
+    pub fn new(blocks: BlockFileReader, chain: Chain) -> Self {
+        Self {
+            blocks: Arc::new(AsyncBlockFileReader::from(blocks)),
+            chain: Arc::new(chain),
+        }
+    }
+    /// The block at `height` on the chain.
+    async fn read(&self, height: u64) -> anyhow::Result<BlockReply> {
+        let entry = self
+            .chain
+            .get(height)
+            .ok_or_else(|| ErrorKind::NotFound.error(format!("failed to find block {height}")))?;
+        let (_, block) = self.blocks.read_at(entry.location, height).await?;
+        let header = block.block_header();
+        Ok(BlockReply {
+            height,
+            hash: to_display_hex(&header.hash()),
+            previous_hash: to_display_hex(&header.previous_hash()),
+            next_hash: self
+                .chain
+                .neighbours(header)
+                .next
+                .map(|next| to_display_hex(&next.hash)),
+            version: header.version(),
+            merkle_root: to_display_hex(&header.merkle_root()),
+            time: header.unix_epoch_time(),
+            bits: header.target(),
+            nonce: header.nonce(),
+            size: block.total_size(),
+            txids: block
+                .transactions()
+                .iter()
+                .map(|tx| to_display_hex(&tx.txid()))
+                .collect(),
+            raw: block.raw_bytes(),
+        })
+    }
+}
+
+/// The status for an error, by its [ErrorKind].
+fn status(error: anyhow::Error) -> Status {
+    let message = format!("{error:#}");
+    match ErrorKind::of(&error) {
+        ErrorKind::NotFound => Status::not_found(message),
+        ErrorKind::Corrupt => Status::data_loss(message),
+        ErrorKind::WrongNetwork => Status::failed_precondition(message),
+        ErrorKind::Io | ErrorKind::Other => Status::internal(message),
+    }
+}
+
+#[tonic::async_trait]
+impl block_finder_server::BlockFinder for BlockFinderService {
+    async fn get_block_by_height(
+        &self,
+        request: Request<HeightRequest>,
+    ) -> Result<Response<BlockReply>, Status> {
+        let height = request.into_inner().height;
+        self.read(height).await.map(Response::new).map_err(status)
+    }
+    async fn get_block_by_hash(
+        &self,
+        request: Request<HashRequest>,
+    ) -> Result<Response<BlockReply>, Status> {
+        let hash = request.into_inner().hash;
+        let hash = from_display_hex(&hash)
+            .map_err(|e| Status::invalid_argument(format!("invalid hash {hash}: {e}")))?;
+        let height = self.chain.height_of(&hash).ok_or_else(|| {
+            Status::not_found(format!("failed to find block {}", to_display_hex(&hash)))
+        })?;
+        self.read(height).await.map(Response::new).map_err(status)
+    }
+
+    type StreamBlocksStream = ReceiverStream<Result<BlockReply, Status>>;
+
+    async fn stream_blocks(
+        &self,
+        request: Request<RangeRequest>,
+    ) -> Result<Response<Self::StreamBlocksStream>, Status> {
+        let RangeRequest {
+            from_height,
+            to_height,
+        } = request.into_inner();
+        let tip = (self.chain.len() as u64)
+            .checked_sub(1)
+            .ok_or_else(|| Status::not_found("failed to find any blocks, the chain is empty"))?;
+        let to_height = to_height.unwrap_or(tip);
+        if from_height > to_height || to_height > tip {
+            return Err(Status::out_of_range(format!(
+                "failed to stream blocks {from_height} to {to_height}, the tip is {tip}"
+            )));
+        }
+        let (sender, receiver) = tokio::sync::mpsc::channel(STREAM_BUFFER);
+        let service = self.clone();
+        tokio::spawn(async move {
+            for height in from_height..=to_height {
+                let reply = service.read(height).await.map_err(status);
+                let failed = reply.is_err();
+                // Stop once the client hangs up or after the first failure.
+                if sender.send(reply).await.is_err() || failed {
+                    break;
+                }
+            }
+        });
+        Ok(Response::new(ReceiverStream::new(receiver)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{block_finder_server::BlockFinder, *};
+    use crate::{
+        chain::BlockLink,
+        util::fixtures::{blk_record, BLOCK_1_HEX, GENESIS_BLOCK_HEX},
+    };
+    use tokio_stream::StreamExt;
+
+    /// Checks blocks are answered by height and hash, with the next block's hash but the
+    /// tip's, and that a range streams in order while heights past the tip are refused.
+    #[test]
+    fn test_block_finder_service() {
+        let datadir = std::env::temp_dir().join("bbf-test-grpc");
+        std::fs::create_dir_all(&datadir).unwrap();
+        std::fs::write(
+            datadir.join("blk00000.dat"),
+            [blk_record(GENESIS_BLOCK_HEX), blk_record(BLOCK_1_HEX)].concat(),
+        )
+        .unwrap();
+        let mut blocks = BlockFileReader::open(&datadir, None).unwrap();
+        let chain = Chain::link(blocks.by_ref().map(|block| {
+            let (block_info, block) = block.unwrap();
+            BlockLink::from_block(&block_info, &block).unwrap()
+        }));
+        let service = BlockFinderService::new(blocks, chain);
+
+        let runtime = tokio::runtime::Runtime::new().unwrap();
+        let (genesis, block_1, streamed, past_tip) = runtime.block_on(async {
+            let genesis = service
+                .get_block_by_height(Request::new(HeightRequest { height: 0 }))
+                .await
+                .unwrap()
+                .into_inner();
+            let block_1 = service
+                .get_block_by_hash(Request::new(HashRequest {
+                    hash: genesis.next_hash.clone().unwrap(),
+                }))
+                .await
+                .unwrap()
+                .into_inner();
+            let streamed = service
+                .stream_blocks(Request::new(RangeRequest {
+                    from_height: 0,
+                    to_height: None,
+                }))
+                .await
+                .unwrap()
+                .into_inner()
+                .map(|reply| reply.unwrap().height)
+                .collect::<Vec<_>>()
+                .await;
+            let past_tip = service
+                .get_block_by_height(Request::new(HeightRequest { height: 2 }))
+                .await;
+            (genesis, block_1, streamed, past_tip)
+        });
+        std::fs::remove_dir_all(&datadir).unwrap();
+
+        assert_eq!(
+            genesis.hash,
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+        assert_eq!(genesis.raw, hex::decode(GENESIS_BLOCK_HEX).unwrap());
+        assert_eq!((block_1.height, block_1.previous_hash), (1, genesis.hash));
+        assert_eq!(block_1.next_hash, None);
+        assert_eq!(block_1.txids.len(), 1);
+        assert_eq!(streamed, vec![0, 1]);
+        assert_eq!(past_tip.unwrap_err().code(), tonic::Code::NotFound);
+    }
+}
@@ -0,0 +1,45 @@
+//! A bitcoin block parser for the blk*.dat files written by Bitcoin Core.
+//!
+//! ```no_run
+//! use bitcoin_block_finder::BlockFileReader;
+//!
+//! for block in BlockFileReader::open("blocks", None)? {
+//!     let (block_info, block) = block?;
+//!     println!("{} {}", block_info.height, block.block_header().block_hash());
+//! }
+//! # Ok::<(), anyhow::Error>(())
+//! ```
+pub mod chain;
+#[cfg(feature = "ffi")]
+pub mod ffi;
+#[cfg(feature = "grpc")]
+pub mod grpc;
+#[cfg(feature = "p2p")]
+pub mod p2p;
+#[cfg(feature = "parquet")]
+pub mod parquet;
+#[cfg(feature = "python")]
+pub mod python;
+pub mod reader;
+#[cfg(feature = "rpc")]
+pub mod rpc;
+#[cfg(feature = "rust-bitcoin")]
+pub mod rust_bitcoin;
+#[cfg(feature = "secp256k1")]
+pub mod signature;
+pub mod util;
+#[cfg(feature = "wasm")]
+pub mod wasm;
+#[cfg(feature = "zmq")]
+pub mod zmq;
+
+pub use chain::Chain;
+#[cfg(feature = "async")]
+pub use reader::async_reader::AsyncBlockFileReader;
+pub use reader::BlockFileReader;
+pub use util::{
+    constant::Network,
+    error::{ErrorKind, ParseError},
+    tx::{OutPoint, Transaction, TxIn, TxOut},
+    types::{Block, BlockHeader, BlockInfo, BlockLocation},
+};
@@ -1,40 +1,527 @@
-use crate::util::types::{Block, BlockInfo};
 use anyhow::Result;
+use bitcoin_block_finder::{
+    chain::utxo,
+    util::{
+        display::{format_utc, set_color, set_network, set_options, DisplayOptions},
+        field::BlockField,
+        hash::to_display_hex,
+        log, log_block_transactions, log_surrounding_headers, Format,
+    },
+    Block, BlockFileReader, BlockInfo, Chain, ErrorKind,
+};
 use clap::Parser;
-use cli::Context;
-use std::fs::read;
-use util::log;
+use cli::{Command, Context, UtxoCommand};
+use std::{
+    fs::File,
+    io::{BufWriter, IsTerminal, Write},
+    ops::RangeInclusive,
+    process::ExitCode,
+};
 
 mod cli;
-mod util;
-
-fn main() -> Result<()> {
-    let Context {
-        block_at_height: height,
-    } = Context::parse();
-    // To make this more dynamic, we could add a path feature
-    // to Context and if one isn't provided it will look in
-    // the current directory. For now, hard coding the file is good enough.
-    let mut raw_bytes = read("blk00000.dat")?;
-    find_block(&mut raw_bytes, height)
-}
-
-fn find_block(mut raw_bytes: &mut Vec<u8>, height: u64) -> Result<()> {
-    raw_bytes.reverse();
-    // Get BlockInfo and Blocks, print on success
-    //
-    // This could be done less destructively by other indexing means,
-    // taking the brute force approach for convenience.
-    let mut block_height = 0;
-    while !raw_bytes.is_empty() {
-        let block_info = BlockInfo::from_raw_bytes(&mut raw_bytes, block_height);
-        block_info.validate_network()?;
-        let block = Block::from_raw_bytes(&mut raw_bytes, block_info.size_as_u32());
-        if block_info.height == height {
-            return Ok(log(block_info, block));
-        }
-        block_height += 1;
-    }
-
-    Err(anyhow::anyhow!("failed to find block"))
+mod command;
+mod progress;
+
+/// The blocks being searched for.
+#[derive(Debug)]
+enum Query {
+    Height(u64),
+    Hash([u8; 32]),
+    Range { from: u64, to: Option<u64> },
+    Time { time: u32, median_time_past: bool },
+}
+impl Query {
+    /// Every block asked for, the heights then the hashes when several are given.
+    fn from_context(context: &Context) -> Vec<Self> {
+        let blocks = context
+            .block_at_height
+            .iter()
+            .map(|height| Query::Height(*height))
+            .chain(context.block_hash.iter().map(|hash| Query::Hash(*hash)))
+            .collect::<Vec<_>>();
+        if !blocks.is_empty() {
+            return blocks;
+        }
+        // clap guarantees one of the others is present.
+        match context.at_time {
+            Some(time) => vec![Query::Time {
+                time,
+                median_time_past: context.median_time_past,
+            }],
+            None => vec![Query::Range {
+                from: context.from_height.unwrap_or(0),
+                to: context.to_height,
+            }],
+        }
+    }
+    /// Resolve the query to the heights it covers on the chain, reading
+    /// headers from `blocks` when searching by time.
+    fn heights(&self, chain: &Chain, blocks: &mut BlockFileReader) -> Result<RangeInclusive<u64>> {
+        let not_found = || ErrorKind::NotFound.error(format!("failed to find block {self}"));
+        match self {
+            Query::Height(height) => chain
+                .get(*height)
+                .map(|_| *height..=*height)
+                .ok_or_else(not_found),
+            Query::Hash(hash) => chain
+                .height_of(hash)
+                .map(|height| height..=height)
+                .ok_or_else(not_found),
+            Query::Range { from, to } => {
+                let tip = (chain.len() as u64).checked_sub(1).ok_or_else(not_found)?;
+                Ok(*from..=to.unwrap_or(tip))
+            }
+            Query::Time {
+                time,
+                median_time_past,
+            } => chain
+                .height_at_time(*time, *median_time_past, |height| {
+                    let entry = chain.get(height).expect("expected a height on the chain");
+                    Ok(blocks.read_header_at(entry.location)?.unix_epoch_time())
+                })?
+                .map(|height| height..=height)
+                .ok_or_else(not_found),
+        }
+    }
+}
+impl std::fmt::Display for Query {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Query::Height(height) => write!(f, "at height {height}"),
+            Query::Hash(hash) => write!(f, "with hash {}", to_display_hex(hash)),
+            Query::Range { from, to: Some(to) } => write!(f, "between heights {from} and {to}"),
+            Query::Range { from, to: None } => write!(f, "from height {from}"),
+            Query::Time {
+                time,
+                median_time_past,
+            } => {
+                write!(f, "at or before {}", format_utc(*time))?;
+                if *median_time_past {
+                    write!(f, " by median time past")?;
+                }
+                Ok(())
+            }
+        }
+    }
+}
+
+fn main() -> ExitCode {
+    let context = Context::parse();
+    let error_format = context.error_format;
+    match run(context) {
+        Ok(()) => ExitCode::SUCCESS,
+        Err(e) => report(&e, error_format),
+    }
+}
+
+/// The exit code of a failure of `kind`, distinct for each kind so scripts can tell them
+/// apart, and clear of the 2 clap exits with for bad arguments.
+fn exit_code(kind: ErrorKind) -> u8 {
+    match kind {
+        ErrorKind::Other => 1,
+        ErrorKind::NotFound => 3,
+        ErrorKind::Corrupt => 4,
+        ErrorKind::WrongNetwork => 5,
+        ErrorKind::Io => 6,
+    }
+}
+
+/// Write `error` to stderr as --error-format asks, returning the exit code for its kind.
+fn report(error: &anyhow::Error, format: Format) -> ExitCode {
+    let kind = ErrorKind::of(error);
+    let code = exit_code(kind);
+    match format {
+        Format::Json => eprintln!(
+            "{}",
+            serde_json::json!({
+                "error": error.to_string(),
+                "causes": error.chain().skip(1).map(ToString::to_string).collect::<Vec<_>>(),
+                "kind": kind.name(),
+                "exit_code": code,
+            })
+        ),
+        _ => eprintln!("Error: {error:?}"),
+    }
+    ExitCode::from(code)
+}
+
+fn run(context: Context) -> Result<()> {
+    if !matches!(context.error_format, Format::Text | Format::Json) {
+        anyhow::bail!("--error-format only supports text and json")
+    }
+    if context.per_tx && context.format != Format::Ndjson {
+        anyhow::bail!("--per-tx is only supported with --format ndjson")
+    }
+    if context.context_blocks.is_some() && context.format != Format::Text {
+        anyhow::bail!("--context is only supported with text output")
+    }
+    if context.fields.is_some() && context.format != Format::Text {
+        anyhow::bail!("--fields is only supported with text output")
+    }
+    tracing_subscriber::fmt()
+        .with_writer(std::io::stderr)
+        .with_ansi(context.color.enabled(std::io::stderr().is_terminal()))
+        .with_max_level(context.log_level())
+        .with_target(false)
+        .without_time()
+        .init();
+    if let Some(threads) = context.threads {
+        rayon::ThreadPoolBuilder::new()
+            .num_threads(threads)
+            .build_global()?;
+    }
+    set_color(context.stdout_color());
+    set_options(DisplayOptions {
+        local_time: context.local_time,
+        network: context.network,
+        verbosity: context.verbose,
+        human: context.human(),
+        show_miner: context.show_miner,
+    });
+    match &context.command {
+        Some(Command::Balance { address }) => command::balance::run(&context, address),
+        Some(Command::Decode { hex, file }) => {
+            command::decode::run(&context, hex.as_deref(), file.as_deref())
+        }
+        Some(Command::Template { file }) => command::template::run(&context, file.as_deref()),
+        Some(Command::DecodeScript { hex }) => command::decode_script::run(&context, hex),
+        Some(Command::FindTx { txid }) => command::find_tx::run(&context, *txid),
+        Some(Command::FindAddress { address }) => command::find_address::run(&context, address),
+        Some(Command::History { script_hash }) => command::history::run(&context, *script_hash),
+        Some(Command::Intervals { from, to, count }) => {
+            command::intervals::run(&context, from.unwrap_or(0), *to, *count)
+        }
+        Some(Command::Hashrate { window, from, to }) => {
+            command::hashrate::run(&context, *window, from.unwrap_or(0), *to)
+        }
+        Some(Command::Index) => command::index::run(&context),
+        Some(Command::Lint { from, to }) => command::lint::run(&context, from.unwrap_or(0), *to),
+        Some(Command::LintTx { from, to }) => {
+            command::lint_tx::run(&context, from.unwrap_or(0), *to)
+        }
+        Some(Command::Nonces { from, to }) => {
+            command::nonces::run(&context, from.unwrap_or(0), *to)
+        }
+        Some(Command::Orphans) => command::orphans::run(&context),
+        Some(Command::VerifyFiles) => command::verify_files::run(&context),
+        Some(Command::Retargets) => command::retargets::run(&context),
+        Some(Command::Stats {
+            from,
+            to,
+            with_fees,
+        }) => command::stats::run(&context, from.unwrap_or(0), *to, *with_fees),
+        Some(Command::Top {
+            by,
+            limit,
+            smallest,
+            from,
+            to,
+        }) => command::top::run(&context, *by, *limit, *smallest, from.unwrap_or(0), *to),
+        Some(Command::Miners { from, to }) => {
+            command::miners::run(&context, from.unwrap_or(0), *to)
+        }
+        Some(Command::EmptyBlocks { from, to, by_miner }) => {
+            command::empty_blocks::run(&context, from.unwrap_or(0), *to, *by_miner)
+        }
+        Some(Command::Export {
+            from_height,
+            to_height,
+        }) => command::export::run(&context, from_height.unwrap_or(0), *to_height),
+        Some(Command::ExportHeaders {
+            from_height,
+            to_height,
+            out,
+        }) => command::export_headers::run(
+            &context,
+            from_height.unwrap_or(0),
+            *to_height,
+            out.as_deref(),
+        ),
+        #[cfg(feature = "parquet")]
+        Some(Command::ExportParquet {
+            from_height,
+            to_height,
+            out_dir,
+            with_fees,
+        }) => command::export_parquet::run(
+            &context,
+            from_height.unwrap_or(0),
+            *to_height,
+            out_dir,
+            *with_fees,
+        ),
+        Some(Command::Utxo { command: utxo }) => match utxo {
+            UtxoCommand::Build {
+                to_height,
+                txoutset,
+            } => command::utxo::build(&context, *to_height, txoutset.as_deref()),
+            UtxoCommand::Import { file } => command::utxo::import(&context, file),
+        },
+        Some(Command::Serve { listen }) => command::serve::run(&context, listen),
+        #[cfg(feature = "grpc")]
+        Some(Command::ServeGrpc { listen }) => command::serve_grpc::run(&context, *listen),
+        #[cfg(feature = "p2p")]
+        Some(Command::Fetch { hash, peer }) => command::fetch::run(&context, *hash, peer),
+        #[cfg(feature = "tui")]
+        Some(Command::Tui) => command::tui::run(&context),
+        None => find_blocks(
+            context.block_files()?,
+            Query::from_context(&context),
+            &context,
+        ),
+    }
+}
+
+fn find_blocks(mut blocks: BlockFileReader, queries: Vec<Query>, context: &Context) -> Result<()> {
+    let mut raw_out: Box<dyn Write> = match &context.out {
+        Some(path) => Box::new(BufWriter::new(File::create(path).map_err(|e| {
+            ErrorKind::Io.error(format!("failed to create {}: {e}", path.display()))
+        })?)),
+        None => Box::new(std::io::stdout()),
+    };
+    // Only blocks asked for one at a time are cached, rather than every block of a range.
+    let caching = !context.no_cache
+        && context.reads_single_blocks()
+        && queries
+            .iter()
+            .all(|query| matches!(query, Query::Height(_) | Query::Hash(_)));
+    let mut print = |block_info: BlockInfo, block: Block| -> Result<()> {
+        set_network(block_info.network()?);
+        if caching {
+            if let Err(e) = command::cache::put(&block_info, &block) {
+                tracing::warn!(
+                    "Failed to cache block {}: {e}",
+                    block.block_header().block_hash()
+                );
+            }
+        }
+        let block = block.with_height(block_info.height);
+        if context.verify {
+            block.block_header().validate_pow()?;
+            block.verify_merkle_root()?;
+            block.verify_witness_commitment()?;
+        }
+        if context.raw {
+            writeln!(raw_out, "{}", hex::encode(block.raw_bytes()))?;
+            // Blocks turn up one at a time while following a node, so don't hold them back.
+            if context.follows() {
+                raw_out.flush()?;
+            }
+        } else if let Some(fields) = &context.fields {
+            println!("{}", BlockField::line(fields, block_info.height, &block));
+        } else if context.per_tx {
+            log_block_transactions(&block_info, &block)?;
+        } else {
+            log(block_info, block, context.format)?;
+        }
+        Ok(())
+    };
+    let found = match context.trust_bip34 {
+        true => find_by_bip34(&mut blocks, &queries)?,
+        false => None,
+    };
+    let found = match found {
+        Some(found) => Some(found),
+        None => find_single_blocks(context, &mut blocks, &queries)?,
+    };
+    if let Some(found) = found {
+        for (block_info, block) in found {
+            print(block_info, block)?;
+        }
+        raw_out.flush()?;
+        return Ok(());
+    }
+    // Link every block into the chain first, since blk files are
+    // not in height order, then seek back to each one requested,
+    // printing them as they are read. However many are asked for,
+    // the files are only scanned the once.
+    let chain = context.chain(&mut blocks)?;
+    let mut utxos = match context.needs_utxos() {
+        true => Some(command::fees::load(context, &chain)?),
+        false => None,
+    };
+    for query in &queries {
+        let heights = match query.heights(&chain, &mut blocks) {
+            Ok(heights) => heights,
+            Err(e) => {
+                // Stale blocks are in the blk files but not on the chain, so are searched for.
+                let stale = match query {
+                    Query::Hash(hash) => blocks.find_stale(&chain, hash)?,
+                    _ => None,
+                };
+                let found = match stale {
+                    Some(found) => Some(found),
+                    None => fetch_missing(context, &blocks, query)?,
+                };
+                let (block_info, block) = found.ok_or(e)?;
+                let neighbours = chain.neighbours(block.block_header());
+                print(block_info.with_neighbours(neighbours), block)?;
+                continue;
+            }
+        };
+        if heights.is_empty() && !context.follows() {
+            return Err(ErrorKind::NotFound
+                .error(format!("failed to find blocks {query}, the range is empty")));
+        }
+        for height in heights.clone() {
+            let (block_info, mut block) = match chain.get(height) {
+                Some(entry) => blocks.read_at(entry.location, height)?,
+                None => {
+                    fetch_missing(context, &blocks, &Query::Height(height))?.ok_or_else(|| {
+                        ErrorKind::NotFound
+                            .error(format!("failed to find block at height {height}"))
+                    })?
+                }
+            };
+            #[cfg_attr(not(feature = "secp256k1"), allow(unused_variables))]
+            let spent = match utxos.as_mut() {
+                Some(utxos) => Some(command::fees::apply(
+                    context,
+                    &mut blocks,
+                    &chain,
+                    utxos,
+                    height,
+                    &mut block,
+                )?),
+                None if context.undo => {
+                    let entry = chain.get(height).ok_or_else(|| {
+                        ErrorKind::NotFound.error(format!("failed to find undo data for block {height}, it isn't in the blk files"))
+                    })?;
+                    let spent = blocks.read_undo(entry.location, &block)?.prevouts();
+                    block.set_spent(&spent);
+                    Some(spent)
+                }
+                None => None,
+            };
+            #[cfg(feature = "secp256k1")]
+            if context.verify_signatures {
+                command::signatures::verify(context, height, &block, spent)?;
+            }
+            let neighbours = chain.neighbours(block.block_header());
+            print(block_info.with_neighbours(neighbours), block)?;
+        }
+        if let Some(count) = context.context_blocks {
+            let target = *heights.start();
+            let surrounding = target.saturating_sub(count)..=target.saturating_add(count);
+            let headers = surrounding
+                .filter_map(|height| Some((height, chain.get(height)?)))
+                .map(|(height, entry)| Ok((height, blocks.read_header_at(entry.location)?)))
+                .collect::<Result<Vec<_>>>()?;
+            log_surrounding_headers(&headers, target);
+        }
+    }
+    if let Some(utxos) = &utxos {
+        utxo::save(&context.utxo_path(), utxos)?;
+    }
+    #[cfg(feature = "zmq")]
+    if let Some(address) = &context.zmq {
+        command::zmq::run(context, address, &mut blocks, chain, &mut print)?;
+        return Ok(());
+    }
+    if context.watch {
+        command::watch::run(context, &mut blocks, chain, &mut print)?;
+    }
+    raw_out.flush()?;
+    Ok(())
+}
+
+/// Read the blocks of `queries` without linking the chain, each hash from the cache of
+/// recently printed blocks and each height from where the block index puts it, or `None`
+/// when they can't all be read that way.
+fn find_single_blocks(
+    context: &Context,
+    blocks: &mut BlockFileReader,
+    queries: &[Query],
+) -> Result<Option<Vec<(BlockInfo, Block)>>> {
+    if !context.reads_single_blocks() {
+        return Ok(None);
+    }
+    let hashes = queries
+        .iter()
+        .map(|query| match query {
+            Query::Hash(hash) => Some(*hash),
+            _ => None,
+        })
+        .collect::<Option<Vec<[u8; 32]>>>();
+    if let Some(hashes) = hashes.filter(|_| !context.no_cache) {
+        return hashes
+            .iter()
+            .map(|hash| command::cache::get(context, hash))
+            .collect::<Result<Option<Vec<_>>>>();
+    }
+    let heights = queries
+        .iter()
+        .map(|query| match query {
+            Query::Height(height) => Some(*height),
+            _ => None,
+        })
+        .collect::<Option<Vec<u64>>>();
+    match heights {
+        Some(heights) => command::index::lookup(context, blocks, &heights),
+        None => Ok(None),
+    }
+}
+
+/// Find the block at each height of `queries` by the height its coinbase commits to (BIP34),
+/// or `None` when a height isn't committed to by any block or is from before BIP34 was
+/// enforced, for every block to be found by linking the chain instead.
+fn find_by_bip34(
+    blocks: &mut BlockFileReader,
+    queries: &[Query],
+) -> Result<Option<Vec<(BlockInfo, Block)>>> {
+    // The network is only known up front when given, otherwise once a block has been read.
+    let trusted = |blocks: &BlockFileReader, height: u64| match blocks.network() {
+        Some(network) if height < network.bip34_height() => {
+            tracing::warn!(
+                "Heights before {} aren't committed to on {}, linking the chain instead",
+                network.bip34_height(),
+                network.name()
+            );
+            false
+        }
+        _ => true,
+    };
+    let mut found = Vec::with_capacity(queries.len());
+    for query in queries {
+        let Query::Height(height) = *query else {
+            return Ok(None);
+        };
+        if !trusted(blocks, height) {
+            return Ok(None);
+        }
+        let Some(location) = blocks.find_by_bip34_height(height)? else {
+            tracing::warn!("No coinbase commits to height {height}, linking the chain instead");
+            return Ok(None);
+        };
+        let (block_info, block) = blocks.read_at(location, height)?;
+        if !trusted(blocks, height) {
+            return Ok(None);
+        }
+        found.push((block_info, block));
+    }
+    Ok(Some(found))
+}
+
+/// Fetch a single block missing from the blk files from the RPC server given by --rpc-url,
+/// or `None` without one.
+#[cfg_attr(not(feature = "rpc"), allow(unused_variables))]
+fn fetch_missing(
+    context: &Context,
+    blocks: &BlockFileReader,
+    query: &Query,
+) -> Result<Option<(BlockInfo, Block)>> {
+    #[cfg(feature = "rpc")]
+    if let Some(rpc) = context.rpc() {
+        let hash = match query {
+            Query::Height(height) => rpc.block_hash(*height)?,
+            Query::Hash(hash) => *hash,
+            Query::Range { .. } | Query::Time { .. } => return Ok(None),
+        };
+        let network = match blocks.network() {
+            Some(network) => network,
+            None => rpc.network()?,
+        };
+        return rpc.block(&hash, network).map(Some);
+    }
+    Ok(None)
 }
@@ -0,0 +1,290 @@
+//! Downloading blocks straight from a peer over the Bitcoin P2P protocol,
+//! for when neither the blk files nor a node's RPC server are at hand.
+//!
+//! Every message is framed by a 24 byte header: the network's magic bytes, a null padded
+//! 12 byte command, the payload length and the first four bytes of the payload's double SHA256.
+use crate::util::{
+    constant::{Network, BLOCK_HEADER},
+    error::ErrorKind,
+    hash::{sha256d, to_display_hex},
+    types::{Block, BlockInfo, ByteCursor, CompactSize},
+};
+use anyhow::{bail, Result};
+use std::{
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+/// The protocol version sent in the handshake, the one that introduced wtxid relay.
+const PROTOCOL_VERSION: i32 = 70016;
+/// The inventory type of a block requested along with its witness data.
+const MSG_WITNESS_BLOCK: u32 = 0x4000_0002;
+/// The most headers a peer sends in reply to a single getheaders.
+const MAX_HEADERS: usize = 2000;
+/// The largest payload accepted, matching the limit nodes enforce.
+const MAX_PAYLOAD: u32 = 32 * 1024 * 1024;
+/// How long to wait for a peer to answer before giving up.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A connection to a peer that has completed the version handshake.
+#[derive(Debug)]
+pub struct Peer {
+    stream: TcpStream,
+    network: Network,
+}
+impl Peer {
+    /// Connect to the peer at `address` and exchange version and verack messages.
+    pub fn connect(address: impl ToSocketAddrs, network: Network) -> Result<Self> {
+        let address = address
+            .to_socket_addrs()
+            .map_err(|e| anyhow::anyhow!("failed to resolve the peer address: {e}"))?
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("failed to resolve the peer address"))?;
+        let stream = TcpStream::connect_timeout(&address, TIMEOUT)
+            .map_err(|e| anyhow::anyhow!("failed to connect to {address}: {e}"))?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        let mut peer = Self { stream, network };
+        peer.send("version", &version_payload())?;
+        let (mut version, mut verack) = (false, false);
+        while !(version && verack) {
+            let (command, _) = peer.receive()?;
+            match command.as_str() {
+                "version" => {
+                    version = true;
+                    peer.send("verack", &[])?;
+                }
+                "verack" => verack = true,
+                _ => {}
+            }
+        }
+        Ok(peer)
+    }
+    /// Send a message with `command` and `payload`.
+    pub fn send(&mut self, command: &str, payload: &[u8]) -> Result<()> {
+        tracing::debug!("sending {command} with a {} byte payload", payload.len());
+        let mut command_bytes = [0; 12];
+        command_bytes[..command.len()].copy_from_slice(command.as_bytes());
+        let message = [
+            self.network.magic_bytes().as_slice(),
+            &command_bytes,
+            &(payload.len() as u32).to_le_bytes(),
+            &sha256d(payload)[..4],
+            payload,
+        ]
+        .concat();
+        self.stream
+            .write_all(&message)
+            .map_err(|e| anyhow::anyhow!("failed to send {command}: {e}"))
+    }
+    /// Receive the next message, returning its command and payload.
+    ///
+    /// Pings are answered along the way, so a peer doesn't drop the connection
+    /// while waiting on a large block.
+    pub fn receive(&mut self) -> Result<(String, Vec<u8>)> {
+        loop {
+            let mut header = [0; 24];
+            self.stream
+                .read_exact(&mut header)
+                .map_err(|e| ErrorKind::Io.error(format!("failed to read from the peer: {e}")))?;
+            if header[..4] != self.network.magic_bytes() {
+                return Err(ErrorKind::WrongNetwork.error(format!(
+                    "failed to read from the peer, it sent magic bytes {} rather than {}'s",
+                    hex::encode(&header[..4]),
+                    self.network
+                )));
+            }
+            let command = String::from_utf8_lossy(&header[4..16])
+                .trim_end_matches('\0')
+                .to_string();
+            let len = u32::from_le_bytes(header[16..20].try_into().expect("expected 4 bytes"));
+            if len > MAX_PAYLOAD {
+                bail!("failed to read {command} from the peer, its {len} byte payload is too large")
+            }
+            let mut payload = vec![0; len as usize];
+            self.stream.read_exact(&mut payload).map_err(|e| {
+                ErrorKind::Io.error(format!("failed to read {command} from the peer: {e}"))
+            })?;
+            if sha256d(&payload)[..4] != header[20..24] {
+                bail!("failed to read {command} from the peer, its checksum doesn't match")
+            }
+            tracing::debug!("received {command} with a {len} byte payload");
+            if command == "ping" {
+                self.send("pong", &payload)?;
+                continue;
+            }
+            return Ok((command, payload));
+        }
+    }
+    /// Download the block with `hash`, in internal byte order.
+    pub fn block(&mut self, hash: &[u8; 32]) -> Result<Block> {
+        let inventory = [
+            CompactSize::encode(1).as_slice(),
+            &MSG_WITNESS_BLOCK.to_le_bytes(),
+            hash,
+        ]
+        .concat();
+        self.send("getdata", &inventory)?;
+        loop {
+            let (command, payload) = self.receive()?;
+            match command.as_str() {
+                "block" => {
+                    let block = Block::from_raw_bytes(&mut ByteCursor::new(payload.as_slice()))?;
+                    // Peers announce other blocks unprompted, so only stop at the one asked for.
+                    if block.block_header().hash() == *hash {
+                        return Ok(block);
+                    }
+                }
+                "notfound" => bail!(
+                    "failed to fetch block {}, the peer doesn't have it",
+                    to_display_hex(hash)
+                ),
+                _ => {}
+            }
+        }
+    }
+    /// The height of the block with `hash` on the peer's best chain, found by
+    /// downloading headers from the genesis block until it turns up.
+    ///
+    /// Heights up to a few hundred thousand take a few hundred requests,
+    /// so this is best kept for blocks that don't commit to their own height.
+    pub fn height_of(&mut self, hash: &[u8; 32]) -> Result<Option<u64>> {
+        // A locator the peer doesn't know starts its reply just after the genesis block.
+        let mut locator = [0; 32];
+        let mut height = 0;
+        loop {
+            let payload = [
+                PROTOCOL_VERSION.to_le_bytes().as_slice(),
+                &CompactSize::encode(1),
+                &locator,
+                &[0; 32],
+            ]
+            .concat();
+            self.send("getheaders", &payload)?;
+            let headers = loop {
+                let (command, payload) = self.receive()?;
+                if command == "headers" {
+                    break payload;
+                }
+            };
+
+            let mut cursor = ByteCursor::new(headers.as_slice());
+            let count = CompactSize::from_raw_bytes(&mut cursor)?.to_u64() as usize;
+            for _ in 0..count {
+                let header = cursor.take(BLOCK_HEADER, "block header")?;
+                let _tx_count = CompactSize::from_raw_bytes(&mut cursor)?;
+                height += 1;
+                locator = sha256d(&header);
+                if locator == *hash {
+                    return Ok(Some(height));
+                }
+            }
+            if count < MAX_HEADERS {
+                return Ok(None);
+            }
+        }
+    }
+    /// Download the block with `hash` and label it as a record read from a blk file would be.
+    pub fn fetch(&mut self, hash: &[u8; 32]) -> Result<(BlockInfo, Block)> {
+        let block = self.block(hash)?;
+        // Blocks since BIP34 commit to their height, older ones have to be looked up.
+        let height = match block
+            .coinbase()
+            .and_then(|coinbase| coinbase.bip34_height())
+        {
+            Some(height) => height,
+            None if block.block_header().is_genesis() => 0,
+            None => self.height_of(hash)?.ok_or_else(|| {
+                ErrorKind::NotFound.error(format!(
+                    "failed to find the height of block {}, it isn't on the peer's best chain",
+                    to_display_hex(hash)
+                ))
+            })?,
+        };
+        let size = (block.raw_bytes().len() as u32).to_le_bytes().to_vec();
+        let block_info = BlockInfo::new(height, self.network.magic_bytes().to_vec(), size)
+            .with_block_hash(*hash);
+        Ok((block_info, block))
+    }
+}
+
+/// The version message payload, advertising no services and no relay
+/// since only the requested blocks are wanted.
+fn version_payload() -> Vec<u8> {
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_or(0, |duration| duration.as_secs() as i64);
+    // Services, an IPv6 address and a big endian port, left unspecified.
+    let address = [0; 26];
+    let user_agent = format!("/bitcoin-block-finder:{}/", env!("CARGO_PKG_VERSION"));
+    [
+        PROTOCOL_VERSION.to_le_bytes().as_slice(),
+        &0u64.to_le_bytes(),
+        &timestamp.to_le_bytes(),
+        &address,
+        &address,
+        // The nonce, only used by nodes to spot connections to themselves.
+        &timestamp.to_le_bytes(),
+        &CompactSize::encode(user_agent.len() as u64),
+        user_agent.as_bytes(),
+        &0i32.to_le_bytes(),
+        &[0],
+    ]
+    .concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Peer;
+    use crate::{util::fixtures::BLOCK_1_HEX, Network};
+    use std::net::TcpListener;
+
+    /// Checks the handshake and that a requested block and its height come back,
+    /// against a peer that serves the first two blocks.
+    #[test]
+    fn test_peer_fetch() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut node = Peer {
+                stream,
+                network: Network::Mainnet,
+            };
+            let block_1 = hex::decode(BLOCK_1_HEX).unwrap();
+            loop {
+                let Ok((command, _)) = node.receive() else {
+                    return;
+                };
+                match command.as_str() {
+                    "version" => {
+                        node.send("version", &super::version_payload()).unwrap();
+                        node.send("ping", &[7; 8]).unwrap();
+                        node.send("verack", &[]).unwrap();
+                    }
+                    "getdata" => node.send("block", &block_1).unwrap(),
+                    "getheaders" => {
+                        // The reply to an unknown locator starts after the genesis block.
+                        let headers = [&[1], &block_1[..80], &[0]].concat();
+                        node.send("headers", &headers).unwrap();
+                    }
+                    _ => {}
+                }
+            }
+        });
+
+        let mut peer = Peer::connect(address, Network::Mainnet).unwrap();
+        let mut hash = crate::util::hash::from_display_hex(
+            "00000000839a8e6886ab5951d76f411475428afc90947ee320161bbf18eb6048",
+        )
+        .unwrap();
+        let (block_info, block) = peer.fetch(&hash).unwrap();
+        assert_eq!(block_info.height, 1);
+        assert_eq!(block.block_header().hash(), hash);
+
+        hash[0] ^= 1;
+        assert_eq!(peer.height_of(&hash).unwrap(), None);
+    }
+}
@@ -0,0 +1,131 @@
+use anyhow::Result;
+use bitcoin_block_finder::{Block, BlockFileReader, BlockHeader, BlockInfo};
+use indicatif::{ProgressBar, ProgressStyle};
+use std::{
+    sync::atomic::{AtomicU64, Ordering},
+    time::Instant,
+};
+
+/// Iterates the blocks of a [BlockFileReader], drawing a progress bar on stderr
+/// as the files are consumed and printing a summary of the scan once it's done.
+pub(crate) struct Progress<'a> {
+    blocks: &'a mut BlockFileReader,
+    bar: Option<ProgressBar>,
+    started: Instant,
+    scanned: u64,
+}
+impl<'a> Progress<'a> {
+    /// Track a scan of `blocks`, drawing nothing at all when `quiet`.
+    pub(crate) fn new(blocks: &'a mut BlockFileReader, quiet: bool) -> Result<Self> {
+        let bar = if quiet {
+            None
+        } else {
+            let bar = ProgressBar::new(blocks.total_size()?);
+            bar.set_style(
+                ProgressStyle::with_template(
+                    "[{elapsed_precise}] {wide_bar} {bytes}/{total_bytes} ({bytes_per_sec}, {eta})",
+                )?
+                .progress_chars("=> "),
+            );
+            Some(bar)
+        };
+        Ok(Self {
+            blocks,
+            bar,
+            started: Instant::now(),
+            scanned: 0,
+        })
+    }
+    /// Read the blocks with [BlockFileReader::par_map] instead of one at a time,
+    /// advancing the bar by each block's size as the threads get through them.
+    pub(crate) fn par_map<T: Send>(
+        self,
+        f: impl Fn(&BlockInfo, &Block) -> T + Sync,
+    ) -> Result<Vec<T>> {
+        self.par_map_with(|blocks, tick| {
+            blocks.par_map(|block_info, block| {
+                tick(&block_info);
+                f(&block_info, &block)
+            })
+        })
+    }
+    /// Read just the headers with [BlockFileReader::par_map_headers], as [Self::par_map] does blocks.
+    pub(crate) fn par_map_headers<T: Send>(
+        self,
+        f: impl Fn(&BlockInfo, &BlockHeader) -> T + Sync,
+    ) -> Result<Vec<T>> {
+        self.par_map_with(|blocks, tick| {
+            blocks.par_map_headers(|block_info, header| {
+                tick(&block_info);
+                f(&block_info, &header)
+            })
+        })
+    }
+    /// Run `map` over the reader, counting each record it passes to `tick`.
+    fn par_map_with<T>(
+        mut self,
+        map: impl FnOnce(&mut BlockFileReader, &(dyn Fn(&BlockInfo) + Sync)) -> Result<Vec<T>>,
+    ) -> Result<Vec<T>> {
+        let scanned = AtomicU64::new(0);
+        let bar = self.bar.clone();
+        let results = map(self.blocks, &|block_info| {
+            scanned.fetch_add(1, Ordering::Relaxed);
+            if let Some(bar) = &bar {
+                // The record's size field doesn't count the magic bytes and itself.
+                bar.inc(u64::from(block_info.size_as_u32()) + 8);
+            }
+        })?;
+        self.scanned = scanned.into_inner();
+        self.finish();
+        Ok(results)
+    }
+    /// Read just the headers one at a time with [BlockFileReader::headers], calling `f` on each.
+    pub(crate) fn map_headers<T>(
+        mut self,
+        f: impl Fn(&BlockInfo, &BlockHeader) -> T,
+    ) -> Result<Vec<T>> {
+        let mut results = Vec::new();
+        loop {
+            let Some(header) = self.blocks.headers().next() else {
+                break;
+            };
+            let (block_info, header) = header?;
+            self.advance();
+            results.push(f(&block_info, &header));
+        }
+        self.finish();
+        Ok(results)
+    }
+    /// Count a record read one at a time, moving the bar to where reading got to.
+    fn advance(&mut self) {
+        self.scanned += 1;
+        if let Some(bar) = &self.bar {
+            bar.set_position(self.blocks.bytes_read());
+        }
+    }
+    fn finish(&mut self) {
+        let Some(bar) = self.bar.take() else {
+            return;
+        };
+        bar.finish_and_clear();
+        let elapsed = self.started.elapsed().as_secs_f64();
+        let megabytes = self.blocks.bytes_read() as f64 / 1_000_000.0;
+        tracing::info!(
+            "Scanned {} blocks ({megabytes:.2} MB) in {elapsed:.2}s, {:.2} MB/s",
+            self.scanned,
+            megabytes / elapsed.max(f64::EPSILON),
+        );
+    }
+}
+impl Iterator for Progress<'_> {
+    type Item = Result<(BlockInfo, Block)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let block = self.blocks.next();
+        match &block {
+            Some(_) => self.advance(),
+            None => self.finish(),
+        }
+        block
+    }
+}
@@ -0,0 +1,369 @@
+//! Python bindings for reading the blk files into blocks and transactions, for notebooks
+//! and scripts to use the parser directly rather than parse the CLI's output.
+//!
+//! Built with `maturin develop` from the crate root, which enables this feature through
+//! pyproject.toml, the module is `bitcoin_block_finder`:
+//!
+//! ```python
+//! import os
+//! from bitcoin_block_finder import BlockReader
+//!
+//! for block in BlockReader(os.path.expanduser("~/.bitcoin/blocks")):
+//!     print(block.height, block.hash, len(block))
+//!     for tx in block:
+//!         print(tx.txid, tx.weight)
+//! ```
+//!
+//! Hashes are hex in the usual display order. `to_dict` gives the JSON `--format json`
+//! prints, as Python objects.
+use crate::{
+    util::{
+        decode::whole,
+        error::ErrorKind,
+        hash::to_display_hex,
+        types::{Block, BlockHeader},
+    },
+    BlockFileReader, Network, Transaction,
+};
+use pyo3::{
+    exceptions::{PyIOError, PyIndexError, PyValueError},
+    prelude::*,
+    types::PyBytes,
+};
+use std::{path::PathBuf, sync::Arc};
+
+/// The Python exception for `error`: an OSError for a failed read, otherwise a ValueError.
+fn py_error(error: anyhow::Error) -> PyErr {
+    let message = format!("{error:#}");
+    match ErrorKind::of(&error) {
+        ErrorKind::Io => PyIOError::new_err(message),
+        _ => PyValueError::new_err(message),
+    }
+}
+
+/// Turn `value` into Python objects through its JSON.
+fn to_dict<'py>(py: Python<'py>, value: &impl serde::Serialize) -> PyResult<Bound<'py, PyAny>> {
+    let json = serde_json::to_string(value)
+        .map_err(|e| PyValueError::new_err(format!("failed to serialize: {e}")))?;
+    py.import("json")?.call_method1("loads", (json,))
+}
+
+/// Iterates the blocks of a blk file, or a directory of them, in the order they were
+/// written.
+#[pyclass(name = "BlockReader", module = "bitcoin_block_finder")]
+pub struct PyBlockReader {
+    reader: BlockFileReader,
+}
+#[pymethods]
+impl PyBlockReader {
+    /// Open `path`, expecting every block to be of `network` if given, or else of the
+    /// network of the first.
+    #[new]
+    #[pyo3(signature = (path, network = None))]
+    fn new(path: PathBuf, network: Option<&str>) -> PyResult<Self> {
+        let network = network
+            .map(str::parse::<Network>)
+            .transpose()
+            .map_err(py_error)?;
+        let reader = BlockFileReader::open(path, network).map_err(py_error)?;
+        Ok(Self { reader })
+    }
+    /// The network being read, once the first block has been.
+    #[getter]
+    fn network(&self) -> Option<String> {
+        self.reader.network().map(|network| network.to_string())
+    }
+    fn __iter__(slf: PyRef<'_, Self>) -> PyRef<'_, Self> {
+        slf
+    }
+    fn __next__(&mut self, py: Python<'_>) -> PyResult<Option<PyBlock>> {
+        // Reading doesn't touch Python objects, so other threads may run meanwhile.
+        let next = py.detach(|| self.reader.next().transpose());
+        let next = next.map_err(py_error)?;
+        Ok(next.map(|(block_info, block)| PyBlock {
+            block: Arc::new(block),
+            height: Some(block_info.height),
+        }))
+    }
+}
+
+/// A block, holding its transactions in order.
+#[pyclass(name = "Block", module = "bitcoin_block_finder", frozen)]
+pub struct PyBlock {
+    block: Arc<Block>,
+    /// The block's position in the files, counting from 0, when it was read from them.
+    height: Option<u64>,
+}
+impl PyBlock {
+    fn header(&self) -> &BlockHeader {
+        self.block.block_header()
+    }
+}
+#[pymethods]
+impl PyBlock {
+    /// Parse a serialized block, failing if any bytes are left over.
+    #[staticmethod]
+    fn from_bytes(data: &[u8]) -> PyResult<Self> {
+        let block = whole(data, Block::from_raw_bytes).map_err(py_error)?;
+        Ok(Self {
+            block: Arc::new(block),
+            height: None,
+        })
+    }
+    /// The block's position in the files, counting from 0, which is its height on a chain
+    /// without stale blocks, or None for a block from bytes.
+    #[getter]
+    fn height(&self) -> Option<u64> {
+        self.height
+    }
+    #[getter]
+    fn hash(&self) -> String {
+        self.header().block_hash()
+    }
+    #[getter]
+    fn previous_hash(&self) -> String {
+        to_display_hex(&self.header().previous_hash())
+    }
+    #[getter]
+    fn merkle_root(&self) -> String {
+        to_display_hex(&self.header().merkle_root())
+    }
+    #[getter]
+    fn version(&self) -> u32 {
+        self.header().version()
+    }
+    /// The unix epoch time of the header.
+    #[getter]
+    fn time(&self) -> u32 {
+        self.header().unix_epoch_time()
+    }
+    /// The compact target of the header, its nBits.
+    #[getter]
+    fn bits(&self) -> u32 {
+        self.header().target()
+    }
+    #[getter]
+    fn nonce(&self) -> u32 {
+        self.header().nonce()
+    }
+    #[getter]
+    fn difficulty(&self) -> f64 {
+        self.header().difficulty()
+    }
+    /// The size of the serialized block in bytes, witnesses included.
+    #[getter]
+    fn size(&self) -> u32 {
+        self.block.total_size()
+    }
+    #[getter]
+    fn weight(&self) -> u32 {
+        self.block.weight()
+    }
+    #[getter]
+    fn transactions(&self) -> Vec<PyTransaction> {
+        (0..self.block.transactions().len())
+            .map(|index| PyTransaction::in_block(&self.block, index))
+            .collect()
+    }
+    /// The serialized block.
+    fn raw_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.block.raw_bytes())
+    }
+    /// The block as `decode --format json` prints it.
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_dict(py, &*self.block)
+    }
+    fn __len__(&self) -> usize {
+        self.block.transactions().len()
+    }
+    fn __getitem__(&self, index: isize) -> PyResult<PyTransaction> {
+        let len = self.block.transactions().len();
+        let position = match index {
+            0.. => index as usize,
+            _ => len.wrapping_sub(index.unsigned_abs()),
+        };
+        if position >= len {
+            return Err(PyIndexError::new_err(format!(
+                "transaction index {index} out of range for a block of {len}"
+            )));
+        }
+        Ok(PyTransaction::in_block(&self.block, position))
+    }
+    fn __iter__(&self, py: Python<'_>) -> PyResult<Py<PyAny>> {
+        let transactions = self.transactions().into_pyobject(py)?;
+        Ok(transactions.try_iter()?.into_any().unbind())
+    }
+    fn __repr__(&self) -> String {
+        format!("Block({})", self.hash())
+    }
+}
+
+/// Where a transaction is held: in the block it was read from, or on its own.
+enum Held {
+    InBlock { block: Arc<Block>, index: usize },
+    Alone(Transaction),
+}
+
+/// A transaction of a block, or parsed on its own.
+#[pyclass(name = "Transaction", module = "bitcoin_block_finder", frozen)]
+pub struct PyTransaction {
+    held: Held,
+}
+impl PyTransaction {
+    fn in_block(block: &Arc<Block>, index: usize) -> Self {
+        Self {
+            held: Held::InBlock {
+                block: Arc::clone(block),
+                index,
+            },
+        }
+    }
+    fn tx(&self) -> &Transaction {
+        match &self.held {
+            Held::InBlock { block, index } => &block.transactions()[*index],
+            Held::Alone(tx) => tx,
+        }
+    }
+}
+#[pymethods]
+impl PyTransaction {
+    /// Parse a serialized transaction, legacy or witness, failing if any bytes are left
+    /// over.
+    #[staticmethod]
+    fn from_bytes(data: &[u8]) -> PyResult<Self> {
+        let tx = whole(data, Transaction::from_raw_bytes).map_err(py_error)?;
+        Ok(Self {
+            held: Held::Alone(tx),
+        })
+    }
+    #[getter]
+    fn txid(&self) -> String {
+        to_display_hex(&self.tx().txid())
+    }
+    #[getter]
+    fn wtxid(&self) -> String {
+        to_display_hex(&self.tx().wtxid())
+    }
+    #[getter]
+    fn version(&self) -> u32 {
+        self.tx().version()
+    }
+    #[getter]
+    fn lock_time(&self) -> u32 {
+        self.tx().lock_time()
+    }
+    #[getter]
+    fn is_coinbase(&self) -> bool {
+        self.tx().is_coinbase()
+    }
+    #[getter]
+    fn is_segwit(&self) -> bool {
+        self.tx().is_segwit()
+    }
+    /// The size of the serialized transaction in bytes, witnesses included.
+    #[getter]
+    fn size(&self) -> u32 {
+        self.tx().total_size()
+    }
+    #[getter]
+    fn vsize(&self) -> u32 {
+        self.tx().vsize()
+    }
+    #[getter]
+    fn weight(&self) -> u32 {
+        self.tx().weight()
+    }
+    #[getter]
+    fn input_count(&self) -> usize {
+        self.tx().inputs().len()
+    }
+    #[getter]
+    fn output_count(&self) -> usize {
+        self.tx().outputs().len()
+    }
+    /// The sum of the output values in satoshis.
+    #[getter]
+    fn output_value(&self) -> u64 {
+        self.tx()
+            .outputs()
+            .iter()
+            .map(|output| output.value())
+            .sum()
+    }
+    /// The serialized transaction, witnesses included.
+    fn raw_bytes<'py>(&self, py: Python<'py>) -> Bound<'py, PyBytes> {
+        PyBytes::new(py, &self.tx().raw_bytes())
+    }
+    /// The transaction as it appears in `decode --format json`.
+    fn to_dict<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        to_dict(py, self.tx())
+    }
+    fn __repr__(&self) -> String {
+        format!("Transaction({})", self.txid())
+    }
+}
+
+/// The `bitcoin_block_finder` Python module.
+#[pymodule]
+fn bitcoin_block_finder(module: &Bound<'_, PyModule>) -> PyResult<()> {
+    module.add_class::<PyBlockReader>()?;
+    module.add_class::<PyBlock>()?;
+    module.add_class::<PyTransaction>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::fixtures::{blk_record, BLOCK_1_HEX, GENESIS_BLOCK_HEX};
+
+    /// Checks the blocks of a blk file iterate from Python with their transactions, that
+    /// negative indices count from the end and that bytes left over are a ValueError.
+    #[test]
+    fn test_python() {
+        let datadir = std::env::temp_dir().join("bbf-test-python");
+        std::fs::create_dir_all(&datadir).unwrap();
+        std::fs::write(
+            datadir.join("blk00000.dat"),
+            [blk_record(GENESIS_BLOCK_HEX), blk_record(BLOCK_1_HEX)].concat(),
+        )
+        .unwrap();
+
+        Python::initialize();
+        Python::attach(|py| {
+            let module = PyModule::new(py, "bitcoin_block_finder").unwrap();
+            bitcoin_block_finder(&module).unwrap();
+            let locals = pyo3::types::PyDict::new(py);
+            locals.set_item("bbf", module).unwrap();
+            locals.set_item("datadir", &datadir).unwrap();
+            locals
+                .set_item("genesis", hex::decode(GENESIS_BLOCK_HEX).unwrap())
+                .unwrap();
+            py.run(
+                pyo3::ffi::c_str!(
+                    r#"
+blocks = list(bbf.BlockReader(datadir))
+assert [block.height for block in blocks] == [0, 1]
+assert blocks[1].previous_hash == blocks[0].hash
+assert [tx.txid for tx in blocks[0]] == [blocks[0][-1].txid] == [blocks[0].merkle_root]
+assert blocks[0][0].is_coinbase and blocks[0][0].output_value == 5_000_000_000
+assert blocks[0].to_dict()["tx_count"] == 1
+
+block = bbf.Block.from_bytes(bytes(genesis))
+assert block.hash == "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+assert block.height is None and len(block) == 1
+try:
+    bbf.Block.from_bytes(bytes(genesis) + b"\0")
+    assert False
+except ValueError as e:
+    assert "left over" in str(e)
+"#
+                ),
+                None,
+                Some(&locals),
+            )
+            .unwrap();
+        });
+        std::fs::remove_dir_all(&datadir).unwrap();
+    }
+}
@@ -0,0 +1,365 @@
+//! An asynchronous reader of the blk files on `tokio::fs`, for servers and fetchers running
+//! on a tokio runtime to read blocks without holding up its worker threads.
+//!
+//! Each record is read whole into memory asynchronously, then parsed there just as
+//! [BlockFileReader] parses it.
+use super::{read_record, BlockFileReader, FileChain};
+use crate::util::{
+    constant::{Network, BLOCK_SIZE, MAGIC_BYTES},
+    error::ErrorKind,
+    types::{Block, BlockInfo, BlockLocation, ByteCursor},
+};
+use anyhow::Result;
+use futures_util::Stream;
+use std::{
+    io::{Cursor, SeekFrom},
+    path::PathBuf,
+    pin::Pin,
+    sync::Arc,
+};
+use tokio::io::{AsyncBufRead, AsyncBufReadExt, AsyncReadExt, AsyncSeek, AsyncSeekExt, BufReader};
+
+/// The bytes of a single blk file, from the file or from a stream read from stdin.
+trait Source: AsyncBufRead + AsyncSeek + Send + Sync + Unpin {}
+impl<T: AsyncBufRead + AsyncSeek + Send + Sync + Unpin> Source for T {}
+
+/// Reads every [Block] in a chain of blk files, in the order they were written, as
+/// [BlockFileReader] iterates them, but asynchronously.
+pub struct AsyncBlockFileReader {
+    files: Vec<PathBuf>,
+    xor_key: Option<[u8; 8]>,
+    /// Everything read from stdin, when opened from [STDIN_PATH](super::STDIN_PATH).
+    stdin: Option<Arc<[u8]>>,
+    network: Option<Network>,
+    /// The file being read and where in it, once iteration has begun.
+    source: Option<Pin<Box<dyn Source>>>,
+    file: usize,
+    position: u64,
+    height: u64,
+}
+impl std::fmt::Debug for AsyncBlockFileReader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AsyncBlockFileReader")
+            .field("files", &self.files)
+            .field("network", &self.network)
+            .field("file", &self.file)
+            .field("position", &self.position)
+            .field("height", &self.height)
+            .finish_non_exhaustive()
+    }
+}
+impl AsyncBlockFileReader {
+    /// Read the files of `files`, expecting `network` if given, or else the network of
+    /// the first block.
+    pub fn new(files: FileChain, network: Option<Network>) -> Self {
+        Self {
+            files: files.files,
+            xor_key: files.xor_key,
+            stdin: files.stdin,
+            network,
+            source: None,
+            file: 0,
+            position: 0,
+            height: 0,
+        }
+    }
+    /// Open a blk file, or a directory of them, as with [FileChain::open], listing the
+    /// files on a blocking thread.
+    pub async fn open(path: impl Into<PathBuf>, network: Option<Network>) -> Result<Self> {
+        let path = path.into();
+        let files = tokio::task::spawn_blocking(move || FileChain::open(path))
+            .await
+            .map_err(|e| anyhow::anyhow!("failed to list the blk files: {e}"))??;
+        Ok(Self::new(files, network))
+    }
+    /// The network being read, once known.
+    pub fn network(&self) -> Option<Network> {
+        self.network
+    }
+    /// The files being read, in reading order.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+    /// Open the file at `file`, positioned `offset` bytes in.
+    async fn open_at(&self, file: usize, offset: u64) -> Result<Pin<Box<dyn Source>>> {
+        let mut source: Pin<Box<dyn Source>> = match &self.stdin {
+            Some(bytes) => Box::pin(Cursor::new(Arc::clone(bytes))),
+            None => {
+                let path = self
+                    .files
+                    .get(file)
+                    .ok_or_else(|| anyhow::anyhow!("no file at position {file} in the chain"))?;
+                let opened = tokio::fs::File::open(path).await.map_err(|e| {
+                    ErrorKind::Io.error(format!("failed to open {}: {e}", path.display()))
+                })?;
+                Box::pin(BufReader::new(opened))
+            }
+        };
+        source.seek(SeekFrom::Start(offset)).await?;
+        Ok(source)
+    }
+    /// Whether `bytes`, de-obfuscated from `offset` in their file, are the padding after the
+    /// last record.
+    fn is_padding_at(&self, offset: u64, bytes: &[u8]) -> bool {
+        bytes.iter().enumerate().all(|(i, byte)| {
+            *byte
+                == self
+                    .xor_key
+                    .map_or(0, |key| key[((offset + i as u64) % 8) as usize])
+        })
+    }
+    /// Read up to `len` bytes of the file at `file` from `source`, undoing the XOR key from
+    /// `offset`, where they start. The buffer grows as bytes arrive, as in
+    /// [ByteCursor::take], so a corrupt size can't allocate more than the file holds.
+    async fn read_bytes(
+        &self,
+        source: &mut Pin<Box<dyn Source>>,
+        file: usize,
+        offset: u64,
+        len: u64,
+    ) -> Result<Vec<u8>> {
+        let mut bytes = Vec::new();
+        source
+            .as_mut()
+            .take(len)
+            .read_to_end(&mut bytes)
+            .await
+            .map_err(|e| ErrorKind::Io.error(format!("failed to read {}: {e}", self.path(file))))?;
+        if let Some(key) = self.xor_key {
+            for (i, byte) in bytes.iter_mut().enumerate() {
+                *byte ^= key[((offset + i as u64) % 8) as usize];
+            }
+        }
+        Ok(bytes)
+    }
+    fn path(&self, file: usize) -> String {
+        self.files
+            .get(file)
+            .map_or_else(|| "?".to_string(), |path| path.display().to_string())
+    }
+    /// Read the bytes of the record at `offset` of the file at `file`, its magic bytes and
+    /// size, then the block the size covers. Only the bytes where the magic bytes would be
+    /// are read when they're padding instead, and none when the file ends at `offset`.
+    async fn read_record_bytes(
+        &self,
+        source: &mut Pin<Box<dyn Source>>,
+        file: usize,
+        offset: u64,
+    ) -> Result<Vec<u8>> {
+        let mut bytes = self
+            .read_bytes(source, file, offset, u64::from(MAGIC_BYTES))
+            .await?;
+        if bytes.len() < MAGIC_BYTES as usize || self.is_padding_at(offset, &bytes) {
+            return Ok(bytes);
+        }
+        let size_offset = offset + u64::from(MAGIC_BYTES);
+        bytes.extend(
+            self.read_bytes(source, file, size_offset, u64::from(BLOCK_SIZE))
+                .await?,
+        );
+        let Some(size) = bytes.get(MAGIC_BYTES as usize..(MAGIC_BYTES + BLOCK_SIZE) as usize)
+        else {
+            return Ok(bytes);
+        };
+        let size = u32::from_le_bytes(size.try_into()?);
+        let block_offset = size_offset + u64::from(BLOCK_SIZE);
+        bytes.extend(
+            self.read_bytes(source, file, block_offset, u64::from(size))
+                .await?,
+        );
+        Ok(bytes)
+    }
+    /// Parse a record read whole from `offset` of the file at `file`.
+    fn parse(
+        &self,
+        bytes: &[u8],
+        file: usize,
+        offset: u64,
+        height: u64,
+        network: &mut Option<Network>,
+    ) -> Result<Option<(BlockInfo, Block)>> {
+        let mut cursor = ByteCursor::starting_at(bytes, offset);
+        // The bytes are de-obfuscated already, but the key still marks the padding.
+        read_record(&mut cursor, file, height, network, self.xor_key).map_err(|e| {
+            anyhow::Error::new(e).context(format!("failed to parse {}", self.path(file)))
+        })
+    }
+    /// Skip the padding `source` is at, leaving it at the end of the file or at whatever
+    /// follows the padding.
+    async fn skip_padding(&mut self) -> Result<()> {
+        let Some(source) = self.source.as_mut() else {
+            return Ok(());
+        };
+        loop {
+            let buf = source.fill_buf().await?;
+            if buf.is_empty() {
+                return Ok(());
+            }
+            // Read straight from the source, padding is zeros before being de-obfuscated.
+            let padding = buf.iter().take_while(|byte| **byte == 0).count();
+            let done = padding < buf.len();
+            source.as_mut().consume(padding);
+            self.position += padding as u64;
+            if done {
+                return Ok(());
+            }
+        }
+    }
+    /// The next block, from where reading left off, or none after the last.
+    pub async fn next(&mut self) -> Result<Option<(BlockInfo, Block)>> {
+        loop {
+            let mut source = match self.source.take() {
+                Some(source) => source,
+                None if self.file < self.files.len() => {
+                    self.position = 0;
+                    self.open_at(self.file, 0).await?
+                }
+                None => return Ok(None),
+            };
+            let offset = self.position;
+            let bytes = self
+                .read_record_bytes(&mut source, self.file, offset)
+                .await?;
+            if bytes.is_empty() {
+                self.file += 1;
+                continue;
+            }
+            if self.is_padding_at(offset, &bytes) {
+                // Padding runs to the end of the file, unless a record was written after it.
+                self.source = Some(source);
+                self.position = offset + bytes.len() as u64;
+                self.skip_padding().await?;
+                continue;
+            }
+            let mut network = self.network;
+            let record = self.parse(&bytes, self.file, offset, self.height, &mut network)?;
+            self.network = network;
+            self.position += bytes.len() as u64;
+            self.source = Some(source);
+            if let Some(record) = record {
+                self.height += 1;
+                return Ok(Some(record));
+            }
+        }
+    }
+    /// Stream every block, as [Self::next] reads them.
+    pub fn into_stream(self) -> impl Stream<Item = Result<(BlockInfo, Block)>> + Send {
+        futures_util::stream::unfold(Some(self), |reader| async move {
+            let mut reader = reader?;
+            match reader.next().await {
+                Ok(Some(record)) => Some((Ok(record), Some(reader))),
+                Ok(None) => None,
+                // Nothing can be read past a record that fails to parse.
+                Err(e) => Some((Err(e), None)),
+            }
+        })
+    }
+    /// Read the single block record at `location`, labelling it with `height`, without
+    /// moving where iteration reads from.
+    pub async fn read_at(
+        &self,
+        location: BlockLocation,
+        height: u64,
+    ) -> Result<(BlockInfo, Block)> {
+        let mut source = self.open_at(location.file, location.offset).await?;
+        let bytes = self
+            .read_record_bytes(&mut source, location.file, location.offset)
+            .await?;
+        let mut network = self.network;
+        self.parse(&bytes, location.file, location.offset, height, &mut network)?
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "no block at offset {} of file {}, only padding",
+                    location.offset,
+                    location.file
+                )
+            })
+    }
+}
+impl From<BlockFileReader> for AsyncBlockFileReader {
+    /// Read the files of `reader` asynchronously, from the start, with the network it
+    /// found.
+    fn from(reader: BlockFileReader) -> Self {
+        Self::new(reader.files, reader.network)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::fixtures::{blk_record, BLOCK_1_HEX, GENESIS_BLOCK_HEX};
+    use futures_util::StreamExt;
+
+    /// Checks the async reader streams the same blocks as the blocking one through XOR
+    /// obfuscated files with padding between records, and reads one back by location.
+    #[test]
+    fn test_async_block_file_reader() {
+        let datadir = std::env::temp_dir().join("bbf-test-async-reader");
+        std::fs::create_dir_all(&datadir).unwrap();
+        let key = [0x5a, 1, 2, 3, 4, 5, 6, 7];
+        // Records are obfuscated from the start of the file, but padding is left as zeros.
+        let blk_file = |segments: &[(Vec<u8>, bool)]| {
+            let mut file = Vec::new();
+            for (bytes, obfuscated) in segments {
+                let start = file.len();
+                file.extend(bytes.iter().enumerate().map(|(i, byte)| match obfuscated {
+                    true => byte ^ key[(start + i) % 8],
+                    false => *byte,
+                }));
+            }
+            file
+        };
+        std::fs::write(datadir.join("xor.dat"), key).unwrap();
+        std::fs::write(
+            datadir.join("blk00000.dat"),
+            blk_file(&[
+                (blk_record(GENESIS_BLOCK_HEX), true),
+                (vec![0; 13], false),
+                (blk_record(BLOCK_1_HEX), true),
+            ]),
+        )
+        .unwrap();
+        std::fs::write(
+            datadir.join("blk00001.dat"),
+            blk_file(&[
+                (blk_record(GENESIS_BLOCK_HEX), true),
+                (vec![0; 4096], false),
+            ]),
+        )
+        .unwrap();
+
+        let expected = BlockFileReader::open(&datadir, None)
+            .unwrap()
+            .map(|record| {
+                let (block_info, block) = record.unwrap();
+                (block_info.height, block_info.location(), block.raw_bytes())
+            })
+            .collect::<Vec<_>>();
+        let runtime = tokio::runtime::Builder::new_current_thread()
+            .enable_all()
+            .build()
+            .unwrap();
+        let (streamed, network, read_at) = runtime.block_on(async {
+            let reader = AsyncBlockFileReader::open(&datadir, None).await.unwrap();
+            let streamed = reader
+                .into_stream()
+                .map(|record| {
+                    let (block_info, block) = record.unwrap();
+                    (block_info.height, block_info.location(), block.raw_bytes())
+                })
+                .collect::<Vec<_>>()
+                .await;
+            let mut reader = AsyncBlockFileReader::open(&datadir, None).await.unwrap();
+            reader.next().await.unwrap();
+            let location = expected[1].1.unwrap();
+            let (_, block) = reader.read_at(location, 1).await.unwrap();
+            (streamed, reader.network(), block.raw_bytes())
+        });
+        std::fs::remove_dir_all(&datadir).unwrap();
+
+        assert_eq!(expected.len(), 3);
+        assert_eq!(streamed, expected);
+        assert_eq!(network, Some(Network::Mainnet));
+        assert_eq!(read_at, hex::decode(BLOCK_1_HEX).unwrap());
+    }
+}
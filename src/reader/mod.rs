@@ -0,0 +1,1327 @@
+//! Readers for walking the blk*.dat files of a bitcoin data directory.
+use crate::{
+    chain::Chain,
+    util::{
+        constant::{Network, BLOCK_HEADER, BLOCK_SIZE, MAGIC_BYTES},
+        error::{ErrorKind, ParseError},
+        hash::to_display_hex,
+        tx::Transaction,
+        types::{Block, BlockHeader, BlockInfo, BlockLocation, ByteCursor, CompactSize},
+        view::BlockView,
+    },
+};
+use anyhow::Result;
+use memmap2::Mmap;
+use rayon::prelude::*;
+use std::{
+    borrow::Cow,
+    fs::File,
+    io::{self, Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+    sync::Arc,
+};
+
+#[cfg(feature = "async")]
+pub mod async_reader;
+pub mod undo;
+pub mod verify;
+
+/// The path that reads a stream of blk records from stdin rather than from a file.
+pub const STDIN_PATH: &str = "-";
+
+/// The path of the blk file with the given number, e.g. `blk00001.dat`.
+pub fn blk_file_path(datadir: &Path, file_number: u32) -> PathBuf {
+    datadir.join(format!("blk{:05}.dat", file_number))
+}
+
+/// The number of blk files in `dir` numbered after `gap`, a file number that's missing.
+fn files_after_gap(dir: &Path, gap: u32) -> usize {
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return 0;
+    };
+    entries
+        .flatten()
+        .filter(|entry| {
+            let name = entry.file_name();
+            let number = name
+                .to_str()
+                .and_then(|name| name.strip_prefix("blk")?.strip_suffix(".dat"))
+                .and_then(|number| number.parse::<u32>().ok());
+            number.is_some_and(|number| number > gap)
+        })
+        .count()
+}
+
+/// The bytes of a single blk file, read from the file itself or from a memory map of it,
+/// and de-obfuscated with the datadir's XOR key when it has one.
+#[derive(Debug)]
+pub struct BlkSource {
+    data: BlkData,
+    xor_key: Option<[u8; 8]>,
+    position: u64,
+}
+#[derive(Debug)]
+enum BlkData {
+    File(File),
+    Mmap(io::Cursor<Mmap>),
+    /// A stream read from stdin, held in memory since it can't be read again.
+    Memory(io::Cursor<Arc<[u8]>>),
+}
+impl BlkSource {
+    fn open(path: &Path, mmap: bool, xor_key: Option<[u8; 8]>) -> Result<Self> {
+        let file = File::open(path)
+            .map_err(|e| ErrorKind::Io.error(format!("failed to open {}: {e}", path.display())))?;
+        let data = if mmap {
+            // SAFETY: blk files are append-only, the mapping is only ever read,
+            // and a node truncating a file mid-scan is no worse than a short read.
+            let map = unsafe { Mmap::map(&file) }.map_err(|e| {
+                ErrorKind::Io.error(format!("failed to map {}: {e}", path.display()))
+            })?;
+            BlkData::Mmap(io::Cursor::new(map))
+        } else {
+            BlkData::File(file)
+        };
+        Ok(Self {
+            data,
+            xor_key,
+            position: 0,
+        })
+    }
+}
+impl BlkSource {
+    /// The whole file de-obfuscated, borrowed straight from the memory map when there's
+    /// no key to undo and read into memory otherwise.
+    fn contents(&mut self) -> io::Result<Cow<'_, [u8]>> {
+        if !matches!(
+            (&self.data, self.xor_key),
+            (BlkData::Mmap(_) | BlkData::Memory(_), None)
+        ) {
+            let mut bytes = Vec::new();
+            self.seek(SeekFrom::Start(0))?;
+            self.read_to_end(&mut bytes)?;
+            return Ok(Cow::Owned(bytes));
+        }
+        match &self.data {
+            BlkData::Mmap(map) => Ok(Cow::Borrowed(map.get_ref())),
+            BlkData::Memory(bytes) => Ok(Cow::Borrowed(bytes.get_ref())),
+            BlkData::File(_) => unreachable!("expected a memory map"),
+        }
+    }
+}
+impl Read for BlkSource {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = match &mut self.data {
+            BlkData::File(file) => file.read(buf)?,
+            BlkData::Mmap(map) => map.read(buf)?,
+            BlkData::Memory(bytes) => bytes.read(buf)?,
+        };
+        if let Some(key) = self.xor_key {
+            // The key repeats from the start of the file, not from where this read began.
+            for (i, byte) in buf[..n].iter_mut().enumerate() {
+                *byte ^= key[((self.position + i as u64) % 8) as usize];
+            }
+        }
+        self.position += n as u64;
+        Ok(n)
+    }
+}
+impl Seek for BlkSource {
+    fn seek(&mut self, pos: SeekFrom) -> io::Result<u64> {
+        self.position = match &mut self.data {
+            BlkData::File(file) => file.seek(pos)?,
+            BlkData::Mmap(map) => map.seek(pos)?,
+            BlkData::Memory(bytes) => bytes.seek(pos)?,
+        };
+        Ok(self.position)
+    }
+}
+
+/// Read the key Bitcoin Core 28+ obfuscates blk files with from `xor.dat` in `dir`.
+///
+/// Returns `None` when there's no key file, or the key is all zeros and so changes nothing.
+pub fn read_xor_key(dir: &Path) -> Result<Option<[u8; 8]>> {
+    let path = dir.join("xor.dat");
+    if !path.is_file() {
+        return Ok(None);
+    }
+    let bytes = std::fs::read(&path)
+        .map_err(|e| ErrorKind::Io.error(format!("failed to read {}: {e}", path.display())))?;
+    let key: [u8; 8] = bytes
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("expected an 8 byte key in {}", path.display()))?;
+    Ok((key != [0; 8]).then_some(key))
+}
+
+/// Iterates a sequence of blk files in order, opening each as a [ByteCursor].
+#[derive(Debug, Clone)]
+pub struct FileChain {
+    files: Vec<PathBuf>,
+    /// The directory the files were listed from, unless a single file was opened.
+    dir: Option<PathBuf>,
+    next: usize,
+    mmap: bool,
+    xor_key: Option<[u8; 8]>,
+    /// Everything read from stdin, when opened from [STDIN_PATH].
+    stdin: Option<Arc<[u8]>>,
+}
+impl FileChain {
+    /// Open a chain of blk files.
+    ///
+    /// A file path yields only that file, while a directory yields
+    /// `blk00000.dat`, `blk00001.dat`, ... up to the first missing file number.
+    /// Files are de-obfuscated with the key in the `xor.dat` beside them, if there is one.
+    ///
+    /// [STDIN_PATH] yields a single stream of records read from stdin instead, which is
+    /// read to the end up front so blocks can be sought back to like those of a file.
+    pub fn open(path: impl Into<PathBuf>) -> Result<Self> {
+        let path = path.into();
+        if path.as_os_str() == STDIN_PATH {
+            let mut bytes = Vec::new();
+            io::stdin()
+                .lock()
+                .read_to_end(&mut bytes)
+                .map_err(|e| ErrorKind::Io.error(format!("failed to read stdin: {e}")))?;
+            return Ok(Self::from_bytes(path, bytes));
+        }
+        let (files, dir, listed) = if path.is_file() {
+            let dir = path.parent().map(Path::to_path_buf).unwrap_or_default();
+            (vec![path], dir, None)
+        } else {
+            let files = (0..)
+                .map(|file_number| blk_file_path(&path, file_number))
+                .take_while(|file| file.is_file())
+                .collect::<Vec<PathBuf>>();
+            if files.is_empty() {
+                return Err(ErrorKind::NotFound
+                    .error(format!("no blk00000.dat found in {}", path.display())));
+            }
+            let gap = files.len() as u32;
+            match files_after_gap(&path, gap) {
+                0 => {}
+                skipped => tracing::warn!(
+                    "{} is missing, so the blk files numbered after it aren't read, {skipped} of them",
+                    blk_file_path(&path, gap).display()
+                ),
+            }
+            (files, path.clone(), Some(path))
+        };
+
+        Ok(Self {
+            files,
+            dir: listed,
+            next: 0,
+            mmap: false,
+            xor_key: read_xor_key(&dir)?,
+            stdin: None,
+        })
+    }
+    /// A chain of a single stream of records already in memory, labelled `name`.
+    pub fn from_bytes(name: impl Into<PathBuf>, bytes: Vec<u8>) -> Self {
+        Self {
+            files: vec![name.into()],
+            dir: None,
+            next: 0,
+            mmap: false,
+            xor_key: None,
+            stdin: Some(bytes.into()),
+        }
+    }
+    /// Add any blk files created after the chain was opened from a directory,
+    /// as a node does once its latest file is full.
+    pub fn refresh(&mut self) {
+        let Some(dir) = &self.dir else {
+            return;
+        };
+        let new_files = (self.files.len() as u32..)
+            .map(|file_number| blk_file_path(dir, file_number))
+            .take_while(|file| file.is_file())
+            .collect::<Vec<PathBuf>>();
+        self.files.extend(new_files);
+    }
+    /// Memory map each file rather than reading it through the file handle.
+    pub fn with_mmap(mut self, mmap: bool) -> Self {
+        self.mmap = mmap;
+        self
+    }
+    /// The files in the chain, in reading order.
+    pub fn files(&self) -> &[PathBuf] {
+        &self.files
+    }
+    /// The combined size in bytes of every file in the chain.
+    pub fn total_size(&self) -> Result<u64> {
+        if let Some(bytes) = &self.stdin {
+            return Ok(bytes.len() as u64);
+        }
+        self.files.iter().try_fold(0, |total, path| {
+            let metadata = std::fs::metadata(path).map_err(|e| {
+                ErrorKind::Io.error(format!("failed to read {}: {e}", path.display()))
+            })?;
+            Ok(total + metadata.len())
+        })
+    }
+    /// Attach the path of the file at `file` in the chain to an error parsing it.
+    fn parse_error(&self, file: usize, e: ParseError) -> anyhow::Error {
+        let path = self
+            .files
+            .get(file)
+            .map_or(Path::new("?"), PathBuf::as_path);
+        anyhow::Error::new(e).context(format!("failed to parse {}", path.display()))
+    }
+    /// Open the file at `file` in the chain, positioned `offset` bytes in.
+    pub fn open_at(&self, file: usize, offset: u64) -> Result<ByteCursor<BlkSource>> {
+        let mut source = self.source(file)?;
+        source.seek(SeekFrom::Start(offset))?;
+        Ok(ByteCursor::starting_at(source, offset))
+    }
+    /// The bytes of the file at `file` in the chain, from its start.
+    fn source(&self, file: usize) -> Result<BlkSource> {
+        if let Some(bytes) = &self.stdin {
+            return Ok(BlkSource {
+                data: BlkData::Memory(io::Cursor::new(bytes.clone())),
+                xor_key: None,
+                position: 0,
+            });
+        }
+        let path = self
+            .files
+            .get(file)
+            .ok_or_else(|| anyhow::anyhow!("no file at position {file} in the chain"))?;
+        BlkSource::open(path, self.mmap, self.xor_key)
+    }
+}
+impl Iterator for FileChain {
+    type Item = Result<ByteCursor<BlkSource>>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.files.get(self.next)?;
+        self.next += 1;
+        Some(self.source(self.next - 1).map(ByteCursor::new))
+    }
+}
+
+/// Iterates every [Block] in a chain of blk files, in the order they were written.
+///
+/// Heights are assigned by position, carrying over from one file to the next,
+/// see [Chain](crate::Chain) for heights in chain order. Without an explicit [Network], every block must match the network of the first one.
+#[derive(Debug)]
+pub struct BlockFileReader {
+    files: FileChain,
+    cursor: Option<ByteCursor<BlkSource>>,
+    file: usize,
+    network: Option<Network>,
+    height: u64,
+    bytes_read: u64,
+    /// The file and offset just after the last undo record found, where the next search of
+    /// the same rev file starts.
+    undo_offset: Option<(usize, u64)>,
+}
+impl BlockFileReader {
+    pub fn new(files: FileChain, network: Option<Network>) -> Self {
+        Self {
+            files,
+            cursor: None,
+            file: 0,
+            network,
+            height: 0,
+            bytes_read: 0,
+            undo_offset: None,
+        }
+    }
+    /// Another reader of the same files, from the start, for reading from another thread.
+    pub fn reopen(&self) -> Self {
+        let files = FileChain {
+            next: 0,
+            ..self.files.clone()
+        };
+        Self::new(files, self.network)
+    }
+    /// Open a blk file, or a directory of them, as with [FileChain::open].
+    pub fn open(path: impl Into<PathBuf>, network: Option<Network>) -> Result<Self> {
+        Ok(Self::new(FileChain::open(path)?, network))
+    }
+    /// The network being read, once known.
+    pub fn network(&self) -> Option<Network> {
+        self.network
+    }
+    /// The combined size in bytes of every file being read.
+    pub fn total_size(&self) -> Result<u64> {
+        self.files.total_size()
+    }
+    /// The number of bytes consumed so far while iterating, across every file.
+    pub fn bytes_read(&self) -> u64 {
+        self.bytes_read + self.cursor.as_ref().map_or(0, ByteCursor::position)
+    }
+    /// Read every file on the rayon thread pool, one file per task, calling `f` on each block.
+    ///
+    /// Results come back in the order the blocks are stored, as when iterating, but without
+    /// heights, since no file knows how many blocks come before it. Networks are checked
+    /// per file, then across files once they're all read.
+    pub fn par_map<T: Send>(&mut self, f: impl Fn(BlockInfo, Block) -> T + Sync) -> Result<Vec<T>> {
+        self.par_map_records(read_record, f)
+    }
+    /// Read just the header of every block on the rayon thread pool, as [Self::par_map]
+    /// reads whole blocks and [Self::headers] reads headers.
+    pub fn par_map_headers<T: Send>(
+        &mut self,
+        f: impl Fn(BlockInfo, BlockHeader) -> T + Sync,
+    ) -> Result<Vec<T>> {
+        self.par_map_records(read_header_record, f)
+    }
+    fn par_map_records<R, T: Send>(
+        &mut self,
+        read: ReadRecord<R>,
+        f: impl Fn(BlockInfo, R) -> T + Sync,
+    ) -> Result<Vec<T>> {
+        let files = &self.files;
+        let network = self.network;
+        let per_file = (0..files.files().len())
+            .into_par_iter()
+            .map(|file| {
+                let mut cursor = files.open_at(file, 0)?;
+                let mut network = network;
+                let mut results = Vec::new();
+                while !cursor.is_empty()? {
+                    let record = read(&mut cursor, file, 0, &mut network, files.xor_key)
+                        .map_err(|e| files.parse_error(file, e))?;
+                    if let Some((block_info, record)) = record {
+                        results.push(f(block_info, record));
+                    }
+                }
+                Ok((network, results))
+            })
+            .collect::<Result<Vec<_>>>()?;
+
+        let mut all = Vec::new();
+        for (network, results) in per_file {
+            match (self.network, network) {
+                (Some(expected), Some(found)) if expected != found => {
+                    return Err(ErrorKind::WrongNetwork
+                        .error(format!("blk files mix {expected} and {found} blocks")))
+                }
+                (None, found) => self.network = found,
+                _ => {}
+            }
+            all.extend(results);
+        }
+        self.bytes_read = self.files.total_size()?;
+        Ok(all)
+    }
+    /// Find the first block, in the order the blocks are stored, whose coinbase commits to
+    /// `height` (BIP34), reading just the header and coinbase of each record, one file per
+    /// task on the rayon thread pool.
+    ///
+    /// Nothing is linked, so the block found may be a stale one rather than the one on the
+    /// best chain, and heights from before BIP34 was enforced may not be the block's own.
+    pub fn find_by_bip34_height(&mut self, height: u64) -> Result<Option<BlockLocation>> {
+        let files = &self.files;
+        let network = self.network;
+        (0..files.files().len())
+            .into_par_iter()
+            .map(|file| {
+                let mut cursor = files.open_at(file, 0)?;
+                let mut network = network;
+                while !cursor.is_empty()? {
+                    let record =
+                        read_bip34_record(&mut cursor, file, 0, &mut network, files.xor_key)
+                            .map_err(|e| files.parse_error(file, e))?;
+                    if let Some((block_info, committed)) = record {
+                        if committed == Some(height) {
+                            return Ok(block_info.location());
+                        }
+                    }
+                }
+                Ok(None)
+            })
+            .find_map_first(Result::transpose)
+            .transpose()
+    }
+    /// Find the first block, in the order the blocks are stored, with `hash`, reading just
+    /// the header of each record, one file per task on the rayon thread pool.
+    pub fn find_by_hash(&mut self, hash: &[u8; 32]) -> Result<Option<BlockLocation>> {
+        let files = &self.files;
+        let network = self.network;
+        (0..files.files().len())
+            .into_par_iter()
+            .map(|file| {
+                let mut cursor = files.open_at(file, 0)?;
+                let mut network = network;
+                while !cursor.is_empty()? {
+                    let record =
+                        read_header_record(&mut cursor, file, 0, &mut network, files.xor_key)
+                            .map_err(|e| files.parse_error(file, e))?;
+                    if let Some((block_info, header)) = record {
+                        if header.hash() == *hash {
+                            return Ok(block_info.location());
+                        }
+                    }
+                }
+                Ok(None)
+            })
+            .find_map_first(Result::transpose)
+            .transpose()
+    }
+    /// Find the block with `hash` in the blk files when it's off `chain`, such as a stale
+    /// block, by [find_by_hash](Self::find_by_hash), labelled with the height it would have
+    /// were its branch the best chain.
+    ///
+    /// Each block of its branch back to where it joins `chain` takes another scan, and a
+    /// branch that never joins it leaves the block without a height, which is an error.
+    pub fn find_stale(
+        &mut self,
+        chain: &Chain,
+        hash: &[u8; 32],
+    ) -> Result<Option<(BlockInfo, Block)>> {
+        let Some(location) = self.find_by_hash(hash)? else {
+            return Ok(None);
+        };
+        let mut depth = 1;
+        let mut parent = self.read_header_at(location)?.previous_hash();
+        let height = loop {
+            if let Some(height) = chain.height_of(&parent) {
+                break height + depth;
+            }
+            let Some(parent_location) = self.find_by_hash(&parent)? else {
+                return Err(ErrorKind::NotFound.error(format!(
+                    "found block {} in file {} at offset {}, but not the block {} its branch \
+                     builds on, so not its height",
+                    to_display_hex(hash),
+                    location.file,
+                    location.offset,
+                    to_display_hex(&parent)
+                )));
+            };
+            parent = self.read_header_at(parent_location)?.previous_hash();
+            depth += 1;
+        };
+        self.read_at(location, height).map(Some)
+    }
+    /// Read the single block record at `location`, labelling it with `height`.
+    pub fn read_at(&mut self, location: BlockLocation, height: u64) -> Result<(BlockInfo, Block)> {
+        let mut cursor = self.files.open_at(location.file, location.offset)?;
+        read_record(
+            &mut cursor,
+            location.file,
+            height,
+            &mut self.network,
+            self.files.xor_key,
+        )
+        .map_err(|e| self.files.parse_error(location.file, e))?
+        .ok_or_else(|| {
+            anyhow::anyhow!(
+                "no block at offset {} of file {}, only padding",
+                location.offset,
+                location.file
+            )
+        })
+    }
+    /// Read just the header of the block record at `location`, skipping its transactions.
+    pub fn read_header_at(&mut self, location: BlockLocation) -> Result<BlockHeader> {
+        let offset = location.offset + u64::from(MAGIC_BYTES + BLOCK_SIZE);
+        let mut cursor = self.files.open_at(location.file, offset)?;
+        BlockHeader::from_raw_bytes(&mut cursor)
+            .map_err(|e| self.files.parse_error(location.file, e))
+    }
+    /// Read every complete block record from `from` to the end of the last file,
+    /// returning them along with where to carry on reading once more are written.
+    ///
+    /// Made for following a node as it writes: new files are picked up, and a
+    /// record in the last file that fails to parse is assumed to be part written,
+    /// so reading stops before it rather than failing. Heights are left at 0.
+    pub fn read_from(
+        &mut self,
+        from: BlockLocation,
+    ) -> Result<(Vec<(BlockInfo, Block)>, BlockLocation)> {
+        self.files.refresh();
+        let mut records = Vec::new();
+        let mut resume = from;
+        for file in from.file..self.files.files().len() {
+            let is_last = file + 1 == self.files.files().len();
+            let offset = if file == from.file { from.offset } else { 0 };
+            let mut cursor = self.files.open_at(file, offset)?;
+            resume = BlockLocation { file, offset };
+            while !cursor.is_empty()? {
+                match read_record(&mut cursor, file, 0, &mut self.network, self.files.xor_key) {
+                    Ok(Some(record)) => {
+                        records.push(record);
+                        resume.offset = cursor.position();
+                    }
+                    // New records are written over the padding, so resume from where it starts.
+                    Ok(None) => {}
+                    Err(_) if is_last => break,
+                    Err(e) => return Err(self.files.parse_error(file, e)),
+                }
+            }
+        }
+        Ok((records, resume))
+    }
+    /// Call `f` on a borrowed [BlockView] of every block, in the order they're stored,
+    /// with heights assigned as when iterating.
+    ///
+    /// Each file is held in memory whole, mapped with [FileChain::with_mmap] or read in
+    /// otherwise, so a block is parsed in place without copying any of its fields.
+    pub fn for_each_view(
+        &mut self,
+        mut f: impl FnMut(BlockInfo, BlockView<'_>) -> Result<()>,
+    ) -> Result<()> {
+        for file in 0..self.files.files().len() {
+            let path = &self.files.files()[file];
+            let mut source = self.files.source(file)?;
+            let bytes = source.contents().map_err(|e| {
+                ErrorKind::Io.error(format!("failed to read {}: {e}", path.display()))
+            })?;
+            let mut offset = 0;
+            while offset < bytes.len() {
+                let mut cursor = ByteCursor::starting_at(&bytes[offset..], offset as u64);
+                let record = read_record_info(
+                    &mut cursor,
+                    file,
+                    self.height,
+                    &mut self.network,
+                    self.files.xor_key,
+                )
+                .map_err(|e| self.files.parse_error(file, e))?;
+                let start = cursor.position() as usize;
+                let Some(block_info) = record else {
+                    offset = start;
+                    continue;
+                };
+                let end = (start + block_info.size_as_u32() as usize).min(bytes.len());
+                let view = BlockView::parse_at(&bytes[start..end], start as u64)
+                    .map_err(|e| self.files.parse_error(file, e))?;
+                if view.total_size() != block_info.size_as_u32() {
+                    let e = ParseError::SizeMismatch {
+                        expected: block_info.size_as_u32(),
+                        parsed: u64::from(view.total_size()),
+                        offset: offset as u64,
+                    };
+                    return Err(self.files.parse_error(file, e));
+                }
+                offset = end;
+                self.height += 1;
+                f(block_info.with_block_hash(view.block_header().hash()), view)?;
+            }
+            self.bytes_read += bytes.len() as u64;
+        }
+        Ok(())
+    }
+    /// Iterate every block, as iterating the reader itself does.
+    pub fn blocks(&mut self) -> impl Iterator<Item = Result<(BlockInfo, Block)>> + '_ {
+        std::iter::from_fn(|| self.next_record(read_record).transpose())
+    }
+    /// Iterate just the header of every block, seeking past the transactions rather
+    /// than reading and parsing them, for scans that need nothing more.
+    ///
+    /// The transactions aren't checked to fill the record's stated size, so a
+    /// truncated record is only noticed if another follows it.
+    pub fn headers(&mut self) -> impl Iterator<Item = Result<(BlockInfo, BlockHeader)>> + '_ {
+        std::iter::from_fn(|| self.next_record(read_header_record).transpose())
+    }
+    /// The next record, read by `read`, from where iteration left off.
+    fn next_record<T>(&mut self, read: ReadRecord<T>) -> Result<Option<(BlockInfo, T)>> {
+        loop {
+            let cursor = match self.cursor.as_mut() {
+                Some(cursor) => cursor,
+                None => match self.files.next() {
+                    Some(cursor) => {
+                        self.file = self.files.next - 1;
+                        self.cursor.insert(cursor?)
+                    }
+                    None => return Ok(None),
+                },
+            };
+            if cursor.is_empty()? {
+                self.bytes_read += cursor.position();
+                self.cursor = None;
+                continue;
+            }
+
+            let record = read(
+                cursor,
+                self.file,
+                self.height,
+                &mut self.network,
+                self.files.xor_key,
+            )
+            .map_err(|e| self.files.parse_error(self.file, e))?;
+            // Without a record, padding was skipped, so carry on from whatever follows it.
+            if let Some(record) = record {
+                self.height += 1;
+                return Ok(Some(record));
+            }
+        }
+    }
+}
+
+impl Iterator for BlockFileReader {
+    type Item = Result<(BlockInfo, Block)>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.next_record(read_record).transpose()
+    }
+}
+
+/// A function reading the record under a cursor, as [read_record] does, into a `T`.
+type ReadRecord<T> = fn(
+    &mut ByteCursor<BlkSource>,
+    usize,
+    u64,
+    &mut Option<Network>,
+    Option<[u8; 8]>,
+) -> Result<Option<(BlockInfo, T)>, ParseError>;
+
+/// Parse the magic bytes and size that start the block record under the cursor,
+/// detecting the network if it isn't known yet.
+///
+/// Nodes preallocate blk files, so the space after the last block reads as zeros, or as the
+/// XOR key repeated once de-obfuscated. Finding that instead of a record's magic bytes, the
+/// whole run is skipped and `None` returned, leaving the cursor at the end of the file or at
+/// whatever follows the padding.
+fn read_record_info<R: Read>(
+    cursor: &mut ByteCursor<R>,
+    file: usize,
+    height: u64,
+    network: &mut Option<Network>,
+    xor_key: Option<[u8; 8]>,
+) -> Result<Option<BlockInfo>, ParseError> {
+    let location = BlockLocation {
+        file,
+        offset: cursor.position(),
+    };
+    let padding =
+        |offset: u64, byte: u8| byte == xor_key.map_or(0, |key| key[(offset % 8) as usize]);
+    let magic_bytes = cursor.take_up_to(MAGIC_BYTES)?;
+    let is_padding = magic_bytes
+        .iter()
+        .enumerate()
+        .all(|(i, byte)| padding(location.offset + i as u64, *byte));
+    if is_padding {
+        cursor.skip_while(padding)?;
+        return Ok(None);
+    }
+    if magic_bytes.len() < MAGIC_BYTES as usize {
+        return Err(ParseError::UnexpectedEof {
+            field: "magic_bytes",
+            offset: location.offset,
+        });
+    }
+    let size = cursor.take(BLOCK_SIZE, "size")?;
+    let block_info = BlockInfo::new(height, magic_bytes, size).with_location(location);
+    let bad_magic = |expected| ParseError::BadMagic {
+        magic_bytes: block_info
+            .magic_bytes()
+            .try_into()
+            .expect("expected 4 bytes for magic_bytes"),
+        expected,
+        offset: location.offset,
+    };
+    let network = match network {
+        Some(network) => *network,
+        None => *network.insert(block_info.network().map_err(|_| bad_magic(None))?),
+    };
+    if block_info.validate_network(network).is_err() {
+        return Err(bad_magic(Some(network)));
+    }
+    Ok(Some(block_info))
+}
+
+/// Parse the block record under the cursor, as [read_record_info] describes.
+fn read_record<R: Read>(
+    cursor: &mut ByteCursor<R>,
+    file: usize,
+    height: u64,
+    network: &mut Option<Network>,
+    xor_key: Option<[u8; 8]>,
+) -> Result<Option<(BlockInfo, Block)>, ParseError> {
+    let Some(block_info) = read_record_info(cursor, file, height, network, xor_key)? else {
+        return Ok(None);
+    };
+    let location = block_info
+        .location()
+        .expect("expected a location for a block record");
+    let start = cursor.position();
+    let block = Block::from_raw_bytes(cursor)?;
+    let parsed = cursor.position() - start;
+    if parsed != u64::from(block_info.size_as_u32()) {
+        return Err(ParseError::SizeMismatch {
+            expected: block_info.size_as_u32(),
+            parsed,
+            offset: location.offset,
+        });
+    }
+
+    Ok(Some((
+        block_info.with_block_hash(block.block_header().hash()),
+        block,
+    )))
+}
+
+/// Parse the header of the block record under the cursor and seek past its transactions,
+/// as [read_record_info] describes.
+fn read_header_record(
+    cursor: &mut ByteCursor<BlkSource>,
+    file: usize,
+    height: u64,
+    network: &mut Option<Network>,
+    xor_key: Option<[u8; 8]>,
+) -> Result<Option<(BlockInfo, BlockHeader)>, ParseError> {
+    let Some(block_info) = read_record_info(cursor, file, height, network, xor_key)? else {
+        return Ok(None);
+    };
+    let header = BlockHeader::from_raw_bytes(cursor)?;
+    let rest = u64::from(block_info.size_as_u32())
+        .checked_sub(u64::from(BLOCK_HEADER))
+        .ok_or(ParseError::SizeMismatch {
+            expected: block_info.size_as_u32(),
+            parsed: u64::from(BLOCK_HEADER),
+            offset: block_info
+                .location()
+                .expect("expected a location for a block record")
+                .offset,
+        })?;
+    cursor.skip(rest)?;
+    Ok(Some((block_info.with_block_hash(header.hash()), header)))
+}
+
+/// Parse the header and coinbase of the block record under the cursor, returning the height
+/// the coinbase commits to (BIP34), and seek past the rest, as [read_record_info] describes.
+fn read_bip34_record(
+    cursor: &mut ByteCursor<BlkSource>,
+    file: usize,
+    height: u64,
+    network: &mut Option<Network>,
+    xor_key: Option<[u8; 8]>,
+) -> Result<Option<(BlockInfo, Option<u64>)>, ParseError> {
+    let Some(block_info) = read_record_info(cursor, file, height, network, xor_key)? else {
+        return Ok(None);
+    };
+    let start = cursor.position();
+    let header = BlockHeader::from_raw_bytes(cursor)?;
+    let tx_count = CompactSize::from_raw_bytes(cursor)?;
+    let coinbase = match tx_count.to_u64() {
+        0 => Vec::new(),
+        _ => vec![Transaction::from_raw_bytes(cursor)?],
+    };
+    let block = Block::new(header, tx_count, coinbase);
+    let parsed = cursor.position() - start;
+    let rest = u64::from(block_info.size_as_u32())
+        .checked_sub(parsed)
+        .ok_or(ParseError::SizeMismatch {
+            expected: block_info.size_as_u32(),
+            parsed,
+            offset: block_info
+                .location()
+                .expect("expected a location for a block record")
+                .offset,
+        })?;
+    cursor.skip(rest)?;
+    let committed = block
+        .coinbase()
+        .and_then(|coinbase| coinbase.bip34_height());
+    Ok(Some((
+        block_info.with_block_hash(block.block_header().hash()),
+        committed,
+    )))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{blk_file_path, BlockFileReader, FileChain};
+    use crate::{
+        util::{
+            constant::Network,
+            error::ParseError,
+            fixtures::{blk_record, block, BLOCK_1_HEX, GENESIS_BLOCK_HEX},
+        },
+        BlockLocation, Chain,
+    };
+
+    /// Checks that files are read in order and iteration stops at the first gap.
+    #[test]
+    fn test_file_chain() {
+        let datadir = std::env::temp_dir().join("bbf-test-file-chain");
+        std::fs::create_dir_all(&datadir).unwrap();
+        for file_number in [0, 1, 3] {
+            std::fs::write(blk_file_path(&datadir, file_number), [file_number as u8]).unwrap();
+        }
+
+        let read_all = |files: FileChain| {
+            files
+                .map(|cursor| cursor.unwrap().take(1, "byte").unwrap())
+                .collect::<Vec<_>>()
+        };
+        let files = read_all(FileChain::open(&datadir).unwrap());
+        let mapped = read_all(FileChain::open(&datadir).unwrap().with_mmap(true));
+        let single_file = read_all(FileChain::open(blk_file_path(&datadir, 3)).unwrap());
+        let after_gap = super::files_after_gap(&datadir, 2);
+        std::fs::remove_dir_all(&datadir).unwrap();
+
+        assert_eq!(files, vec![vec![0], vec![1]]);
+        assert_eq!(mapped, files);
+        assert_eq!(single_file, vec![vec![3]]);
+        assert_eq!(after_gap, 1);
+        assert!(FileChain::open(&datadir).is_err());
+    }
+
+    /// Checks that a stream held in memory, as read from stdin, is read like a file.
+    #[test]
+    fn test_from_bytes() {
+        let mut stream = blk_record(GENESIS_BLOCK_HEX);
+        stream.extend(blk_record(BLOCK_1_HEX));
+        let mut blocks = BlockFileReader::new(FileChain::from_bytes("-", stream), None);
+
+        let locations = blocks
+            .by_ref()
+            .map(|block| block.unwrap().0.location().unwrap())
+            .collect::<Vec<_>>();
+        let (_, block) = blocks.read_at(locations[1], 1).unwrap();
+
+        assert_eq!(locations.len(), 2);
+        assert_eq!(blocks.total_size().unwrap(), 293 + 223);
+        assert_eq!(
+            block.block_header().block_hash(),
+            "00000000839a8e6886ab5951d76f411475428afc90947ee320161bbf18eb6048"
+        );
+    }
+
+    /// Checks that heights carry over from one blk file to the next, and that a
+    /// header can be read back on its own.
+    #[test]
+    fn test_block_file_reader() {
+        let datadir = std::env::temp_dir().join("bbf-test-block-file-reader");
+        std::fs::create_dir_all(&datadir).unwrap();
+        std::fs::write(blk_file_path(&datadir, 0), blk_record(GENESIS_BLOCK_HEX)).unwrap();
+        std::fs::write(blk_file_path(&datadir, 1), blk_record(BLOCK_1_HEX)).unwrap();
+
+        let mut blocks = BlockFileReader::open(&datadir, None).unwrap();
+        let hashes = blocks
+            .by_ref()
+            .map(|block| {
+                let (block_info, block) = block.unwrap();
+                (block_info.height, block.block_header().block_hash())
+            })
+            .collect::<Vec<_>>();
+        let total_size = blocks.total_size().unwrap();
+        let header = blocks
+            .read_header_at(BlockLocation { file: 1, offset: 0 })
+            .unwrap();
+        std::fs::remove_dir_all(&datadir).unwrap();
+
+        assert_eq!(blocks.network(), Some(Network::Mainnet));
+        assert_eq!(total_size, 285 + 8 + 215 + 8);
+        assert_eq!(blocks.bytes_read(), total_size);
+        assert_eq!(hex::encode(header.raw_bytes()), BLOCK_1_HEX[..160]);
+        assert_eq!(
+            hashes,
+            vec![
+                (
+                    0,
+                    "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f".to_string()
+                ),
+                (
+                    1,
+                    "00000000839a8e6886ab5951d76f411475428afc90947ee320161bbf18eb6048".to_string()
+                ),
+            ]
+        );
+    }
+
+    /// Checks that malformed records are reported with the offset where parsing failed.
+    #[test]
+    fn test_parse_errors() {
+        let datadir = std::env::temp_dir().join("bbf-test-parse-errors");
+        std::fs::create_dir_all(&datadir).unwrap();
+        let genesis = blk_record(GENESIS_BLOCK_HEX);
+        let parse = |bytes: &[u8]| {
+            let path = datadir.join("blk.dat");
+            std::fs::write(&path, bytes).unwrap();
+            let error = BlockFileReader::open(&path, None)
+                .unwrap()
+                .find_map(Result::err)
+                .unwrap();
+            error.downcast::<ParseError>().unwrap()
+        };
+
+        let truncated = parse(&[genesis.as_slice(), &genesis[..50]].concat());
+        let mut bad_magic = genesis.clone();
+        bad_magic.extend(blk_record(BLOCK_1_HEX));
+        bad_magic[genesis.len()] = 0x0b;
+        let bad_magic = parse(&bad_magic);
+        let mut padded = genesis.clone();
+        padded[4] += 1;
+        padded.push(0);
+        let padded = parse(&padded);
+        // The coinbase scriptSig given a length of 2^32, which a u32 would cut down to 0.
+        let mut too_long = genesis.clone();
+        too_long.splice(130..131, [0xff, 0, 0, 0, 0, 1, 0, 0, 0]);
+        too_long[4] += 8;
+        let too_long = parse(&too_long);
+        std::fs::remove_dir_all(&datadir).unwrap();
+
+        assert!(matches!(
+            truncated,
+            ParseError::UnexpectedEof { field: "merkle hash", offset } if offset == 293 + 8 + 36
+        ));
+        assert!(matches!(
+            bad_magic,
+            ParseError::BadMagic {
+                expected: Some(Network::Mainnet),
+                offset: 293,
+                ..
+            }
+        ));
+        assert!(matches!(
+            padded,
+            ParseError::SizeMismatch {
+                expected: 286,
+                parsed: 285,
+                offset: 0
+            }
+        ));
+        assert!(matches!(
+            too_long,
+            ParseError::BadLength {
+                field: "script_sig",
+                length: 0x1_0000_0000,
+                offset: 130
+            }
+        ));
+    }
+
+    /// Checks that zero padding after and between records is skipped, however long it is.
+    #[test]
+    fn test_padding() {
+        let datadir = std::env::temp_dir().join("bbf-test-padding");
+        std::fs::create_dir_all(&datadir).unwrap();
+        let mut records = blk_record(GENESIS_BLOCK_HEX);
+        records.extend([0; 1000]);
+        records.extend(blk_record(BLOCK_1_HEX));
+        records.extend([0; 3]);
+        std::fs::write(blk_file_path(&datadir, 0), &records).unwrap();
+
+        let mut blocks = BlockFileReader::open(&datadir, None).unwrap();
+        let heights = blocks
+            .by_ref()
+            .map(|block| block.unwrap().0.height)
+            .collect::<Vec<_>>();
+        let parallel = blocks
+            .par_map(|block_info, _| block_info.location())
+            .unwrap();
+        std::fs::remove_dir_all(&datadir).unwrap();
+
+        assert_eq!(heights, vec![0, 1]);
+        assert_eq!(blocks.bytes_read(), records.len() as u64);
+        assert_eq!(
+            parallel,
+            vec![
+                Some(BlockLocation { file: 0, offset: 0 }),
+                Some(BlockLocation {
+                    file: 0,
+                    offset: 293 + 1000
+                }),
+            ]
+        );
+    }
+
+    /// Checks that header-only scans, one at a time and in parallel, find the same blocks
+    /// as a full one across padding and files, and reject a record too short for a header.
+    #[test]
+    fn test_headers() {
+        let datadir = std::env::temp_dir().join("bbf-test-headers");
+        std::fs::create_dir_all(&datadir).unwrap();
+        let mut records = blk_record(GENESIS_BLOCK_HEX);
+        records.extend([0; 100]);
+        std::fs::write(blk_file_path(&datadir, 0), &records).unwrap();
+        std::fs::write(blk_file_path(&datadir, 1), blk_record(BLOCK_1_HEX)).unwrap();
+
+        let mut blocks = BlockFileReader::open(&datadir, None).unwrap();
+        let full = blocks
+            .blocks()
+            .map(|block| {
+                let (block_info, block) = block.unwrap();
+                (block_info, block.block_header().hash())
+            })
+            .collect::<Vec<_>>();
+        let mut headers = BlockFileReader::open(&datadir, None).unwrap();
+        let header_only = headers
+            .headers()
+            .map(|header| {
+                let (block_info, header) = header.unwrap();
+                (block_info, header.hash())
+            })
+            .collect::<Vec<_>>();
+        let parallel = headers
+            .par_map_headers(|block_info, header| (block_info.location(), header.hash()))
+            .unwrap();
+
+        let mut short = blk_record(GENESIS_BLOCK_HEX);
+        short[4..8].copy_from_slice(&79u32.to_le_bytes());
+        std::fs::write(blk_file_path(&datadir, 1), &short).unwrap();
+        let error = BlockFileReader::open(blk_file_path(&datadir, 1), None)
+            .unwrap()
+            .headers()
+            .find_map(Result::err)
+            .unwrap()
+            .downcast::<ParseError>()
+            .unwrap();
+        std::fs::remove_dir_all(&datadir).unwrap();
+
+        assert_eq!(header_only.len(), 2);
+        assert_eq!(header_only, full);
+        assert_eq!(headers.bytes_read(), blocks.bytes_read());
+        assert_eq!(
+            parallel,
+            full.iter()
+                .map(|(block_info, hash)| (block_info.location(), *hash))
+                .collect::<Vec<_>>()
+        );
+        assert!(matches!(
+            error,
+            ParseError::SizeMismatch {
+                expected: 79,
+                parsed: 80,
+                offset: 0
+            }
+        ));
+    }
+
+    /// Checks that following a file as it's written picks up new records and new files,
+    /// stopping before a record that's only part written.
+    #[test]
+    fn test_read_from() {
+        let datadir = std::env::temp_dir().join("bbf-test-read-from");
+        std::fs::create_dir_all(&datadir).unwrap();
+        let mut records = blk_record(GENESIS_BLOCK_HEX);
+        std::fs::write(
+            blk_file_path(&datadir, 0),
+            [&records[..], &[0; 100]].concat(),
+        )
+        .unwrap();
+        let mut blocks = BlockFileReader::open(&datadir, None).unwrap();
+        let (first, resume) = blocks
+            .read_from(BlockLocation { file: 0, offset: 0 })
+            .unwrap();
+
+        records.extend(blk_record(BLOCK_1_HEX));
+        let part_written = &blk_record(GENESIS_BLOCK_HEX)[..50];
+        std::fs::write(
+            blk_file_path(&datadir, 0),
+            [&records[..], part_written].concat(),
+        )
+        .unwrap();
+        let (second, part_written_at) = blocks.read_from(resume).unwrap();
+
+        std::fs::write(blk_file_path(&datadir, 0), &records).unwrap();
+        std::fs::write(blk_file_path(&datadir, 1), blk_record(BLOCK_1_HEX)).unwrap();
+        let (third, end) = blocks.read_from(part_written_at).unwrap();
+        std::fs::remove_dir_all(&datadir).unwrap();
+
+        assert_eq!(first.len(), 1);
+        assert_eq!(
+            resume,
+            BlockLocation {
+                file: 0,
+                offset: 293
+            }
+        );
+        assert_eq!(second.len(), 1);
+        assert_eq!(
+            second[0].1.block_header().hash(),
+            third[0].1.block_header().hash()
+        );
+        assert_eq!(
+            part_written_at,
+            BlockLocation {
+                file: 0,
+                offset: 516
+            }
+        );
+        assert_eq!(third.len(), 1);
+        assert_eq!(
+            end,
+            BlockLocation {
+                file: 1,
+                offset: 223
+            }
+        );
+    }
+
+    /// Checks that reading files in parallel finds the same blocks, in the same order.
+    #[test]
+    fn test_par_map() {
+        let datadir = std::env::temp_dir().join("bbf-test-par-map");
+        std::fs::create_dir_all(&datadir).unwrap();
+        std::fs::write(blk_file_path(&datadir, 0), blk_record(GENESIS_BLOCK_HEX)).unwrap();
+        std::fs::write(blk_file_path(&datadir, 1), blk_record(BLOCK_1_HEX)).unwrap();
+
+        let hash = |_, block: crate::Block| block.block_header().hash();
+        let sequential = BlockFileReader::open(&datadir, None)
+            .unwrap()
+            .map(|block| {
+                let (block_info, block) = block.unwrap();
+                hash(block_info, block)
+            })
+            .collect::<Vec<_>>();
+        let mut blocks = BlockFileReader::open(&datadir, None).unwrap();
+        let parallel = blocks.par_map(hash).unwrap();
+        let total_size = blocks.total_size().unwrap();
+        std::fs::remove_dir_all(&datadir).unwrap();
+
+        assert_eq!(parallel, sequential);
+        assert_eq!(blocks.network(), Some(Network::Mainnet));
+        assert_eq!(blocks.bytes_read(), total_size);
+    }
+
+    /// Checks a block is found by the height its coinbase commits to, which only blocks of
+    /// version 2 on make, ignoring blocks in earlier files that commit to none.
+    #[test]
+    fn test_find_by_bip34_height() {
+        let datadir = std::env::temp_dir().join("bbf-test-find-by-bip34-height");
+        std::fs::create_dir_all(&datadir).unwrap();
+        // Block 1 as version 2, its coinbase's first push, the bits, read as the height.
+        let version_2 = format!("02{}", &BLOCK_1_HEX[2..]);
+        let records = [blk_record(BLOCK_1_HEX), blk_record(&version_2)].concat();
+        std::fs::write(blk_file_path(&datadir, 0), blk_record(GENESIS_BLOCK_HEX)).unwrap();
+        std::fs::write(blk_file_path(&datadir, 1), records).unwrap();
+
+        let mut blocks = BlockFileReader::open(&datadir, None).unwrap();
+        let found = blocks.find_by_bip34_height(0x1d00ffff).unwrap();
+        let missing = blocks.find_by_bip34_height(1).unwrap();
+        std::fs::remove_dir_all(&datadir).unwrap();
+
+        let offset = blk_record(BLOCK_1_HEX).len() as u64;
+        assert_eq!(found, Some(BlockLocation { file: 1, offset }));
+        assert_eq!(missing, None);
+    }
+
+    /// Checks a block is found by its hash, and that a stale sibling of block 1, left off the
+    /// chain, is found with the height it would have had.
+    #[test]
+    fn test_find_stale() {
+        let datadir = std::env::temp_dir().join("bbf-test-find-stale");
+        std::fs::create_dir_all(&datadir).unwrap();
+        // Block 1 with another nonce, so another hash on the same parent.
+        let sibling = format!("{}00000000{}", &BLOCK_1_HEX[..152], &BLOCK_1_HEX[160..]);
+        let records = [
+            blk_record(GENESIS_BLOCK_HEX),
+            blk_record(BLOCK_1_HEX),
+            blk_record(&sibling),
+        ]
+        .concat();
+        std::fs::write(blk_file_path(&datadir, 0), records).unwrap();
+
+        let mut blocks = BlockFileReader::open(&datadir, None).unwrap();
+        let chain = Chain::build(blocks.by_ref()).unwrap();
+        let sibling_hash = block(&sibling).block_header().hash();
+        let block_1 = blocks
+            .find_by_hash(&block(BLOCK_1_HEX).block_header().hash())
+            .unwrap();
+        let stale = blocks.find_stale(&chain, &sibling_hash).unwrap();
+        let missing = blocks.find_stale(&chain, &[0xab; 32]).unwrap();
+        std::fs::remove_dir_all(&datadir).unwrap();
+
+        let offset = blk_record(GENESIS_BLOCK_HEX).len() as u64;
+        assert_eq!(block_1, Some(BlockLocation { file: 0, offset }));
+        assert_eq!(chain.len(), 2);
+        assert_eq!(chain.height_of(&sibling_hash), None);
+        let (block_info, stale) = stale.unwrap();
+        assert_eq!(block_info.height, 1);
+        assert_eq!(stale.block_header().hash(), sibling_hash);
+        assert!(missing.is_none());
+    }
+
+    /// Checks that blk files obfuscated with an `xor.dat` key read the same as plain ones,
+    /// including when seeking straight to a later block.
+    #[test]
+    fn test_xor_obfuscation() {
+        let datadir = std::env::temp_dir().join("bbf-test-xor-obfuscation");
+        std::fs::create_dir_all(&datadir).unwrap();
+        let key = [0x3a, 0x91, 0x00, 0xff, 0x12, 0x7e, 0xc4, 0x55];
+        let mut records = blk_record(GENESIS_BLOCK_HEX);
+        records.extend(blk_record(BLOCK_1_HEX));
+        let mut obfuscated = records
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| byte ^ key[i % 8])
+            .collect::<Vec<u8>>();
+        // Preallocated space is left as plain zeros on disk.
+        obfuscated.extend([0; 100]);
+        std::fs::write(datadir.join("xor.dat"), key).unwrap();
+        std::fs::write(blk_file_path(&datadir, 0), obfuscated).unwrap();
+
+        let read_all = |mmap: bool| {
+            let files = FileChain::open(&datadir).unwrap().with_mmap(mmap);
+            let mut blocks = BlockFileReader::new(files, None);
+            let hashes = blocks
+                .by_ref()
+                .map(|block| block.unwrap().1.block_header().hash())
+                .collect::<Vec<_>>();
+            let location = BlockLocation {
+                file: 0,
+                offset: 285 + 8,
+            };
+            let (_, block) = blocks.read_at(location, 1).unwrap();
+            (hashes, block.block_header().hash())
+        };
+        let (hashes, seeked) = read_all(false);
+        let mapped = read_all(true);
+        std::fs::remove_dir_all(&datadir).unwrap();
+
+        assert_eq!(hashes.len(), 2);
+        assert_eq!(seeked, hashes[1]);
+        assert_eq!(mapped, (hashes, seeked));
+    }
+
+    /// Checks that borrowed views find the same blocks as iterating, whether the files
+    /// are read or mapped, obfuscated or not, skipping padding between records.
+    #[test]
+    fn test_for_each_view() {
+        let datadir = std::env::temp_dir().join("bbf-test-for-each-view");
+        std::fs::create_dir_all(&datadir).unwrap();
+        let key = [0x3a, 0x91, 0x00, 0xff, 0x12, 0x7e, 0xc4, 0x55];
+        let mut records = blk_record(GENESIS_BLOCK_HEX);
+        records.extend([0; 100]);
+        records.extend(blk_record(BLOCK_1_HEX));
+        std::fs::write(blk_file_path(&datadir, 0), &records).unwrap();
+        std::fs::write(blk_file_path(&datadir, 1), blk_record(BLOCK_1_HEX)).unwrap();
+
+        let read_all = |mmap: bool| {
+            let files = FileChain::open(&datadir).unwrap().with_mmap(mmap);
+            let mut blocks = BlockFileReader::new(files, None);
+            let mut views = Vec::new();
+            blocks
+                .for_each_view(|block_info, view| {
+                    views.push((block_info.height, block_info.location(), view.tx_count()));
+                    Ok(())
+                })
+                .unwrap();
+            let iterated = BlockFileReader::open(&datadir, None)
+                .unwrap()
+                .map(|block| {
+                    let (block_info, block) = block.unwrap();
+                    (block_info.height, block_info.location(), block.tx_count())
+                })
+                .collect::<Vec<_>>();
+            (views, iterated, blocks.bytes_read())
+        };
+        let (views, iterated, bytes_read) = read_all(false);
+        let mapped = read_all(true);
+        let mut obfuscated = records
+            .iter()
+            .enumerate()
+            .map(|(i, byte)| byte ^ key[i % 8])
+            .collect::<Vec<u8>>();
+        // Preallocated space is left as plain zeros on disk.
+        obfuscated[293..393].fill(0);
+        std::fs::write(datadir.join("xor.dat"), key).unwrap();
+        std::fs::write(blk_file_path(&datadir, 0), obfuscated).unwrap();
+        std::fs::remove_file(blk_file_path(&datadir, 1)).unwrap();
+        let de_obfuscated = read_all(true);
+        std::fs::remove_dir_all(&datadir).unwrap();
+
+        assert_eq!(views.len(), 3);
+        assert_eq!(views, iterated);
+        assert_eq!(bytes_read, (records.len() + 215 + 8) as u64);
+        assert_eq!(mapped, (views.clone(), iterated, bytes_read));
+        assert_eq!(de_obfuscated.0, views[..2]);
+    }
+}
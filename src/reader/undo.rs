@@ -0,0 +1,294 @@
+//! Reading the undo data Bitcoin Core keeps in the rev*.dat files beside the blk files: the
+//! outputs each block spent, so the node can restore them if the block is disconnected.
+//!
+//! A rev file is a sequence of records, each the network's magic bytes, the size of the undo
+//! data, the undo data and a checksum, the double SHA256 of the parent block's hash followed
+//! by the undo data. The undo data is the number of transactions after the coinbase, then
+//! for each the number of its inputs and the coin each one spent, in input order: its height
+//! doubled and plus one for a coinbase, a version kept for compatibility when the height
+//! isn't 0, and the output in Core's compressed serialization.
+//!
+//! Records are written as blocks are connected rather than as they're stored, so a block's
+//! undo data is in the rev file numbered like its blk file but not at the same position.
+use super::{read_record_info, BlkSource, BlockFileReader};
+use crate::{
+    chain::{
+        txoutset::{decompress_txout, read_varint},
+        utxo::Coin,
+    },
+    util::{
+        constant::Network,
+        error::ErrorKind,
+        hash::{sha256d, to_display_hex},
+        tx::TxOut,
+        types::{Block, BlockLocation, ByteCursor, CompactSize},
+    },
+};
+use anyhow::{bail, Result};
+use std::{
+    io::{Read, Seek, SeekFrom},
+    path::{Path, PathBuf},
+};
+
+/// The size of the checksum following each record's undo data.
+const CHECKSUM: u32 = 32;
+
+/// The rev file holding the undo data for the blocks of the blk file at `blk`, e.g.
+/// `rev00001.dat` beside `blk00001.dat`, or none if `blk` isn't named like a blk file.
+pub fn rev_file_path(blk: &Path) -> Option<PathBuf> {
+    let name = blk.file_name()?.to_str()?;
+    let number = name.strip_prefix("blk")?.strip_suffix(".dat")?;
+    Some(blk.with_file_name(format!("rev{number}.dat")))
+}
+
+/// The coins a block's transactions spent, from its record in a rev file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BlockUndo {
+    /// The coins spent by each transaction after the coinbase, in the order of its inputs.
+    spent: Vec<Vec<Coin>>,
+}
+impl BlockUndo {
+    pub fn from_raw_bytes<R: Read>(cursor: &mut ByteCursor<R>) -> Result<Self> {
+        let spent = (0..CompactSize::from_raw_bytes(cursor)?.to_u64())
+            .map(|_| {
+                (0..CompactSize::from_raw_bytes(cursor)?.to_u64())
+                    .map(|_| {
+                        let code = read_varint(cursor)?;
+                        if code / 2 > 0 {
+                            read_varint(cursor)?;
+                        }
+                        Ok(Coin {
+                            output: decompress_txout(cursor)?,
+                            height: (code / 2) as u32,
+                            coinbase: code % 2 == 1,
+                        })
+                    })
+                    .collect::<Result<Vec<Coin>>>()
+            })
+            .collect::<Result<Vec<Vec<Coin>>>>()?;
+        Ok(Self { spent })
+    }
+    /// The coins spent by each transaction after the coinbase, in the order of its inputs.
+    pub fn spent(&self) -> &[Vec<Coin>] {
+        &self.spent
+    }
+    /// The outputs spent by each transaction of the block, none for the coinbase, as
+    /// [UtxoSet::apply](crate::chain::utxo::UtxoSet::apply) returns them.
+    pub fn prevouts(&self) -> Vec<Vec<TxOut>> {
+        std::iter::once(Vec::new())
+            .chain(self.spent.iter().map(|coins| {
+                coins
+                    .iter()
+                    .map(|coin| coin.output.clone())
+                    .collect::<Vec<TxOut>>()
+            }))
+            .collect()
+    }
+    /// Whether this is the undo data of `block`, spending as many coins in each
+    /// transaction as it has inputs.
+    fn fits(&self, block: &Block) -> bool {
+        let transactions = block.transactions();
+        self.spent.len() + 1 == transactions.len()
+            && self
+                .spent
+                .iter()
+                .zip(transactions.iter().skip(1))
+                .all(|(coins, tx)| coins.len() == tx.inputs().len())
+    }
+}
+
+/// Find the undo data of the block whose parent is `previous_hash` in the rev file read by
+/// `cursor`, by the checksum of each record, without parsing the records that don't match.
+///
+/// Records are read as [read_record_info] reads those of blk files, skipping the padding and
+/// checking the magic bytes against `network`. The search stops at `end` when given, and
+/// leaves the cursor after the record found.
+fn find_undo<R: Read>(
+    cursor: &mut ByteCursor<R>,
+    previous_hash: &[u8; 32],
+    network: &mut Option<Network>,
+    xor_key: Option<[u8; 8]>,
+    end: Option<u64>,
+) -> Result<Option<BlockUndo>> {
+    while !cursor.is_empty()? && end.is_none_or(|end| cursor.position() < end) {
+        let Some(record) = read_record_info(cursor, 0, 0, network, xor_key)? else {
+            continue;
+        };
+        let data = cursor.take(record.size_as_u32(), "undo_data")?;
+        let checksum = cursor.take(CHECKSUM, "undo_checksum")?;
+        if sha256d(&[previous_hash.as_slice(), &data].concat()) == checksum.as_slice() {
+            return BlockUndo::from_raw_bytes(&mut ByteCursor::new(data.as_slice())).map(Some);
+        }
+    }
+    Ok(None)
+}
+
+impl BlockFileReader {
+    /// Read the undo data of `block`, stored at `location`, from the rev file beside its
+    /// blk file, de-obfuscated with the same key.
+    ///
+    /// Blocks are mostly connected in the order they're read by height, so the search
+    /// starts after the last record found in the same rev file and only wraps round to the
+    /// start of it when the record isn't further on.
+    ///
+    /// The genesis block has no undo data, since its coinbase can't be spent, so it's
+    /// given none rather than looked for.
+    pub fn read_undo(&mut self, location: BlockLocation, block: &Block) -> Result<BlockUndo> {
+        if block.block_header().previous_hash() == [0; 32] {
+            return Ok(BlockUndo { spent: Vec::new() });
+        }
+        let blk =
+            self.files.files().get(location.file).ok_or_else(|| {
+                anyhow::anyhow!("no file at position {} in the chain", location.file)
+            })?;
+        let rev = rev_file_path(blk).ok_or_else(|| {
+            ErrorKind::NotFound.error(format!("failed to find the rev file for {}", blk.display()))
+        })?;
+        let hash = to_display_hex(&block.block_header().hash());
+        let previous_hash = block.block_header().previous_hash();
+        let start = match self.undo_offset {
+            Some((file, offset)) if file == location.file => offset,
+            _ => 0,
+        };
+        let xor_key = self.files.xor_key;
+        let mut search = |from: u64, end: Option<u64>| -> Result<Option<(BlockUndo, u64)>> {
+            let mut source = BlkSource::open(&rev, false, xor_key)?;
+            source.seek(SeekFrom::Start(from))?;
+            let mut cursor = ByteCursor::starting_at(source, from);
+            let undo = find_undo(&mut cursor, &previous_hash, &mut self.network, xor_key, end)
+                .map_err(|e| anyhow::anyhow!("failed to parse {}: {e}", rev.display()))?;
+            Ok(undo.map(|undo| (undo, cursor.position())))
+        };
+        let found = match search(start, None)? {
+            None if start > 0 => search(0, Some(start))?,
+            found => found,
+        };
+        let (undo, offset) = found.ok_or_else(|| {
+            ErrorKind::NotFound.error(format!(
+                "failed to find undo data for block {hash} in {}",
+                rev.display()
+            ))
+        })?;
+        if !undo.fits(block) {
+            bail!(
+                "the undo data for block {hash} in {} doesn't match its inputs",
+                rev.display()
+            )
+        }
+        self.undo_offset = Some((location.file, offset));
+        Ok(undo)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::{
+        constant::Network,
+        fixtures::{blk_record, BLOCK_1_HEX, GENESIS_BLOCK_HEX},
+    };
+
+    /// Checks undo data parses into the coins spent and that a block's record is found in
+    /// the rev file beside its blk file by its checksum, skipping the records of others,
+    /// again when searched for from after it, and in files obfuscated with an `xor.dat` key
+    /// whose padding is still plain zeros on disk.
+    #[test]
+    fn test_read_undo() {
+        // One transaction after the coinbase, spending a 50 BTC OP_TRUE output of the coinbase
+        // at height 1: its code, version, compressed amount and script of size 1.
+        let undo_data = [
+            CompactSize::encode(1),
+            CompactSize::encode(1),
+            vec![0x03, 0x00, 0x32, 0x07, 0x51],
+        ]
+        .concat();
+        let undo = BlockUndo::from_raw_bytes(&mut ByteCursor::new(undo_data.as_slice())).unwrap();
+        let spent = TxOut::new(5_000_000_000, vec![0x51]);
+        assert_eq!(undo.prevouts(), vec![Vec::new(), vec![spent]]);
+        assert_eq!(
+            (undo.spent()[0][0].height, undo.spent()[0][0].coinbase),
+            (1, true)
+        );
+
+        let datadir = std::env::temp_dir().join("bbf-test-read-undo");
+        std::fs::create_dir_all(&datadir).unwrap();
+        let genesis_record = blk_record(GENESIS_BLOCK_HEX);
+        std::fs::write(
+            datadir.join("blk00000.dat"),
+            [genesis_record.as_slice(), &blk_record(BLOCK_1_HEX)].concat(),
+        )
+        .unwrap();
+        let record = |previous_hash: &[u8; 32], data: &[u8]| {
+            [
+                Network::Mainnet.magic_bytes().as_slice(),
+                &(data.len() as u32).to_le_bytes(),
+                data,
+                &sha256d(&[previous_hash.as_slice(), data].concat()),
+            ]
+            .concat()
+        };
+        let mut reader = BlockFileReader::open(&datadir, None).unwrap();
+        let blocks = reader.by_ref().collect::<Result<Vec<_>>>().unwrap();
+        let (_, block_1) = &blocks[1];
+        let location = BlockLocation {
+            file: 0,
+            offset: genesis_record.len() as u64,
+        };
+        let other = record(&[0xab; 32], &undo_data);
+        std::fs::write(datadir.join("rev00000.dat"), &other).unwrap();
+        let missing = reader.read_undo(location, block_1);
+        let block_1_undo = record(
+            &block_1.block_header().previous_hash(),
+            &CompactSize::encode(0),
+        );
+        std::fs::write(
+            datadir.join("rev00000.dat"),
+            [other.as_slice(), &block_1_undo, &[0; 16]].concat(),
+        )
+        .unwrap();
+        let found = reader.read_undo(location, block_1);
+        let undo_offset = reader.undo_offset;
+        let found_again = reader.read_undo(location, block_1);
+        let (_, genesis) = &blocks[0];
+        let genesis_undo = reader.read_undo(BlockLocation { file: 0, offset: 0 }, genesis);
+
+        let key = [0x3a, 0x91, 0x00, 0xff, 0x12, 0x7e, 0xc4, 0x55];
+        let obfuscate = |bytes: &[u8]| {
+            let mut obfuscated = bytes
+                .iter()
+                .enumerate()
+                .map(|(i, byte)| byte ^ key[i % 8])
+                .collect::<Vec<u8>>();
+            obfuscated.extend([0; 100]);
+            obfuscated
+        };
+        let blk = std::fs::read(datadir.join("blk00000.dat")).unwrap();
+        std::fs::write(datadir.join("blk00000.dat"), obfuscate(&blk)).unwrap();
+        std::fs::write(datadir.join("xor.dat"), key).unwrap();
+        let mut obfuscated_reader = BlockFileReader::open(&datadir, None).unwrap();
+        std::fs::write(datadir.join("rev00000.dat"), obfuscate(&other)).unwrap();
+        let obfuscated_missing = obfuscated_reader.read_undo(location, block_1);
+        let rev = [other.as_slice(), &block_1_undo].concat();
+        std::fs::write(datadir.join("rev00000.dat"), obfuscate(&rev)).unwrap();
+        let obfuscated_found = obfuscated_reader.read_undo(location, block_1);
+        std::fs::remove_dir_all(&datadir).unwrap();
+
+        assert!(missing.is_err());
+        assert_eq!(found.unwrap().prevouts(), vec![Vec::<TxOut>::new()]);
+        assert_eq!(undo_offset, Some((0, rev.len() as u64)));
+        assert_eq!(found_again.unwrap().prevouts(), vec![Vec::<TxOut>::new()]);
+        assert_eq!(
+            ErrorKind::of(&obfuscated_missing.unwrap_err()),
+            ErrorKind::NotFound
+        );
+        assert_eq!(
+            obfuscated_found.unwrap().prevouts(),
+            vec![Vec::<TxOut>::new()]
+        );
+        assert!(genesis_undo.unwrap().spent().is_empty());
+        assert_eq!(
+            rev_file_path(Path::new("blocks/blk00042.dat")),
+            Some(PathBuf::from("blocks/rev00042.dat"))
+        );
+    }
+}
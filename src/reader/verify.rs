@@ -0,0 +1,185 @@
+//! Checking the records of the blk files are intact, reporting every damaged region with
+//! where it is rather than stopping at the first, as a search does.
+use super::{read_record_info, BlockFileReader};
+use crate::util::{constant::Network, error::ErrorKind, types::ByteCursor, view::BlockView};
+use anyhow::Result;
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// A run of bytes in a blk file that doesn't hold an intact record.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct CorruptRegion {
+    pub file: PathBuf,
+    /// The byte offset of the start of the region within the file.
+    pub start: u64,
+    /// The byte offset just past the region, where the next record's magic bytes were
+    /// found or the end of the file.
+    pub end: u64,
+    pub problem: String,
+}
+
+/// What checking every blk file found.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct VerifyReport {
+    pub files: u64,
+    pub bytes: u64,
+    /// The records read and parsed whole, their headers meeting their targets.
+    pub records: u64,
+    pub corrupt: Vec<CorruptRegion>,
+}
+
+impl BlockFileReader {
+    /// Check every record of every file: that it starts with the network's magic bytes,
+    /// that its block parses to exactly the size it declares and that the block's hash
+    /// meets its target.
+    ///
+    /// A record that fails only the proof of work check is reported on its own, while one
+    /// that can't be parsed is reported from its start up to the next magic bytes, where
+    /// checking carries on.
+    pub fn verify(&mut self) -> Result<VerifyReport> {
+        let mut report = VerifyReport::default();
+        for file in 0..self.files.files().len() {
+            let path = self.files.files()[file].clone();
+            let mut source = self.files.source(file)?;
+            let bytes = source.contents().map_err(|e| {
+                ErrorKind::Io.error(format!("failed to read {}: {e}", path.display()))
+            })?;
+            let mut offset = 0;
+            while offset < bytes.len() {
+                let start = offset;
+                let problem = match self.check_record(file, &bytes, &mut offset) {
+                    Ok(true) => {
+                        report.records += 1;
+                        continue;
+                    }
+                    Ok(false) => continue,
+                    Err(problem) => problem,
+                };
+                // A record that was framed properly ends where it says, otherwise
+                // nothing after its start can be trusted until the next magic bytes.
+                if offset == start {
+                    offset = self.next_magic(&bytes, start + 1).unwrap_or(bytes.len());
+                }
+                tracing::debug!("{} is corrupt at {start}: {problem}", path.display());
+                report.corrupt.push(CorruptRegion {
+                    file: path.clone(),
+                    start: start as u64,
+                    end: offset as u64,
+                    problem,
+                });
+            }
+            report.files += 1;
+            report.bytes += bytes.len() as u64;
+            self.bytes_read += bytes.len() as u64;
+        }
+        Ok(report)
+    }
+    /// Check the record at `offset` in a file's `bytes`, moving `offset` past it. Returns
+    /// whether there was a record, rather than padding, or why it's damaged, leaving
+    /// `offset` where it was unless the damage is only to the block's proof of work.
+    fn check_record(
+        &mut self,
+        file: usize,
+        bytes: &[u8],
+        offset: &mut usize,
+    ) -> Result<bool, String> {
+        let mut cursor = ByteCursor::starting_at(&bytes[*offset..], *offset as u64);
+        let record = read_record_info(
+            &mut cursor,
+            file,
+            self.height,
+            &mut self.network,
+            self.files.xor_key,
+        )
+        .map_err(|e| e.to_string())?;
+        let start = cursor.position() as usize;
+        let Some(block_info) = record else {
+            *offset = start;
+            return Ok(false);
+        };
+        let size = block_info.size_as_u32();
+        let end = start.saturating_add(size as usize).min(bytes.len());
+        let view =
+            BlockView::parse_at(&bytes[start..end], start as u64).map_err(|e| e.to_string())?;
+        if view.total_size() != size {
+            return Err(format!(
+                "the record declares {size} bytes but its block is {}",
+                view.total_size()
+            ));
+        }
+        *offset = end;
+        self.height += 1;
+        view.block_header()
+            .to_owned()
+            .validate_pow()
+            .map_err(|e| e.to_string())?;
+        Ok(true)
+    }
+    /// The offset of the next magic bytes in `bytes` from `from`, of the network being
+    /// read or of any network when it isn't known yet.
+    fn next_magic(&self, bytes: &[u8], from: usize) -> Option<usize> {
+        let networks = match self.network {
+            Some(network) => vec![network],
+            None => Network::ALL.to_vec(),
+        };
+        bytes
+            .get(from..)?
+            .windows(4)
+            .position(|window| {
+                networks
+                    .iter()
+                    .any(|network| window == network.magic_bytes())
+            })
+            .map(|position| from + position)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{
+        reader::{blk_file_path, BlockFileReader},
+        util::fixtures::{blk_record, BLOCK_1_HEX, GENESIS_BLOCK_HEX},
+    };
+
+    /// Checks that damaged records are reported where they are, and the intact ones around
+    /// them still counted.
+    #[test]
+    fn test_verify() {
+        let datadir = std::env::temp_dir().join("bbf-test-verify");
+        std::fs::create_dir_all(&datadir).unwrap();
+        // A valid genesis record, then block 1 with its nonce changed so its hash misses
+        // the target, then garbage, then genesis again cut short.
+        let mut blk = blk_record(GENESIS_BLOCK_HEX);
+        let mut block_1 = blk_record(BLOCK_1_HEX);
+        block_1[8 + 76] ^= 0xff;
+        blk.extend(&block_1);
+        blk.extend([0xde, 0xad, 0xbe, 0xef, 0x01]);
+        blk.extend(&blk_record(GENESIS_BLOCK_HEX)[..100]);
+        std::fs::write(blk_file_path(&datadir, 0), &blk).unwrap();
+        std::fs::write(blk_file_path(&datadir, 1), blk_record(BLOCK_1_HEX)).unwrap();
+
+        let report = BlockFileReader::open(&datadir, None)
+            .unwrap()
+            .verify()
+            .unwrap();
+        std::fs::remove_dir_all(&datadir).unwrap();
+
+        assert_eq!(report.files, 2);
+        assert_eq!(report.records, 2);
+        let regions = report
+            .corrupt
+            .iter()
+            .map(|region| (region.file.file_name().unwrap(), region.start, region.end))
+            .collect::<Vec<_>>();
+        assert_eq!(
+            regions,
+            [
+                ("blk00000.dat".as_ref(), 293, 516),
+                ("blk00000.dat".as_ref(), 516, 521),
+                ("blk00000.dat".as_ref(), 521, 621),
+            ]
+        );
+        assert!(report.corrupt[0].problem.contains("target"));
+        assert!(report.corrupt[2].problem.contains("unexpected end"));
+    }
+}
@@ -0,0 +1,204 @@
+//! Fetching blocks from a Bitcoin Core node over JSON-RPC, for blocks the local blk
+//! files don't have, such as those a pruned node has already deleted.
+use crate::util::{
+    constant::Network,
+    error::ErrorKind,
+    hash::{from_display_hex, to_display_hex},
+    tx::{Transaction, TxOut},
+    types::{Block, BlockInfo, ByteCursor},
+};
+use anyhow::{bail, Result};
+use base64::Engine;
+use serde_json::{json, Value};
+
+/// A connection to a node's RPC server.
+#[derive(Debug, Clone)]
+pub struct RpcClient {
+    url: String,
+    /// The `Authorization` header, when a user and password are given.
+    auth: Option<String>,
+}
+impl RpcClient {
+    pub fn new(url: impl Into<String>, user: Option<&str>, pass: Option<&str>) -> Self {
+        let auth = user.map(|user| {
+            let credentials = format!("{user}:{}", pass.unwrap_or_default());
+            format!(
+                "Basic {}",
+                base64::engine::general_purpose::STANDARD.encode(credentials)
+            )
+        });
+        Self {
+            url: url.into(),
+            auth,
+        }
+    }
+    /// Call `method` with `params`, returning its result.
+    pub fn call(&self, method: &str, params: Value) -> Result<Value> {
+        tracing::debug!("calling {method} on {} with {params}", self.url);
+        let mut request = ureq::post(&self.url);
+        if let Some(auth) = &self.auth {
+            request = request.set("Authorization", auth);
+        }
+        let body = json!({"jsonrpc": "1.0", "id": "bitcoin-block-finder", "method": method, "params": params});
+        // Core answers failed calls with an error status, but still with a JSON body explaining why.
+        let response: Value = match request.send_json(body) {
+            Ok(response) => response.into_json()?,
+            Err(ureq::Error::Status(status, response)) => response
+                .into_json()
+                .map_err(|_| anyhow::anyhow!("failed to call {method}: HTTP status {status}"))?,
+            Err(e) => bail!("failed to call {method} on {}: {e}", self.url),
+        };
+        if let Some(message) = response["error"]["message"].as_str() {
+            bail!("failed to call {method}: {message}")
+        }
+        Ok(response["result"].clone())
+    }
+    /// The network the node is running on.
+    pub fn network(&self) -> Result<Network> {
+        let info = self.call("getblockchaininfo", json!([]))?;
+        match info["chain"].as_str() {
+            Some("main") => Ok(Network::Mainnet),
+            Some("test") => Ok(Network::Testnet3),
+            Some("testnet4") => Ok(Network::Testnet4),
+            Some("signet") => Ok(Network::Signet),
+            Some("regtest") => Ok(Network::Regtest),
+            chain => bail!("failed to recognise the node's chain {chain:?}"),
+        }
+    }
+    /// The hash of the block at `height` on the node's best chain.
+    pub fn block_hash(&self, height: u64) -> Result<[u8; 32]> {
+        let hash = self.call("getblockhash", json!([height]))?;
+        let hash = hash
+            .as_str()
+            .ok_or_else(|| anyhow::anyhow!("expected a hash from getblockhash"))?;
+        from_display_hex(hash)
+    }
+    /// Fetch the block with `hash`, labelled with its height as a record read from a blk file would be.
+    pub fn block(&self, hash: &[u8; 32], network: Network) -> Result<(BlockInfo, Block)> {
+        let hash_hex = to_display_hex(hash);
+        let header = self.call("getblockheader", json!([hash_hex]))?;
+        let height = header["height"]
+            .as_u64()
+            .ok_or_else(|| anyhow::anyhow!("expected a height from getblockheader"))?;
+        let raw_hex = self.call("getblock", json!([hash_hex, 0]))?;
+        let raw_bytes = hex::decode(
+            raw_hex
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("expected hex from getblock"))?,
+        )?;
+
+        let block = Block::from_raw_bytes(&mut ByteCursor::new(raw_bytes.as_slice()))?;
+        if block.block_header().hash() != *hash {
+            bail!("failed to fetch block {hash_hex}, the node returned a different block")
+        }
+        let size = (raw_bytes.len() as u32).to_le_bytes().to_vec();
+        let block_info =
+            BlockInfo::new(height, network.magic_bytes().to_vec(), size).with_block_hash(*hash);
+        Ok((block_info, block))
+    }
+    /// Fetch the transaction with `txid`, given in display hex, with getrawtransaction.
+    /// The node needs -txindex for transactions outside its mempool.
+    pub fn transaction(&self, txid: &str) -> Result<Transaction> {
+        let raw_hex = self.call("getrawtransaction", json!([txid]))?;
+        let raw_bytes = hex::decode(
+            raw_hex
+                .as_str()
+                .ok_or_else(|| anyhow::anyhow!("expected hex from getrawtransaction"))?,
+        )?;
+
+        let transaction = Transaction::from_raw_bytes(&mut ByteCursor::new(raw_bytes.as_slice()))?;
+        if to_display_hex(&transaction.txid()) != txid {
+            bail!("failed to fetch transaction {txid}, the node returned a different transaction")
+        }
+        Ok(transaction)
+    }
+    /// The outputs spent by each input of `transaction`, fetching the transactions that
+    /// created them. A coinbase spends none.
+    pub fn prevouts(&self, transaction: &Transaction) -> Result<Vec<TxOut>> {
+        if transaction.is_coinbase() {
+            return Ok(Vec::new());
+        }
+        transaction
+            .inputs()
+            .iter()
+            .map(|input| {
+                let outpoint = input.previous_output();
+                let previous = self.transaction(&outpoint.txid())?;
+                previous
+                    .outputs()
+                    .get(outpoint.vout() as usize)
+                    .cloned()
+                    .ok_or_else(|| {
+                        ErrorKind::NotFound.error(format!("failed to find output {outpoint}"))
+                    })
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::RpcClient;
+    use crate::{util::fixtures::GENESIS_BLOCK_HEX, Network};
+    use std::{
+        io::{BufRead, BufReader, Read, Write},
+        net::TcpListener,
+    };
+
+    /// Answer each request made to the returned url with the next of `results`.
+    fn serve(results: Vec<serde_json::Value>) -> String {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        std::thread::spawn(move || {
+            for result in results {
+                let (stream, _) = listener.accept().unwrap();
+                let mut reader = BufReader::new(stream);
+                let mut content_length = 0;
+                loop {
+                    let mut line = String::new();
+                    reader.read_line(&mut line).unwrap();
+                    if let Some(length) = line.to_lowercase().strip_prefix("content-length:") {
+                        content_length = length.trim().parse().unwrap();
+                    }
+                    if line == "\r\n" {
+                        break;
+                    }
+                }
+                reader
+                    .by_ref()
+                    .take(content_length)
+                    .read_to_end(&mut Vec::new())
+                    .unwrap();
+                let body =
+                    serde_json::json!({"result": result, "error": null, "id": 0}).to_string();
+                write!(
+                    reader.get_mut(),
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{body}",
+                    body.len()
+                )
+                .unwrap();
+            }
+        });
+        url
+    }
+
+    /// Checks that a block fetched by height is parsed and labelled like one read from disk.
+    #[test]
+    fn test_rpc_block() {
+        let hash = "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f";
+        let url = serve(vec![
+            hash.into(),
+            serde_json::json!({"height": 0}),
+            GENESIS_BLOCK_HEX.into(),
+        ]);
+        let client = RpcClient::new(url, Some("user"), Some("pass"));
+
+        let hash = client.block_hash(0).unwrap();
+        let (block_info, block) = client.block(&hash, Network::Mainnet).unwrap();
+
+        assert_eq!(block_info.height, 0);
+        assert_eq!(block_info.size_as_u32(), 285);
+        assert_eq!(block_info.network().unwrap(), Network::Mainnet);
+        assert_eq!(block.block_header().hash(), hash);
+    }
+}
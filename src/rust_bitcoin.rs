@@ -0,0 +1,161 @@
+//! Conversions between this crate's blocks, headers and transactions and those of the
+//! `bitcoin` crate, for parsing the blk files here and then signing, validating or
+//! building with the wider ecosystem.
+//!
+//! Blocks and transactions convert through their serialization, so what's converted is
+//! byte for byte what was read. Converting to the `bitcoin` crate can fail, since it's
+//! stricter about what it decodes: a witness serialized transaction without any witness
+//! is refused, for one.
+use crate::util::{
+    error::ParseError,
+    tx::Transaction,
+    types::{Block, BlockHeader, ByteCursor},
+};
+use bitcoin::{
+    block::{Header, Version},
+    consensus::{deserialize, encode, serialize},
+    hashes::Hash,
+    BlockHash, CompactTarget, TxMerkleNode,
+};
+
+impl From<&BlockHeader> for Header {
+    fn from(header: &BlockHeader) -> Self {
+        Header {
+            version: Version::from_consensus(header.version() as i32),
+            prev_blockhash: BlockHash::from_byte_array(header.previous_hash()),
+            merkle_root: TxMerkleNode::from_byte_array(header.merkle_root()),
+            time: header.unix_epoch_time(),
+            bits: CompactTarget::from_consensus(header.target()),
+            nonce: header.nonce(),
+        }
+    }
+}
+impl From<BlockHeader> for Header {
+    fn from(header: BlockHeader) -> Self {
+        Header::from(&header)
+    }
+}
+impl From<&Header> for BlockHeader {
+    fn from(header: &Header) -> Self {
+        BlockHeader::new(
+            header.version.to_consensus().to_le_bytes().to_vec(),
+            header.prev_blockhash.to_byte_array().to_vec(),
+            header.merkle_root.to_byte_array().to_vec(),
+            header.time.to_le_bytes().to_vec(),
+            header.bits.to_consensus().to_le_bytes().to_vec(),
+            header.nonce.to_le_bytes().to_vec(),
+        )
+    }
+}
+impl From<Header> for BlockHeader {
+    fn from(header: Header) -> Self {
+        BlockHeader::from(&header)
+    }
+}
+
+impl TryFrom<&Transaction> for bitcoin::Transaction {
+    type Error = encode::Error;
+
+    fn try_from(tx: &Transaction) -> Result<Self, Self::Error> {
+        deserialize(&tx.raw_bytes())
+    }
+}
+impl TryFrom<Transaction> for bitcoin::Transaction {
+    type Error = encode::Error;
+
+    fn try_from(tx: Transaction) -> Result<Self, Self::Error> {
+        bitcoin::Transaction::try_from(&tx)
+    }
+}
+impl TryFrom<&bitcoin::Transaction> for Transaction {
+    type Error = ParseError;
+
+    fn try_from(tx: &bitcoin::Transaction) -> Result<Self, Self::Error> {
+        Transaction::from_raw_bytes(&mut ByteCursor::new(serialize(tx).as_slice()))
+    }
+}
+impl TryFrom<bitcoin::Transaction> for Transaction {
+    type Error = ParseError;
+
+    fn try_from(tx: bitcoin::Transaction) -> Result<Self, Self::Error> {
+        Transaction::try_from(&tx)
+    }
+}
+
+impl TryFrom<&Block> for bitcoin::Block {
+    type Error = encode::Error;
+
+    fn try_from(block: &Block) -> Result<Self, Self::Error> {
+        deserialize(&block.raw_bytes())
+    }
+}
+impl TryFrom<Block> for bitcoin::Block {
+    type Error = encode::Error;
+
+    fn try_from(block: Block) -> Result<Self, Self::Error> {
+        bitcoin::Block::try_from(&block)
+    }
+}
+impl TryFrom<&bitcoin::Block> for Block {
+    type Error = ParseError;
+
+    fn try_from(block: &bitcoin::Block) -> Result<Self, Self::Error> {
+        Block::from_raw_bytes(&mut ByteCursor::new(serialize(block).as_slice()))
+    }
+}
+impl TryFrom<bitcoin::Block> for Block {
+    type Error = ParseError;
+
+    fn try_from(block: bitcoin::Block) -> Result<Self, Self::Error> {
+        Block::try_from(&block)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::fixtures::{arb_transaction, block, GENESIS_BLOCK_HEX};
+    use proptest::prelude::*;
+
+    proptest! {
+        /// Checks that a transaction converts to the `bitcoin` crate's with the same txid and
+        /// wtxid and back to the bytes it was parsed from, or is refused only for being
+        /// witness serialized without any witness.
+        #[test]
+        fn test_transaction_conversion(raw_bytes in arb_transaction()) {
+            let tx = Transaction::from_raw_bytes(&mut ByteCursor::new(raw_bytes.as_slice())).unwrap();
+            match bitcoin::Transaction::try_from(&tx) {
+                Ok(converted) => {
+                    prop_assert_eq!(converted.compute_txid().to_byte_array(), tx.txid());
+                    prop_assert_eq!(converted.compute_wtxid().to_byte_array(), tx.wtxid());
+                    let back = Transaction::try_from(converted).unwrap();
+                    prop_assert_eq!(back.raw_bytes(), raw_bytes);
+                }
+                Err(_) => prop_assert!(
+                    tx.is_segwit() && tx.inputs().iter().all(|input| input.witness().is_empty())
+                ),
+            }
+        }
+    }
+
+    /// Checks the genesis block converts to the `bitcoin` crate's own genesis block, header
+    /// and all, and back to the same bytes.
+    #[test]
+    fn test_block_conversion() {
+        let block = block(GENESIS_BLOCK_HEX);
+        let converted = bitcoin::Block::try_from(&block).unwrap();
+        assert_eq!(
+            converted,
+            bitcoin::constants::genesis_block(bitcoin::Network::Bitcoin)
+        );
+        assert_eq!(Header::from(block.block_header()), converted.header);
+        assert_eq!(
+            BlockHeader::from(converted.header).raw_bytes(),
+            block.block_header().raw_bytes()
+        );
+        assert_eq!(
+            Block::try_from(converted).unwrap().raw_bytes(),
+            block.raw_bytes()
+        );
+    }
+}
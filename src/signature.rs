@@ -0,0 +1,305 @@
+//! Checking input signatures against the outputs they spend, for the standard single key
+//! output types: P2PK, P2PKH, P2WPKH, P2WPKH nested in P2SH and taproot key path spends.
+//!
+//! Inputs spending anything else, multisig and other scripts, are skipped rather than run,
+//! as that needs a full script interpreter.
+use crate::util::{
+    asm::push_only,
+    hash::hash160,
+    script::{Script, ScriptType},
+    sighash::{p2wpkh_script_code, SIGHASH_DEFAULT},
+    taproot::TaprootSpend,
+    tx::TxOut,
+};
+use crate::{Block, Transaction};
+use anyhow::{bail, Result};
+use secp256k1::{ecdsa, schnorr, Message, PublicKey, XOnlyPublicKey};
+
+/// How many of a transaction's or block's inputs had their signatures checked.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct SignatureCounts {
+    pub verified: u64,
+    /// The inputs spending outputs of a type whose signatures aren't checked.
+    pub skipped: u64,
+}
+impl std::ops::AddAssign for SignatureCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.verified += other.verified;
+        self.skipped += other.skipped;
+    }
+}
+
+impl Transaction {
+    /// Check the signature of every input spending a standard single key output, given
+    /// `prevouts`, the outputs each input spends in order. A coinbase has none to check.
+    pub fn verify_signatures(&self, prevouts: &[TxOut]) -> Result<SignatureCounts> {
+        let mut counts = SignatureCounts::default();
+        if self.is_coinbase() {
+            return Ok(counts);
+        }
+        if prevouts.len() != self.inputs().len() {
+            bail!(
+                "expected the {} outputs spent by the transaction, found {}",
+                self.inputs().len(),
+                prevouts.len()
+            )
+        }
+        for index in 0..self.inputs().len() {
+            let verified = self.verify_input(index, prevouts).map_err(|e| {
+                anyhow::anyhow!(
+                    "signature verification failed for input {index} of {}: {e}",
+                    self.inputs()[index].previous_output()
+                )
+            })?;
+            match verified {
+                true => counts.verified += 1,
+                false => counts.skipped += 1,
+            }
+        }
+        Ok(counts)
+    }
+
+    /// Check the signature of input `index`, returning whether it spends a type that's checked.
+    fn verify_input(&self, index: usize, prevouts: &[TxOut]) -> Result<bool> {
+        let input = &self.inputs()[index];
+        let prevout = &prevouts[index];
+        let script = prevout.script();
+        let pushes = push_only(input.script_sig_bytes()).unwrap_or_default();
+        match script.script_type() {
+            ScriptType::P2pk => {
+                let [signature] = pushes.as_slice() else {
+                    bail!("expected a signature alone in the scriptSig")
+                };
+                let pubkey = &script.bytes()[1..script.bytes().len() - 1];
+                verify_ecdsa(signature, pubkey, |sighash_type| {
+                    self.legacy_sighash(index, script.bytes(), sighash_type)
+                })?;
+            }
+            ScriptType::P2pkh => {
+                let [signature, pubkey] = pushes.as_slice() else {
+                    bail!("expected a signature and public key in the scriptSig")
+                };
+                check_key_hash(pubkey, &script.bytes()[3..23])?;
+                verify_ecdsa(signature, pubkey, |sighash_type| {
+                    self.legacy_sighash(index, script.bytes(), sighash_type)
+                })?;
+            }
+            ScriptType::P2wpkh => {
+                let (_, pubkey_hash) = script
+                    .witness_program()
+                    .expect("expected a witness program");
+                self.verify_p2wpkh(index, prevout.value(), pubkey_hash)?;
+            }
+            ScriptType::P2sh => {
+                // Only a P2WPKH redeem script is checked, pushed alone in the scriptSig.
+                let [redeem_script] = pushes.as_slice() else {
+                    return Ok(false);
+                };
+                let redeem_script = Script::new(redeem_script);
+                if redeem_script.script_type() != ScriptType::P2wpkh {
+                    return Ok(false);
+                }
+                if hash160(redeem_script.bytes()) != script.bytes()[2..22] {
+                    bail!("the redeem script doesn't match the script hash")
+                }
+                let (_, pubkey_hash) = redeem_script
+                    .witness_program()
+                    .expect("expected a witness program");
+                self.verify_p2wpkh(index, prevout.value(), pubkey_hash)?;
+            }
+            ScriptType::P2tr => {
+                let Some(TaprootSpend::KeyPath { signature, annex }) = input.taproot_spend() else {
+                    return Ok(false);
+                };
+                let output_key = script.taproot_output_key().expect("expected an output key");
+                let (signature, sighash_type) = match signature.len() {
+                    64 => (signature, SIGHASH_DEFAULT),
+                    65 if signature[64] != SIGHASH_DEFAULT => (&signature[..64], signature[64]),
+                    65 => bail!("a 65 byte signature can't have the default sighash type"),
+                    len => bail!("expected a 64 or 65 byte signature, found {len} bytes"),
+                };
+                let sighash = self.taproot_sighash(index, prevouts, sighash_type, None, annex)?;
+                let signature = schnorr::Signature::from_byte_array(
+                    signature.try_into().expect("expected a 64 byte signature"),
+                );
+                let output_key = XOnlyPublicKey::from_byte_array(
+                    output_key
+                        .try_into()
+                        .expect("expected a 32 byte output key"),
+                )?;
+                schnorr::verify(&signature, &sighash, &output_key)?;
+            }
+            _ => return Ok(false),
+        }
+        Ok(true)
+    }
+
+    /// Check a P2WPKH witness, a signature and the key hashing to `pubkey_hash`.
+    fn verify_p2wpkh(&self, index: usize, value: u64, pubkey_hash: &[u8]) -> Result<()> {
+        let [signature, pubkey] = self.inputs()[index].witness() else {
+            bail!("expected a signature and public key in the witness")
+        };
+        check_key_hash(pubkey, pubkey_hash)?;
+        let script_code =
+            p2wpkh_script_code(pubkey_hash.try_into().expect("expected a 20 byte key hash"));
+        verify_ecdsa(signature, pubkey, |sighash_type| {
+            self.segwit_v0_sighash(index, &script_code, value, sighash_type)
+        })
+    }
+}
+
+impl Block {
+    /// Check the signatures of every transaction, given `spent`, the outputs spent by each
+    /// transaction in order as returned by [UtxoSet::apply](crate::chain::utxo::UtxoSet::apply).
+    pub fn verify_signatures(&self, spent: &[Vec<TxOut>]) -> Result<SignatureCounts> {
+        if spent.len() != self.transactions().len() {
+            bail!(
+                "expected the outputs spent by {} transactions, found {}",
+                self.transactions().len(),
+                spent.len()
+            )
+        }
+        let mut counts = SignatureCounts::default();
+        for (transaction, prevouts) in self.transactions().iter().zip(spent) {
+            counts += transaction.verify_signatures(prevouts)?;
+        }
+        Ok(counts)
+    }
+}
+
+/// Check that `pubkey` hashes to the `pubkey_hash` the output pays to.
+fn check_key_hash(pubkey: &[u8], pubkey_hash: &[u8]) -> Result<()> {
+    if hash160(pubkey) != pubkey_hash {
+        bail!("the public key doesn't match the key hash")
+    }
+    Ok(())
+}
+
+/// Check an ECDSA `signature`, DER encoded and followed by its sighash type, against
+/// `pubkey` and the sighash `sighash` computes for that type.
+///
+/// Signatures from before BIP66 needn't be strict DER, and before BIP146 needn't have a
+/// low s value, so both are accepted as consensus accepts them.
+fn verify_ecdsa(
+    signature: &[u8],
+    pubkey: &[u8],
+    sighash: impl FnOnce(u32) -> Result<[u8; 32]>,
+) -> Result<()> {
+    let Some((sighash_type, der)) = signature.split_last() else {
+        bail!("expected a signature, found an empty push")
+    };
+    let mut signature = ecdsa::Signature::from_der_lax(der)?;
+    signature.normalize_s();
+    let pubkey = PublicKey::from_slice(pubkey)?;
+    let message = Message::from_digest(sighash(u32::from(*sighash_type))?);
+    ecdsa::verify(&signature, message, &pubkey)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SignatureCounts;
+    use crate::{
+        util::{hash::to_display_hex, tx::TxOut, types::ByteCursor},
+        Transaction,
+    };
+
+    /// Mainnet transaction 0543411b2e7fbd8af4c46c593049dbac2d4007c0f5f12e4e5c71a07566e787c4,
+    /// spending a P2PKH output and two P2WPKH outputs nested in P2SH.
+    const P2PKH_P2SH_P2WPKH_TX_HEX: &str = "020000000001031cfbc8f54fbfa4a33a30068841371f80dbfe166211242213188428f437445c91000000006a47304402206fbcec8d2d2e740d824d3d36cc345b37d9f65d665a99f5bd5c9e8d42270a03a8022013959632492332200c2908459547bf8dbf97c65ab1a28dec377d6f1d41d3d63e012103d7279dfb90ce17fe139ba60a7c41ddf605b25e1c07a4ddcb9dfef4e7d6710f48feffffff476222484f5e35b3f0e43f65fc76e21d8be7818dd6a989c160b1e5039b7835fc00000000171600140914414d3c94af70ac7e25407b0689e0baa10c77feffffffa83d954a62568bbc99cc644c62eb7383d7c2a2563041a0aeb891a6a4055895570000000017160014795d04cc2d4f31480d9a3710993fbd80d04301dffeffffff06fef72f000000000017a91476fd7035cd26f1a32a5ab979e056713aac25796887a5000f00000000001976a914b8332d502a529571c6af4be66399cd33379071c588ac3fda0500000000001976a914fc1d692f8de10ae33295f090bea5fe49527d975c88ac522e1b00000000001976a914808406b54d1044c429ac54c0e189b0d8061667e088ac6eb68501000000001976a914dfab6085f3a8fb3e6710206a5a959313c5618f4d88acbba20000000000001976a914eb3026552d7e3f3073457d0bee5d4757de48160d88ac0002483045022100bee24b63212939d33d513e767bc79300051f7a0d433c3fcf1e0e3bf03b9eb1d70220588dc45a9ce3a939103b4459ce47500b64e23ab118dfc03c9caa7d6bfc32b9c601210354fd80328da0f9ae6eef2b3a81f74f9a6f66761fadf96f1d1d22b1fd6845876402483045022100e29c7e3a5efc10da6269e5fc20b6a1cb8beb92130cc52c67e46ef40aaa5cac5f0220644dd1b049727d991aece98a105563416e10a5ac4221abac7d16931842d5c322012103960b87412d6e169f30e12106bdf70122aabb9eb61f455518322a18b920a4dfa887d30700";
+    /// Mainnet transaction 7b0f56318564c39718d11af98115bf1af39d6d938e1bcd27dc72ac3e75fb6cf8 from
+    /// block 702861, spending a P2WPKH output created earlier in the same block.
+    const P2WPKH_TX_HEX: &str = "020000000001014768e5ce8096f40814ec466689063a910aa81d70a98912f9d4c74b3dbaf476db0600000000fdffffff01b3698713000000001600144068add9d8059196e4758bc2b017b02446fe146b02473044022034763889ef9a20477e108a4d2ddb3a89b473409b4996dad886172dbd879be0410220563b77ca4b480d1ea64e36fb5b06d8eb615523956c345726400e5f390db4c061012102034d44f740dbf6295431c1bf5c8ae561858b3403a17ade8e224cf7fa5bcdf47500000000";
+    /// Mainnet transaction 33e794d097969002ee05d336686fc03c9e15a597c1b9827669460fac98799036,
+    /// the first taproot spend, down the key path.
+    const P2TR_TX_HEX: &str = "01000000000101d1f1c1f8cdf6759167b90f52c9ad358a369f95284e841d7a2536cef31c0549580100000000fdffffff020000000000000000316a2f49206c696b65205363686e6f7272207369677320616e6420492063616e6e6f74206c69652e204062697462756734329e06010000000000225120a37c3903c8d0db6512e2b40b0dffa05e5a3ab73603ce8c9c4b7771e5412328f90140a60c383f71bac0ec919b1d7dbc3eb72dd56e7aa99583615564f9f99b8ae4e837b758773a5b2e4c51348854c8389f008e05029db7f464a5ff2e01d5e6e626174affd30a00";
+    /// Mainnet transaction 1a3e89644985fbbb41e0dcfe176739813542b5937003c46a07de1e3ee7a4a7f3,
+    /// spending a P2WSH output.
+    const P2WSH_TX_HEX: &str = "010000000001011f97548fbbe7a0db7588a66e18d803d0089315aa7d4cc28360b6ec50ef36718a0100000000ffffffff02df1776000000000017a9146c002a686959067f4866b8fb493ad7970290ab728757d29f0000000000220020701a8d401c84fb13e6baf169d59684e17abd9fa216c8cc5b9fc63d622ff8c58d04004730440220565d170eed95ff95027a69b313758450ba84a01224e1f7f130dda46e94d13f8602207bdd20e307f062594022f12ed5017bbf4a055a06aea91c10110a0e3bb23117fc014730440220647d2dc5b15f60bc37dc42618a370b2a1490293f9e5c8464f53ec4fe1dfe067302203598773895b4b16d37485cbe21b337f4e4b650739880098c592553add7dd4355016952210375e00eb72e29da82b89367947f29ef34afb75e8654f6ea368e0acdfd92976b7c2103a1b26313f430c4b15bb1fdce663207659d8cac749a0e53d70eff01874496feff2103c96d495bfdd5ba4145e3e046fee45e84a8a48ad05bd8dbb395c011a32cf9f88053ae00000000";
+
+    fn transaction(tx_hex: &str) -> Transaction {
+        let raw_bytes = hex::decode(tx_hex).unwrap();
+        Transaction::from_raw_bytes(&mut ByteCursor::new(raw_bytes.as_slice())).unwrap()
+    }
+
+    fn prevouts(spent: &[(&str, u64)]) -> Vec<TxOut> {
+        spent
+            .iter()
+            .map(|(script, value)| TxOut::new(*value, hex::decode(script).unwrap()))
+            .collect()
+    }
+
+    /// Checks real mainnet P2PKH, P2SH-P2WPKH, P2WPKH and taproot key path signatures against
+    /// the outputs they spent, that a P2WSH input is skipped, and that flipping a bit of a
+    /// signature or leaving out a spent output fails.
+    #[test]
+    fn test_verify_signatures() {
+        let verified = |count| SignatureCounts {
+            verified: count,
+            skipped: 0,
+        };
+        let nested = transaction(P2PKH_P2SH_P2WPKH_TX_HEX);
+        let nested_prevouts = prevouts(&[
+            (
+                "76a9149f44b06f6ee92ddbc4686f71afe528c09727a5c788ac",
+                986_422,
+            ),
+            ("a914f3890da1b99e44cd3d52f7bcea6a1351658ea7be87", 22_904_040),
+            ("a914f895e1dd9b29cb228e9b06a15204e3b57feaf7cc87", 8_000_000),
+        ]);
+        assert_eq!(
+            to_display_hex(&nested.txid()),
+            "0543411b2e7fbd8af4c46c593049dbac2d4007c0f5f12e4e5c71a07566e787c4"
+        );
+        assert_eq!(
+            nested.verify_signatures(&nested_prevouts).unwrap(),
+            verified(3)
+        );
+        assert!(nested.verify_signatures(&nested_prevouts[..2]).is_err());
+
+        let p2wpkh = transaction(P2WPKH_TX_HEX);
+        let p2wpkh_prevouts =
+            prevouts(&[("00145dff7a05c5ceda42a53e929ad2e2ef4b82cbd83e", 327_644_613)]);
+        assert_eq!(
+            p2wpkh.verify_signatures(&p2wpkh_prevouts).unwrap(),
+            verified(1)
+        );
+        // The witness commits to the value spent, so it fails for any other.
+        assert!(p2wpkh
+            .verify_signatures(&prevouts(&[(
+                "00145dff7a05c5ceda42a53e929ad2e2ef4b82cbd83e",
+                327_644_614
+            )]))
+            .is_err());
+
+        let p2tr = transaction(P2TR_TX_HEX);
+        let p2tr_prevouts = prevouts(&[(
+            "5120339ce7e165e67d93adb3fef88a6d4beed33f01fa876f05a225242b82a631abc0",
+            88_480,
+        )]);
+        assert_eq!(
+            to_display_hex(&p2tr.txid()),
+            "33e794d097969002ee05d336686fc03c9e15a597c1b9827669460fac98799036"
+        );
+        assert_eq!(p2tr.verify_signatures(&p2tr_prevouts).unwrap(), verified(1));
+        let signature = hex::encode(&p2tr.inputs()[0].witness()[0]);
+        let mut flipped = hex::decode(&signature).unwrap();
+        flipped[40] ^= 0x01;
+        let tampered = transaction(&P2TR_TX_HEX.replacen(&signature, &hex::encode(flipped), 1));
+        assert!(tampered.verify_signatures(&p2tr_prevouts).is_err());
+
+        let p2wsh = transaction(P2WSH_TX_HEX);
+        assert_eq!(
+            p2wsh
+                .verify_signatures(&prevouts(&[(
+                    "0020701a8d401c84fb13e6baf169d59684e17abd9fa216c8cc5b9fc63d622ff8c58d",
+                    18_393_430,
+                )]))
+                .unwrap(),
+            SignatureCounts {
+                verified: 0,
+                skipped: 1
+            }
+        );
+    }
+}
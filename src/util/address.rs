@@ -0,0 +1,139 @@
+//! Encoding keys, hashes and witness programs as addresses, and decoding addresses back
+//! into the output scripts they pay to.
+use crate::{
+    util::{
+        hash::{hash160, sha256d},
+        script::{
+            OP_0, OP_1, OP_CHECKSIG, OP_DUP, OP_EQUAL, OP_EQUALVERIFY, OP_HASH160, OP_PUSHBYTES_20,
+        },
+    },
+    Network,
+};
+use anyhow::{bail, Result};
+use bech32::{segwit, Fe32, Hrp};
+
+/// The pay to public key hash address of a 20 byte public key hash.
+pub fn p2pkh(pubkey_hash: &[u8; 20], network: Network) -> String {
+    base58check(network.p2pkh_prefix(), pubkey_hash)
+}
+
+/// The pay to public key hash address of a public key, compressed or not.
+pub fn p2pkh_from_pubkey(pubkey: &[u8], network: Network) -> String {
+    p2pkh(&hash160(pubkey), network)
+}
+
+/// The pay to script hash address of a 20 byte script hash.
+pub fn p2sh(script_hash: &[u8; 20], network: Network) -> String {
+    base58check(network.p2sh_prefix(), script_hash)
+}
+
+/// The segwit address of a witness program, bech32 for version 0 and bech32m after,
+/// or `None` if the version or program length is out of range.
+pub fn segwit(version: u8, program: &[u8], network: Network) -> Option<String> {
+    let hrp = Hrp::parse(network.bech32_hrp()).expect("expected a valid hrp");
+    let version = Fe32::try_from(version).ok()?;
+    segwit::encode(hrp, version, program).ok()
+}
+
+/// The pay to witness public key hash address of a public key. Only compressed
+/// keys are standard in segwit outputs, so an uncompressed key gives `None`.
+pub fn p2wpkh_from_pubkey(pubkey: &[u8], network: Network) -> Option<String> {
+    if pubkey.len() != 33 {
+        return None;
+    }
+    segwit(0, &hash160(pubkey), network)
+}
+
+/// Encode `payload` behind a version byte, with the first four bytes of its double SHA256 as a checksum.
+pub fn base58check(version: u8, payload: &[u8]) -> String {
+    let mut bytes = vec![version];
+    bytes.extend_from_slice(payload);
+    let checksum = sha256d(&bytes);
+    bytes.extend_from_slice(&checksum[..4]);
+    bs58::encode(bytes).into_string()
+}
+
+/// The output script an address pays to, the inverse of [Script::address](crate::util::script::Script::address).
+///
+/// Base58check and segwit addresses for any network are accepted, so the same
+/// address can be looked up in whichever blk files it belongs to.
+pub fn to_script_pubkey(address: &str) -> Result<Vec<u8>> {
+    if let Ok((_, version, program)) = segwit::decode(address) {
+        let version = version.to_u8();
+        let version = if version == 0 {
+            OP_0
+        } else {
+            OP_1 + version - 1
+        };
+        return Ok([&[version, program.len() as u8], program.as_slice()].concat());
+    }
+
+    let bytes = bs58::decode(address)
+        .into_vec()
+        .map_err(|e| anyhow::anyhow!("invalid address {address}: {e}"))?;
+    let Some((payload, checksum)) = bytes.split_last_chunk::<4>() else {
+        bail!("invalid address {address}: too short")
+    };
+    if sha256d(payload)[..4] != checksum[..] {
+        bail!("invalid address {address}: bad checksum")
+    }
+    match payload {
+        [0x00 | 0x6f, hash @ ..] if hash.len() == 20 => Ok([
+            &[OP_DUP, OP_HASH160, OP_PUSHBYTES_20],
+            hash,
+            &[OP_EQUALVERIFY, OP_CHECKSIG],
+        ]
+        .concat()),
+        [0x05 | 0xc4, hash @ ..] if hash.len() == 20 => {
+            Ok([&[OP_HASH160, OP_PUSHBYTES_20], hash, &[OP_EQUAL]].concat())
+        }
+        _ => bail!("invalid address {address}: unknown version or length"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::Network;
+
+    /// Checks addresses derived from public keys against known ones,
+    /// the genesis coinbase key and the BIP173 example key.
+    #[test]
+    fn test_pubkey_addresses() {
+        let genesis_pubkey = hex::decode("04678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5f").unwrap();
+        assert_eq!(
+            super::p2pkh_from_pubkey(&genesis_pubkey, Network::Mainnet),
+            "1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"
+        );
+        assert_eq!(
+            super::p2wpkh_from_pubkey(&genesis_pubkey, Network::Mainnet),
+            None
+        );
+
+        let pubkey =
+            hex::decode("0279be667ef9dcbbac55a06295ce870b07029bfcdb2dce28d959f2815b16f81798")
+                .unwrap();
+        assert_eq!(
+            super::p2pkh_from_pubkey(&pubkey, Network::Mainnet),
+            "1BgGZ9tcN4rm9KBzDn7KprQz87SZ26SAMH"
+        );
+        assert_eq!(
+            super::p2wpkh_from_pubkey(&pubkey, Network::Mainnet).unwrap(),
+            "bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"
+        );
+        assert_eq!(
+            super::p2wpkh_from_pubkey(&pubkey, Network::Testnet3).unwrap(),
+            "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"
+        );
+    }
+
+    /// Checks that malformed addresses are rejected.
+    #[test]
+    fn test_to_script_pubkey() {
+        assert!(super::to_script_pubkey("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNb").is_err());
+        assert!(super::to_script_pubkey("not an address").is_err());
+        assert_eq!(
+            super::to_script_pubkey("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy").unwrap(),
+            hex::decode("a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87").unwrap()
+        );
+    }
+}
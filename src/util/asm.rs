@@ -0,0 +1,219 @@
+//! Disassembling scripts into their opcodes and pushes, written the way Bitcoin Core's
+//! `asm` fields are: opcodes by name and pushed data in hex, separated by spaces.
+const OP_PUSHDATA1: u8 = 0x4c;
+const OP_PUSHDATA2: u8 = 0x4d;
+const OP_PUSHDATA4: u8 = 0x4e;
+
+/// A single step of a script.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Instruction<'a> {
+    /// The data pushed by OP_0, a direct push or one of the OP_PUSHDATA opcodes.
+    Push(&'a [u8]),
+    /// Any other opcode.
+    Op(u8),
+    /// A push running past the end of the script, with whatever bytes were left after
+    /// its opcode. Nothing follows it.
+    Truncated(&'a [u8]),
+}
+
+/// The instructions of a script in order, as returned by [instructions].
+#[derive(Debug, Clone)]
+pub struct Instructions<'a> {
+    rest: &'a [u8],
+}
+impl<'a> Iterator for Instructions<'a> {
+    type Item = Instruction<'a>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let (&opcode, after) = self.rest.split_first()?;
+        let (len_bytes, len) = match opcode {
+            0x00..=0x4b => (0, Some(opcode as usize)),
+            OP_PUSHDATA1 => (1, after.first().map(|len| *len as usize)),
+            OP_PUSHDATA2 => (
+                2,
+                after
+                    .get(..2)
+                    .map(|len| u16::from_le_bytes([len[0], len[1]]) as usize),
+            ),
+            OP_PUSHDATA4 => (
+                4,
+                after
+                    .get(..4)
+                    .map(|len| u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize),
+            ),
+            _ => {
+                self.rest = after;
+                return Some(Instruction::Op(opcode));
+            }
+        };
+        match len.and_then(|len| after.get(len_bytes..len_bytes + len)) {
+            Some(data) => {
+                self.rest = &after[len_bytes + data.len()..];
+                Some(Instruction::Push(data))
+            }
+            None => {
+                self.rest = &[];
+                Some(Instruction::Truncated(after))
+            }
+        }
+    }
+}
+
+/// Step through `script` an instruction at a time.
+pub fn instructions(script: &[u8]) -> Instructions<'_> {
+    Instructions { rest: script }
+}
+
+/// The data of each push in `script`, or `None` if it has any other opcode or a push
+/// runs past its end.
+pub fn push_only(script: &[u8]) -> Option<Vec<&[u8]>> {
+    instructions(script)
+        .map(|instruction| match instruction {
+            Instruction::Push(data) => Some(data),
+            Instruction::Op(_) | Instruction::Truncated(_) => None,
+        })
+        .collect()
+}
+
+/// The script as space separated opcode names and pushes in hex, such as
+/// `OP_DUP OP_HASH160 62e907b15cbf27d5425399ebf6f0fb50ebb88f18 OP_EQUALVERIFY OP_CHECKSIG`.
+/// An empty push is written OP_0 and a truncated one as `[error]`, as Bitcoin Core does.
+pub fn disassemble(script: &[u8]) -> String {
+    instructions(script)
+        .map(|instruction| match instruction {
+            Instruction::Push([]) => "OP_0".to_string(),
+            Instruction::Push(data) => hex::encode(data),
+            Instruction::Op(opcode) => opcode_name(opcode),
+            Instruction::Truncated(_) => "[error]".to_string(),
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// The name of an opcode other than a push, or OP_UNKNOWN with its value for those not
+/// assigned one.
+pub fn opcode_name(opcode: u8) -> String {
+    let name = match opcode {
+        0x4f => "OP_1NEGATE",
+        0x50 => "OP_RESERVED",
+        0x51..=0x60 => return format!("OP_{}", opcode - 0x50),
+        0x61 => "OP_NOP",
+        0x62 => "OP_VER",
+        0x63 => "OP_IF",
+        0x64 => "OP_NOTIF",
+        0x65 => "OP_VERIF",
+        0x66 => "OP_VERNOTIF",
+        0x67 => "OP_ELSE",
+        0x68 => "OP_ENDIF",
+        0x69 => "OP_VERIFY",
+        0x6a => "OP_RETURN",
+        0x6b => "OP_TOALTSTACK",
+        0x6c => "OP_FROMALTSTACK",
+        0x6d => "OP_2DROP",
+        0x6e => "OP_2DUP",
+        0x6f => "OP_3DUP",
+        0x70 => "OP_2OVER",
+        0x71 => "OP_2ROT",
+        0x72 => "OP_2SWAP",
+        0x73 => "OP_IFDUP",
+        0x74 => "OP_DEPTH",
+        0x75 => "OP_DROP",
+        0x76 => "OP_DUP",
+        0x77 => "OP_NIP",
+        0x78 => "OP_OVER",
+        0x79 => "OP_PICK",
+        0x7a => "OP_ROLL",
+        0x7b => "OP_ROT",
+        0x7c => "OP_SWAP",
+        0x7d => "OP_TUCK",
+        0x7e => "OP_CAT",
+        0x7f => "OP_SUBSTR",
+        0x80 => "OP_LEFT",
+        0x81 => "OP_RIGHT",
+        0x82 => "OP_SIZE",
+        0x83 => "OP_INVERT",
+        0x84 => "OP_AND",
+        0x85 => "OP_OR",
+        0x86 => "OP_XOR",
+        0x87 => "OP_EQUAL",
+        0x88 => "OP_EQUALVERIFY",
+        0x89 => "OP_RESERVED1",
+        0x8a => "OP_RESERVED2",
+        0x8b => "OP_1ADD",
+        0x8c => "OP_1SUB",
+        0x8d => "OP_2MUL",
+        0x8e => "OP_2DIV",
+        0x8f => "OP_NEGATE",
+        0x90 => "OP_ABS",
+        0x91 => "OP_NOT",
+        0x92 => "OP_0NOTEQUAL",
+        0x93 => "OP_ADD",
+        0x94 => "OP_SUB",
+        0x95 => "OP_MUL",
+        0x96 => "OP_DIV",
+        0x97 => "OP_MOD",
+        0x98 => "OP_LSHIFT",
+        0x99 => "OP_RSHIFT",
+        0x9a => "OP_BOOLAND",
+        0x9b => "OP_BOOLOR",
+        0x9c => "OP_NUMEQUAL",
+        0x9d => "OP_NUMEQUALVERIFY",
+        0x9e => "OP_NUMNOTEQUAL",
+        0x9f => "OP_LESSTHAN",
+        0xa0 => "OP_GREATERTHAN",
+        0xa1 => "OP_LESSTHANOREQUAL",
+        0xa2 => "OP_GREATERTHANOREQUAL",
+        0xa3 => "OP_MIN",
+        0xa4 => "OP_MAX",
+        0xa5 => "OP_WITHIN",
+        0xa6 => "OP_RIPEMD160",
+        0xa7 => "OP_SHA1",
+        0xa8 => "OP_SHA256",
+        0xa9 => "OP_HASH160",
+        0xaa => "OP_HASH256",
+        0xab => "OP_CODESEPARATOR",
+        0xac => "OP_CHECKSIG",
+        0xad => "OP_CHECKSIGVERIFY",
+        0xae => "OP_CHECKMULTISIG",
+        0xaf => "OP_CHECKMULTISIGVERIFY",
+        0xb0 => "OP_NOP1",
+        0xb1 => "OP_CHECKLOCKTIMEVERIFY",
+        0xb2 => "OP_CHECKSEQUENCEVERIFY",
+        0xb3..=0xb9 => return format!("OP_NOP{}", opcode - 0xaf),
+        0xba => "OP_CHECKSIGADD",
+        0xff => "OP_INVALIDOPCODE",
+        _ => return format!("OP_UNKNOWN({opcode:#04x})"),
+    };
+    name.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{disassemble, instructions, Instruction};
+
+    /// Checks standard scripts, every push opcode and a truncated push.
+    #[test]
+    fn test_disassemble() {
+        for (script, asm) in [
+            (
+                "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac",
+                "OP_DUP OP_HASH160 62e907b15cbf27d5425399ebf6f0fb50ebb88f18 OP_EQUALVERIFY OP_CHECKSIG",
+            ),
+            (
+                "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+                "OP_0 751e76e8199196d454941c45d1b3a323f1433bd6",
+            ),
+            ("5121aa52ae", "OP_1 [error]"),
+            ("4c02abcd4d0100ef4e01000000ff", "abcd ef ff"),
+            ("b1b2b3b9babbff", "OP_CHECKLOCKTIMEVERIFY OP_CHECKSEQUENCEVERIFY OP_NOP4 OP_NOP10 OP_CHECKSIGADD OP_UNKNOWN(0xbb) OP_INVALIDOPCODE"),
+            ("6a0b68656c6c6f20776f726c64", "OP_RETURN 68656c6c6f20776f726c64"),
+            ("", ""),
+        ] {
+            assert_eq!(disassemble(&hex::decode(script).unwrap()), asm);
+        }
+        assert_eq!(
+            instructions(&[0x02, 0xaa]).collect::<Vec<_>>(),
+            [Instruction::Truncated(&[0xaa])]
+        );
+    }
+}
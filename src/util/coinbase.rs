@@ -0,0 +1,343 @@
+//! Decoding the coinbase transaction, the first transaction of every block.
+use crate::util::{
+    constant::{Network, SATS_PER_BTC},
+    display::format_amount,
+    miner,
+    tx::Transaction,
+    types::Block,
+};
+use colored::*;
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+/// The first block version that commits to its height in the coinbase (BIP34).
+const BIP34_VERSION: u32 = 2;
+/// The shortest run of printable bytes treated as text rather than noise.
+const MIN_TAG_LEN: usize = 4;
+/// The subsidy of the first blocks, before any halving.
+const INITIAL_SUBSIDY: u64 = 50 * SATS_PER_BTC;
+
+/// The new coins a block at `height` on `network` is allowed to create, halving every
+/// [Network::halving_interval] blocks until it runs out.
+pub fn block_subsidy(height: u64, network: Network) -> u64 {
+    let halvings = height / network.halving_interval();
+    // As in Core, shifting by 64 or more is undefined, so the subsidy is zero by then.
+    if halvings >= 64 {
+        return 0;
+    }
+    INITIAL_SUBSIDY >> halvings
+}
+
+/// What a block's coinbase was allowed to claim and what it claimed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct BlockReward {
+    pub height: u64,
+    /// The new coins allowed at the block's height.
+    pub subsidy: u64,
+    /// The total value of the coinbase outputs.
+    pub claimed: u64,
+    /// The fees of the block's transactions, known only once it's been applied to a
+    /// [UtxoSet](crate::chain::utxo::UtxoSet).
+    pub fees: Option<u64>,
+}
+impl BlockReward {
+    /// The fees the coinbase claims on top of the subsidy.
+    pub fn implied_fees(&self) -> u64 {
+        self.claimed.saturating_sub(self.subsidy)
+    }
+    /// How much less than allowed the coinbase claims, the subsidy plus the fees when
+    /// they're known or the subsidy alone when they aren't. Whatever's left unclaimed is
+    /// never created.
+    pub fn unclaimed(&self) -> u64 {
+        (self.subsidy + self.fees.unwrap_or(0)).saturating_sub(self.claimed)
+    }
+}
+// Only the subsidy, and the fees implied by the coinbase when the real ones aren't known,
+// as it follows the coinbase's own reward and the block's total fees.
+impl std::fmt::Display for BlockReward {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Subsidy               : {} at height {}",
+            format_amount(self.subsidy),
+            self.height,
+        )?;
+        if self.fees.is_none() {
+            write!(
+                f,
+                "\nImplied Fees          : {}",
+                format_amount(self.implied_fees())
+            )?;
+        }
+        Ok(())
+    }
+}
+impl Serialize for BlockReward {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("BlockReward", 6)?;
+        state.serialize_field("height", &self.height)?;
+        state.serialize_field("subsidy", &self.subsidy)?;
+        state.serialize_field("claimed", &self.claimed)?;
+        state.serialize_field("fees", &self.fees)?;
+        state.serialize_field("implied_fees", &self.implied_fees())?;
+        state.serialize_field("unclaimed", &self.unclaimed())?;
+        state.end()
+    }
+}
+
+/// A view of a block's coinbase transaction.
+#[derive(Debug)]
+pub struct Coinbase<'a> {
+    tx: &'a Transaction,
+    block_version: u32,
+}
+impl<'a> Coinbase<'a> {
+    /// The coinbase of `block`, if its first transaction is one.
+    pub fn from_block(block: &'a Block) -> Option<Self> {
+        let tx = block.transactions().first()?;
+        tx.is_coinbase().then(|| Self {
+            tx,
+            block_version: block.block_header().version(),
+        })
+    }
+    pub fn transaction(&self) -> &Transaction {
+        self.tx
+    }
+    /// The coinbase scriptSig, which miners are free to fill with arbitrary data.
+    pub fn script_sig(&self) -> &[u8] {
+        self.tx.inputs()[0].script_sig_bytes()
+    }
+    /// The height committed to by the first push of the scriptSig.
+    ///
+    /// Only blocks of version 2 and above make this commitment,
+    /// older blocks return `None`.
+    pub fn bip34_height(&self) -> Option<u64> {
+        if self.block_version < BIP34_VERSION {
+            return None;
+        }
+        let (height, _) = first_push_number(self.script_sig())?;
+        Some(height)
+    }
+    /// The extraNonce miners count up when they've run out of header nonces, the number
+    /// the original client pushed after the bits, or the height from BIP34 on.
+    ///
+    /// Other mining software lays its scriptSig out differently,
+    /// so this is `None` whenever the second push isn't a number of up to 8 bytes.
+    pub fn extra_nonce(&self) -> Option<u64> {
+        let script_sig = self.script_sig();
+        let (_, first_len) = first_push_number(script_sig)?;
+        let (extra_nonce, _) = first_push_number(&script_sig[first_len..])?;
+        Some(extra_nonce)
+    }
+    /// The witness commitment of the block (BIP141), carried by the last output matching
+    /// the commitment template when there's more than one.
+    pub fn witness_commitment(&self) -> Option<[u8; 32]> {
+        self.tx
+            .outputs()
+            .iter()
+            .rev()
+            .find_map(|output| output.script().witness_commitment())
+    }
+    /// The witness reserved value hashed with the witness merkle root into the commitment,
+    /// the coinbase input's whole witness when it's a single 32 byte item.
+    pub fn witness_reserved_value(&self) -> Option<[u8; 32]> {
+        match self.tx.inputs()[0].witness() {
+            [value] => value.as_slice().try_into().ok(),
+            _ => None,
+        }
+    }
+    /// The total value of the coinbase outputs in satoshis,
+    /// which is the subsidy plus fees the miner claimed.
+    pub fn reward(&self) -> u64 {
+        self.tx.outputs().iter().map(|output| output.value()).sum()
+    }
+    /// Human readable text embedded in the scriptSig, such as a pool name.
+    ///
+    /// Runs of printable ASCII within the pushed data are joined with spaces,
+    /// skipping the height commitment when there is one.
+    pub fn miner_tag(&self) -> Option<String> {
+        let script_sig = self.script_sig();
+        let skip = match self.block_version >= BIP34_VERSION {
+            true => first_push_number(script_sig).map_or(0, |(_, len)| len),
+            false => 0,
+        };
+        let tag = push_data(&script_sig[skip..])
+            .into_iter()
+            .flat_map(printable_runs)
+            .collect::<Vec<String>>()
+            .join(" ");
+        (!tag.is_empty()).then_some(tag)
+    }
+    /// The pool that mined the block, when the scriptSig carries a known pool's marker.
+    pub fn miner(&self) -> Option<&'static str> {
+        miner::identify(self.script_sig())
+    }
+}
+impl std::fmt::Display for Coinbase<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}\nScript Sig            : {}\nBIP34 Height          : {}\nReward                : {}\nMiner Tag             : {}",
+            "Coinbase:".cyan(),
+            hex::encode(self.script_sig()),
+            self.bip34_height()
+                .map_or("none".to_string(), |height| height.to_string()),
+            format_amount(self.reward()),
+            self.miner_tag().unwrap_or_else(|| "none".to_string()),
+        )
+    }
+}
+impl Serialize for Coinbase<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Coinbase", 5)?;
+        state.serialize_field("script_sig", &hex::encode(self.script_sig()))?;
+        state.serialize_field("bip34_height", &self.bip34_height())?;
+        state.serialize_field("reward", &self.reward())?;
+        state.serialize_field("miner_tag", &self.miner_tag())?;
+        state.serialize_field("miner", &self.miner())?;
+        state.end()
+    }
+}
+
+/// Decode the first push of a script as a script number,
+/// returning it along with the number of script bytes it took up.
+fn first_push_number(script: &[u8]) -> Option<(u64, usize)> {
+    match *script.first()? {
+        // OP_0
+        0x00 => Some((0, 1)),
+        // OP_1 through OP_16
+        opcode @ 0x51..=0x60 => Some((u64::from(opcode - 0x50), 1)),
+        // A direct push of up to 8 bytes, a little endian number
+        // with the sign in the top bit of the last byte.
+        len @ 0x01..=0x08 => {
+            let bytes = script.get(1..=len as usize)?;
+            if bytes.last()? & 0x80 != 0 {
+                return None;
+            }
+            let value = bytes
+                .iter()
+                .rev()
+                .fold(0, |value, byte| (value << 8) | u64::from(*byte));
+            Some((value, 1 + len as usize))
+        }
+        _ => None,
+    }
+}
+
+/// The data pushed by each push opcode in a script, skipping any other opcodes.
+///
+/// Coinbase scripts are never executed so they needn't be valid, anything
+/// left after a truncated push is returned as one final chunk.
+fn push_data(script: &[u8]) -> Vec<&[u8]> {
+    let mut pushes = Vec::new();
+    let mut rest = script;
+    while let Some((&opcode, after)) = rest.split_first() {
+        let (len_bytes, len) = match opcode {
+            0x01..=0x4b => (0, opcode as usize),
+            0x4c => (1, after.first().map(|len| *len as usize).unwrap_or(0)),
+            0x4d => (
+                2,
+                after
+                    .get(..2)
+                    .map_or(0, |len| u16::from_le_bytes([len[0], len[1]]) as usize),
+            ),
+            0x4e => (
+                4,
+                after.get(..4).map_or(0, |len| {
+                    u32::from_le_bytes([len[0], len[1], len[2], len[3]]) as usize
+                }),
+            ),
+            _ => {
+                rest = after;
+                continue;
+            }
+        };
+        match after.get(len_bytes..len_bytes + len) {
+            Some(data) => {
+                pushes.push(data);
+                rest = &after[len_bytes + len..];
+            }
+            None => {
+                pushes.push(after);
+                break;
+            }
+        }
+    }
+    pushes
+}
+
+/// Every maximal run of printable ASCII at least [MIN_TAG_LEN] long.
+pub(crate) fn printable_runs(bytes: &[u8]) -> Vec<String> {
+    bytes
+        .split(|byte| !(0x20..=0x7e).contains(byte))
+        .filter(|run| run.len() >= MIN_TAG_LEN)
+        .map(|run| String::from_utf8_lossy(run).trim().to_string())
+        .filter(|run| !run.is_empty())
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::fixtures::{block, GENESIS_BLOCK_HEX};
+
+    /// Checks that the genesis coinbase message is recovered and no height is claimed.
+    #[test]
+    fn test_genesis_coinbase() {
+        let block = block(GENESIS_BLOCK_HEX);
+        let coinbase = super::Coinbase::from_block(&block).unwrap();
+
+        assert_eq!(coinbase.bip34_height(), None);
+        assert_eq!(coinbase.extra_nonce(), Some(4));
+        assert_eq!(coinbase.reward(), 5_000_000_000);
+        assert_eq!(
+            coinbase.miner_tag().unwrap(),
+            "The Times 03/Jan/2009 Chancellor on brink of second bailout for banks"
+        );
+    }
+
+    /// Checks the subsidy across halvings and that an underclaiming coinbase is flagged.
+    #[test]
+    fn test_block_reward() {
+        use super::{block_subsidy, BlockReward};
+        use crate::Network;
+
+        assert_eq!(block_subsidy(0, Network::Mainnet), 5_000_000_000);
+        assert_eq!(block_subsidy(209_999, Network::Mainnet), 5_000_000_000);
+        assert_eq!(block_subsidy(840_000, Network::Mainnet), 312_500_000);
+        assert_eq!(block_subsidy(6_929_999, Network::Mainnet), 1);
+        assert_eq!(block_subsidy(6_930_000, Network::Mainnet), 0);
+        assert_eq!(block_subsidy(300, Network::Regtest), 1_250_000_000);
+        assert_eq!(block_subsidy(u64::MAX, Network::Regtest), 0);
+        assert_eq!(block_subsidy(210_000 << 32, Network::Mainnet), 0);
+
+        // A coinbase claiming a sat less than its subsidy and fees.
+        let reward = BlockReward {
+            height: 124_724,
+            subsidy: 5_000_000_000,
+            claimed: 5_000_999_999,
+            fees: Some(1_000_000),
+        };
+        assert_eq!(reward.implied_fees(), 999_999);
+        assert_eq!(reward.unclaimed(), 1);
+        assert_eq!(
+            BlockReward {
+                fees: None,
+                ..reward
+            }
+            .unclaimed(),
+            0
+        );
+    }
+
+    /// Checks height commitments of each push form.
+    #[test]
+    fn test_first_push_number() {
+        // Block 227,931, the first to enforce BIP34.
+        assert_eq!(
+            super::first_push_number(&[0x03, 0x5b, 0x7a, 0x03, 0x2f]),
+            Some((227_931, 4))
+        );
+        assert_eq!(super::first_push_number(&[0x51]), Some((1, 1)));
+        assert_eq!(super::first_push_number(&[0x01, 0x80]), None);
+        assert_eq!(super::first_push_number(&[0x4c]), None);
+    }
+}
@@ -1,13 +1,144 @@
-pub(crate) const MAINNET_HEX: &str = "f9beb4d9";
+/// The bitcoin networks a blk file can belong to, identified by the
+/// magic bytes that prefix every block record.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Network {
+    Mainnet,
+    Testnet3,
+    Testnet4,
+    Signet,
+    Regtest,
+}
+impl Network {
+    pub const ALL: [Network; 5] = [
+        Network::Mainnet,
+        Network::Testnet3,
+        Network::Testnet4,
+        Network::Signet,
+        Network::Regtest,
+    ];
 
-// Byte sizes for for Block & BlockInfo fields
+    pub fn magic_bytes(&self) -> [u8; 4] {
+        match self {
+            Network::Mainnet => [0xf9, 0xbe, 0xb4, 0xd9],
+            Network::Testnet3 => [0x0b, 0x11, 0x09, 0x07],
+            Network::Testnet4 => [0x1c, 0x16, 0x3f, 0x28],
+            Network::Signet => [0x0a, 0x03, 0xcf, 0x40],
+            Network::Regtest => [0xfa, 0xbf, 0xb5, 0xda],
+        }
+    }
+    pub fn from_magic_bytes(magic_bytes: &[u8]) -> Option<Self> {
+        Network::ALL
+            .into_iter()
+            .find(|network| network.magic_bytes() == magic_bytes)
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "mainnet",
+            Network::Testnet3 => "testnet3",
+            Network::Testnet4 => "testnet4",
+            Network::Signet => "signet",
+            Network::Regtest => "regtest",
+        }
+    }
+    /// The port nodes listen for peers on by default.
+    pub fn default_port(&self) -> u16 {
+        match self {
+            Network::Mainnet => 8333,
+            Network::Testnet3 => 18333,
+            Network::Testnet4 => 48333,
+            Network::Signet => 38333,
+            Network::Regtest => 18444,
+        }
+    }
+    /// The number of blocks between halvings of the block subsidy.
+    pub fn halving_interval(&self) -> u64 {
+        match self {
+            Network::Regtest => 150,
+            _ => 210_000,
+        }
+    }
+    /// The height from which every block must commit to its height in the coinbase (BIP34).
+    pub fn bip34_height(&self) -> u64 {
+        match self {
+            Network::Mainnet => 227_931,
+            Network::Testnet3 => 21_111,
+            Network::Testnet4 | Network::Signet | Network::Regtest => 1,
+        }
+    }
+    /// The base58check version byte of pay to public key hash addresses.
+    pub fn p2pkh_prefix(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0x00,
+            _ => 0x6f,
+        }
+    }
+    /// The base58check version byte of pay to script hash addresses.
+    pub fn p2sh_prefix(&self) -> u8 {
+        match self {
+            Network::Mainnet => 0x05,
+            _ => 0xc4,
+        }
+    }
+    /// The human readable part of segwit addresses.
+    pub fn bech32_hrp(&self) -> &'static str {
+        match self {
+            Network::Mainnet => "bc",
+            Network::Regtest => "bcrt",
+            _ => "tb",
+        }
+    }
+}
+impl std::str::FromStr for Network {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Network::ALL
+            .into_iter()
+            .find(|network| network.name() == s)
+            .ok_or_else(|| {
+                anyhow::anyhow!(
+                    "unknown network '{s}', expected one of: {}",
+                    Network::ALL.map(|network| network.name()).join(", ")
+                )
+            })
+    }
+}
+impl std::fmt::Display for Network {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// The compact target of the genesis block, the easiest allowed and difficulty 1.
+pub(crate) const MAX_TARGET_BITS: u32 = 0x1d00ffff;
+
+// Byte sizes for Block & BlockInfo fields
 pub(crate) const MAGIC_BYTES: u32 = 4;
 pub(crate) const BLOCK_SIZE: u32 = 4;
 pub(crate) const BLOCK_HEADER: u32 = 80;
-pub(crate) const TX_COUNT: u32 = 4;
 pub(crate) const BLOCK_HEADER_VERSION: u32 = 4;
 pub(crate) const PREVIOUS_BLOCK_HEADER_HASH: u32 = 32;
 pub(crate) const MERKLE_ROOT_HASH: u32 = 32;
 pub(crate) const UNIX_EPOCH_TIME: u32 = 4;
 pub(crate) const TARGET: u32 = 4;
 pub(crate) const NONCE: u32 = 4;
+
+// Byte sizes for Transaction fields
+pub(crate) const TX_VERSION: u32 = 4;
+pub(crate) const OUTPOINT_TXID: u32 = 32;
+pub(crate) const OUTPOINT_VOUT: u32 = 4;
+pub(crate) const SEQUENCE: u32 = 4;
+pub(crate) const TX_OUT_VALUE: u32 = 8;
+pub(crate) const LOCK_TIME: u32 = 4;
+
+// The marker and flag bytes that follow the version of a witness serialized transaction
+pub(crate) const SEGWIT_MARKER: u8 = 0x00;
+pub(crate) const SEGWIT_FLAG: u8 = 0x01;
+
+/// The satoshis in one bitcoin.
+pub(crate) const SATS_PER_BTC: u64 = 100_000_000;
+/// The seconds between blocks the difficulty adjusts towards.
+pub(crate) const TARGET_SPACING: u32 = 600;
+
+/// How many weight units each non-witness byte counts for under BIP141.
+pub(crate) const WITNESS_SCALE_FACTOR: u32 = 4;
@@ -0,0 +1,91 @@
+//! Decoding a single block, header or transaction given in hex, as printed by a node's RPC
+//! or a block explorer, rather than read from a blk file.
+use crate::{
+    util::{
+        error::ParseError,
+        types::{Block, BlockHeader, ByteCursor},
+    },
+    Transaction,
+};
+use anyhow::Result;
+
+/// The size of a serialized block header, which nothing else decoded can be.
+const HEADER_SIZE: usize = 80;
+
+/// What a hex string turned out to hold.
+#[derive(Debug)]
+pub enum Decoded {
+    Block(Block),
+    Header(BlockHeader),
+    Transaction(Transaction),
+}
+impl Decoded {
+    /// Decode hex, ignoring any whitespace around it.
+    pub fn from_hex(hex: &str) -> Result<Self> {
+        let bytes =
+            hex::decode(hex.trim()).map_err(|e| anyhow::anyhow!("failed to decode hex: {e}"))?;
+        Self::from_bytes(&bytes)
+    }
+    /// Decode `bytes` as a header when there are exactly 80 of them, otherwise as whichever
+    /// of a block or a transaction they parse as exactly, trying a block first.
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self> {
+        if bytes.len() == HEADER_SIZE {
+            let header = BlockHeader::from_raw_bytes(&mut ByteCursor::new(bytes))?;
+            return Ok(Decoded::Header(header));
+        }
+        let block = whole(bytes, Block::from_raw_bytes);
+        let tx = whole(bytes, Transaction::from_raw_bytes);
+        match (block, tx) {
+            (Ok(block), _) => Ok(Decoded::Block(block)),
+            (_, Ok(tx)) => Ok(Decoded::Transaction(tx)),
+            (Err(block), Err(tx)) => anyhow::bail!(
+                "failed to decode {} bytes as a header, a block ({block}) or a transaction ({tx})",
+                bytes.len()
+            ),
+        }
+    }
+}
+
+/// Parse all of `bytes` with `parse`, failing if any are left over.
+pub(crate) fn whole<'a, T>(
+    bytes: &'a [u8],
+    parse: impl FnOnce(&mut ByteCursor<&'a [u8]>) -> Result<T, ParseError>,
+) -> Result<T> {
+    let mut cursor = ByteCursor::new(bytes);
+    let parsed = parse(&mut cursor)?;
+    let left = bytes.len() as u64 - cursor.position();
+    if left != 0 {
+        anyhow::bail!("{left} bytes left over");
+    }
+    Ok(parsed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Decoded;
+    use crate::util::fixtures::{BLOCK_1_HEX, GENESIS_BLOCK_HEX};
+
+    /// Checks that a header, block and transaction are each told apart, and that anything
+    /// else is refused.
+    #[test]
+    fn test_decode() {
+        let Decoded::Block(block) = Decoded::from_hex(BLOCK_1_HEX).unwrap() else {
+            panic!("expected a block");
+        };
+        let header_hex = hex::encode(block.block_header().raw_bytes());
+        let Decoded::Header(header) = Decoded::from_hex(&format!("{header_hex}\n")).unwrap() else {
+            panic!("expected a header");
+        };
+        assert_eq!(header.block_hash(), block.block_header().block_hash());
+
+        let tx_hex = hex::encode(block.transactions()[0].raw_bytes());
+        let Decoded::Transaction(tx) = Decoded::from_hex(&tx_hex).unwrap() else {
+            panic!("expected a transaction");
+        };
+        assert_eq!(tx.txid(), block.transactions()[0].txid());
+
+        let truncated = &GENESIS_BLOCK_HEX[..GENESIS_BLOCK_HEX.len() - 2];
+        assert!(Decoded::from_hex(truncated).is_err());
+        assert!(Decoded::from_hex("not hex").is_err());
+    }
+}
@@ -0,0 +1,263 @@
+//! Process wide options controlling how values are rendered as human readable text.
+use crate::{util::constant::SATS_PER_BTC, Network};
+use chrono::{DateTime, Local, SecondsFormat, Utc};
+use std::sync::RwLock;
+
+/// Options read by the [Display](std::fmt::Display) implementations of the parsed types.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct DisplayOptions {
+    /// Show timestamps in the local timezone as well as UTC.
+    pub local_time: bool,
+    /// The network addresses are encoded for, mainnet when unknown.
+    pub network: Option<Network>,
+    /// How much of a block to describe: 0 for just its header, 1 to add the transaction
+    /// summary and coinbase, 2 to add every transaction, 3 to add the raw bytes of each field.
+    pub verbosity: u8,
+    /// Show sizes, amounts, difficulty and hashrate in human units alongside the raw numbers.
+    pub human: bool,
+    /// Name the pool that mined each block, from the markers in its coinbase.
+    pub show_miner: bool,
+}
+
+static OPTIONS: RwLock<DisplayOptions> = RwLock::new(DisplayOptions {
+    local_time: false,
+    network: None,
+    verbosity: 0,
+    human: false,
+    show_miner: false,
+});
+
+/// Replace the options used for all subsequent rendering.
+pub fn set_options(options: DisplayOptions) {
+    *OPTIONS.write().expect("display options lock poisoned") = options;
+}
+
+/// Set the network addresses are encoded for, leaving the other options as they are.
+pub fn set_network(network: Network) {
+    OPTIONS
+        .write()
+        .expect("display options lock poisoned")
+        .network = Some(network);
+}
+
+/// The options currently in effect.
+pub fn options() -> DisplayOptions {
+    *OPTIONS.read().expect("display options lock poisoned")
+}
+
+/// When text output is colored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum ColorChoice {
+    /// Color only when writing to a terminal.
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+impl ColorChoice {
+    /// Whether to color output written to a stream that is or isn't a terminal.
+    pub fn enabled(&self, is_terminal: bool) -> bool {
+        match self {
+            ColorChoice::Auto => is_terminal,
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+        }
+    }
+}
+impl std::str::FromStr for ColorChoice {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "auto" => Ok(ColorChoice::Auto),
+            "always" => Ok(ColorChoice::Always),
+            "never" => Ok(ColorChoice::Never),
+            _ => anyhow::bail!("unknown color choice '{s}', expected one of: auto, always, never"),
+        }
+    }
+}
+
+/// Turn the ANSI escapes of every [colored] string rendered from now on on or off.
+pub fn set_color(enabled: bool) {
+    colored::control::set_override(enabled);
+}
+
+/// Parse a moment given as unix seconds or an RFC 3339 datetime, as unix seconds.
+pub fn parse_timestamp(s: &str) -> anyhow::Result<u32> {
+    if let Ok(timestamp) = s.parse::<u32>() {
+        return Ok(timestamp);
+    }
+    let datetime = DateTime::parse_from_rfc3339(s).map_err(|e| {
+        anyhow::anyhow!("invalid time '{s}', expected unix seconds or an RFC 3339 datetime: {e}")
+    })?;
+    u32::try_from(datetime.timestamp())
+        .map_err(|_| anyhow::anyhow!("invalid time '{s}', it's outside the range of block times"))
+}
+
+/// Format a unix timestamp as an RFC 3339 UTC datetime.
+pub fn format_utc(timestamp: u32) -> String {
+    DateTime::<Utc>::from_timestamp(i64::from(timestamp), 0)
+        .expect("a u32 timestamp is always in range")
+        .to_rfc3339_opts(SecondsFormat::Secs, true)
+}
+
+/// Format a unix timestamp as an RFC 3339 datetime in the local timezone.
+pub fn format_local(timestamp: u32) -> String {
+    DateTime::<Utc>::from_timestamp(i64::from(timestamp), 0)
+        .expect("a u32 timestamp is always in range")
+        .with_timezone(&Local)
+        .to_rfc3339_opts(SecondsFormat::Secs, false)
+}
+
+/// Format a unix timestamp for display, showing the raw value alongside the datetime.
+pub fn format_timestamp(timestamp: u32) -> String {
+    if options().local_time {
+        format!(
+            "{timestamp} ({}, {})",
+            format_utc(timestamp),
+            format_local(timestamp)
+        )
+    } else {
+        format!("{timestamp} ({})", format_utc(timestamp))
+    }
+}
+
+/// Format a size in bytes for display, followed by it in KB, MB or GB when human.
+pub fn format_size(bytes: u64) -> String {
+    match options().human {
+        true => human_size(bytes),
+        false => bytes.to_string(),
+    }
+}
+
+/// A size in bytes alongside it in the largest of KB, MB or GB it's at least a hundredth of,
+/// counting in powers of 1000 as block size limits do.
+pub fn human_size(bytes: u64) -> String {
+    let (divisor, unit) = match bytes {
+        ..1_000_000 => (1e3, "KB"),
+        1_000_000..1_000_000_000 => (1e6, "MB"),
+        _ => (1e9, "GB"),
+    };
+    format!("{bytes} B / {:.2} {unit}", bytes as f64 / divisor)
+}
+
+/// Format an amount of satoshis for display, followed by it in BTC when human.
+pub fn format_amount(sats: u64) -> String {
+    match options().human {
+        true => human_amount(sats),
+        false => format!("{sats} sats"),
+    }
+}
+
+/// An amount in satoshis alongside it in BTC to all eight decimal places.
+pub fn human_amount(sats: u64) -> String {
+    format!(
+        "{sats} sats / {}.{:08} BTC",
+        sats / SATS_PER_BTC,
+        sats % SATS_PER_BTC
+    )
+}
+
+/// Format a difficulty for display, scaled to a metric prefix when human.
+pub fn format_difficulty(difficulty: f64) -> String {
+    match options().human {
+        true => human_si(difficulty, ""),
+        false => difficulty.to_string(),
+    }
+}
+
+/// Format a hashrate in hashes per second for display, scaled to a metric prefix when human.
+pub fn format_hashrate(hashes_per_second: f64) -> String {
+    match options().human {
+        true => human_si(hashes_per_second, "H/s"),
+        false => format!("{hashes_per_second:.0} H/s"),
+    }
+}
+
+/// Format a number of seconds as hours, minutes and seconds, the leading units left out
+/// when zero, such as "2h 05m 13s" or "-40s".
+pub fn format_interval(seconds: i64) -> String {
+    let sign = if seconds < 0 { "-" } else { "" };
+    let seconds = seconds.unsigned_abs();
+    let (hours, minutes, seconds) = (seconds / 3600, seconds / 60 % 60, seconds % 60);
+    match (hours, minutes) {
+        (0, 0) => format!("{sign}{seconds}s"),
+        (0, _) => format!("{sign}{minutes}m {seconds:02}s"),
+        _ => format!("{sign}{hours}h {minutes:02}m {seconds:02}s"),
+    }
+}
+
+/// A value scaled down by the largest metric prefix it's at least one of, to two decimal
+/// places and followed by `unit`.
+pub fn human_si(value: f64, unit: &str) -> String {
+    const PREFIXES: [&str; 7] = ["", "k", "M", "G", "T", "P", "E"];
+    let mut scaled = value;
+    let mut prefix = 0;
+    while scaled.abs() >= 1000.0 && prefix < PREFIXES.len() - 1 {
+        scaled /= 1000.0;
+        prefix += 1;
+    }
+    let separator = match PREFIXES[prefix].is_empty() && unit.is_empty() {
+        true => "",
+        false => " ",
+    };
+    format!("{scaled:.2}{separator}{}{unit}", PREFIXES[prefix])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::ColorChoice;
+
+    /// Checks that auto only colors terminals and the others ignore the stream.
+    #[test]
+    fn test_color_choice() {
+        let auto: ColorChoice = "auto".parse().unwrap();
+        assert!(auto.enabled(true));
+        assert!(!auto.enabled(false));
+        assert!("always".parse::<ColorChoice>().unwrap().enabled(false));
+        assert!(!"never".parse::<ColorChoice>().unwrap().enabled(true));
+        assert!("sometimes".parse::<ColorChoice>().is_err());
+    }
+
+    /// Checks that both unix seconds and datetimes with an offset are accepted.
+    #[test]
+    fn test_parse_timestamp() {
+        assert_eq!(super::parse_timestamp("1231006505").unwrap(), 1231006505);
+        assert_eq!(
+            super::parse_timestamp("2009-01-03T19:15:05+01:00").unwrap(),
+            1231006505
+        );
+        assert!(super::parse_timestamp("1969-12-31T23:59:59Z").is_err());
+        assert!(super::parse_timestamp("yesterday").is_err());
+    }
+
+    /// Checks the genesis block timestamp renders as its well known date, and intervals
+    /// drop the units they don't reach.
+    #[test]
+    fn test_format_timestamp() {
+        assert_eq!(super::format_utc(1231006505), "2009-01-03T18:15:05Z");
+        assert_eq!(
+            super::format_timestamp(1231006505),
+            "1231006505 (2009-01-03T18:15:05Z)"
+        );
+        assert_eq!(super::format_interval(463_160), "128h 39m 20s");
+        assert_eq!(super::format_interval(605), "10m 05s");
+        assert_eq!(super::format_interval(-40), "-40s");
+    }
+
+    /// Checks sizes pick their unit by magnitude and amounts keep every satoshi.
+    #[test]
+    fn test_human_units() {
+        assert_eq!(super::human_size(285), "285 B / 0.28 KB");
+        assert_eq!(super::human_size(1_534_567), "1534567 B / 1.53 MB");
+        assert_eq!(super::human_size(2_000_000_000), "2000000000 B / 2.00 GB");
+        assert_eq!(
+            super::human_amount(5_000_000_000),
+            "5000000000 sats / 50.00000000 BTC"
+        );
+        assert_eq!(super::human_amount(1), "1 sats / 0.00000001 BTC");
+        assert_eq!(super::human_si(1.0, ""), "1.00");
+        assert_eq!(super::human_si(16307.420938523983, ""), "16.31 k");
+        assert_eq!(super::human_si(7.158e6, "H/s"), "7.16 MH/s");
+    }
+}
@@ -0,0 +1,148 @@
+//! The blocks of a range holding only their coinbase, as reported by the empty-blocks
+//! subcommand for studying which miners publish empty blocks and how often.
+use crate::{util::coinbase::Coinbase, Block};
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// A block with no transactions besides its coinbase.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EmptyBlock {
+    pub height: u64,
+    pub hash: String,
+    pub time: u32,
+    /// The text in the coinbase scriptSig, as [Coinbase::miner_tag] finds it.
+    pub miner_tag: Option<String>,
+}
+
+/// The empty blocks sharing a coinbase tag.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct MinerEmptyBlocks {
+    pub miner_tag: Option<String>,
+    pub count: u64,
+    pub heights: Vec<u64>,
+}
+
+/// The empty blocks from height `from` to `to`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct EmptyBlockReport {
+    pub from: u64,
+    pub to: u64,
+    pub block_count: u64,
+    pub empty_count: u64,
+    /// The percentage of the blocks that were empty.
+    pub empty_percent: f64,
+    pub blocks: Vec<EmptyBlock>,
+    /// The empty blocks grouped by coinbase tag, most first, when asked for.
+    pub by_miner: Option<Vec<MinerEmptyBlocks>>,
+}
+
+/// Collects the empty blocks of a range, one block at a time in height order.
+#[derive(Debug, Default)]
+pub struct EmptyBlockCollector {
+    block_count: u64,
+    blocks: Vec<EmptyBlock>,
+}
+impl EmptyBlockCollector {
+    /// Count `block`, the block at `height`, keeping it if it's empty.
+    pub fn add(&mut self, height: u64, block: &Block) {
+        self.block_count += 1;
+        if block.tx_count() != 1 {
+            return;
+        }
+        let header = block.block_header();
+        self.blocks.push(EmptyBlock {
+            height,
+            hash: header.block_hash(),
+            time: header.unix_epoch_time(),
+            miner_tag: Coinbase::from_block(block).and_then(|coinbase| coinbase.miner_tag()),
+        });
+    }
+    /// The empty blocks found, labelled as the range `from` to `to`, grouped by their
+    /// coinbase tag when `by_miner`. Groups of the same size are ordered by their first height.
+    pub fn finish(self, from: u64, to: u64, by_miner: bool) -> EmptyBlockReport {
+        let by_miner = by_miner.then(|| {
+            let mut groups: Vec<MinerEmptyBlocks> = Vec::new();
+            let mut positions = HashMap::new();
+            for block in &self.blocks {
+                let position = *positions.entry(&block.miner_tag).or_insert_with(|| {
+                    groups.push(MinerEmptyBlocks {
+                        miner_tag: block.miner_tag.clone(),
+                        count: 0,
+                        heights: Vec::new(),
+                    });
+                    groups.len() - 1
+                });
+                groups[position].count += 1;
+                groups[position].heights.push(block.height);
+            }
+            // A stable sort, so ties stay in the order they were first seen.
+            groups.sort_by_key(|group| std::cmp::Reverse(group.count));
+            groups
+        });
+        let empty_count = self.blocks.len() as u64;
+        EmptyBlockReport {
+            from,
+            to,
+            block_count: self.block_count,
+            empty_count,
+            empty_percent: match self.block_count {
+                0 => 0.0,
+                count => empty_count as f64 * 100.0 / count as f64,
+            },
+            blocks: self.blocks,
+            by_miner,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::EmptyBlockCollector;
+    use crate::util::{
+        fixtures::{block, BLOCK_1_HEX, GENESIS_BLOCK_HEX},
+        types::{Block, ByteCursor},
+    };
+
+    /// Checks only blocks holding just a coinbase are kept, and that they're grouped by
+    /// tag with the untagged together.
+    #[test]
+    fn test_empty_blocks() {
+        let block_1 = hex::decode(BLOCK_1_HEX).unwrap();
+        // Block 1 with its coinbase twice, which is enough to not be empty.
+        let full = [&block_1[..80], &[2], &block_1[81..], &block_1[81..]].concat();
+        let full = Block::from_raw_bytes(&mut ByteCursor::new(full.as_slice())).unwrap();
+        let mut collector = EmptyBlockCollector::default();
+        for (height, block) in [
+            block(GENESIS_BLOCK_HEX),
+            block(BLOCK_1_HEX),
+            full,
+            block(BLOCK_1_HEX),
+        ]
+        .iter()
+        .enumerate()
+        {
+            collector.add(height as u64, block);
+        }
+        let report = collector.finish(0, 3, true);
+
+        assert_eq!((report.block_count, report.empty_count), (4, 3));
+        assert_eq!(report.empty_percent, 75.0);
+        assert_eq!(
+            report.blocks.iter().map(|b| b.height).collect::<Vec<_>>(),
+            [0, 1, 3]
+        );
+        let by_miner = report.by_miner.unwrap();
+        assert_eq!(by_miner[0].miner_tag, None);
+        assert_eq!(by_miner[0].heights, [1, 3]);
+        assert!(by_miner[1]
+            .miner_tag
+            .as_ref()
+            .unwrap()
+            .starts_with("The Times"));
+
+        assert_eq!(
+            EmptyBlockCollector::default().finish(0, 0, false).by_miner,
+            None
+        );
+    }
+}
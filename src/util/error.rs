@@ -0,0 +1,224 @@
+//! Errors raised while parsing blk file records, and the kinds of failure any error falls
+//! into, for telling them apart without matching on messages.
+use crate::Network;
+
+/// Why a record couldn't be parsed, along with the byte offset in its file where parsing failed.
+#[derive(Debug)]
+pub enum ParseError {
+    /// The data ran out partway through `field`.
+    UnexpectedEof { field: &'static str, offset: u64 },
+    /// The record didn't start with the magic bytes of a known network,
+    /// or of the network the rest of the files belong to.
+    BadMagic {
+        magic_bytes: [u8; 4],
+        expected: Option<Network>,
+        offset: u64,
+    },
+    /// The record's size field doesn't match the number of bytes its block was parsed from.
+    SizeMismatch {
+        expected: u32,
+        parsed: u64,
+        offset: u64,
+    },
+    /// The length of `field` is too long to be real, more bytes than a blk file could hold.
+    BadLength {
+        field: &'static str,
+        length: u64,
+        offset: u64,
+    },
+    /// A witness serialized transaction had a flag byte other than 1.
+    BadSegwitFlag { flag: u8, offset: u64 },
+    /// Reading the underlying source failed.
+    Io { source: std::io::Error, offset: u64 },
+}
+impl ParseError {
+    /// The kind of failure this is: a record of another network, a failed read or otherwise a
+    /// corrupt file.
+    pub fn kind(&self) -> ErrorKind {
+        match self {
+            ParseError::BadMagic {
+                expected: Some(_), ..
+            } => ErrorKind::WrongNetwork,
+            ParseError::Io { .. } => ErrorKind::Io,
+            _ => ErrorKind::Corrupt,
+        }
+    }
+    /// The byte offset where parsing failed.
+    pub fn offset(&self) -> u64 {
+        match self {
+            ParseError::UnexpectedEof { offset, .. }
+            | ParseError::BadMagic { offset, .. }
+            | ParseError::SizeMismatch { offset, .. }
+            | ParseError::BadLength { offset, .. }
+            | ParseError::BadSegwitFlag { offset, .. }
+            | ParseError::Io { offset, .. } => *offset,
+        }
+    }
+}
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            ParseError::UnexpectedEof { field, offset } => {
+                write!(
+                    f,
+                    "unexpected end of data reading {field} at offset {offset}"
+                )
+            }
+            ParseError::BadMagic {
+                magic_bytes,
+                expected: Some(network),
+                offset,
+            } => write!(
+                f,
+                "bad magic bytes {} at offset {offset}, expected {network} magic bytes {}",
+                hex::encode(magic_bytes),
+                hex::encode(network.magic_bytes())
+            ),
+            ParseError::BadMagic {
+                magic_bytes,
+                expected: None,
+                offset,
+            } => write!(
+                f,
+                "unknown network magic bytes {} at offset {offset}",
+                hex::encode(magic_bytes)
+            ),
+            ParseError::SizeMismatch {
+                expected,
+                parsed,
+                offset,
+            } => write!(
+                f,
+                "block at offset {offset} is {parsed} bytes, but its record says {expected}"
+            ),
+            ParseError::BadLength {
+                field,
+                length,
+                offset,
+            } => write!(
+                f,
+                "{field} at offset {offset} claims to be {length} bytes long"
+            ),
+            ParseError::BadSegwitFlag { flag, offset } => {
+                write!(
+                    f,
+                    "expected a segwit flag of 1 at offset {offset}, found {flag}"
+                )
+            }
+            ParseError::Io { source, offset } => {
+                write!(f, "failed to read at offset {offset}: {source}")
+            }
+        }
+    }
+}
+impl std::error::Error for ParseError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ParseError::Io { source, .. } => Some(source),
+            _ => None,
+        }
+    }
+}
+
+/// The kinds of failure an error can be.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ErrorKind {
+    /// A block, transaction or anything else asked for isn't there.
+    NotFound,
+    /// A file couldn't be parsed.
+    Corrupt,
+    /// Blocks or files belong to a network other than the one expected.
+    WrongNetwork,
+    /// Reading or writing a file, or talking to a peer, failed.
+    Io,
+    /// Anything else.
+    Other,
+}
+impl ErrorKind {
+    /// An error of this kind with `message`.
+    pub fn error(self, message: impl std::fmt::Display) -> anyhow::Error {
+        anyhow::Error::new(KindError {
+            kind: self,
+            message: message.to_string(),
+        })
+    }
+    /// The kind of `error`, from the first error in its chain of causes that's known to be
+    /// of one: raised by [ErrorKind::error], a [ParseError] or an IO error.
+    pub fn of(error: &anyhow::Error) -> Self {
+        error
+            .chain()
+            .find_map(|cause| {
+                if let Some(e) = cause.downcast_ref::<KindError>() {
+                    return Some(e.kind);
+                }
+                if let Some(e) = cause.downcast_ref::<ParseError>() {
+                    return Some(e.kind());
+                }
+                cause
+                    .downcast_ref::<std::io::Error>()
+                    .map(|_| ErrorKind::Io)
+            })
+            .unwrap_or(ErrorKind::Other)
+    }
+    pub fn name(&self) -> &'static str {
+        match self {
+            ErrorKind::NotFound => "not_found",
+            ErrorKind::Corrupt => "corrupt",
+            ErrorKind::WrongNetwork => "wrong_network",
+            ErrorKind::Io => "io",
+            ErrorKind::Other => "other",
+        }
+    }
+}
+
+/// An error raised by [ErrorKind::error], carrying its kind.
+#[derive(Debug)]
+struct KindError {
+    kind: ErrorKind,
+    message: String,
+}
+impl std::fmt::Display for KindError {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+impl std::error::Error for KindError {}
+
+#[cfg(test)]
+mod tests {
+    use super::{ErrorKind, ParseError};
+    use crate::Network;
+    use anyhow::Context;
+
+    /// Checks the kind of an error is found through the context wrapped around it.
+    #[test]
+    fn test_error_kind() {
+        let wrong_network = ParseError::BadMagic {
+            magic_bytes: Network::Mainnet.magic_bytes(),
+            expected: Some(Network::Regtest),
+            offset: 0,
+        };
+        let corrupt = ParseError::SizeMismatch {
+            expected: 80,
+            parsed: 81,
+            offset: 0,
+        };
+        let io = std::io::Error::from(std::io::ErrorKind::NotFound);
+        let kind = |result: anyhow::Result<()>| ErrorKind::of(&result.unwrap_err());
+
+        assert_eq!(
+            kind(Err(wrong_network).context("failed to parse")),
+            ErrorKind::WrongNetwork
+        );
+        assert_eq!(
+            kind(Err(corrupt).context("failed to parse")),
+            ErrorKind::Corrupt
+        );
+        assert_eq!(kind(Err(io).context("failed to open")), ErrorKind::Io);
+        assert_eq!(
+            kind(Err(ErrorKind::NotFound.error("failed to find block"))),
+            ErrorKind::NotFound
+        );
+        assert_eq!(kind(Err(anyhow::anyhow!("failed"))), ErrorKind::Other);
+    }
+}
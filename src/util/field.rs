@@ -0,0 +1,121 @@
+//! The block fields --fields selects, printed on a single line per block for shell pipelines.
+use crate::Block;
+
+/// A value of a block that can be printed on its own.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BlockField {
+    Height,
+    Hash,
+    PrevHash,
+    MerkleRoot,
+    Time,
+    Version,
+    Bits,
+    Difficulty,
+    Nonce,
+    TxCount,
+    Size,
+    Weight,
+    /// The total fees, only known with --with-fees and empty otherwise.
+    Fees,
+}
+impl BlockField {
+    pub const ALL: [BlockField; 13] = [
+        BlockField::Height,
+        BlockField::Hash,
+        BlockField::PrevHash,
+        BlockField::MerkleRoot,
+        BlockField::Time,
+        BlockField::Version,
+        BlockField::Bits,
+        BlockField::Difficulty,
+        BlockField::Nonce,
+        BlockField::TxCount,
+        BlockField::Size,
+        BlockField::Weight,
+        BlockField::Fees,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            BlockField::Height => "height",
+            BlockField::Hash => "hash",
+            BlockField::PrevHash => "prev_hash",
+            BlockField::MerkleRoot => "merkle_root",
+            BlockField::Time => "time",
+            BlockField::Version => "version",
+            BlockField::Bits => "bits",
+            BlockField::Difficulty => "difficulty",
+            BlockField::Nonce => "nonce",
+            BlockField::TxCount => "tx_count",
+            BlockField::Size => "size",
+            BlockField::Weight => "weight",
+            BlockField::Fees => "fees",
+        }
+    }
+    /// The field of the block at `height`, written the way the export subcommand writes it:
+    /// hashes in display hex, the time in unix seconds and the bits as hex.
+    pub fn value(&self, height: u64, block: &Block) -> String {
+        let header = block.block_header();
+        match self {
+            BlockField::Height => height.to_string(),
+            BlockField::Hash => header.block_hash(),
+            BlockField::PrevHash => header.previous_block_header_hash(),
+            BlockField::MerkleRoot => header.merkle_root_hash(),
+            BlockField::Time => header.unix_epoch_time().to_string(),
+            BlockField::Version => header.version().to_string(),
+            BlockField::Bits => format!("{:08x}", header.target()),
+            BlockField::Difficulty => header.difficulty().to_string(),
+            BlockField::Nonce => header.nonce().to_string(),
+            BlockField::TxCount => block.tx_count().to_string(),
+            BlockField::Size => block.total_size().to_string(),
+            BlockField::Weight => block.weight().to_string(),
+            BlockField::Fees => block
+                .total_fees()
+                .map_or(String::new(), |fees| fees.to_string()),
+        }
+    }
+    /// The `fields` of the block at `height` as a single tab separated line.
+    pub fn line(fields: &[BlockField], height: u64, block: &Block) -> String {
+        fields
+            .iter()
+            .map(|field| field.value(height, block))
+            .collect::<Vec<_>>()
+            .join("\t")
+    }
+}
+impl std::str::FromStr for BlockField {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        BlockField::ALL
+            .into_iter()
+            .find(|field| field.name() == s)
+            .ok_or_else(|| {
+                let names = BlockField::ALL.map(|field| field.name());
+                anyhow::anyhow!("unknown field '{s}', expected one of: {}", names.join(", "))
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::BlockField;
+    use crate::util::fixtures::{block, BLOCK_1_HEX};
+
+    /// Checks that fields are parsed by name and printed in the order they were given.
+    #[test]
+    fn test_block_fields() {
+        let block = block(BLOCK_1_HEX);
+        let fields = "tx_count,height,hash,bits,fees"
+            .split(',')
+            .map(|name| name.parse().unwrap())
+            .collect::<Vec<BlockField>>();
+
+        assert_eq!(
+            BlockField::line(&fields, 1, &block),
+            "1\t1\t00000000839a8e6886ab5951d76f411475428afc90947ee320161bbf18eb6048\t1d00ffff\t"
+        );
+        assert!("txs".parse::<BlockField>().is_err());
+    }
+}
@@ -0,0 +1,85 @@
+//! Known mainnet blocks and generated serializations shared by the unit tests.
+use super::types::{Block, ByteCursor, CompactSize};
+use proptest::{collection::vec, prelude::*};
+
+/// The serialized genesis block, without its magic bytes and size.
+pub(crate) const GENESIS_BLOCK_HEX: &str = "0100000000000000000000000000000000000000000000000000000000000000000000003ba3edfd7a7b12b27ac72c3e67768f617fc81bc3888a51323a9fb8aa4b1e5e4a29ab5f49ffff001d1dac2b7c0101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff4d04ffff001d0104455468652054696d65732030332f4a616e2f32303039204368616e63656c6c6f72206f6e206272696e6b206f66207365636f6e64206261696c6f757420666f722062616e6b73ffffffff0100f2052a01000000434104678afdb0fe5548271967f1a67130b7105cd6a828e03909a67962e0ea1f61deb649f6bc3f4cef38c4f35504e51ec112de5c384df7ba0b8d578a4c702b6bf11d5fac00000000";
+
+/// The serialized block at height 1, without its magic bytes and size.
+pub(crate) const BLOCK_1_HEX: &str = "010000006fe28c0ab6f1b372c1a6a246ae63f74f931e8365e15a089c68d6190000000000982051fd1e4ba744bbbe680e1fee14677ba1a3c3540bf7b1cdb606e857233e0e61bc6649ffff001d01e362990101000000010000000000000000000000000000000000000000000000000000000000000000ffffffff0704ffff001d0104ffffffff0100f2052a0100000043410496b538e853519c726a2c91e61ec11600ae1390813a627c66fb8be7947be63c52da7589379515d4e0a604f8141781e62294721166bf621e73a82cbf2342c858eeac00000000";
+
+/// Parse a serialized block such as [GENESIS_BLOCK_HEX] or [BLOCK_1_HEX].
+pub(crate) fn block(block_hex: &str) -> Block {
+    let raw_bytes = hex::decode(block_hex).unwrap();
+    Block::from_raw_bytes(&mut ByteCursor::new(raw_bytes.as_slice())).unwrap()
+}
+
+/// Frame a serialized block as a mainnet blk file record.
+pub(crate) fn blk_record(block_hex: &str) -> Vec<u8> {
+    let block = hex::decode(block_hex).unwrap();
+    [
+        [0xf9, 0xbe, 0xb4, 0xd9].as_slice(),
+        &(block.len() as u32).to_le_bytes(),
+        &block,
+    ]
+    .concat()
+}
+
+/// A compact size prefixed serialization of `bytes`.
+fn prefixed(bytes: &[u8]) -> Vec<u8> {
+    [CompactSize::encode(bytes.len() as u64), bytes.to_vec()].concat()
+}
+
+/// Canonically serialized transactions, legacy or witness, of a few inputs and outputs
+/// with arbitrary scripts.
+pub(crate) fn arb_transaction() -> impl Strategy<Value = Vec<u8>> {
+    let input = (
+        vec(any::<u8>(), 36),
+        vec(any::<u8>(), 0..100),
+        any::<u32>(),
+        vec(vec(any::<u8>(), 0..80), 0..4),
+    );
+    let output = (any::<u64>(), vec(any::<u8>(), 0..60));
+    (
+        any::<u32>(),
+        any::<bool>(),
+        vec(input, 1..4),
+        vec(output, 0..4),
+        any::<u32>(),
+    )
+        .prop_map(|(version, segwit, inputs, outputs, lock_time)| {
+            let mut raw_bytes = version.to_le_bytes().to_vec();
+            if segwit {
+                raw_bytes.extend([0x00, 0x01]);
+            }
+            raw_bytes.extend(CompactSize::encode(inputs.len() as u64));
+            for (outpoint, script_sig, sequence, _) in &inputs {
+                raw_bytes.extend(outpoint);
+                raw_bytes.extend(prefixed(script_sig));
+                raw_bytes.extend(sequence.to_le_bytes());
+            }
+            raw_bytes.extend(CompactSize::encode(outputs.len() as u64));
+            for (value, script_pubkey) in &outputs {
+                raw_bytes.extend(value.to_le_bytes());
+                raw_bytes.extend(prefixed(script_pubkey));
+            }
+            if segwit {
+                for (_, _, _, witness) in &inputs {
+                    raw_bytes.extend(CompactSize::encode(witness.len() as u64));
+                    raw_bytes.extend(witness.iter().flat_map(|item| prefixed(item)));
+                }
+            }
+            raw_bytes.extend(lock_time.to_le_bytes());
+            raw_bytes
+        })
+}
+
+/// Canonically serialized blocks of an arbitrary header and a few transactions.
+pub(crate) fn arb_block() -> impl Strategy<Value = Vec<u8>> {
+    (vec(any::<u8>(), 80), vec(arb_transaction(), 0..4)).prop_map(|(header, transactions)| {
+        let mut raw_bytes = header;
+        raw_bytes.extend(CompactSize::encode(transactions.len() as u64));
+        raw_bytes.extend(transactions.concat());
+        raw_bytes
+    })
+}
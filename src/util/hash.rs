@@ -0,0 +1,115 @@
+//! Hashing primitives used to identify blocks, transactions and addresses.
+use anyhow::Result;
+use hex::ToHex;
+use ripemd::Ripemd160;
+use sha2::{Digest, Sha256};
+
+/// Double SHA256, the hash function behind block hashes and txids.
+pub fn sha256d(bytes: &[u8]) -> [u8; 32] {
+    Sha256::digest(Sha256::digest(bytes)).into()
+}
+
+/// A single SHA256.
+pub fn sha256(bytes: &[u8]) -> [u8; 32] {
+    Sha256::digest(bytes).into()
+}
+
+/// The BIP340 tagged hash of `bytes`, the SHA256 of them behind the hash of `tag` twice,
+/// so hashes made for one purpose can't be passed off as another's.
+pub fn tagged_hash(tag: &str, bytes: &[u8]) -> [u8; 32] {
+    let tag = sha256(tag.as_bytes());
+    Sha256::new()
+        .chain_update(tag)
+        .chain_update(tag)
+        .chain_update(bytes)
+        .finalize()
+        .into()
+}
+
+/// RIPEMD160 of SHA256, the hash behind pay to public key hash and script hash addresses.
+pub fn hash160(bytes: &[u8]) -> [u8; 20] {
+    Ripemd160::digest(Sha256::digest(bytes)).into()
+}
+
+/// The Electrum protocol's script hash of an output, the SHA256 of its script pubkey, by
+/// which servers index the history of a script. Like other hashes it's shown reversed.
+pub fn script_hash(script_pubkey: &[u8]) -> [u8; 32] {
+    sha256(script_pubkey)
+}
+
+/// Compute the merkle root of a list of hashes.
+///
+/// Each level hashes adjacent pairs together, pairing the last hash
+/// with itself when a level has an odd number of them.
+pub fn merkle_root(mut hashes: Vec<[u8; 32]>) -> Option<[u8; 32]> {
+    while hashes.len() > 1 {
+        hashes = hashes
+            .chunks(2)
+            .map(|pair| sha256d(&[pair[0], *pair.last().expect("chunks are never empty")].concat()))
+            .collect();
+    }
+    hashes.first().copied()
+}
+
+/// Hex encode a hash in human readable order.
+///
+/// Hashes are serialized little endian, so the bytes are reversed
+/// to match the big endian form shown by block explorers and RPCs.
+pub fn to_display_hex(hash: &[u8]) -> String {
+    hash.iter()
+        .rev()
+        .copied()
+        .collect::<Vec<u8>>()
+        .encode_hex::<String>()
+}
+
+/// Parse a hash given in human readable order back into serialized byte order.
+pub fn from_display_hex(hex: &str) -> Result<[u8; 32]> {
+    let mut hash: [u8; 32] = hex::decode(hex)?
+        .try_into()
+        .map_err(|_| anyhow::anyhow!("expected a 32 byte hash"))?;
+    hash.reverse();
+    Ok(hash)
+}
+
+#[cfg(test)]
+mod tests {
+    /// Checks that display hex round trips and is the reverse of serialized order.
+    #[test]
+    fn test_display_hex() {
+        let hex = "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f";
+        let hash = super::from_display_hex(hex).unwrap();
+
+        assert_eq!(hash[0], 0x6f);
+        assert_eq!(super::to_display_hex(&hash), hex);
+        assert!(super::from_display_hex("6f").is_err());
+    }
+
+    /// Checks the script hash of the genesis coinbase's address against the Electrum
+    /// protocol documentation's example.
+    #[test]
+    fn test_script_hash() {
+        let script_pubkey =
+            hex::decode("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac").unwrap();
+
+        assert_eq!(
+            super::to_display_hex(&super::script_hash(&script_pubkey)),
+            "8b01df4e368ea28f8dc0423bcf7a4923e3a12d307c875e47a0cfbf90b5c39161"
+        );
+    }
+
+    /// Checks that odd levels pair their last hash with itself.
+    #[test]
+    fn test_merkle_root() {
+        let [a, b, c] = [[1; 32], [2; 32], [3; 32]];
+        let ab = super::sha256d(&[a, b].concat());
+        let cc = super::sha256d(&[c, c].concat());
+
+        assert_eq!(super::merkle_root(vec![]), None);
+        assert_eq!(super::merkle_root(vec![a]), Some(a));
+        assert_eq!(
+            super::merkle_root(vec![a, b, c]),
+            Some(super::sha256d(&[ab, cc].concat()))
+        );
+    }
+}
@@ -0,0 +1,227 @@
+//! Recognising the signatures, public keys and scripts an input pushes in its scriptSig or
+//! witness to satisfy the output it spends.
+//!
+//! As with [TaprootSpend](crate::util::taproot::TaprootSpend), the output being spent isn't
+//! known from the input alone, so each item is recognised by its shape.
+use crate::util::{
+    asm::push_only,
+    script::{Script, OP_CHECKSIG},
+    taproot::sighash_name,
+};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+const OP_CHECKSIGVERIFY: u8 = 0xad;
+const OP_CHECKMULTISIG: u8 = 0xae;
+const OP_CHECKMULTISIGVERIFY: u8 = 0xaf;
+/// The DER marker of a sequence, which a signature is, and of an integer, as r and s are.
+const DER_SEQUENCE: u8 = 0x30;
+const DER_INTEGER: u8 = 0x02;
+
+/// Something an input pushes.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InputItem<'a> {
+    /// A DER encoded ECDSA signature, followed by the sighash type it commits to.
+    Signature { der: &'a [u8], sighash_type: u8 },
+    /// A public key, 33 bytes when compressed and 65 when not.
+    PublicKey { key: &'a [u8], compressed: bool },
+    /// The script a pay to script hash output commits to, the last push of a scriptSig.
+    RedeemScript(&'a [u8]),
+    /// The script a pay to witness script hash output commits to, the last witness item.
+    WitnessScript(&'a [u8]),
+    /// Anything else, such as the dummy item CHECKMULTISIG pops.
+    Data(&'a [u8]),
+}
+impl<'a> InputItem<'a> {
+    /// The items a scriptSig pushes, or none if it isn't push only or nothing in it is
+    /// recognised, as with coinbase scripts.
+    pub fn from_script_sig(script_sig: &'a [u8]) -> Vec<Self> {
+        let Some(pushes) = push_only(script_sig) else {
+            return Vec::new();
+        };
+        Self::recognise(&pushes, InputItem::RedeemScript)
+    }
+    /// The items of a witness stack, or none if nothing in it is recognised.
+    pub fn from_witness(witness: &'a [Vec<u8>]) -> Vec<Self> {
+        let items = witness.iter().map(Vec::as_slice).collect::<Vec<_>>();
+        Self::recognise(&items, InputItem::WitnessScript)
+    }
+    /// Recognise each of `items`, taking the last for a script, made with `script`, when it
+    /// looks like one and isn't a signature or key.
+    fn recognise(items: &[&'a [u8]], script: fn(&'a [u8]) -> Self) -> Vec<Self> {
+        let recognised = items
+            .iter()
+            .enumerate()
+            .map(|(index, item)| match Self::from_bytes(item) {
+                InputItem::Data(bytes) if index == items.len() - 1 && looks_like_script(bytes) => {
+                    script(bytes)
+                }
+                recognised => recognised,
+            })
+            .collect::<Vec<_>>();
+        match recognised
+            .iter()
+            .all(|item| matches!(item, InputItem::Data(_)))
+        {
+            true => Vec::new(),
+            false => recognised,
+        }
+    }
+    /// Recognise a single item as a signature or key, or leave it as data.
+    fn from_bytes(bytes: &'a [u8]) -> Self {
+        match bytes {
+            [der @ .., sighash_type] if is_der_signature(der) => InputItem::Signature {
+                der,
+                sighash_type: *sighash_type,
+            },
+            [0x02 | 0x03, ..] if bytes.len() == 33 => InputItem::PublicKey {
+                key: bytes,
+                compressed: true,
+            },
+            [0x04, ..] if bytes.len() == 65 => InputItem::PublicKey {
+                key: bytes,
+                compressed: false,
+            },
+            _ => InputItem::Data(bytes),
+        }
+    }
+    pub fn kind(&self) -> &'static str {
+        match self {
+            InputItem::Signature { .. } => "signature",
+            InputItem::PublicKey { .. } => "public_key",
+            InputItem::RedeemScript(_) => "redeem_script",
+            InputItem::WitnessScript(_) => "witness_script",
+            InputItem::Data(_) => "data",
+        }
+    }
+    /// The bytes of the item, without the sighash type of a signature.
+    pub fn bytes(&self) -> &'a [u8] {
+        match self {
+            InputItem::Signature { der, .. } => der,
+            InputItem::PublicKey { key, .. } => key,
+            InputItem::RedeemScript(bytes)
+            | InputItem::WitnessScript(bytes)
+            | InputItem::Data(bytes) => bytes,
+        }
+    }
+}
+impl std::fmt::Display for InputItem<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let hex = match self.bytes() {
+            [] => "empty".to_string(),
+            bytes => hex::encode(bytes),
+        };
+        match self {
+            // Unlike a Schnorr signature's, an ECDSA signature's sighash type can't be 0.
+            InputItem::Signature { sighash_type, .. } => write!(
+                f,
+                "Signature             : {hex} ({})",
+                match sighash_type {
+                    0 => "unknown (0x00)".to_string(),
+                    sighash_type => sighash_name(*sighash_type),
+                }
+            ),
+            InputItem::PublicKey { compressed, .. } => write!(
+                f,
+                "Public Key            : {hex} ({})",
+                match compressed {
+                    true => "compressed",
+                    false => "uncompressed",
+                }
+            ),
+            InputItem::RedeemScript(_) => write!(f, "Redeem Script         : {hex}"),
+            InputItem::WitnessScript(_) => write!(f, "Witness Script        : {hex}"),
+            InputItem::Data(_) => write!(f, "Data                  : {hex}"),
+        }
+    }
+}
+
+impl Serialize for InputItem<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("InputItem", 3)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("hex", &hex::encode(self.bytes()))?;
+        match self {
+            InputItem::Signature { sighash_type, .. } => {
+                state.serialize_field("sighash_type", sighash_type)?
+            }
+            _ => state.skip_field("sighash_type")?,
+        }
+        state.end()
+    }
+}
+
+/// Whether `bytes` is shaped like a DER encoded signature: a sequence of two integers, r and
+/// s, with every length matching. Signatures from before BIP66 made this strict needn't
+/// follow the rest of its rules, so they aren't checked.
+fn is_der_signature(bytes: &[u8]) -> bool {
+    let [DER_SEQUENCE, len, DER_INTEGER, r_len, rest @ ..] = bytes else {
+        return false;
+    };
+    let (r_len, len) = (*r_len as usize, *len as usize);
+    if len + 2 != bytes.len() || r_len == 0 || rest.len() < r_len + 2 {
+        return false;
+    }
+    match &rest[r_len..] {
+        [DER_INTEGER, s_len, s @ ..] => *s_len != 0 && s.len() == *s_len as usize,
+        _ => false,
+    }
+}
+
+/// Whether `bytes` looks like a script rather than data: a witness program, as P2SH wrapped
+/// segwit redeem scripts are, or a script ending in a signature check.
+fn looks_like_script(bytes: &[u8]) -> bool {
+    Script::new(bytes).witness_program().is_some()
+        || matches!(
+            bytes.last(),
+            Some(&OP_CHECKSIG | &OP_CHECKSIGVERIFY | &OP_CHECKMULTISIG | &OP_CHECKMULTISIGVERIFY)
+        )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::InputItem;
+
+    /// Checks a P2PKH scriptSig, a 1 of 1 P2SH multisig scriptSig and a P2WSH witness, and
+    /// that a coinbase script isn't decoded.
+    #[test]
+    fn test_input_items() {
+        let der = [
+            [0x30, 0x44, 0x02, 0x20].as_slice(),
+            &[0x45; 32],
+            &[0x02, 0x20],
+            &[0x1f; 32],
+        ]
+        .concat();
+        let signature = [der.as_slice(), &[0x01]].concat();
+        let key = [&[0x02], [0x11; 32].as_slice()].concat();
+        let p2pkh = [&[0x47], signature.as_slice(), &[0x21], &key].concat();
+        assert_eq!(
+            InputItem::from_script_sig(&p2pkh),
+            [
+                InputItem::Signature {
+                    der: &der,
+                    sighash_type: 0x01
+                },
+                InputItem::PublicKey {
+                    key: &key,
+                    compressed: true
+                },
+            ]
+        );
+
+        // OP_1 <key> OP_1 OP_CHECKMULTISIG, behind the dummy item and the signature.
+        let redeem_script = [&[0x51, 0x21], key.as_slice(), &[0x51, 0xae]].concat();
+        let p2sh = [&[0x00, 0x47], signature.as_slice(), &[0x25], &redeem_script].concat();
+        let items = InputItem::from_script_sig(&p2sh);
+        assert_eq!(items[0], InputItem::Data(&[]));
+        assert_eq!(items[2], InputItem::RedeemScript(&redeem_script));
+        assert_eq!(items[0].to_string(), "Data                  : empty");
+
+        let witness = [Vec::new(), signature.clone(), redeem_script.clone()];
+        let items = InputItem::from_witness(&witness);
+        assert_eq!(items[2], InputItem::WitnessScript(&redeem_script));
+        assert!(items[1].to_string().ends_with("(SIGHASH_ALL)"));
+
+        assert!(InputItem::from_script_sig(&hex::decode("04ffff001d0104").unwrap()).is_empty());
+    }
+}
@@ -0,0 +1,168 @@
+//! Spotting unusual transaction outputs: dust worth less than it costs to spend, scripts
+//! matching no standard template, and value burned in an unspendable `OP_RETURN`.
+use crate::{
+    util::{
+        hash::to_display_hex,
+        script::{ScriptType, DUST_RELAY_FEE},
+    },
+    Block,
+};
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+/// What's unusual about an output.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum OutputAnomalyKind {
+    /// Holding less than it would cost to spend, which Bitcoin Core won't relay.
+    Dust { threshold: u64 },
+    /// Locked by a script matching no standard template, nor a bare multisig.
+    NonStandard,
+    /// Holding value in an `OP_RETURN`, which no one can ever spend.
+    ValuedOpReturn,
+}
+impl OutputAnomalyKind {
+    pub fn name(&self) -> &'static str {
+        match self {
+            OutputAnomalyKind::Dust { .. } => "dust",
+            OutputAnomalyKind::NonStandard => "nonstandard",
+            OutputAnomalyKind::ValuedOpReturn => "valued_op_return",
+        }
+    }
+}
+
+/// An output that stands out, along with the transaction it's in.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct OutputAnomaly {
+    pub height: u64,
+    pub txid: [u8; 32],
+    pub vout: u32,
+    pub value: u64,
+    pub script_type: ScriptType,
+    pub kind: OutputAnomalyKind,
+}
+impl std::fmt::Display for OutputAnomaly {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self.kind {
+            OutputAnomalyKind::Dust { threshold } => write!(
+                f,
+                "{} sats to a {} script is below its dust threshold of {threshold} sats at {DUST_RELAY_FEE} sat/kvB",
+                self.value, self.script_type
+            ),
+            OutputAnomalyKind::NonStandard => {
+                write!(f, "the script matches no standard template")
+            }
+            OutputAnomalyKind::ValuedOpReturn => write!(
+                f,
+                "{} sats are burned in an unspendable OP_RETURN",
+                self.value
+            ),
+        }
+    }
+}
+impl Serialize for OutputAnomaly {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("OutputAnomaly", 7)?;
+        state.serialize_field("height", &self.height)?;
+        state.serialize_field("txid", &to_display_hex(&self.txid))?;
+        state.serialize_field("vout", &self.vout)?;
+        state.serialize_field("value", &self.value)?;
+        state.serialize_field("script_type", self.script_type.name())?;
+        state.serialize_field("kind", self.kind.name())?;
+        state.serialize_field("description", &self.to_string())?;
+        state.end()
+    }
+}
+
+/// Check every output of `block`, the block at `height`, in order. An output can be
+/// reported for more than one thing, a non-standard script holding dust for one.
+pub fn lint_outputs(height: u64, block: &Block) -> Vec<OutputAnomaly> {
+    let mut anomalies = Vec::new();
+    for tx in block.transactions() {
+        let txid = tx.txid();
+        for (vout, output) in (0..).zip(tx.outputs()) {
+            let (value, script) = (output.value(), output.script());
+            let script_type = script.script_type();
+            let mut report = |kind| {
+                anomalies.push(OutputAnomaly {
+                    height,
+                    txid,
+                    vout,
+                    value,
+                    script_type,
+                    kind,
+                })
+            };
+            let threshold = script.dust_threshold();
+            if value < threshold {
+                report(OutputAnomalyKind::Dust { threshold });
+            }
+            if script_type == ScriptType::NonStandard && !script.is_bare_multisig() {
+                report(OutputAnomalyKind::NonStandard);
+            }
+            if script_type == ScriptType::OpReturn && value > 0 {
+                report(OutputAnomalyKind::ValuedOpReturn);
+            }
+        }
+    }
+    anomalies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{lint_outputs, OutputAnomalyKind};
+    use crate::util::{
+        fixtures::{block, GENESIS_BLOCK_HEX},
+        tx::{Transaction, TxOut},
+        types::{Block, ByteCursor},
+    };
+
+    /// Checks dust, a non-standard script and a valued OP_RETURN are each reported for the
+    /// output they're in, and that the genesis block has nothing to report.
+    #[test]
+    fn test_lint_outputs() {
+        let genesis = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        let block = block(GENESIS_BLOCK_HEX);
+        assert!(lint_outputs(0, &block).is_empty());
+
+        // The genesis coinbase with its one output swapped for these.
+        let p2wpkh = hex::decode("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+        let outputs = [
+            TxOut::new(293, p2wpkh.clone()),
+            TxOut::new(294, p2wpkh),
+            TxOut::new(1000, vec![0x51]),
+            TxOut::new(5, vec![0x6a, 0x01, 0x00]),
+        ];
+        let coinbase = &genesis[81..];
+        let inputs_end = coinbase.len() - 4 - 1 - (8 + 1 + 0x43);
+        let mut raw_tx = coinbase[..inputs_end].to_vec();
+        raw_tx.push(outputs.len() as u8);
+        for output in &outputs {
+            raw_tx.extend_from_slice(&output.value().to_le_bytes());
+            raw_tx.push(output.script_pubkey_bytes().len() as u8);
+            raw_tx.extend_from_slice(output.script_pubkey_bytes());
+        }
+        raw_tx.extend_from_slice(&[0; 4]);
+        let raw_block = [&genesis[..81], raw_tx.as_slice()].concat();
+        let block = Block::from_raw_bytes(&mut ByteCursor::new(raw_block.as_slice())).unwrap();
+        let tx = Transaction::from_raw_bytes(&mut ByteCursor::new(raw_tx.as_slice())).unwrap();
+
+        let anomalies = lint_outputs(7, &block);
+        assert_eq!(
+            anomalies
+                .iter()
+                .map(|a| (a.vout, a.kind))
+                .collect::<Vec<_>>(),
+            [
+                (0, OutputAnomalyKind::Dust { threshold: 294 }),
+                (2, OutputAnomalyKind::NonStandard),
+                (3, OutputAnomalyKind::ValuedOpReturn),
+            ]
+        );
+        assert!(anomalies
+            .iter()
+            .all(|a| a.height == 7 && a.txid == tx.txid()));
+        assert_eq!(
+            anomalies[2].to_string(),
+            "5 sats are burned in an unspendable OP_RETURN"
+        );
+    }
+}
@@ -0,0 +1,142 @@
+//! Naming the pool that mined a block from the markers pools leave in their coinbase
+//! scriptSig, as shown by --show-miner and counted by the miners subcommand.
+use crate::Block;
+use serde::Serialize;
+use std::collections::HashMap;
+
+/// Known pools and the markers any one of which in a coinbase scriptSig identifies them,
+/// matched ignoring ASCII case. The first pool with a matching marker wins.
+const POOLS: &[(&str, &[&str])] = &[
+    ("Foundry USA", &["Foundry USA Pool"]),
+    ("AntPool", &["/AntPool/", "Mined by AntPool"]),
+    ("F2Pool", &["/F2Pool/", "七彩神仙鱼"]),
+    ("ViaBTC", &["/ViaBTC/", "viabtc.com"]),
+    ("Binance Pool", &["/Binance/"]),
+    ("MARA Pool", &["MARA Pool"]),
+    ("Luxor", &["/LUXOR/", "Luxor Tech"]),
+    ("Braiins Pool", &["/slush/", "Braiins"]),
+    ("Poolin", &["/poolin.com", "/poolin/"]),
+    ("BTC.com", &["/BTC.COM/", "/BTC.com/"]),
+    ("SBI Crypto", &["/SBICrypto.com Pool/"]),
+    ("SpiderPool", &["SpiderPool"]),
+    ("Ocean", &["OCEAN.XYZ"]),
+    ("SECPOOL", &["SecPool"]),
+    ("Huobi Pool", &["/HuoBi/", "/Huobi/"]),
+    ("BTC.TOP", &["/BTC.TOP/"]),
+    ("BW.COM", &["/BW Pool/", "BW.COM"]),
+    ("Solo CKPool", &["solo.ckpool.org"]),
+    ("KanoPool", &["kanopool.com", "/Kano/"]),
+    ("BitFury", &["/BitFury/", "Bitfury"]),
+    ("GHash.IO", &["ghash.io"]),
+    ("KnCMiner", &["KnCMiner"]),
+    ("BTC Guild", &["BTC Guild"]),
+    ("Eligius", &["Eligius"]),
+];
+
+/// The pool whose marker appears in `script_sig`, if any known one does.
+pub fn identify(script_sig: &[u8]) -> Option<&'static str> {
+    let script_sig = script_sig.to_ascii_lowercase();
+    POOLS
+        .iter()
+        .find(|(_, markers)| {
+            markers.iter().any(|marker| {
+                let marker = marker.as_bytes().to_ascii_lowercase();
+                script_sig
+                    .windows(marker.len())
+                    .any(|window| window == marker)
+            })
+        })
+        .map(|(name, _)| *name)
+}
+
+/// The blocks one pool mined.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MinerShare {
+    /// The pool, `None` for the blocks no known marker identifies.
+    pub miner: Option<&'static str>,
+    pub blocks: u64,
+    /// The percentage of the range's blocks.
+    pub percent: f64,
+}
+
+/// The blocks each pool mined from height `from` to `to`, most first.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct MinerShares {
+    pub from: u64,
+    pub to: u64,
+    pub block_count: u64,
+    pub miners: Vec<MinerShare>,
+}
+
+/// Counts the blocks each pool mined, one block at a time.
+#[derive(Debug, Default)]
+pub struct MinerCollector {
+    block_count: u64,
+    counts: HashMap<Option<&'static str>, u64>,
+}
+impl MinerCollector {
+    pub fn add(&mut self, block: &Block) {
+        self.block_count += 1;
+        let miner = block.coinbase().and_then(|coinbase| coinbase.miner());
+        *self.counts.entry(miner).or_default() += 1;
+    }
+    /// The shares of the blocks added, labelled as the range `from` to `to`. Pools with as
+    /// many blocks as each other are ordered by name, and the unidentified blocks come last.
+    pub fn finish(self, from: u64, to: u64) -> MinerShares {
+        let mut miners = self
+            .counts
+            .into_iter()
+            .map(|(miner, blocks)| MinerShare {
+                miner,
+                blocks,
+                percent: blocks as f64 * 100.0 / self.block_count as f64,
+            })
+            .collect::<Vec<_>>();
+        miners.sort_by_key(|share| {
+            (
+                share.miner.is_none(),
+                std::cmp::Reverse(share.blocks),
+                share.miner,
+            )
+        });
+        MinerShares {
+            from,
+            to,
+            block_count: self.block_count,
+            miners,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{identify, MinerCollector};
+    use crate::util::fixtures::{block, BLOCK_1_HEX, GENESIS_BLOCK_HEX};
+
+    /// Checks markers are found anywhere in a scriptSig whatever their case, and that the
+    /// first blocks, which carry none, count as unidentified.
+    #[test]
+    fn test_identify_miner() {
+        assert_eq!(
+            identify(b"\x03\x40\x0d\x0c/f2pool/\x00\x01"),
+            Some("F2Pool")
+        );
+        assert_eq!(identify(b"Mined by AntPool usa1"), Some("AntPool"));
+        assert_eq!(identify(b"/solo.ckpool.org/"), Some("Solo CKPool"));
+        assert_eq!(
+            identify(b"/Foundry USA Pool #dropgold/"),
+            Some("Foundry USA")
+        );
+        assert_eq!(identify(b"F2Poo"), None);
+
+        let mut collector = MinerCollector::default();
+        for block_hex in [GENESIS_BLOCK_HEX, BLOCK_1_HEX] {
+            collector.add(&block(block_hex));
+        }
+        let shares = collector.finish(0, 1);
+        assert_eq!(shares.block_count, 2);
+        assert_eq!(shares.miners.len(), 1);
+        assert_eq!(shares.miners[0].miner, None);
+        assert_eq!(shares.miners[0].percent, 100.0);
+    }
+}
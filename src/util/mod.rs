@@ -1,11 +1,1153 @@
-use crate::{Block, BlockInfo};
+use crate::{
+    chain::{
+        hashrate::HashrateEstimate,
+        interval::{BlockInterval, IntervalExtremes},
+        lint::HeaderAnomaly,
+        retarget::Retarget,
+        StaleBlock,
+    },
+    reader::verify::VerifyReport,
+    util::{
+        decode::Decoded,
+        display::{
+            format_amount, format_difficulty, format_hashrate, format_interval, format_utc, options,
+        },
+        empty::EmptyBlockReport,
+        hash::to_display_hex,
+        input::InputItem,
+        lint_tx::{OutputAnomaly, OutputAnomalyKind},
+        miner::MinerShares,
+        nonce::{NonceSample, NonceStats},
+        script::Script,
+        stats::BlockStats,
+        top::{Leaderboard, Ranking},
+    },
+    Block, BlockHeader, BlockInfo, Network, Transaction,
+};
+use anyhow::Result;
+use colored::*;
+use serde::Serialize;
+use std::io::Write;
 
-mod constant;
-pub(crate) mod types;
+pub mod address;
+pub mod asm;
+pub mod coinbase;
+pub mod constant;
+pub mod decode;
+pub mod display;
+pub mod empty;
+pub mod error;
+pub mod field;
+#[cfg(test)]
+pub(crate) mod fixtures;
+pub mod hash;
+pub mod input;
+pub mod lint_tx;
+pub mod miner;
+pub mod nonce;
+pub mod script;
+pub mod sighash;
+pub mod stats;
+pub mod taproot;
+pub mod template;
+pub mod timelock;
+pub mod top;
+pub mod tx;
+pub mod types;
+pub mod versionbits;
+pub mod view;
+
+/// The output formats values can be printed in.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Format {
+    /// Colored, human readable text.
+    #[default]
+    Text,
+    /// A single pretty printed JSON object.
+    Json,
+    /// Comma separated values, one row per block, only written by the export, nonces and
+    /// hashrate subcommands.
+    Csv,
+    /// One serialized header per line in hex, only written by the export-headers subcommand.
+    Hex,
+    /// One compact JSON object per line, written as each block is read so output can be
+    /// streamed into another tool, only when finding blocks and by the export subcommand.
+    Ndjson,
+}
+impl Format {
+    /// The error for printing something in a format only some subcommands write.
+    pub fn unsupported(&self) -> anyhow::Error {
+        match self {
+            Format::Csv => anyhow::anyhow!(
+                "csv output is only supported by the export, nonces and hashrate subcommands"
+            ),
+            Format::Hex => {
+                anyhow::anyhow!("hex output is only supported by the export-headers subcommand")
+            }
+            Format::Ndjson => anyhow::anyhow!(
+                "ndjson output is only supported when finding blocks and by the export and nonces subcommands"
+            ),
+            Format::Text | Format::Json => anyhow::anyhow!("{self:?} output isn't supported here"),
+        }
+    }
+}
+impl std::str::FromStr for Format {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "text" => Ok(Format::Text),
+            "json" => Ok(Format::Json),
+            "csv" => Ok(Format::Csv),
+            "hex" => Ok(Format::Hex),
+            "ndjson" => Ok(Format::Ndjson),
+            _ => {
+                anyhow::bail!("unknown format '{s}', expected one of: text, json, csv, hex, ndjson")
+            }
+        }
+    }
+}
 
 /// Print all values.
 /// Written as a util fn to make adding extra printing functionality easier.
-pub(crate) fn log(block_info: BlockInfo, block: Block) {
-    block_info.log();
-    block.log();
+///
+/// This and the other `log_*` renderers print results to stdout rather than through
+/// `tracing`, which writes to stderr and is filtered by `--log-level`: results have to reach
+/// stdout whatever the level so they can be piped or parsed as JSON, while progress,
+/// warnings and summaries of a scan go through `tracing` and stay out of the way.
+pub fn log(block_info: BlockInfo, block: Block, format: Format) -> Result<()> {
+    #[derive(Serialize)]
+    struct Output<'a> {
+        block_info: &'a BlockInfo,
+        block: &'a Block,
+    }
+    let output = Output {
+        block_info: &block_info,
+        block: &block,
+    };
+    match format {
+        Format::Text => {
+            block_info.log();
+            block.log();
+        }
+        Format::Csv | Format::Hex => return Err(format.unsupported()),
+        Format::Json => println!("{}", serde_json::to_string_pretty(&output)?),
+        Format::Ndjson => println!("{}", serde_json::to_string(&output)?),
+    }
+    Ok(())
+}
+
+/// Print a block, header or transaction decoded from hex, as it would be printed when found
+/// in the blk files but without anything only a record of them tells.
+pub fn log_decoded(decoded: &Decoded, format: Format) -> Result<()> {
+    match (decoded, format) {
+        (Decoded::Block(block), Format::Text) => block.log(),
+        (Decoded::Header(header), Format::Text) => {
+            println!("{}\n\n{header}", "> BlockHeader".green());
+            if options().verbosity >= 3 {
+                println!("\n{}", "Raw Header Fields:".cyan());
+                for (name, bytes) in header.raw_fields() {
+                    println!("{name:<22}: {}", hex::encode(bytes));
+                }
+            }
+        }
+        (Decoded::Transaction(tx), Format::Text) => println!(
+            "{}\n\nTXID                  : {}\nWTXID                 : {}\n{tx}",
+            "> Transaction".green(),
+            to_display_hex(&tx.txid()),
+            to_display_hex(&tx.wtxid()),
+        ),
+        (_, Format::Csv | Format::Hex | Format::Ndjson) => return Err(format.unsupported()),
+        (Decoded::Block(block), Format::Json) => {
+            println!("{}", serde_json::to_string_pretty(block)?)
+        }
+        (Decoded::Header(header), Format::Json) => {
+            println!("{}", serde_json::to_string_pretty(header)?)
+        }
+        (Decoded::Transaction(tx), Format::Json) => {
+            #[derive(Serialize)]
+            struct Output<'a> {
+                txid: String,
+                transaction: &'a Transaction,
+            }
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&Output {
+                    txid: to_display_hex(&tx.txid()),
+                    transaction: tx,
+                })?
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Print a script on its own, disassembled along with its template and address when it's
+/// an output script, or the signatures and keys it pushes when it's a scriptSig.
+pub fn log_script(script: Script, format: Format) -> Result<()> {
+    let address = script.address(options().network.unwrap_or(Network::Mainnet));
+    let items = InputItem::from_script_sig(script.bytes());
+    match format {
+        Format::Text => {
+            println!(
+                "{}\n\nScript Asm            : {}\nScript Type           : {}",
+                "> Script".green(),
+                script.asm(),
+                script.script_type()
+            );
+            if let Some(address) = address {
+                println!("Address               : {address}");
+            }
+            for item in items {
+                println!("{item}");
+            }
+        }
+        Format::Csv | Format::Hex | Format::Ndjson => return Err(format.unsupported()),
+        Format::Json => {
+            #[derive(Serialize)]
+            struct Output<'a> {
+                hex: String,
+                asm: String,
+                script_type: &'static str,
+                address: Option<String>,
+                items: Vec<InputItem<'a>>,
+            }
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&Output {
+                    hex: hex::encode(script.bytes()),
+                    asm: script.asm(),
+                    script_type: script.script_type().name(),
+                    address,
+                    items,
+                })?
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Print each transaction in `block` as a line of JSON, along with the block it's in
+/// and its position there, for streaming transactions rather than whole blocks.
+pub fn log_block_transactions(block_info: &BlockInfo, block: &Block) -> Result<()> {
+    #[derive(Serialize)]
+    struct Output<'a> {
+        block_height: u64,
+        block_hash: &'a str,
+        index: usize,
+        txid: String,
+        transaction: &'a Transaction,
+    }
+    let block_hash = block.block_header().block_hash();
+    let mut stdout = std::io::stdout().lock();
+    for (index, tx) in block.transactions().iter().enumerate() {
+        serde_json::to_writer(
+            &mut stdout,
+            &Output {
+                block_height: block_info.height,
+                block_hash: &block_hash,
+                index,
+                txid: to_display_hex(&tx.txid()),
+                transaction: tx,
+            },
+        )?;
+        writeln!(stdout)?;
+    }
+    Ok(())
+}
+
+/// Print a row for each of `headers` around the block at height `target`, marking it out.
+pub fn log_surrounding_headers(headers: &[(u64, BlockHeader)], target: u64) {
+    println!(
+        "\n{}",
+        format!(
+            "  {:>8}  {:<64}  {:<20}  {:>8}  {:>10}",
+            "Height", "Hash", "Time", "Bits", "Nonce"
+        )
+        .green()
+    );
+    for (height, header) in headers {
+        let row = format!(
+            "{} {height:>8}  {:<64}  {:<20}  {:>8}  {:>10}",
+            match *height == target {
+                true => ">",
+                false => " ",
+            },
+            header.block_hash(),
+            format_utc(header.unix_epoch_time()),
+            format!("{:08x}", header.target()),
+            header.nonce(),
+        );
+        match *height == target {
+            true => println!("{}", row.bold()),
+            false => println!("{row}"),
+        }
+    }
+}
+
+/// Print a transaction along with the block it was found in.
+///
+/// The height is `None` when the block isn't on the best chain.
+pub fn log_transaction(
+    height: Option<u64>,
+    block_hash: [u8; 32],
+    tx: &Transaction,
+    format: Format,
+) -> Result<()> {
+    match format {
+        Format::Text => {
+            println!(
+                "{}\n\nBlock at Height       : {}\nBlock Hash            : {}\nTXID                  : {}\nWTXID                 : {}\n{}",
+                "> Transaction".green(),
+                height.map_or("unknown, not on the best chain".to_string(), |height| height
+                    .to_string()),
+                to_display_hex(&block_hash),
+                to_display_hex(&tx.txid()),
+                to_display_hex(&tx.wtxid()),
+                tx
+            );
+        }
+        Format::Csv | Format::Hex | Format::Ndjson => return Err(format.unsupported()),
+        Format::Json => {
+            #[derive(Serialize)]
+            struct Output<'a> {
+                block_height: Option<u64>,
+                block_hash: String,
+                txid: String,
+                transaction: &'a Transaction,
+            }
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&Output {
+                    block_height: height,
+                    block_hash: to_display_hex(&block_hash),
+                    txid: to_display_hex(&tx.txid()),
+                    transaction: tx,
+                })?
+            );
+        }
+    }
+    Ok(())
+}
+
+/// An output found paying to a searched address.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AddressOutput {
+    /// The height of the containing block, `None` when it isn't on the best chain.
+    pub block_height: Option<u64>,
+    pub block_hash: [u8; 32],
+    pub txid: [u8; 32],
+    pub vout: u32,
+    /// The output value in satoshis.
+    pub value: u64,
+}
+impl Serialize for AddressOutput {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("AddressOutput", 5)?;
+        state.serialize_field("block_height", &self.block_height)?;
+        state.serialize_field("block_hash", &to_display_hex(&self.block_hash))?;
+        state.serialize_field("txid", &to_display_hex(&self.txid))?;
+        state.serialize_field("vout", &self.vout)?;
+        state.serialize_field("value", &self.value)?;
+        state.end()
+    }
+}
+pub fn log_address_outputs(address: &str, outputs: &[AddressOutput], format: Format) -> Result<()> {
+    let total = outputs.iter().map(|output| output.value).sum::<u64>();
+    match format {
+        Format::Text => {
+            println!("{}\n", format!("> Outputs paying {address}").green());
+            for output in outputs {
+                println!(
+                    "Block at Height       : {}\nBlock Hash            : {}\nTXID                  : {}\nVout                  : {}\nValue                 : {}\n",
+                    output.block_height.map_or("unknown, not on the best chain".to_string(), |height| height.to_string()),
+                    to_display_hex(&output.block_hash),
+                    to_display_hex(&output.txid),
+                    output.vout,
+                    format_amount(output.value),
+                );
+            }
+            println!(
+                "Total Received        : {} in {} outputs",
+                format_amount(total),
+                outputs.len()
+            );
+        }
+        Format::Csv | Format::Hex | Format::Ndjson => return Err(format.unsupported()),
+        Format::Json => {
+            #[derive(Serialize)]
+            struct Output<'a> {
+                address: &'a str,
+                total_received: u64,
+                outputs: &'a [AddressOutput],
+            }
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&Output {
+                    address,
+                    total_received: total,
+                    outputs,
+                })?
+            );
+        }
+    }
+    Ok(())
+}
+
+/// A transaction on the best chain paying to or spending from a script, as Electrum servers
+/// list the history of a script hash.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct HistoryEntry {
+    pub height: u64,
+    /// The position of the transaction in its block, ordering those in the same one.
+    pub position: usize,
+    pub txid: [u8; 32],
+}
+impl Serialize for HistoryEntry {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("HistoryEntry", 2)?;
+        state.serialize_field("tx_hash", &to_display_hex(&self.txid))?;
+        state.serialize_field("height", &self.height)?;
+        state.end()
+    }
+}
+
+/// Print the transactions paying to or spending from the script with `script_hash`, as
+/// JSON in the shape of Electrum's `blockchain.scripthash.get_history` result.
+pub fn log_script_history(
+    script_hash: &[u8; 32],
+    history: &[HistoryEntry],
+    format: Format,
+) -> Result<()> {
+    match format {
+        Format::Text => {
+            println!(
+                "{}\n",
+                format!("> History of script hash {}", to_display_hex(script_hash)).green()
+            );
+            for entry in history {
+                println!(
+                    "Block at Height       : {}\nTXID                  : {}\n",
+                    entry.height,
+                    to_display_hex(&entry.txid),
+                );
+            }
+            println!("Transactions          : {}", history.len());
+        }
+        Format::Csv | Format::Hex | Format::Ndjson => return Err(format.unsupported()),
+        Format::Json => println!("{}", serde_json::to_string_pretty(history)?),
+    }
+    Ok(())
+}
+
+/// Print the block a template proposes, followed by what's amiss with it on `network`.
+pub fn log_template(
+    template: &template::BlockTemplate,
+    network: Network,
+    format: Format,
+) -> Result<()> {
+    let problems = template.problems(network);
+    match format {
+        Format::Text => {
+            println!("{template}\n");
+            match problems.is_empty() {
+                true => println!("Problems              : none"),
+                false => {
+                    for problem in &problems {
+                        println!("Problem               : {problem}");
+                    }
+                }
+            }
+        }
+        Format::Csv | Format::Hex | Format::Ndjson => return Err(format.unsupported()),
+        Format::Json => println!(
+            "{}",
+            serde_json::to_string_pretty(
+                &serde_json::json!({ "template": template, "problems": problems })
+            )?
+        ),
+    }
+    Ok(())
+}
+
+/// An unspent output paying to an address, from the utxo set.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct UnspentOutput {
+    pub txid: [u8; 32],
+    pub vout: u32,
+    /// The output value in satoshis.
+    pub value: u64,
+    /// The height of the block that created it.
+    pub height: u32,
+}
+impl Serialize for UnspentOutput {
+    fn serialize<S: serde::Serializer>(
+        &self,
+        serializer: S,
+    ) -> std::result::Result<S::Ok, S::Error> {
+        use serde::ser::SerializeStruct;
+        let mut state = serializer.serialize_struct("UnspentOutput", 4)?;
+        state.serialize_field("txid", &to_display_hex(&self.txid))?;
+        state.serialize_field("vout", &self.vout)?;
+        state.serialize_field("value", &self.value)?;
+        state.serialize_field("height", &self.height)?;
+        state.end()
+    }
+}
+/// Print the balance of `address` as of the block at `height`, the sum of `outputs`.
+pub fn log_balance(
+    address: &str,
+    height: u64,
+    outputs: &[UnspentOutput],
+    format: Format,
+) -> Result<()> {
+    let balance = outputs.iter().map(|output| output.value).sum::<u64>();
+    match format {
+        Format::Text => {
+            println!(
+                "{}\n",
+                format!("> Unspent outputs paying {address} as of height {height}").green()
+            );
+            for output in outputs {
+                println!(
+                    "Outpoint              : {}:{}\nValue                 : {}\nBlock at Height       : {}\n",
+                    to_display_hex(&output.txid),
+                    output.vout,
+                    format_amount(output.value),
+                    output.height,
+                );
+            }
+            println!(
+                "Balance               : {} in {} outputs",
+                format_amount(balance),
+                outputs.len()
+            );
+        }
+        Format::Csv | Format::Hex | Format::Ndjson => return Err(format.unsupported()),
+        Format::Json => {
+            #[derive(Serialize)]
+            struct Output<'a> {
+                address: &'a str,
+                height: u64,
+                balance: u64,
+                outputs: &'a [UnspentOutput],
+            }
+            println!(
+                "{}",
+                serde_json::to_string_pretty(&Output {
+                    address,
+                    height,
+                    balance,
+                    outputs,
+                })?
+            );
+        }
+    }
+    Ok(())
+}
+
+/// The chain metadata of a single block, as written by the export subcommand.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BlockSummary {
+    pub height: u64,
+    pub hash: String,
+    /// The header timestamp, in seconds since the unix epoch.
+    pub time: u32,
+    pub version: u32,
+    /// The compact target, in hex as it's usually written.
+    pub bits: String,
+    pub nonce: u32,
+    pub tx_count: u64,
+    /// The size of the block in bytes, including witness data.
+    pub size: u32,
+}
+impl BlockSummary {
+    /// The column names, in the order [BlockSummary::to_csv] writes them.
+    pub const CSV_HEADER: &'static str = "height,hash,time,version,bits,nonce,tx_count,size";
+
+    pub fn new(height: u64, block: &Block) -> Self {
+        let header = block.block_header();
+        Self {
+            height,
+            hash: header.block_hash(),
+            time: header.unix_epoch_time(),
+            version: header.version(),
+            bits: format!("{:08x}", header.target()),
+            nonce: header.nonce(),
+            tx_count: block.tx_count(),
+            size: block.total_size(),
+        }
+    }
+    /// The summary as a CSV row. None of the fields can contain a comma, so none are quoted.
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{},{},{},{},{}",
+            self.height,
+            self.hash,
+            self.time,
+            self.version,
+            self.bits,
+            self.nonce,
+            self.tx_count,
+            self.size
+        )
+    }
+}
+
+/// Print a row for each block in `summaries`, as an aligned table, a JSON array or CSV with a header.
+pub fn log_block_summaries(summaries: &[BlockSummary], format: Format) -> Result<()> {
+    match format {
+        Format::Text => {
+            println!(
+                "{}",
+                format!(
+                    "{:>8}  {:<64}  {:>10}  {:>10}  {:>8}  {:>10}  {:>8}  {:>8}",
+                    "Height", "Hash", "Time", "Version", "Bits", "Nonce", "Txs", "Size"
+                )
+                .green()
+            );
+            for summary in summaries {
+                println!(
+                    "{:>8}  {:<64}  {:>10}  {:>10}  {:>8}  {:>10}  {:>8}  {:>8}",
+                    summary.height,
+                    summary.hash,
+                    summary.time,
+                    summary.version,
+                    summary.bits,
+                    summary.nonce,
+                    summary.tx_count,
+                    summary.size
+                );
+            }
+        }
+        Format::Json => println!("{}", serde_json::to_string_pretty(summaries)?),
+        Format::Ndjson => {
+            for summary in summaries {
+                println!("{}", serde_json::to_string(summary)?);
+            }
+        }
+        Format::Hex => return Err(format.unsupported()),
+        Format::Csv => {
+            println!("{}", BlockSummary::CSV_HEADER);
+            for summary in summaries {
+                println!("{}", summary.to_csv());
+            }
+        }
+    }
+    Ok(())
+}
+
+/// Print the statistics of a range of blocks.
+pub fn log_block_stats(stats: &BlockStats, format: Format) -> Result<()> {
+    match format {
+        Format::Text => {
+            let distribution = |distribution: &stats::Distribution| {
+                format!(
+                    "{:.2} average, {} median, {} min, {} max",
+                    distribution.average, distribution.median, distribution.min, distribution.max
+                )
+            };
+            println!(
+                "{}\n\nBlocks                : {}\nSize                  : {}\nTX Count              : {}\nAverage Interval      : {}\nTotal Fees            : {}\nSegwit Transactions   : {}",
+                format!("> Statistics for heights {} to {}", stats.from, stats.to).green(),
+                stats.block_count,
+                distribution(&stats.size),
+                distribution(&stats.tx_count),
+                stats
+                    .average_interval
+                    .map_or("none, there's only one block".to_string(), |interval| format!(
+                        "{interval:.2}s"
+                    )),
+                stats.total_fees.map_or(
+                    "unknown, use --with-fees to work them out".to_string(),
+                    format_amount
+                ),
+                stats
+                    .segwit_percent
+                    .map_or("none, there are only coinbases".to_string(), |percent| format!(
+                        "{percent:.2}%"
+                    )),
+            );
+        }
+        Format::Csv | Format::Hex | Format::Ndjson => return Err(format.unsupported()),
+        Format::Json => println!("{}", serde_json::to_string_pretty(stats)?),
+    }
+    Ok(())
+}
+
+/// Print the blocks each pool mined, as an aligned table or a JSON object.
+pub fn log_miner_shares(shares: &MinerShares, format: Format) -> Result<()> {
+    match format {
+        Format::Text => {
+            println!(
+                "{}\n",
+                format!("> Miners between heights {} and {}", shares.from, shares.to).green()
+            );
+            println!(
+                "{}",
+                format!("{:>8}  {:>8}  Miner", "Blocks", "Share").green()
+            );
+            for share in &shares.miners {
+                println!(
+                    "{:>8}  {:>7.2}%  {}",
+                    share.blocks,
+                    share.percent,
+                    share.miner.unwrap_or("unknown")
+                );
+            }
+            println!("\nTotal                 : {} blocks", shares.block_count);
+        }
+        Format::Csv | Format::Hex | Format::Ndjson => return Err(format.unsupported()),
+        Format::Json => println!("{}", serde_json::to_string_pretty(shares)?),
+    }
+    Ok(())
+}
+
+/// Print the empty blocks of a range, each on a line or grouped by miner tag, or as JSON.
+pub fn log_empty_blocks(report: &EmptyBlockReport, format: Format) -> Result<()> {
+    match format {
+        Format::Text => {
+            println!(
+                "{}\n",
+                format!(
+                    "> Empty blocks between heights {} and {}",
+                    report.from, report.to
+                )
+                .green()
+            );
+            let tag = |miner_tag: &Option<String>| miner_tag.clone().unwrap_or("none".to_string());
+            match &report.by_miner {
+                Some(groups) => {
+                    println!("{}", format!("{:>8}  Miner Tag", "Blocks").green());
+                    for group in groups {
+                        println!("{:>8}  {}", group.count, tag(&group.miner_tag));
+                    }
+                }
+                None => {
+                    println!(
+                        "{}",
+                        format!(
+                            "{:>8}  {:<20}  {:<64}  Miner Tag",
+                            "Height", "Time", "Block Hash"
+                        )
+                        .green()
+                    );
+                    for block in &report.blocks {
+                        println!(
+                            "{:>8}  {:<20}  {:<64}  {}",
+                            block.height,
+                            format_utc(block.time),
+                            block.hash,
+                            tag(&block.miner_tag)
+                        );
+                    }
+                }
+            }
+            println!(
+                "\nTotal                 : {} of {} blocks ({:.2}%)",
+                report.empty_count, report.block_count, report.empty_percent
+            );
+        }
+        Format::Csv | Format::Hex | Format::Ndjson => return Err(format.unsupported()),
+        Format::Json => println!("{}", serde_json::to_string_pretty(report)?),
+    }
+    Ok(())
+}
+
+/// Print how the nonces of a range of blocks are distributed, as a summary or JSON, or the
+/// nonces of each block in `samples` as CSV with a header or a line of JSON per block.
+pub fn log_nonce_stats(stats: &NonceStats, samples: &[NonceSample], format: Format) -> Result<()> {
+    match format {
+        Format::Text => {
+            let distribution = |distribution: &stats::Distribution| {
+                format!(
+                    "{:.2} average, {} median, {} min, {} max",
+                    distribution.average, distribution.median, distribution.min, distribution.max
+                )
+            };
+            println!(
+                "{}\n\nBlocks                : {}\nNonce                 : {}\nExtraNonce            : {}\nExtraNonce Resets     : {}",
+                format!("> Nonces for heights {} to {}", stats.from, stats.to).green(),
+                stats.block_count,
+                distribution(&stats.nonce),
+                stats.extra_nonce.as_ref().map_or(
+                    "none, no coinbase pushes one after the bits or height".to_string(),
+                    |extra_nonce| format!(
+                        "{}, in {} blocks",
+                        distribution(extra_nonce),
+                        stats.extra_nonce_blocks
+                    )
+                ),
+                stats.extra_nonce_resets,
+            );
+            println!("\n{}", "Nonce Ranges:".cyan());
+            let range_len = (1u64 << 32) / nonce::NONCE_RANGES as u64;
+            for (index, count) in stats.nonce_ranges.iter().enumerate() {
+                let start = index as u64 * range_len;
+                println!("{start:#010x}-{:#010x} : {count}", start + range_len - 1);
+            }
+            println!("\n{}", "Nonce Least Significant Byte:".cyan());
+            for (row, counts) in stats.nonce_low_byte.chunks(16).enumerate() {
+                println!(
+                    "{:#04x} : {}",
+                    row * 16,
+                    counts
+                        .iter()
+                        .map(|count| format!("{count:>6}"))
+                        .collect::<String>()
+                );
+            }
+        }
+        Format::Json => println!("{}", serde_json::to_string_pretty(stats)?),
+        Format::Ndjson => {
+            for sample in samples {
+                println!("{}", serde_json::to_string(sample)?);
+            }
+        }
+        Format::Csv => {
+            println!("{}", NonceSample::CSV_HEADER);
+            for sample in samples {
+                println!("{}", sample.to_csv());
+            }
+        }
+        Format::Hex => return Err(format.unsupported()),
+    }
+    Ok(())
+}
+
+/// Print each difficulty adjustment, as an aligned table or a JSON array.
+pub fn log_retargets(retargets: &[Retarget], format: Format) -> Result<()> {
+    match format {
+        Format::Text => {
+            println!(
+                "{}",
+                format!(
+                    "{:>8}  {:<20}  {:>13}  {:>10}  {:>20}  {:>10}  {:>10}",
+                    "Height",
+                    "Period Start",
+                    "Period Length",
+                    "Of Target",
+                    "Difficulty",
+                    "Change",
+                    "Expected"
+                )
+                .green()
+            );
+            for retarget in retargets {
+                println!(
+                    "{:>8}  {:<20}  {:>12.2}d  {:>9.2}%  {:>20}  {:>+9.2}%  {:>+9.2}%",
+                    retarget.height,
+                    retarget.period_start,
+                    retarget.actual_timespan as f64 / 86_400.0,
+                    retarget.actual_timespan as f64 * 100.0 / retarget.expected_timespan as f64,
+                    match options().human {
+                        true => format_difficulty(retarget.difficulty),
+                        false => format!("{:.2}", retarget.difficulty),
+                    },
+                    retarget.adjustment_percent,
+                    retarget.expected_adjustment_percent,
+                );
+            }
+        }
+        Format::Csv | Format::Hex | Format::Ndjson => return Err(format.unsupported()),
+        Format::Json => println!("{}", serde_json::to_string_pretty(retargets)?),
+    }
+    Ok(())
+}
+
+/// Print the hashrate estimated over each window of `window` blocks, as an aligned table, CSV
+/// rows or a JSON array.
+pub fn log_hashrate(estimates: &[HashrateEstimate], window: u64, format: Format) -> Result<()> {
+    match format {
+        Format::Text => {
+            println!(
+                "{}",
+                format!(
+                    "{:>8}  {:<20}  {:>12}  {:>20}  {:>16}",
+                    "Height", "Time", "Avg Interval", "Difficulty", "Hashrate"
+                )
+                .green()
+            );
+            for estimate in estimates {
+                println!(
+                    "{:>8}  {:<20}  {:>11.2}m  {:>20}  {:>16}",
+                    estimate.height,
+                    format_utc(estimate.time),
+                    estimate.timespan as f64 / 60.0 / window as f64,
+                    match options().human {
+                        true => format_difficulty(estimate.difficulty),
+                        false => format!("{:.2}", estimate.difficulty),
+                    },
+                    estimate.hashrate.map_or("-".to_string(), format_hashrate),
+                );
+            }
+        }
+        Format::Csv => {
+            println!("{}", HashrateEstimate::CSV_HEADER);
+            for estimate in estimates {
+                println!("{}", estimate.to_csv());
+            }
+        }
+        Format::Hex | Format::Ndjson => return Err(format.unsupported()),
+        Format::Json => println!("{}", serde_json::to_string_pretty(estimates)?),
+    }
+    Ok(())
+}
+
+/// Print the longest and shortest intervals between blocks, as aligned tables or a JSON object.
+pub fn log_interval_extremes(extremes: &IntervalExtremes, format: Format) -> Result<()> {
+    match format {
+        Format::Text => {
+            let table = |title: &str, intervals: &[BlockInterval]| {
+                println!(
+                    "{}\n",
+                    format!(
+                        "> {title} intervals between heights {} and {}",
+                        extremes.from, extremes.to
+                    )
+                    .green()
+                );
+                println!(
+                    "{}",
+                    format!(
+                        "{:>8}  {:<20}  {:<20}  {:>14}",
+                        "Height", "Time", "Parent Time", "Interval"
+                    )
+                    .green()
+                );
+                for interval in intervals {
+                    println!(
+                        "{:>8}  {:<20}  {:<20}  {:>14}",
+                        interval.height,
+                        format_utc(interval.time),
+                        format_utc(interval.parent_time),
+                        format_interval(interval.interval),
+                    );
+                }
+            };
+            table("Longest", &extremes.longest);
+            println!();
+            table("Shortest", &extremes.shortest);
+        }
+        Format::Csv | Format::Hex | Format::Ndjson => return Err(format.unsupported()),
+        Format::Json => println!("{}", serde_json::to_string_pretty(extremes)?),
+    }
+    Ok(())
+}
+
+/// Print the leading blocks and transactions, as aligned tables or a JSON object.
+pub fn log_leaderboard(leaderboard: &Leaderboard, format: Format) -> Result<()> {
+    match format {
+        Format::Text => {
+            let order = match leaderboard.smallest {
+                true => "Smallest",
+                false => "Biggest",
+            };
+            let title = |of: &str| {
+                format!(
+                    "> {order} {of} by {} between heights {} and {}",
+                    leaderboard.by.name(),
+                    leaderboard.from,
+                    leaderboard.to
+                )
+                .green()
+            };
+            let fees = |fees: Option<u64>| fees.map_or("-".to_string(), format_amount);
+            println!("{}\n", title("blocks"));
+            println!(
+                "{}",
+                format!(
+                    "{:>8}  {:<64}  {:>8}  {:>8}  {:>16}",
+                    "Height", "Block Hash", "Size", "TX Count", "Fees"
+                )
+                .green()
+            );
+            for block in &leaderboard.blocks {
+                println!(
+                    "{:>8}  {:<64}  {:>8}  {:>8}  {:>16}",
+                    block.height,
+                    block.hash,
+                    block.size,
+                    block.tx_count,
+                    fees(block.fees)
+                );
+            }
+            if leaderboard.by != Ranking::TxCount {
+                println!("\n{}\n", title("transactions"));
+                println!(
+                    "{}",
+                    format!(
+                        "{:>8}  {:<64}  {:>8}  {:>16}",
+                        "Height", "Txid", "Size", "Fee"
+                    )
+                    .green()
+                );
+                for tx in &leaderboard.transactions {
+                    println!(
+                        "{:>8}  {:<64}  {:>8}  {:>16}",
+                        tx.height,
+                        tx.txid,
+                        tx.size,
+                        fees(tx.fee)
+                    );
+                }
+            }
+        }
+        Format::Csv | Format::Hex | Format::Ndjson => return Err(format.unsupported()),
+        Format::Json => println!("{}", serde_json::to_string_pretty(leaderboard)?),
+    }
+    Ok(())
+}
+
+/// Print the headers with suspicious timestamps found between heights `from` and `to`.
+pub fn log_header_anomalies(
+    anomalies: &[HeaderAnomaly],
+    from: u64,
+    to: u64,
+    format: Format,
+) -> Result<()> {
+    match format {
+        Format::Text => {
+            println!(
+                "{}\n",
+                format!("> Timestamp anomalies between heights {from} and {to}").green()
+            );
+            for anomaly in anomalies {
+                println!(
+                    "Block Hash            : {}\nHeight                : {}\nAnomaly               : {}\n",
+                    to_display_hex(&anomaly.hash),
+                    anomaly.height,
+                    anomaly
+                );
+            }
+            println!("Total                 : {} anomalies", anomalies.len());
+        }
+        Format::Csv | Format::Hex | Format::Ndjson => return Err(format.unsupported()),
+        Format::Json => println!("{}", serde_json::to_string_pretty(anomalies)?),
+    }
+    Ok(())
+}
+
+/// Print the unusual outputs found between heights `from` and `to`, with a count of each kind.
+pub fn log_output_anomalies(
+    anomalies: &[OutputAnomaly],
+    from: u64,
+    to: u64,
+    format: Format,
+) -> Result<()> {
+    match format {
+        Format::Text => {
+            println!(
+                "{}\n",
+                format!("> Output anomalies between heights {from} and {to}").green()
+            );
+            for anomaly in anomalies {
+                println!(
+                    "TXID                  : {}\nOutput                : {}\nHeight                : {}\nAnomaly               : {}\n",
+                    to_display_hex(&anomaly.txid),
+                    anomaly.vout,
+                    anomaly.height,
+                    anomaly
+                );
+            }
+            let count = |is_kind: fn(&OutputAnomalyKind) -> bool| {
+                anomalies
+                    .iter()
+                    .filter(|anomaly| is_kind(&anomaly.kind))
+                    .count()
+            };
+            println!(
+                "Total                 : {} anomalies, {} dust, {} nonstandard, {} valued OP_RETURN",
+                anomalies.len(),
+                count(|kind| matches!(kind, OutputAnomalyKind::Dust { .. })),
+                count(|kind| *kind == OutputAnomalyKind::NonStandard),
+                count(|kind| *kind == OutputAnomalyKind::ValuedOpReturn),
+            );
+        }
+        Format::Csv | Format::Hex | Format::Ndjson => return Err(format.unsupported()),
+        Format::Json => println!("{}", serde_json::to_string_pretty(anomalies)?),
+    }
+    Ok(())
+}
+
+/// Print the blocks found off the best chain, with where each one is stored.
+pub fn log_stale_blocks(stale: &[StaleBlock], format: Format) -> Result<()> {
+    match format {
+        Format::Text => {
+            println!("{}\n", "> Blocks off the best chain".green());
+            for block in stale {
+                println!(
+                    "Block Hash            : {}\nHeight                : {}\nFile                  : {}\nOffset                : {}\n",
+                    to_display_hex(&block.hash),
+                    block.height.map_or("unknown, its parent isn't in the blk files".to_string(), |height| height.to_string()),
+                    block.location.file,
+                    block.location.offset,
+                );
+            }
+            println!("Total                 : {} blocks", stale.len());
+        }
+        Format::Csv | Format::Hex | Format::Ndjson => return Err(format.unsupported()),
+        Format::Json => println!("{}", serde_json::to_string_pretty(stale)?),
+    }
+    Ok(())
+}
+
+/// Print what checking the blk files found, each corrupt region with its file and offsets.
+pub fn log_verify_report(report: &VerifyReport, format: Format) -> Result<()> {
+    match format {
+        Format::Text => {
+            println!("{}\n", "> Blk file integrity".green());
+            for region in &report.corrupt {
+                println!(
+                    "File                  : {}\nOffsets               : {} to {} ({} bytes)\nProblem               : {}\n",
+                    region.file.display(),
+                    region.start,
+                    region.end,
+                    region.end - region.start,
+                    region.problem,
+                );
+            }
+            println!(
+                "Files                 : {}\nBytes                 : {}\nIntact Records        : {}\nCorrupt Regions       : {}",
+                report.files,
+                report.bytes,
+                report.records,
+                report.corrupt.len()
+            );
+        }
+        Format::Csv | Format::Hex | Format::Ndjson => return Err(format.unsupported()),
+        Format::Json => println!("{}", serde_json::to_string_pretty(report)?),
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{
+        fixtures::{block, GENESIS_BLOCK_HEX},
+        BlockSummary,
+    };
+
+    /// Checks the csv row of the genesis block against its known header fields.
+    #[test]
+    fn test_block_summary_csv() {
+        let block = block(GENESIS_BLOCK_HEX);
+        let summary = BlockSummary::new(0, &block);
+        assert_eq!(
+            BlockSummary::CSV_HEADER.split(',').count(),
+            summary.to_csv().split(',').count()
+        );
+        assert_eq!(
+            summary.to_csv(),
+            "0,000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f,1231006505,1,1d00ffff,2083236893,1,285"
+        );
+    }
 }
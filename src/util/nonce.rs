@@ -0,0 +1,150 @@
+//! The header nonces and coinbase extraNonces of a range of blocks, as reported by the nonces
+//! subcommand for studying how early miners searched for blocks.
+use crate::{
+    util::{coinbase::Coinbase, hash::to_display_hex, stats::Distribution},
+    Block,
+};
+use serde::Serialize;
+
+/// How many equal ranges the nonce space is split into for [NonceStats::nonce_ranges].
+pub const NONCE_RANGES: usize = 16;
+
+/// The nonces of a single block.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct NonceSample {
+    pub height: u64,
+    pub hash: String,
+    pub nonce: u32,
+    /// The extraNonce of the coinbase, when it has one the original client's way.
+    pub extra_nonce: Option<u64>,
+}
+impl NonceSample {
+    /// The column names, in the order [NonceSample::to_csv] writes them.
+    pub const CSV_HEADER: &'static str = "height,hash,nonce,extra_nonce";
+
+    pub fn new(height: u64, block: &Block) -> Self {
+        Self {
+            height,
+            hash: to_display_hex(&block.block_header().hash()),
+            nonce: block.block_header().nonce(),
+            extra_nonce: Coinbase::from_block(block).and_then(|coinbase| coinbase.extra_nonce()),
+        }
+    }
+    /// The sample as a CSV row, with an empty extra_nonce when there isn't one.
+    pub fn to_csv(&self) -> String {
+        format!(
+            "{},{},{},{}",
+            self.height,
+            self.hash,
+            self.nonce,
+            self.extra_nonce
+                .map_or(String::new(), |extra_nonce| extra_nonce.to_string())
+        )
+    }
+}
+
+/// How the nonces of the blocks from height `from` to `to` are distributed.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct NonceStats {
+    pub from: u64,
+    pub to: u64,
+    pub block_count: u64,
+    pub nonce: Distribution,
+    /// The blocks whose nonce falls in each of [NONCE_RANGES] equal ranges, lowest first.
+    pub nonce_ranges: Vec<u64>,
+    /// The blocks whose nonce has each value of its least significant byte, which is
+    /// where the Patoshi pattern shows up as a few favoured ranges.
+    pub nonce_low_byte: Vec<u64>,
+    /// The extraNonces of the blocks that have one, `None` when none do.
+    pub extra_nonce: Option<Distribution>,
+    pub extra_nonce_blocks: u64,
+    /// How often a block's extraNonce was lower than the last one seen, as when a
+    /// miner restarts and counts up from the beginning again.
+    pub extra_nonce_resets: u64,
+}
+
+/// Collects the nonces [NonceStats] are worked out from, one block at a time in height order.
+#[derive(Debug, Default)]
+pub struct NonceCollector {
+    nonces: Vec<u64>,
+    extra_nonces: Vec<u64>,
+    extra_nonce_resets: u64,
+}
+impl NonceCollector {
+    pub fn add(&mut self, sample: &NonceSample) {
+        self.nonces.push(u64::from(sample.nonce));
+        if let Some(extra_nonce) = sample.extra_nonce {
+            if self
+                .extra_nonces
+                .last()
+                .is_some_and(|last| extra_nonce < *last)
+            {
+                self.extra_nonce_resets += 1;
+            }
+            self.extra_nonces.push(extra_nonce);
+        }
+    }
+    /// The distributions of the nonces added, labelled as the range `from` to `to`,
+    /// or `None` when none were.
+    pub fn finish(self, from: u64, to: u64) -> Option<NonceStats> {
+        let mut nonce_ranges = vec![0; NONCE_RANGES];
+        let mut nonce_low_byte = vec![0; 256];
+        for nonce in &self.nonces {
+            nonce_ranges[((nonce * NONCE_RANGES as u64) >> 32) as usize] += 1;
+            nonce_low_byte[(nonce & 0xff) as usize] += 1;
+        }
+        Some(NonceStats {
+            from,
+            to,
+            block_count: self.nonces.len() as u64,
+            extra_nonce_blocks: self.extra_nonces.len() as u64,
+            extra_nonce: Distribution::new(self.extra_nonces),
+            nonce: Distribution::new(self.nonces)?,
+            nonce_ranges,
+            nonce_low_byte,
+            extra_nonce_resets: self.extra_nonce_resets,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{NonceCollector, NonceSample};
+    use crate::util::fixtures::{block, BLOCK_1_HEX, GENESIS_BLOCK_HEX};
+
+    /// Checks the nonces of the first two blocks and a reset of the extraNonce.
+    #[test]
+    fn test_nonce_stats() {
+        let mut collector = NonceCollector::default();
+        let mut samples = Vec::new();
+        for (height, block_hex) in [GENESIS_BLOCK_HEX, BLOCK_1_HEX].into_iter().enumerate() {
+            let block = block(block_hex);
+            samples.push(NonceSample::new(height as u64, &block));
+        }
+        assert_eq!(samples[0].nonce, 2_083_236_893);
+        assert_eq!(samples[0].extra_nonce, Some(4));
+        assert_eq!(
+            samples[1].to_csv(),
+            "1,00000000839a8e6886ab5951d76f411475428afc90947ee320161bbf18eb6048,2573394689,4"
+        );
+        samples.push(NonceSample {
+            height: 2,
+            extra_nonce: Some(1),
+            ..samples[1].clone()
+        });
+        samples.iter().for_each(|sample| collector.add(sample));
+        let stats = collector.finish(0, 2).unwrap();
+
+        assert_eq!(stats.block_count, 3);
+        assert_eq!(stats.nonce.min, 2_083_236_893);
+        assert_eq!(stats.nonce_ranges[7], 1);
+        assert_eq!(stats.nonce_ranges[9], 2);
+        assert_eq!(stats.nonce_low_byte[0x1d], 1);
+        assert_eq!(stats.nonce_low_byte[0x01], 2);
+        assert_eq!(stats.extra_nonce_blocks, 3);
+        assert_eq!(stats.extra_nonce.unwrap().max, 4);
+        assert_eq!(stats.extra_nonce_resets, 1);
+
+        assert_eq!(NonceCollector::default().finish(0, 0), None);
+    }
+}
@@ -0,0 +1,309 @@
+//! Classifying output scripts by their standard templates and encoding them as addresses.
+use crate::{
+    util::{address, asm::disassemble, types::CompactSize},
+    Network,
+};
+
+pub(crate) const OP_0: u8 = 0x00;
+pub(crate) const OP_PUSHBYTES_20: u8 = 0x14;
+const OP_PUSHBYTES_33: u8 = 0x21;
+const OP_PUSHBYTES_65: u8 = 0x41;
+const OP_RETURN: u8 = 0x6a;
+pub(crate) const OP_1: u8 = 0x51;
+const OP_16: u8 = 0x60;
+pub(crate) const OP_DUP: u8 = 0x76;
+pub(crate) const OP_EQUAL: u8 = 0x87;
+pub(crate) const OP_EQUALVERIFY: u8 = 0x88;
+pub(crate) const OP_HASH160: u8 = 0xa9;
+pub(crate) const OP_CHECKSIG: u8 = 0xac;
+const OP_CHECKMULTISIG: u8 = 0xae;
+
+/// The fee rate below which an output costs more to spend than it holds, Bitcoin Core's
+/// default -dustrelayfee, in satoshis per 1000 virtual bytes.
+pub const DUST_RELAY_FEE: u64 = 3000;
+/// The longest script that can ever be spent.
+const MAX_SCRIPT_SIZE: usize = 10_000;
+/// The most keys a bare multisig output can have and still be relayed.
+const MAX_BARE_MULTISIG_KEYS: u8 = 3;
+
+/// What a witness commitment output script starts with (BIP141): `OP_RETURN`, a 36 byte push
+/// and the commitment header `aa21a9ed`, ahead of the 32 byte commitment.
+const WITNESS_COMMITMENT_PREFIX: [u8; 6] = [OP_RETURN, 0x24, 0xaa, 0x21, 0xa9, 0xed];
+
+/// The standard output templates.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ScriptType {
+    /// Pay to a bare public key, as in the earliest coinbases.
+    P2pk,
+    P2pkh,
+    P2sh,
+    P2wpkh,
+    P2wsh,
+    P2tr,
+    /// A witness program of a version or length without its own template yet.
+    WitnessUnknown,
+    /// A provably unspendable data carrier.
+    OpReturn,
+    NonStandard,
+}
+impl ScriptType {
+    pub fn name(&self) -> &'static str {
+        match self {
+            ScriptType::P2pk => "p2pk",
+            ScriptType::P2pkh => "p2pkh",
+            ScriptType::P2sh => "p2sh",
+            ScriptType::P2wpkh => "p2wpkh",
+            ScriptType::P2wsh => "p2wsh",
+            ScriptType::P2tr => "p2tr",
+            ScriptType::WitnessUnknown => "witness_unknown",
+            ScriptType::OpReturn => "op_return",
+            ScriptType::NonStandard => "nonstandard",
+        }
+    }
+}
+impl std::fmt::Display for ScriptType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// An output script, the scriptPubKey that locks an output's value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Script<'a> {
+    bytes: &'a [u8],
+}
+impl<'a> Script<'a> {
+    pub fn new(bytes: &'a [u8]) -> Self {
+        Self { bytes }
+    }
+    pub fn bytes(&self) -> &'a [u8] {
+        self.bytes
+    }
+    /// The script as opcode names and pushes in hex.
+    pub fn asm(&self) -> String {
+        disassemble(self.bytes)
+    }
+    /// The standard template the script matches.
+    pub fn script_type(&self) -> ScriptType {
+        match self.bytes {
+            [OP_PUSHBYTES_33, _pubkey @ .., OP_CHECKSIG] if self.bytes.len() == 35 => {
+                ScriptType::P2pk
+            }
+            [OP_PUSHBYTES_65, _pubkey @ .., OP_CHECKSIG] if self.bytes.len() == 67 => {
+                ScriptType::P2pk
+            }
+            [OP_DUP, OP_HASH160, OP_PUSHBYTES_20, _hash @ .., OP_EQUALVERIFY, OP_CHECKSIG]
+                if self.bytes.len() == 25 =>
+            {
+                ScriptType::P2pkh
+            }
+            [OP_HASH160, OP_PUSHBYTES_20, _hash @ .., OP_EQUAL] if self.bytes.len() == 23 => {
+                ScriptType::P2sh
+            }
+            [OP_RETURN, ..] => ScriptType::OpReturn,
+            _ => match self.witness_program() {
+                Some((0, program)) if program.len() == 20 => ScriptType::P2wpkh,
+                Some((0, program)) if program.len() == 32 => ScriptType::P2wsh,
+                Some((1, program)) if program.len() == 32 => ScriptType::P2tr,
+                Some((1.., _)) => ScriptType::WitnessUnknown,
+                _ => ScriptType::NonStandard,
+            },
+        }
+    }
+    /// The witness version and program, if the script is a version byte followed
+    /// by a single push of 2 to 40 bytes.
+    pub fn witness_program(&self) -> Option<(u8, &'a [u8])> {
+        let (&version, rest) = self.bytes.split_first()?;
+        let version = match version {
+            OP_0 => 0,
+            OP_1..=OP_16 => version - OP_1 + 1,
+            _ => return None,
+        };
+        let (&len, program) = rest.split_first()?;
+        (program.len() == len as usize && (2..=40).contains(&program.len()))
+            .then_some((version, program))
+    }
+    /// The x-only output key of a pay to taproot script.
+    pub fn taproot_output_key(&self) -> Option<&'a [u8]> {
+        match self.script_type() {
+            ScriptType::P2tr => self.witness_program().map(|(_, program)| program),
+            _ => None,
+        }
+    }
+    /// The commitment to a block's witness data, if this is a coinbase output carrying one.
+    pub fn witness_commitment(&self) -> Option<[u8; 32]> {
+        self.bytes
+            .strip_prefix(WITNESS_COMMITMENT_PREFIX.as_slice())?
+            .get(..32)?
+            .try_into()
+            .ok()
+    }
+    /// Whether no input can ever spend the script, for starting with `OP_RETURN` or being
+    /// too long to run.
+    pub fn is_unspendable(&self) -> bool {
+        self.bytes.first() == Some(&OP_RETURN) || self.bytes.len() > MAX_SCRIPT_SIZE
+    }
+    /// Whether the script is a bare multisig Bitcoin Core relays: `OP_m`, `n` public keys,
+    /// `OP_n` and `OP_CHECKMULTISIG`, with at most three keys.
+    pub fn is_bare_multisig(&self) -> bool {
+        let [required @ OP_1..=OP_16, keys @ .., total @ OP_1..=OP_16, OP_CHECKMULTISIG] =
+            self.bytes
+        else {
+            return false;
+        };
+        let (required, total) = (required - OP_1 + 1, total - OP_1 + 1);
+        let mut rest = keys;
+        let mut count = 0;
+        while let Some((&len, after)) = rest.split_first() {
+            if !matches!(len, OP_PUSHBYTES_33 | OP_PUSHBYTES_65) || after.len() < len as usize {
+                return false;
+            }
+            rest = &after[len as usize..];
+            count += 1;
+        }
+        required <= total && total == count && total <= MAX_BARE_MULTISIG_KEYS
+    }
+    /// The least an output locked by the script can hold without being dust at
+    /// [DUST_RELAY_FEE], the fee to create it and later spend it as Bitcoin Core reckons
+    /// them, or zero for an unspendable script, which can't be dust.
+    pub fn dust_threshold(&self) -> u64 {
+        if self.is_unspendable() {
+            return 0;
+        }
+        let len = self.bytes.len() as u64;
+        let output_size = 8 + u64::from(CompactSize::encoded_len(len)) + len;
+        // An outpoint, an empty scriptSig's length, a sequence and a 107 byte signature and
+        // key, paid for at a quarter of the size when they're a witness.
+        let input_size = match self.witness_program() {
+            Some(_) => 32 + 4 + 1 + 107 / 4 + 4,
+            None => 32 + 4 + 1 + 107 + 4,
+        };
+        (output_size + input_size) * DUST_RELAY_FEE / 1000
+    }
+    /// The address the script pays to on `network`, for templates that have one.
+    pub fn address(&self, network: Network) -> Option<String> {
+        match self.script_type() {
+            ScriptType::P2pkh => Some(address::p2pkh(
+                self.bytes[3..23]
+                    .try_into()
+                    .expect("expected a 20 byte hash"),
+                network,
+            )),
+            ScriptType::P2sh => Some(address::p2sh(
+                self.bytes[2..22]
+                    .try_into()
+                    .expect("expected a 20 byte hash"),
+                network,
+            )),
+            ScriptType::P2wpkh
+            | ScriptType::P2wsh
+            | ScriptType::P2tr
+            | ScriptType::WitnessUnknown => {
+                let (version, program) = self.witness_program()?;
+                address::segwit(version, program, network)
+            }
+            ScriptType::P2pk | ScriptType::OpReturn | ScriptType::NonStandard => None,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Script, ScriptType};
+    use crate::{util::address::to_script_pubkey, Network};
+
+    /// Checks each template against a known address.
+    #[test]
+    fn test_script_address() {
+        for (script, script_type, address) in [
+            (
+                "76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac",
+                ScriptType::P2pkh,
+                Some("1A1zP1eP5QGefi2DMPTfTL5SLmv7DivfNa"),
+            ),
+            (
+                "a914b472a266d0bd89c13706a4132ccfb16f7c3b9fcb87",
+                ScriptType::P2sh,
+                Some("3J98t1WpEZ73CNmQviecrnyiWrnqRhWNLy"),
+            ),
+            (
+                "0014751e76e8199196d454941c45d1b3a323f1433bd6",
+                ScriptType::P2wpkh,
+                Some("bc1qw508d6qejxtdg4y5r3zarvary0c5xw7kv8f3t4"),
+            ),
+            (
+                "00201863143c14c5166804bd19203356da136c985678cd4d27a1b8c6329604903262",
+                ScriptType::P2wsh,
+                Some("bc1qrp33g0q5c5txsp9arysrx4k6zdkfs4nce4xj0gdcccefvpysxf3qccfmv3"),
+            ),
+            (
+                "5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c",
+                ScriptType::P2tr,
+                Some("bc1p5cyxnuxmeuwuvkwfem96lqzszd02n6xdcjrs20cac6yqjjwudpxqkedrcr"),
+            ),
+            ("6a0b68656c6c6f20776f726c64", ScriptType::OpReturn, None),
+            ("51", ScriptType::NonStandard, None),
+        ] {
+            let bytes = hex::decode(script).unwrap();
+            let script = Script::new(&bytes);
+            assert_eq!(script.script_type(), script_type);
+            assert_eq!(script.address(Network::Mainnet).as_deref(), address);
+            assert_eq!(
+                script.taproot_output_key().is_some(),
+                script_type == ScriptType::P2tr
+            );
+            if let Some(address) = address {
+                assert_eq!(to_script_pubkey(address).unwrap(), bytes);
+            }
+        }
+    }
+
+    /// Checks the dust thresholds Bitcoin Core gives the common templates, and which bare
+    /// multisig scripts count as standard.
+    #[test]
+    fn test_dust_threshold() {
+        for (script, threshold) in [
+            ("76a91462e907b15cbf27d5425399ebf6f0fb50ebb88f1888ac", 546),
+            ("0014751e76e8199196d454941c45d1b3a323f1433bd6", 294),
+            (
+                "5120a60869f0dbcf1dc659c9cecbaf8050135ea9e8cdc487053f1dc6880949dc684c",
+                330,
+            ),
+            ("6a0b68656c6c6f20776f726c64", 0),
+        ] {
+            let bytes = hex::decode(script).unwrap();
+            assert_eq!(Script::new(&bytes).dust_threshold(), threshold);
+        }
+
+        let key = [0x02; 33];
+        let multisig = |required: u8, keys: usize| {
+            let pushes = (0..keys).flat_map(|_| [&[0x21], key.as_slice()].concat());
+            [
+                vec![0x50 + required],
+                pushes.collect(),
+                vec![0x50 + keys as u8, 0xae],
+            ]
+            .concat()
+        };
+        assert!(Script::new(&multisig(1, 1)).is_bare_multisig());
+        assert!(Script::new(&multisig(2, 3)).is_bare_multisig());
+        assert!(!Script::new(&multisig(3, 2)).is_bare_multisig());
+        assert!(!Script::new(&multisig(1, 4)).is_bare_multisig());
+        assert!(!Script::new(&multisig(1, 1)[..30]).is_bare_multisig());
+    }
+
+    /// Checks that testnet and regtest addresses use their own prefixes.
+    #[test]
+    fn test_script_address_network() {
+        let bytes = hex::decode("0014751e76e8199196d454941c45d1b3a323f1433bd6").unwrap();
+        let script = Script::new(&bytes);
+        assert_eq!(
+            script.address(Network::Testnet3).unwrap(),
+            "tb1qw508d6qejxtdg4y5r3zarvary0c5xw7kxpjzsx"
+        );
+        assert!(script
+            .address(Network::Regtest)
+            .unwrap()
+            .starts_with("bcrt1q"));
+    }
+}
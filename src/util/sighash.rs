@@ -0,0 +1,599 @@
+//! The message digests an input's signatures commit to, the sighashes, computed the
+//! original way, the BIP143 way for segwit v0 inputs and the BIP341 way for taproot inputs.
+//!
+//! None of these know which output an input spends, so the caller passes what they need
+//! of it: the script code and value, or for taproot every output spent by the transaction.
+use crate::{
+    util::{
+        asm::{instructions, Instruction},
+        hash::{sha256, sha256d, tagged_hash},
+        taproot::TAPSCRIPT_LEAF_VERSION,
+        tx::TxOut,
+        types::CompactSize,
+    },
+    Transaction,
+};
+use anyhow::Result;
+
+/// Treated as [SIGHASH_ALL], but only taproot signatures may use it, by leaving the sighash
+/// type byte off.
+pub const SIGHASH_DEFAULT: u8 = 0x00;
+/// Commit to every input and output.
+pub const SIGHASH_ALL: u8 = 0x01;
+/// Commit to every input and no outputs.
+pub const SIGHASH_NONE: u8 = 0x02;
+/// Commit to every input and the output at the same index as the one being signed.
+pub const SIGHASH_SINGLE: u8 = 0x03;
+/// Combined with one of the others, commit to only the input being signed.
+pub const SIGHASH_ANYONECANPAY: u8 = 0x80;
+
+const OP_CODESEPARATOR: u8 = 0xab;
+/// What a legacy SIGHASH_SINGLE sighash is when there's no output at the input's index,
+/// a hash of 1 that Bitcoin Core has to keep accepting signatures for.
+const SIGHASH_SINGLE_BUG: [u8; 32] = {
+    let mut one = [0; 32];
+    one[0] = 1;
+    one
+};
+/// The epoch byte a BIP341 signature message starts with, leaving room for new versions.
+const TAPROOT_EPOCH: u8 = 0x00;
+/// The key version BIP342 signatures commit to on the script path.
+const TAPSCRIPT_KEY_VERSION: u8 = 0x00;
+/// The last OP_CODESEPARATOR position a tapscript signature commits to when none was run.
+const NO_CODESEPARATOR: u32 = u32::MAX;
+
+/// The tapscript leaf a script path signature is for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct TapLeaf<'a> {
+    pub script: &'a [u8],
+    pub leaf_version: u8,
+}
+impl<'a> TapLeaf<'a> {
+    /// A leaf of tapscript, the only leaf version defined so far.
+    pub fn tapscript(script: &'a [u8]) -> Self {
+        Self {
+            script,
+            leaf_version: TAPSCRIPT_LEAF_VERSION,
+        }
+    }
+    /// The hash committing to the leaf in the script tree (BIP341).
+    pub fn hash(&self) -> [u8; 32] {
+        tagged_hash(
+            "TapLeaf",
+            &[
+                [self.leaf_version].as_slice(),
+                &CompactSize::encode(self.script.len() as u64),
+                self.script,
+            ]
+            .concat(),
+        )
+    }
+}
+
+/// The script code a P2WPKH input's signature commits to, the P2PKH script of its key hash.
+pub fn p2wpkh_script_code(pubkey_hash: &[u8; 20]) -> Vec<u8> {
+    [[0x76, 0xa9, 0x14].as_slice(), pubkey_hash, &[0x88, 0xac]].concat()
+}
+
+impl Transaction {
+    /// The sighash of a pre-segwit signature for input `index`, with `script_code` the script
+    /// of the output it spends, or the redeem script for P2SH.
+    ///
+    /// Any OP_CODESEPARATOR is removed from the script code, but it's left to the caller to
+    /// remove the signature itself, as signing never needs to.
+    pub fn legacy_sighash(
+        &self,
+        index: usize,
+        script_code: &[u8],
+        sighash_type: u32,
+    ) -> Result<[u8; 32]> {
+        self.check_input(index)?;
+        let base_type = (sighash_type & 0x1f) as u8;
+        let anyone_can_pay = sighash_type & u32::from(SIGHASH_ANYONECANPAY) != 0;
+        if base_type == SIGHASH_SINGLE && index >= self.outputs().len() {
+            return Ok(SIGHASH_SINGLE_BUG);
+        }
+        let script_code = without_codeseparators(script_code);
+
+        let mut preimage = self.version().to_le_bytes().to_vec();
+        let inputs = self
+            .inputs()
+            .iter()
+            .enumerate()
+            .filter(|(other, _)| !anyone_can_pay || *other == index)
+            .collect::<Vec<_>>();
+        preimage.extend(CompactSize::encode(inputs.len() as u64));
+        for (other, input) in inputs {
+            preimage.extend(input.previous_output().raw_bytes());
+            match other == index {
+                true => {
+                    preimage.extend(CompactSize::encode(script_code.len() as u64));
+                    preimage.extend(&script_code);
+                }
+                false => preimage.extend(CompactSize::encode(0)),
+            }
+            let sequence =
+                match other != index && matches!(base_type, SIGHASH_NONE | SIGHASH_SINGLE) {
+                    true => 0,
+                    false => input.sequence(),
+                };
+            preimage.extend(sequence.to_le_bytes());
+        }
+        match base_type {
+            SIGHASH_NONE => preimage.extend(CompactSize::encode(0)),
+            SIGHASH_SINGLE => {
+                preimage.extend(CompactSize::encode(index as u64 + 1));
+                for _ in 0..index {
+                    // A blank output: a value of -1 and an empty script.
+                    preimage.extend(u64::MAX.to_le_bytes());
+                    preimage.extend(CompactSize::encode(0));
+                }
+                preimage.extend(self.outputs()[index].raw_bytes());
+            }
+            _ => {
+                preimage.extend(CompactSize::encode(self.outputs().len() as u64));
+                for output in self.outputs() {
+                    preimage.extend(output.raw_bytes());
+                }
+            }
+        }
+        preimage.extend(self.lock_time().to_le_bytes());
+        preimage.extend(sighash_type.to_le_bytes());
+        Ok(sha256d(&preimage))
+    }
+
+    /// The sighash of a segwit v0 signature for input `index` (BIP143), spending `value`
+    /// satoshis with `script_code`: the witness script for P2WSH, or for P2WPKH the script
+    /// from [p2wpkh_script_code].
+    pub fn segwit_v0_sighash(
+        &self,
+        index: usize,
+        script_code: &[u8],
+        value: u64,
+        sighash_type: u32,
+    ) -> Result<[u8; 32]> {
+        self.check_input(index)?;
+        let base_type = (sighash_type & 0x1f) as u8;
+        let anyone_can_pay = sighash_type & u32::from(SIGHASH_ANYONECANPAY) != 0;
+        let input = &self.inputs()[index];
+
+        let hash_prevouts = match anyone_can_pay {
+            true => [0; 32],
+            false => sha256d(&self.prevouts_raw_bytes()),
+        };
+        let hash_sequence =
+            match anyone_can_pay || matches!(base_type, SIGHASH_NONE | SIGHASH_SINGLE) {
+                true => [0; 32],
+                false => sha256d(&self.sequences_raw_bytes()),
+            };
+        let hash_outputs = match base_type {
+            SIGHASH_NONE => [0; 32],
+            SIGHASH_SINGLE => self
+                .outputs()
+                .get(index)
+                .map_or([0; 32], |output| sha256d(&output.raw_bytes())),
+            _ => sha256d(&self.outputs_raw_bytes()),
+        };
+        let preimage = [
+            self.version().to_le_bytes().as_slice(),
+            &hash_prevouts,
+            &hash_sequence,
+            &input.previous_output().raw_bytes(),
+            &CompactSize::encode(script_code.len() as u64),
+            script_code,
+            &value.to_le_bytes(),
+            &input.sequence().to_le_bytes(),
+            &hash_outputs,
+            &self.lock_time().to_le_bytes(),
+            &sighash_type.to_le_bytes(),
+        ]
+        .concat();
+        Ok(sha256d(&preimage))
+    }
+
+    /// The sighash of a taproot signature for input `index` (BIP341), given `prevouts`, the
+    /// outputs every input spends in order. A key path signature has no `leaf`, while a
+    /// script path one is for the tapscript `leaf`, as if no OP_CODESEPARATOR had been run.
+    pub fn taproot_sighash(
+        &self,
+        index: usize,
+        prevouts: &[TxOut],
+        sighash_type: u8,
+        leaf: Option<TapLeaf>,
+        annex: Option<&[u8]>,
+    ) -> Result<[u8; 32]> {
+        self.check_input(index)?;
+        if !matches!(sighash_type, 0x00..=0x03 | 0x81..=0x83) {
+            anyhow::bail!("invalid taproot sighash type {sighash_type:#04x}")
+        }
+        if prevouts.len() != self.inputs().len() {
+            anyhow::bail!(
+                "expected the {} outputs spent by the transaction, found {}",
+                self.inputs().len(),
+                prevouts.len()
+            )
+        }
+        let base_type = sighash_type & 0x03;
+        let anyone_can_pay = sighash_type & SIGHASH_ANYONECANPAY != 0;
+        let input = &self.inputs()[index];
+
+        let mut message = vec![TAPROOT_EPOCH, sighash_type];
+        message.extend(self.version().to_le_bytes());
+        message.extend(self.lock_time().to_le_bytes());
+        if !anyone_can_pay {
+            message.extend(sha256(&self.prevouts_raw_bytes()));
+            message.extend(sha256(
+                &prevouts
+                    .iter()
+                    .flat_map(|prevout| prevout.value().to_le_bytes())
+                    .collect::<Vec<u8>>(),
+            ));
+            message.extend(sha256(
+                &prevouts
+                    .iter()
+                    .flat_map(|prevout| script_raw_bytes(prevout.script_pubkey_bytes()))
+                    .collect::<Vec<u8>>(),
+            ));
+            message.extend(sha256(&self.sequences_raw_bytes()));
+        }
+        if !matches!(base_type, SIGHASH_NONE | SIGHASH_SINGLE) {
+            message.extend(sha256(&self.outputs_raw_bytes()));
+        }
+        let spend_type = u8::from(leaf.is_some()) * 2 + u8::from(annex.is_some());
+        message.push(spend_type);
+        match anyone_can_pay {
+            true => {
+                message.extend(input.previous_output().raw_bytes());
+                message.extend(prevouts[index].value().to_le_bytes());
+                message.extend(script_raw_bytes(prevouts[index].script_pubkey_bytes()));
+                message.extend(input.sequence().to_le_bytes());
+            }
+            false => message.extend((index as u32).to_le_bytes()),
+        }
+        if let Some(annex) = annex {
+            message.extend(sha256(&script_raw_bytes(annex)));
+        }
+        if base_type == SIGHASH_SINGLE {
+            let output = self.outputs().get(index).ok_or_else(|| {
+                anyhow::anyhow!(
+                    "SIGHASH_SINGLE signature for input {index} has no output to commit to"
+                )
+            })?;
+            message.extend(sha256(&output.raw_bytes()));
+        }
+        if let Some(leaf) = leaf {
+            message.extend(leaf.hash());
+            message.push(TAPSCRIPT_KEY_VERSION);
+            message.extend(NO_CODESEPARATOR.to_le_bytes());
+        }
+        Ok(tagged_hash("TapSighash", &message))
+    }
+
+    fn check_input(&self, index: usize) -> Result<()> {
+        if index >= self.inputs().len() {
+            anyhow::bail!(
+                "input {index} is out of range, the transaction has {} inputs",
+                self.inputs().len()
+            )
+        }
+        Ok(())
+    }
+    fn prevouts_raw_bytes(&self) -> Vec<u8> {
+        self.inputs()
+            .iter()
+            .flat_map(|input| input.previous_output().raw_bytes())
+            .collect()
+    }
+    fn sequences_raw_bytes(&self) -> Vec<u8> {
+        self.inputs()
+            .iter()
+            .flat_map(|input| input.sequence().to_le_bytes())
+            .collect()
+    }
+    fn outputs_raw_bytes(&self) -> Vec<u8> {
+        self.outputs().iter().flat_map(TxOut::raw_bytes).collect()
+    }
+}
+
+/// A script prefixed with its length, as it's serialized in a transaction.
+fn script_raw_bytes(script: &[u8]) -> Vec<u8> {
+    [CompactSize::encode(script.len() as u64).as_slice(), script].concat()
+}
+
+/// `script` with every OP_CODESEPARATOR taken out, as legacy signatures don't commit to them.
+fn without_codeseparators(script: &[u8]) -> Vec<u8> {
+    let mut kept = Vec::with_capacity(script.len());
+    let mut rest = script;
+    for instruction in instructions(script) {
+        let len = match instruction {
+            Instruction::Op(_) => 1,
+            // The push opcode and any length bytes before the data.
+            Instruction::Push(data) => 1 + push_len_bytes(rest[0]) + data.len(),
+            Instruction::Truncated(_) => rest.len(),
+        };
+        if instruction != Instruction::Op(OP_CODESEPARATOR) {
+            kept.extend(&rest[..len]);
+        }
+        rest = &rest[len..];
+    }
+    kept
+}
+
+/// How many length bytes follow a push opcode.
+fn push_len_bytes(opcode: u8) -> usize {
+    match opcode {
+        0x4c => 1,
+        0x4d => 2,
+        0x4e => 4,
+        _ => 0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{p2wpkh_script_code, TapLeaf, SIGHASH_ALL, SIGHASH_ANYONECANPAY, SIGHASH_SINGLE};
+    use crate::{
+        util::{
+            hash::to_display_hex,
+            tx::TxOut,
+            types::{ByteCursor, CompactSize},
+        },
+        Transaction,
+    };
+
+    /// The unsigned transaction of the native P2WPKH example in BIP143.
+    const BIP143_TX_HEX: &str = "0100000002fff7f7881a8099afa6940d42d1e7f6362bec38171ea3edf433541db4e4ad969f0000000000eeffffffef51e1b804cc89d182d279655c3aa89e815b1b309fe287d9b2b55d57b90ec68a0100000000ffffffff02202cb206000000001976a9148280b37df378db99f66f85c95a783a76ac7a6d5988ac9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac11000000";
+
+    /// The unsigned transaction of the key path spending example in BIP341's test vectors.
+    const BIP341_TX_HEX: &str = "02000000097de20cbff686da83a54981d2b9bab3586f4ca7e48f57f5b55963115f3b334e9c010000000000000000d7b7cab57b1393ace2d064f4d4a2cb8af6def61273e127517d44759b6dafdd990000000000fffffffff8e1f583384333689228c5d28eac13366be082dc57441760d957275419a418420000000000fffffffff0689180aa63b30cb162a73c6d2a38b7eeda2a83ece74310fda0843ad604853b0100000000feffffffaa5202bdf6d8ccd2ee0f0202afbbb7461d9264a25e5bfd3c5a52ee1239e0ba6c0000000000feffffff956149bdc66faa968eb2be2d2faa29718acbfe3941215893a2a3446d32acd050000000000000000000e664b9773b88c09c32cb70a2a3e4da0ced63b7ba3b22f848531bbb1d5d5f4c94010000000000000000e9aa6b8e6c9de67619e6a3924ae25696bb7b694bb677a632a74ef7eadfd4eabf0000000000ffffffffa778eb6a263dc090464cd125c466b5a99667720b1c110468831d058aa1b82af10100000000ffffffff0200ca9a3b000000001976a91406afd46bcdfd22ef94ac122aa11f241244a37ecc88ac807840cb0000000020ac9a87f5594be208f8532db38cff670c450ed2fea8fcdefcc9a663f78bab962b0065cd1d";
+    /// The script and value of each output the BIP341 example spends, in input order.
+    const BIP341_SPENT: [(&str, u64); 9] = [
+        (
+            "512053a1f6e454df1aa2776a2814a721372d6258050de330b3c6d10ee8f4e0dda343",
+            420_000_000,
+        ),
+        (
+            "5120147c9c57132f6e7ecddba9800bb0c4449251c92a1e60371ee77557b6620f3ea3",
+            462_000_000,
+        ),
+        (
+            "76a914751e76e8199196d454941c45d1b3a323f1433bd688ac",
+            294_000_000,
+        ),
+        (
+            "5120e4d810fd50586274face62b8a807eb9719cef49c04177cc6b76a9a4251d5450e",
+            504_000_000,
+        ),
+        (
+            "512091b64d5324723a985170e4dc5a0f84c041804f2cd12660fa5dec09fc21783605",
+            630_000_000,
+        ),
+        ("00147dd65592d0ab2fe0d0257d571abf032cd9db93dc", 378_000_000),
+        (
+            "512075169f4001aa68f15bbed28b218df1d0a62cbbcf1188c6665110c293c907b831",
+            672_000_000,
+        ),
+        (
+            "5120712447206d7a5238acc7ff53fbe94a3b64539ad291c7cdbc490b7577e4b17df5",
+            546_000_000,
+        ),
+        (
+            "512077e30a5522dd9f894c3f8b8bd4c4b2cf82ca7da8a3ea6a239655c39c050ab220",
+            588_000_000,
+        ),
+    ];
+
+    fn transaction(tx_hex: &str) -> Transaction {
+        let raw_bytes = hex::decode(tx_hex).unwrap();
+        Transaction::from_raw_bytes(&mut ByteCursor::new(raw_bytes.as_slice())).unwrap()
+    }
+
+    /// The outputs serialized in `prevouts_hex` after their count, as Bitcoin Core's
+    /// functional tests give the outputs a transaction spends.
+    fn prevouts(prevouts_hex: &str) -> Vec<TxOut> {
+        let raw_bytes = hex::decode(prevouts_hex).unwrap();
+        let mut cursor = ByteCursor::new(raw_bytes.as_slice());
+        (0..CompactSize::from_raw_bytes(&mut cursor).unwrap().to_u64())
+            .map(|_| TxOut::from_raw_bytes(&mut cursor).unwrap())
+            .collect()
+    }
+
+    /// Checks the BIP143 example's P2WPKH sighash.
+    #[test]
+    fn test_segwit_v0_sighash() {
+        let tx = transaction(BIP143_TX_HEX);
+        let pubkey_hash = hex::decode("1d0f172a0ecb48aee1be1f2687d2963ae33f71a1").unwrap();
+        let script_code = p2wpkh_script_code(&pubkey_hash.try_into().unwrap());
+        let sighash = tx
+            .segwit_v0_sighash(1, &script_code, 600_000_000, SIGHASH_ALL.into())
+            .unwrap();
+        assert_eq!(
+            hex::encode(sighash),
+            "c37af31116d1b27caf68aae9e3ac82f1477929014d5b917657d0eb49478cb670"
+        );
+        assert!(tx.segwit_v0_sighash(2, &script_code, 0, 1).is_err());
+    }
+
+    /// Checks legacy sighashes against Bitcoin Core's sighash.json vectors, shown byte
+    /// reversed as Core prints them, that the sighash commits to the script code without its
+    /// code separators, and that SIGHASH_SINGLE without a matching output hashes to 1.
+    #[test]
+    fn test_legacy_sighash() {
+        for (tx_hex, script_code, index, sighash_type, expected) in [
+            (
+                "4db591ab018adcef5f4f3f2060e41f7829ce3a07ea41d681e8cb70a0e37685561e4767ac3b0000000005000052acabd280e63601ae6ef20000000000036a636326c908f7",
+                "ac6a51526300630052",
+                0,
+                0x336e7706,
+                "355ccaf30697c9c5b966e619a554d3323d7494c3ea280a9b0dfb73f953f5c1cb",
+            ),
+            (
+                "2f7353dd02e395b0a4d16da0f7472db618857cd3de5b9e2789232952a9b154d249102245fd030000000151617fd88f103280b85b0a198198e438e7cab1a4c92ba58409709997cc7a65a619eb9eec3c0200000003636aabffffffff0397481c0200000000045300636a0dc97803000000000009d389030000000003ac6a53134007bb",
+                "0000536552526a",
+                0,
+                0x8dfdcf42,
+                "30c4cd4bd6b291f7e9489cc4b4440a083f93a7664ea1f93e77a9597dab8ded9c",
+            ),
+            (
+                "6f62138301436f33a00b84a26a0457ccbfc0f82403288b9cbae39986b34357cb2ff9b889b302000000045253655335a7ff6701bac9960400000000086552ab656352635200000000",
+                "6aac51",
+                0,
+                0x56180303,
+                "502a2435fd02898d2ff3ab08a3c19078414b32ec9b73d64a944834efc9dae10c",
+            ),
+            // SIGHASH_SINGLE | SIGHASH_ANYONECANPAY, with junk in the bits Core ignores.
+            (
+                "d3b7421e011f4de0f1cea9ba7458bf3486bee722519efab711a963fa8c100970cf7488b7bb0200000003525352dcd61b300148be5d05000000000000000000",
+                "535251536aac536a",
+                0,
+                0x8b2ad183,
+                "29aa6d2d752d3310eba20442770ad345b7f6a35f96161ede5f07b33e92053e2a",
+            ),
+        ] {
+            let sighash = transaction(tx_hex)
+                .legacy_sighash(index, &hex::decode(script_code).unwrap(), sighash_type)
+                .unwrap();
+            assert_eq!(to_display_hex(&sighash), expected, "{tx_hex}");
+        }
+
+        let tx = transaction(BIP143_TX_HEX);
+        let script_code =
+            hex::decode("76a9141d0f172a0ecb48aee1be1f2687d2963ae33f71a188ac").unwrap();
+        let with_separator = [script_code.as_slice(), &[0xab]].concat();
+        let sighash = tx.legacy_sighash(0, &script_code, 1).unwrap();
+        assert_eq!(tx.legacy_sighash(0, &with_separator, 1).unwrap(), sighash);
+        assert_ne!(tx.legacy_sighash(1, &script_code, 1).unwrap(), sighash);
+
+        let mut one = [0; 32];
+        one[0] = 1;
+        assert_ne!(
+            tx.legacy_sighash(1, &script_code, SIGHASH_SINGLE.into())
+                .unwrap(),
+            one
+        );
+        let single_output = transaction(
+            &BIP143_TX_HEX
+                .replacen("ffffffff02", "ffffffff01", 1)
+                .replacen(
+                    "9093510d000000001976a9143bde42dbee7e4dbe6a21b2d50ce2f0167faa815988ac",
+                    "",
+                    1,
+                ),
+        );
+        assert_eq!(
+            single_output
+                .legacy_sighash(1, &script_code, SIGHASH_SINGLE.into())
+                .unwrap(),
+            one
+        );
+    }
+
+    /// Checks taproot sighashes against every key path input of BIP341's test vectors, and a
+    /// script path and an annex from Bitcoin Core's functional tests, which the BIP's vectors
+    /// have none of. Then that they commit to the leaf, the annex and the outputs spent, and
+    /// refuse invalid sighash types.
+    #[test]
+    fn test_taproot_sighash() {
+        let bip341 = transaction(BIP341_TX_HEX);
+        let spent =
+            BIP341_SPENT.map(|(script, value)| TxOut::new(value, hex::decode(script).unwrap()));
+        for (index, sighash_type, expected) in [
+            (
+                0,
+                0x03,
+                "2514a6272f85cfa0f45eb907fcb0d121b808ed37c6ea160a5a9046ed5526d555",
+            ),
+            (
+                1,
+                0x83,
+                "325a644af47e8a5a2591cda0ab0723978537318f10e6a63d4eed783b96a71a4d",
+            ),
+            (
+                3,
+                0x01,
+                "bf013ea93474aa67815b1b6cc441d23b64fa310911d991e713cd34c7f5d46669",
+            ),
+            (
+                4,
+                0x00,
+                "4f900a0bae3f1446fd48490c2958b5a023228f01661cda3496a11da502a7f7ef",
+            ),
+            (
+                6,
+                0x02,
+                "15f25c298eb5cdc7eb1d638dd2d45c97c4c59dcaec6679cfc16ad84f30876b85",
+            ),
+            (
+                7,
+                0x82,
+                "cd292de50313804dabe4685e83f923d2969577191a3e1d2882220dca88cbeb10",
+            ),
+            (
+                8,
+                0x81,
+                "cccb739eca6c13a8a89e6e5cd317ffe55669bbda23f2fd37b0f18755e008edd2",
+            ),
+        ] {
+            let sighash = bip341
+                .taproot_sighash(index, &spent, sighash_type, None, None)
+                .unwrap();
+            assert_eq!(hex::encode(sighash), expected, "input {index}");
+        }
+
+        let script =
+            hex::decode("20cc4e1107aea1d170c5ff5b6817e1303010049724fb3caa7941792ea9d29b3e2bacab")
+                .unwrap();
+        let script_path = transaction("020000000189fc651483f9296b906455dd939813bf086b1bbe7c77635e157c8e14ae29062195010000004445b5c7044561320000000000160014331414dbdada7fb578f700f38fb69995fc9b5ab958020000000000001976a914268db0a8104cc6d8afd91233cc8b3d1ace8ac3ef88ac580200000000000017a914ec00dcb368d6a693e11986d265f659d2f59e8be2875802000000000000160014c715799a49a0bae3956df9c17cb4440a673ac0df6f010000")
+            .taproot_sighash(
+                0,
+                &prevouts("011bec34000000000022512028055142ea437db73382e991861446040b61dd2185c4891d7daf6893d79f7182"),
+                SIGHASH_ALL,
+                Some(TapLeaf::tapscript(&script)),
+                None,
+            )
+            .unwrap();
+        assert_eq!(
+            hex::encode(script_path),
+            "d66de5274a60400c7b08c86ba6b7f198f40660079edf53aca89d2a9501317f2e"
+        );
+        let annex = hex::decode("507b979802e62d397acb29f56743a791894b99372872fc5af06a4f6e8d242d0615cda53062bb20e6ec79756fe39183f0c128adfe85559a8fa042b042c018aa8010143799e44f0893c40e1e").unwrap();
+        let with_annex = transaction("0200000001df8123752e8f37d132c4e9f1ff7e4f9b986ade9211267e9ebd5fd22a5e718dec6d01000000ce4023b903cb7b23000000000017a914a18b36ea7a094db2f4940fc09edf154e86de7bd787580200000000000017a914afd0d512a2c5c2b40e25669e9cc460303c325b8b87580200000000000017a914a18b36ea7a094db2f4940fc09edf154e86de7bd787f6020000")
+            .taproot_sighash(
+                0,
+                &prevouts("01ea49260000000000225120ab5e9800806bf18cb246edcf5fe63441208fe955a4b5a35bbff65f5db622a010"),
+                SIGHASH_SINGLE | SIGHASH_ANYONECANPAY,
+                None,
+                Some(&annex),
+            )
+            .unwrap();
+        assert_eq!(
+            hex::encode(with_annex),
+            "3b003000add359a364a156e73e02846782a59d0d95ca8c4638aaad99f2ef915c"
+        );
+
+        let tx = transaction(BIP143_TX_HEX);
+        let prevout =
+            |value: u64| TxOut::new(value, [[0x51, 0x20].as_slice(), &[0x11; 32]].concat());
+        let prevouts = [prevout(1000), prevout(2000)];
+        let key_path = tx.taproot_sighash(0, &prevouts, 0, None, None).unwrap();
+        let leaf = TapLeaf::tapscript(&[0x51]);
+        for other in [
+            tx.taproot_sighash(0, &prevouts, 0, Some(leaf), None),
+            tx.taproot_sighash(0, &prevouts, 0, None, Some(&[0x50])),
+            tx.taproot_sighash(0, &prevouts, 0x81, None, None),
+            tx.taproot_sighash(0, &[prevout(1000), prevout(2001)], 0, None, None),
+        ] {
+            assert_ne!(other.unwrap(), key_path);
+        }
+        // Only the spent output of the input signed matters with ANYONECANPAY.
+        assert_eq!(
+            tx.taproot_sighash(0, &prevouts, 0x81, None, None).unwrap(),
+            tx.taproot_sighash(0, &[prevout(1000), prevout(1)], 0x81, None, None)
+                .unwrap()
+        );
+        assert!(tx.taproot_sighash(0, &prevouts, 0x04, None, None).is_err());
+        assert!(tx
+            .taproot_sighash(0, &prevouts[..1], 0, None, None)
+            .is_err());
+    }
+}
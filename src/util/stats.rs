@@ -0,0 +1,129 @@
+//! Aggregate statistics over a range of blocks, as printed by the stats subcommand.
+use crate::Block;
+use serde::Serialize;
+
+/// The smallest, median, largest and mean of a set of values.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Distribution {
+    pub min: u64,
+    pub median: f64,
+    pub max: u64,
+    pub average: f64,
+}
+impl Distribution {
+    /// The distribution of `values`, or `None` when there aren't any.
+    pub fn new(mut values: Vec<u64>) -> Option<Self> {
+        values.sort_unstable();
+        let (&min, &max) = (values.first()?, values.last()?);
+        let middle = values.len() / 2;
+        let median = match values.len() % 2 {
+            0 => (values[middle - 1] as f64 + values[middle] as f64) / 2.0,
+            _ => values[middle] as f64,
+        };
+        let average = values.iter().sum::<u64>() as f64 / values.len() as f64;
+        Some(Self {
+            min,
+            median,
+            max,
+            average,
+        })
+    }
+}
+
+/// Statistics over the blocks from height `from` to `to`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct BlockStats {
+    pub from: u64,
+    pub to: u64,
+    pub block_count: u64,
+    /// Block sizes in bytes, including witness data.
+    pub size: Distribution,
+    pub tx_count: Distribution,
+    /// The mean seconds between consecutive header timestamps, `None` for a single block.
+    /// Timestamps needn't increase, so this can be negative over a short range.
+    pub average_interval: Option<f64>,
+    /// The fees paid across the range, when every block's fees are known.
+    pub total_fees: Option<u64>,
+    /// The percentage of transactions other than coinbases that were witness serialized,
+    /// `None` when there were only coinbases.
+    pub segwit_percent: Option<f64>,
+}
+
+/// Collects the values [BlockStats] are worked out from, one block at a time in height order.
+#[derive(Debug, Default)]
+pub struct StatsCollector {
+    sizes: Vec<u64>,
+    tx_counts: Vec<u64>,
+    first_time: Option<u32>,
+    last_time: u32,
+    total_fees: Option<u64>,
+    missing_fees: bool,
+    transactions: u64,
+    segwit_transactions: u64,
+}
+impl StatsCollector {
+    pub fn add(&mut self, block: &Block) {
+        let time = block.block_header().unix_epoch_time();
+        self.first_time.get_or_insert(time);
+        self.last_time = time;
+        self.sizes.push(u64::from(block.total_size()));
+        self.tx_counts.push(block.tx_count());
+        match block.total_fees() {
+            Some(fees) => *self.total_fees.get_or_insert(0) += fees,
+            None => self.missing_fees = true,
+        }
+        for tx in block.transactions().iter().filter(|tx| !tx.is_coinbase()) {
+            self.transactions += 1;
+            self.segwit_transactions += u64::from(tx.is_segwit());
+        }
+    }
+    /// The statistics of the blocks added, labelled as the range `from` to `to`,
+    /// or `None` when none were.
+    pub fn finish(self, from: u64, to: u64) -> Option<BlockStats> {
+        let block_count = self.sizes.len() as u64;
+        let average_interval = (block_count > 1).then(|| {
+            (i64::from(self.last_time) - i64::from(self.first_time.unwrap_or_default())) as f64
+                / (block_count - 1) as f64
+        });
+        let segwit_percent = (self.transactions > 0)
+            .then(|| self.segwit_transactions as f64 * 100.0 / self.transactions as f64);
+        Some(BlockStats {
+            from,
+            to,
+            block_count,
+            size: Distribution::new(self.sizes)?,
+            tx_count: Distribution::new(self.tx_counts)?,
+            average_interval,
+            total_fees: self.total_fees.filter(|_| !self.missing_fees),
+            segwit_percent,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{Distribution, StatsCollector};
+    use crate::util::fixtures::{block, BLOCK_1_HEX, GENESIS_BLOCK_HEX};
+
+    /// Checks the statistics of the first two blocks against their known sizes and times.
+    #[test]
+    fn test_block_stats() {
+        let mut collector = StatsCollector::default();
+        for block_hex in [GENESIS_BLOCK_HEX, BLOCK_1_HEX] {
+            collector.add(&block(block_hex));
+        }
+        let stats = collector.finish(0, 1).unwrap();
+
+        assert_eq!(stats.block_count, 2);
+        assert_eq!(stats.size.min, 215);
+        assert_eq!(stats.size.max, 285);
+        assert_eq!(stats.size.median, 250.0);
+        assert_eq!(stats.tx_count.average, 1.0);
+        assert_eq!(stats.average_interval, Some(463_160.0));
+        assert_eq!(stats.total_fees, None);
+        assert_eq!(stats.segwit_percent, None);
+
+        assert_eq!(StatsCollector::default().finish(0, 0), None);
+        assert_eq!(Distribution::new(vec![3, 1, 2]).unwrap().median, 2.0);
+    }
+}
@@ -0,0 +1,264 @@
+//! Decoding the witness of an input spending a pay to taproot output (BIP341), either
+//! by a signature for the output key or by revealing a tapscript and its control block.
+//!
+//! The output being spent isn't known from the input alone, so a witness is recognised by
+//! its shape: a lone 64 or 65 byte signature, or a control block as its last item.
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+/// The first byte of an annex, an optional last witness item reserved for future use.
+const ANNEX_TAG: u8 = 0x50;
+/// The leaf version of tapscript (BIP342).
+pub const TAPSCRIPT_LEAF_VERSION: u8 = 0xc0;
+/// The control block byte bits holding the leaf version, the rest is the output key parity.
+const LEAF_VERSION_MASK: u8 = 0xfe;
+/// A control block's leaf version byte and internal key, before the merkle path.
+const CONTROL_BLOCK_BASE_SIZE: usize = 33;
+const CONTROL_BLOCK_NODE_SIZE: usize = 32;
+/// The most merkle path nodes a control block can have, the depth limit of the script tree.
+const TAPROOT_CONTROL_MAX_NODE_COUNT: usize = 128;
+
+/// The proof that a tapscript is committed to by the output key.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ControlBlock<'a> {
+    pub leaf_version: u8,
+    /// Whether the output key has an odd y coordinate.
+    pub output_key_parity: bool,
+    pub internal_key: &'a [u8],
+    /// The hashes of the other branches on the way from the leaf to the root.
+    pub merkle_path: Vec<&'a [u8]>,
+}
+impl<'a> ControlBlock<'a> {
+    /// Parse a control block, or `None` if `bytes` isn't shaped like one.
+    pub fn from_bytes(bytes: &'a [u8]) -> Option<Self> {
+        let path = bytes.get(CONTROL_BLOCK_BASE_SIZE..)?;
+        if path.len() % CONTROL_BLOCK_NODE_SIZE != 0
+            || path.len() / CONTROL_BLOCK_NODE_SIZE > TAPROOT_CONTROL_MAX_NODE_COUNT
+        {
+            return None;
+        }
+        let leaf_version = bytes[0] & LEAF_VERSION_MASK;
+        // Every leaf version is even and never 0x50, so the byte can't be mistaken for an annex.
+        if leaf_version == ANNEX_TAG {
+            return None;
+        }
+        Some(Self {
+            leaf_version,
+            output_key_parity: bytes[0] & 1 == 1,
+            internal_key: &bytes[1..CONTROL_BLOCK_BASE_SIZE],
+            merkle_path: path.chunks(CONTROL_BLOCK_NODE_SIZE).collect(),
+        })
+    }
+}
+
+/// How a taproot output was spent.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TaprootSpend<'a> {
+    /// A Schnorr signature for the output key.
+    KeyPath {
+        signature: &'a [u8],
+        annex: Option<&'a [u8]>,
+    },
+    /// A script from the tree committed to by the output key, with the items it's run on.
+    ScriptPath {
+        stack: &'a [Vec<u8>],
+        script: &'a [u8],
+        control_block: ControlBlock<'a>,
+        annex: Option<&'a [u8]>,
+    },
+}
+impl<'a> TaprootSpend<'a> {
+    /// Decode `witness` as a taproot spend, or `None` if it doesn't look like one.
+    ///
+    /// Only tapscript leaves are recognised on the script path, since any other leaf
+    /// version is as likely to be the witness script of a P2WSH spend.
+    pub fn from_witness(witness: &'a [Vec<u8>]) -> Option<Self> {
+        let (witness, annex) = match witness {
+            [rest @ .., last] if !rest.is_empty() && last.first() == Some(&ANNEX_TAG) => {
+                (rest, Some(last.as_slice()))
+            }
+            _ => (witness, None),
+        };
+        match witness {
+            [signature] if matches!(signature.len(), 64 | 65) => {
+                Some(TaprootSpend::KeyPath { signature, annex })
+            }
+            [stack @ .., script, control_block] => {
+                let control_block = ControlBlock::from_bytes(control_block)
+                    .filter(|control_block| control_block.leaf_version == TAPSCRIPT_LEAF_VERSION)?;
+                Some(TaprootSpend::ScriptPath {
+                    stack,
+                    script,
+                    control_block,
+                    annex,
+                })
+            }
+            _ => None,
+        }
+    }
+    /// The sighash type a key path signature commits to, `None` on the script path.
+    pub fn sighash_type(&self) -> Option<u8> {
+        match self {
+            TaprootSpend::KeyPath { signature, .. } => {
+                Some(signature.get(64).copied().unwrap_or(0))
+            }
+            TaprootSpend::ScriptPath { .. } => None,
+        }
+    }
+}
+impl std::fmt::Display for TaprootSpend<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TaprootSpend::KeyPath { signature, annex } => {
+                write!(
+                    f,
+                    "Taproot Spend         : key path\nSignature             : {}\nSighash Type          : {}",
+                    hex::encode(signature),
+                    sighash_name(self.sighash_type().unwrap_or_default())
+                )?;
+                if let Some(annex) = annex {
+                    write!(f, "\nAnnex                 : {}", hex::encode(annex))?;
+                }
+            }
+            TaprootSpend::ScriptPath {
+                stack,
+                script,
+                control_block,
+                annex,
+            } => {
+                write!(
+                    f,
+                    "Taproot Spend         : script path, leaf version {:#04x}, depth {}\nTapscript             : {}\nInternal Key          : {}\nOutput Key Parity     : {}",
+                    control_block.leaf_version,
+                    control_block.merkle_path.len(),
+                    hex::encode(script),
+                    hex::encode(control_block.internal_key),
+                    if control_block.output_key_parity { "odd" } else { "even" },
+                )?;
+                for node in &control_block.merkle_path {
+                    write!(f, "\nMerkle Path Node      : {}", hex::encode(node))?;
+                }
+                if !stack.is_empty() {
+                    write!(
+                        f,
+                        "\nScript Inputs         : {}",
+                        stack
+                            .iter()
+                            .map(hex::encode)
+                            .collect::<Vec<String>>()
+                            .join(" ")
+                    )?;
+                }
+                if let Some(annex) = annex {
+                    write!(f, "\nAnnex                 : {}", hex::encode(annex))?;
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for TaprootSpend<'_> {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        match self {
+            TaprootSpend::KeyPath { signature, annex } => {
+                let mut state = serializer.serialize_struct("TaprootSpend", 4)?;
+                state.serialize_field("path", "key")?;
+                state.serialize_field("signature", &hex::encode(signature))?;
+                state.serialize_field("sighash_type", &self.sighash_type())?;
+                state.serialize_field("annex", &annex.map(hex::encode))?;
+                state.end()
+            }
+            TaprootSpend::ScriptPath {
+                stack,
+                script,
+                control_block,
+                annex,
+            } => {
+                let mut state = serializer.serialize_struct("TaprootSpend", 8)?;
+                state.serialize_field("path", "script")?;
+                state.serialize_field("script", &hex::encode(script))?;
+                state.serialize_field("leaf_version", &control_block.leaf_version)?;
+                state.serialize_field("internal_key", &hex::encode(control_block.internal_key))?;
+                state.serialize_field("output_key_parity", &control_block.output_key_parity)?;
+                state.serialize_field(
+                    "merkle_path",
+                    &control_block
+                        .merkle_path
+                        .iter()
+                        .map(hex::encode)
+                        .collect::<Vec<String>>(),
+                )?;
+                state.serialize_field(
+                    "stack",
+                    &stack.iter().map(hex::encode).collect::<Vec<String>>(),
+                )?;
+                state.serialize_field("annex", &annex.map(hex::encode))?;
+                state.end()
+            }
+        }
+    }
+}
+
+/// The name of a BIP341 sighash type.
+pub(crate) fn sighash_name(sighash_type: u8) -> String {
+    let name = match sighash_type {
+        0x00 => "SIGHASH_DEFAULT",
+        0x01 => "SIGHASH_ALL",
+        0x02 => "SIGHASH_NONE",
+        0x03 => "SIGHASH_SINGLE",
+        0x81 => "SIGHASH_ALL|ANYONECANPAY",
+        0x82 => "SIGHASH_NONE|ANYONECANPAY",
+        0x83 => "SIGHASH_SINGLE|ANYONECANPAY",
+        _ => return format!("unknown ({sighash_type:#04x})"),
+    };
+    name.to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{TaprootSpend, TAPSCRIPT_LEAF_VERSION};
+
+    /// Checks key path and script path witnesses, and that a P2WPKH witness isn't mistaken for either.
+    #[test]
+    fn test_taproot_spend() {
+        let key_path = [vec![0x11; 64]];
+        let spend = TaprootSpend::from_witness(&key_path).unwrap();
+        assert!(matches!(spend, TaprootSpend::KeyPath { annex: None, .. }));
+        assert_eq!(spend.sighash_type(), Some(0));
+
+        let key_path_with_annex = [vec![0x11; 65], vec![0x50, 0xaa]];
+        let spend = TaprootSpend::from_witness(&key_path_with_annex).unwrap();
+        assert_eq!(spend.sighash_type(), Some(0x11));
+        assert!(matches!(
+            spend,
+            TaprootSpend::KeyPath {
+                annex: Some([0x50, 0xaa]),
+                ..
+            }
+        ));
+
+        // A signature for a tapscript of <key> OP_CHECKSIG, one level deep in the tree.
+        let script = [&[0x20], [0x22; 32].as_slice(), &[0xac]].concat();
+        let control_block = [&[0xc1], [0x33; 32].as_slice(), &[0x44; 32]].concat();
+        let script_path = [vec![0x11; 64], script.clone(), control_block];
+        let TaprootSpend::ScriptPath {
+            stack,
+            script: tapscript,
+            control_block,
+            annex: None,
+        } = TaprootSpend::from_witness(&script_path).unwrap()
+        else {
+            panic!("expected a script path spend")
+        };
+        assert_eq!(stack, [vec![0x11; 64]]);
+        assert_eq!(tapscript, script);
+        assert_eq!(control_block.leaf_version, TAPSCRIPT_LEAF_VERSION);
+        assert!(control_block.output_key_parity);
+        assert_eq!(control_block.internal_key, [0x33; 32]);
+        assert_eq!(control_block.merkle_path, [[0x44; 32]]);
+
+        let p2wpkh = [vec![0x30; 71], [&[0x02], [0x55; 32].as_slice()].concat()];
+        assert_eq!(TaprootSpend::from_witness(&p2wpkh), None);
+        assert_eq!(TaprootSpend::from_witness(&[]), None);
+    }
+}
@@ -0,0 +1,422 @@
+//! Reading a block template, the result of a node's `getblocktemplate`, to preview the block
+//! it proposes before any work goes into mining it.
+//!
+//! A template has no coinbase, which the miner builds from the value the template allows,
+//! so nothing that commits to it is shown: the merkle root and the block hash.
+use crate::util::{
+    coinbase::block_subsidy,
+    constant::Network,
+    display::{format_amount, format_difficulty, format_timestamp, format_utc, options},
+    hash::{from_display_hex, to_display_hex},
+    tx::Transaction,
+    types::{BlockHeader, ByteCursor},
+};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use serde::{ser::SerializeStruct, Deserialize, Serialize, Serializer};
+
+/// A transaction of a template as the node lists it, its fee, sigops and weight alongside.
+#[derive(Debug, Deserialize)]
+struct RawTransaction {
+    data: String,
+    txid: String,
+    hash: String,
+    fee: u64,
+    sigops: u64,
+    weight: u64,
+    #[serde(default)]
+    depends: Vec<usize>,
+}
+
+/// The fields of a `getblocktemplate` result that are read.
+#[derive(Debug, Deserialize)]
+struct RawTemplate {
+    version: u32,
+    previousblockhash: String,
+    curtime: u32,
+    bits: String,
+    height: u64,
+    coinbasevalue: u64,
+    transactions: Vec<RawTransaction>,
+    mintime: Option<u32>,
+    sigoplimit: Option<u64>,
+    weightlimit: Option<u64>,
+    default_witness_commitment: Option<String>,
+}
+
+/// A transaction a template proposes, with what the node says of it.
+#[derive(Debug)]
+pub struct TemplateTransaction {
+    pub tx: Transaction,
+    /// The txid and wtxid as the template gives them, in display order.
+    pub listed_txid: String,
+    pub listed_wtxid: String,
+    pub fee: u64,
+    pub sigops: u64,
+    pub weight: u64,
+    /// The positions of the transactions this one spends, counted from 1 for the first.
+    pub depends: Vec<usize>,
+}
+impl Serialize for TemplateTransaction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("TemplateTransaction", 6)?;
+        state.serialize_field("txid", &to_display_hex(&self.tx.txid()))?;
+        state.serialize_field("wtxid", &to_display_hex(&self.tx.wtxid()))?;
+        state.serialize_field("fee", &self.fee)?;
+        state.serialize_field("sigops", &self.sigops)?;
+        state.serialize_field("weight", &self.weight)?;
+        state.serialize_field("depends", &self.depends)?;
+        state.end()
+    }
+}
+
+/// The block proposed by a `getblocktemplate` result.
+#[derive(Debug)]
+pub struct BlockTemplate {
+    pub height: u64,
+    /// The header the template describes, with an all zero merkle root and nonce since it
+    /// has no coinbase and no work done.
+    header: BlockHeader,
+    pub transactions: Vec<TemplateTransaction>,
+    /// What the coinbase may claim: the subsidy and the fees.
+    pub coinbase_value: u64,
+    pub min_time: Option<u32>,
+    pub sigop_limit: Option<u64>,
+    pub weight_limit: Option<u64>,
+    /// The output script committing to the witnesses, when any transaction has one.
+    pub witness_commitment: Option<Vec<u8>>,
+}
+impl BlockTemplate {
+    /// Read a template from JSON, either the result itself or the whole RPC response
+    /// holding it.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let mut value: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| anyhow::anyhow!("failed to parse the block template: {e}"))?;
+        if let Some(result) = value.get_mut("result") {
+            value = result.take();
+        }
+        let raw: RawTemplate = serde_json::from_value(value)
+            .map_err(|e| anyhow::anyhow!("failed to parse the block template: {e}"))?;
+
+        let bits = u32::from_str_radix(&raw.bits, 16)
+            .map_err(|e| anyhow::anyhow!("failed to parse the template bits {}: {e}", raw.bits))?;
+        let header = [
+            raw.version.to_le_bytes().as_slice(),
+            &from_display_hex(&raw.previousblockhash)
+                .context("failed to parse the template's previous block hash")?,
+            &[0; 32],
+            &raw.curtime.to_le_bytes(),
+            &bits.to_le_bytes(),
+            &[0; 4],
+        ]
+        .concat();
+        let header = BlockHeader::from_raw_bytes(&mut ByteCursor::new(header.as_slice()))?;
+        let transactions = raw
+            .transactions
+            .into_iter()
+            .enumerate()
+            .map(|(index, raw)| {
+                let data = hex::decode(&raw.data).map_err(|e| {
+                    anyhow::anyhow!(
+                        "failed to decode transaction {} of the template: {e}",
+                        index + 1
+                    )
+                })?;
+                let tx = Transaction::from_raw_bytes(&mut ByteCursor::new(data.as_slice()))
+                    .map_err(|e| {
+                        anyhow::anyhow!(
+                            "failed to parse transaction {} of the template: {e}",
+                            index + 1
+                        )
+                    })?;
+                Ok(TemplateTransaction {
+                    tx,
+                    listed_txid: raw.txid,
+                    listed_wtxid: raw.hash,
+                    fee: raw.fee,
+                    sigops: raw.sigops,
+                    weight: raw.weight,
+                    depends: raw.depends,
+                })
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let witness_commitment = raw
+            .default_witness_commitment
+            .map(hex::decode)
+            .transpose()
+            .map_err(|e| anyhow::anyhow!("failed to decode the witness commitment: {e}"))?;
+        Ok(Self {
+            height: raw.height,
+            header,
+            transactions,
+            coinbase_value: raw.coinbasevalue,
+            min_time: raw.mintime,
+            sigop_limit: raw.sigoplimit,
+            weight_limit: raw.weightlimit,
+            witness_commitment,
+        })
+    }
+    /// The header of the proposed block, without its merkle root or nonce.
+    pub fn header(&self) -> &BlockHeader {
+        &self.header
+    }
+    pub fn total_fees(&self) -> u64 {
+        self.transactions.iter().map(|tx| tx.fee).sum()
+    }
+    /// The sigop cost of the transactions, as the template counts them.
+    pub fn sigops(&self) -> u64 {
+        self.transactions.iter().map(|tx| tx.sigops).sum()
+    }
+    /// The weight of the transactions, as the template counts them, without the header's or
+    /// the coinbase's.
+    pub fn weight(&self) -> u64 {
+        self.transactions.iter().map(|tx| tx.weight).sum()
+    }
+    /// Everything amiss with the template on `network`: transactions whose data doesn't
+    /// match what's said of them or that come before those they spend, a coinbase value
+    /// other than the subsidy and fees, limits exceeded and a time before the earliest
+    /// allowed.
+    pub fn problems(&self, network: Network) -> Vec<String> {
+        let mut problems = Vec::new();
+        for (index, tx) in self.transactions.iter().enumerate() {
+            let position = index + 1;
+            let txid = to_display_hex(&tx.tx.txid());
+            if txid != tx.listed_txid {
+                problems.push(format!(
+                    "transaction {position} is listed as {} but its data hashes to {txid}",
+                    tx.listed_txid
+                ));
+            }
+            let wtxid = to_display_hex(&tx.tx.wtxid());
+            if wtxid != tx.listed_wtxid {
+                problems.push(format!(
+                    "transaction {position} is listed with wtxid {} but its data hashes to {wtxid}",
+                    tx.listed_wtxid
+                ));
+            }
+            if u64::from(tx.tx.weight()) != tx.weight {
+                problems.push(format!(
+                    "transaction {position} is listed as weighing {} WU but weighs {} WU",
+                    tx.weight,
+                    tx.tx.weight()
+                ));
+            }
+            for depend in &tx.depends {
+                if *depend == 0 || *depend >= position {
+                    problems.push(format!(
+                        "transaction {position} depends on transaction {depend}, which doesn't come before it"
+                    ));
+                }
+            }
+        }
+        let (subsidy, fees) = (block_subsidy(self.height, network), self.total_fees());
+        if self.coinbase_value != subsidy + fees {
+            problems.push(format!(
+                "the coinbase value {} isn't the subsidy {} plus the fees {}",
+                format_amount(self.coinbase_value),
+                format_amount(subsidy),
+                format_amount(fees)
+            ));
+        }
+        if let Some(limit) = self.sigop_limit.filter(|limit| self.sigops() > *limit) {
+            problems.push(format!(
+                "the transactions' {} sigops exceed the limit of {limit}",
+                self.sigops()
+            ));
+        }
+        if let Some(limit) = self.weight_limit.filter(|limit| self.weight() > *limit) {
+            problems.push(format!(
+                "the transactions' {} WU exceed the limit of {limit} WU",
+                self.weight()
+            ));
+        }
+        if let Some(min_time) = self
+            .min_time
+            .filter(|min_time| self.header.unix_epoch_time() < *min_time)
+        {
+            problems.push(format!(
+                "the time {} is before the earliest allowed, {}",
+                format_utc(self.header.unix_epoch_time()),
+                format_utc(min_time)
+            ));
+        }
+        let has_witness = self.transactions.iter().any(|tx| tx.tx.is_segwit());
+        if has_witness && self.witness_commitment.is_none() {
+            problems
+                .push("transactions have witnesses but there's no witness commitment".to_string());
+        }
+        problems
+    }
+}
+impl std::fmt::Display for BlockTemplate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let limit =
+            |limit: Option<u64>| limit.map_or(String::new(), |limit| format!(" of {limit}"));
+        write!(
+            f,
+            "{}\n\nBlock at Height       : {}\nVersion               : {}\nPrev BlockHeader Hash : {}\nUnix Epoch Time       : {}",
+            "> Block Template".green(),
+            self.height,
+            self.header.version_summary(),
+            self.header.previous_block_header_hash(),
+            format_timestamp(self.header.unix_epoch_time()),
+        )?;
+        if let Some(min_time) = self.min_time {
+            write!(
+                f,
+                "\nMin Time              : {}",
+                format_timestamp(min_time)
+            )?;
+        }
+        write!(
+            f,
+            "\nTarget                : {:#010x}\nTarget Hash           : {}\nDifficulty            : {}\nTX Count              : {}, and the coinbase\nTotal Fees            : {}\nCoinbase Value        : {}\nSigops                : {}{}\nWeight                : {}{} WU\nWitness Commitment    : {}",
+            self.header.target(),
+            self.header
+                .expanded_target()
+                .map_or_else(|e| e.to_string(), hex::encode),
+            format_difficulty(self.header.difficulty()),
+            self.transactions.len(),
+            format_amount(self.total_fees()),
+            format_amount(self.coinbase_value),
+            self.sigops(),
+            limit(self.sigop_limit),
+            self.weight(),
+            limit(self.weight_limit),
+            self.witness_commitment
+                .as_ref()
+                .map_or("none".to_string(), hex::encode),
+        )?;
+        let verbosity = options().verbosity;
+        if verbosity >= 1 {
+            write!(f, "\n\n{}", "Transactions:".cyan())?;
+            for (index, tx) in self.transactions.iter().enumerate() {
+                write!(
+                    f,
+                    "\n{:>5}  {}  {}, {} sigops, {} WU",
+                    index + 1,
+                    to_display_hex(&tx.tx.txid()),
+                    format_amount(tx.fee),
+                    tx.sigops,
+                    tx.weight
+                )?;
+            }
+        }
+        if verbosity >= 2 {
+            for (index, tx) in self.transactions.iter().enumerate() {
+                write!(
+                    f,
+                    "\n\n{}\nTXID                  : {}\nWTXID                 : {}\n{}",
+                    format!("Transaction {}:", index + 1).cyan(),
+                    to_display_hex(&tx.tx.txid()),
+                    to_display_hex(&tx.tx.wtxid()),
+                    tx.tx
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+impl Serialize for BlockTemplate {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("BlockTemplate", 15)?;
+        state.serialize_field("height", &self.height)?;
+        state.serialize_field("version", &self.header.version())?;
+        state.serialize_field("signaling_bits", &self.header.signaling_bits())?;
+        state.serialize_field(
+            "previous_block_header_hash",
+            &self.header.previous_block_header_hash(),
+        )?;
+        state.serialize_field("unix_epoch_time", &self.header.unix_epoch_time())?;
+        state.serialize_field("min_time", &self.min_time)?;
+        state.serialize_field("target", &self.header.target())?;
+        state.serialize_field(
+            "target_hash",
+            &self.header.expanded_target().ok().map(hex::encode),
+        )?;
+        state.serialize_field("difficulty", &self.header.difficulty())?;
+        state.serialize_field("total_fees", &self.total_fees())?;
+        state.serialize_field("coinbase_value", &self.coinbase_value)?;
+        state.serialize_field("sigops", &self.sigops())?;
+        state.serialize_field("weight", &self.weight())?;
+        state.serialize_field(
+            "witness_commitment",
+            &self.witness_commitment.as_ref().map(hex::encode),
+        )?;
+        state.serialize_field("transactions", &self.transactions)?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Checks a template's transactions are parsed and totalled, and that a txid that
+    /// doesn't match, a dependency out of order and a coinbase value claiming too much
+    /// are all found.
+    #[test]
+    fn test_block_template() {
+        // Spending an output of 0x11 repeated to an OP_TRUE output.
+        let data = [
+            "02000000",
+            "01",
+            &"11".repeat(32),
+            "00000000",
+            "00",
+            "ffffffff",
+            "01",
+            "e803000000000000",
+            "0151",
+            "00000000",
+        ]
+        .concat();
+        let tx = Transaction::from_raw_bytes(&mut ByteCursor::new(
+            hex::decode(&data).unwrap().as_slice(),
+        ))
+        .unwrap();
+        let txid = to_display_hex(&tx.txid());
+        let template = |txid: &str, depends: Vec<usize>, coinbase_value: u64| {
+            json!({
+                "result": {
+                    "version": 0x2000_0000,
+                    "previousblockhash": "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f",
+                    "curtime": 1_231_469_665,
+                    "mintime": 1_231_006_506,
+                    "bits": "1d00ffff",
+                    "height": 1,
+                    "coinbasevalue": coinbase_value,
+                    "sigoplimit": 80_000,
+                    "weightlimit": 4_000_000,
+                    "transactions": [{
+                        "data": data,
+                        "txid": txid,
+                        "hash": txid,
+                        "fee": 500,
+                        "sigops": 4,
+                        "weight": tx.weight(),
+                        "depends": depends,
+                    }],
+                },
+                "error": null,
+            })
+            .to_string()
+        };
+
+        let valid = BlockTemplate::from_json(&template(&txid, vec![], 5_000_000_500)).unwrap();
+        assert_eq!(valid.height, 1);
+        assert_eq!(valid.header().target(), 0x1d00ffff);
+        assert_eq!((valid.total_fees(), valid.sigops()), (500, 4));
+        assert_eq!(valid.problems(Network::Mainnet), Vec::<String>::new());
+
+        let invalid =
+            BlockTemplate::from_json(&template(&"00".repeat(32), vec![1], 5_000_001_000)).unwrap();
+        let problems = invalid.problems(Network::Mainnet);
+        assert_eq!(problems.len(), 4);
+        assert!(problems[0].contains("is listed as"));
+        assert!(problems[2].contains("depends on transaction 1"));
+        assert!(problems[3].contains("isn't the subsidy"));
+        assert!(BlockTemplate::from_json("{\"result\": {}}").is_err());
+    }
+}
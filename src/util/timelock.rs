@@ -0,0 +1,140 @@
+//! Interpreting a transaction's nLockTime and its inputs' nSequence numbers:
+//! absolute timelocks, relative timelocks (BIP68) and replace by fee signaling (BIP125).
+use crate::util::display::format_utc;
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+
+/// Lock times below this are block heights, and at or above it unix timestamps.
+const LOCKTIME_THRESHOLD: u32 = 500_000_000;
+/// A sequence number that makes its input final, disabling the transaction's lock time.
+pub(crate) const SEQUENCE_FINAL: u32 = 0xffff_ffff;
+/// Inputs with a sequence below this opt in to replacement by fee.
+const MAX_NON_RBF_SEQUENCE: u32 = 0xffff_fffe;
+/// When set, the sequence number carries no relative lock time.
+const SEQUENCE_DISABLE_FLAG: u32 = 1 << 31;
+/// When set, a relative lock time is in units of 512 seconds rather than blocks.
+const SEQUENCE_TYPE_FLAG: u32 = 1 << 22;
+const SEQUENCE_LOCKTIME_MASK: u32 = 0x0000_ffff;
+/// The granularity of time based relative lock times, in seconds.
+const SEQUENCE_GRANULARITY: u32 = 512;
+/// The first transaction version that enforces BIP68 relative lock times.
+const BIP68_VERSION: u32 = 2;
+
+/// What a transaction's nLockTime means.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockTime {
+    /// No lock time, the transaction can be mined in any block.
+    None,
+    /// The transaction can't be mined before this block height.
+    Height(u32),
+    /// The transaction can't be mined before this unix time, compared to the median time past.
+    Time(u32),
+}
+impl LockTime {
+    pub fn from_consensus(lock_time: u32) -> Self {
+        match lock_time {
+            0 => LockTime::None,
+            height if height < LOCKTIME_THRESHOLD => LockTime::Height(height),
+            time => LockTime::Time(time),
+        }
+    }
+    /// The kind of lock time, as named in JSON output.
+    pub fn kind(&self) -> &'static str {
+        match self {
+            LockTime::None => "none",
+            LockTime::Height(_) => "height",
+            LockTime::Time(_) => "time",
+        }
+    }
+}
+impl std::fmt::Display for LockTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            LockTime::None => write!(f, "none"),
+            LockTime::Height(height) => write!(f, "block {height}"),
+            LockTime::Time(time) => write!(f, "time {}", format_utc(*time)),
+        }
+    }
+}
+
+/// How long after the output it spends was mined an input has to wait, under BIP68.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RelativeLockTime {
+    Blocks(u16),
+    /// A multiple of 512 seconds.
+    Seconds(u32),
+}
+impl RelativeLockTime {
+    /// The relative lock time a sequence number encodes in a transaction of `version`,
+    /// if BIP68 applies to it.
+    pub fn from_sequence(sequence: u32, version: u32) -> Option<Self> {
+        if version < BIP68_VERSION || sequence & SEQUENCE_DISABLE_FLAG != 0 {
+            return None;
+        }
+        let value = sequence & SEQUENCE_LOCKTIME_MASK;
+        match sequence & SEQUENCE_TYPE_FLAG != 0 {
+            true => Some(RelativeLockTime::Seconds(value * SEQUENCE_GRANULARITY)),
+            false => Some(RelativeLockTime::Blocks(value as u16)),
+        }
+    }
+}
+impl std::fmt::Display for RelativeLockTime {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            RelativeLockTime::Blocks(blocks) => write!(f, "{blocks} blocks"),
+            RelativeLockTime::Seconds(seconds) => write!(f, "{seconds} seconds"),
+        }
+    }
+}
+
+impl Serialize for RelativeLockTime {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let (unit, value) = match self {
+            RelativeLockTime::Blocks(blocks) => ("blocks", u32::from(*blocks)),
+            RelativeLockTime::Seconds(seconds) => ("seconds", *seconds),
+        };
+        let mut state = serializer.serialize_struct("RelativeLockTime", 2)?;
+        state.serialize_field("unit", unit)?;
+        state.serialize_field("value", &value)?;
+        state.end()
+    }
+}
+
+/// Whether an input with `sequence` signals that its transaction can be replaced by fee.
+pub fn signals_rbf(sequence: u32) -> bool {
+    sequence < MAX_NON_RBF_SEQUENCE
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{signals_rbf, LockTime, RelativeLockTime};
+
+    /// Checks each kind of lock time and sequence number.
+    #[test]
+    fn test_timelocks() {
+        assert_eq!(LockTime::from_consensus(0), LockTime::None);
+        assert_eq!(LockTime::from_consensus(740_000), LockTime::Height(740_000));
+        assert_eq!(
+            LockTime::from_consensus(740_000).to_string(),
+            "block 740000"
+        );
+        assert_eq!(
+            LockTime::from_consensus(1_700_000_000).to_string(),
+            "time 2023-11-14T22:13:20Z"
+        );
+
+        assert_eq!(
+            RelativeLockTime::from_sequence(144, 2),
+            Some(RelativeLockTime::Blocks(144))
+        );
+        assert_eq!(RelativeLockTime::from_sequence(144, 1), None);
+        assert_eq!(
+            RelativeLockTime::from_sequence((1 << 22) | 2, 2),
+            Some(RelativeLockTime::Seconds(1024))
+        );
+        assert_eq!(RelativeLockTime::from_sequence(0xffff_fffd, 2), None);
+
+        assert!(signals_rbf(0xffff_fffd));
+        assert!(!signals_rbf(0xffff_fffe));
+        assert!(!signals_rbf(0xffff_ffff));
+    }
+}
@@ -0,0 +1,265 @@
+//! Leaderboards of the biggest blocks and transactions of a range, as reported by the top
+//! subcommand, kept in a single pass with memory bounded by how many are listed.
+use crate::{util::hash::to_display_hex, Block};
+use serde::{Serialize, Serializer};
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+/// What blocks and transactions are ranked by.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Ranking {
+    /// Serialized size in bytes, witnesses included.
+    Size,
+    /// The number of transactions in a block. Transactions have no count, so aren't ranked.
+    TxCount,
+    /// The fees paid, by a block's transactions together or by a transaction.
+    Fee,
+}
+impl Ranking {
+    pub const ALL: [Ranking; 3] = [Ranking::Size, Ranking::TxCount, Ranking::Fee];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Ranking::Size => "size",
+            Ranking::TxCount => "txcount",
+            Ranking::Fee => "fee",
+        }
+    }
+}
+impl std::str::FromStr for Ranking {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Ranking::ALL
+            .into_iter()
+            .find(|ranking| ranking.name() == s)
+            .ok_or_else(|| {
+                let names = Ranking::ALL.map(|ranking| ranking.name());
+                anyhow::anyhow!(
+                    "unknown ranking '{s}', expected one of: {}",
+                    names.join(", ")
+                )
+            })
+    }
+}
+impl Serialize for Ranking {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(self.name())
+    }
+}
+
+/// A block on a leaderboard.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RankedBlock {
+    pub height: u64,
+    pub hash: String,
+    pub size: u32,
+    pub tx_count: u64,
+    /// The total fees, known only when ranking by them.
+    pub fees: Option<u64>,
+}
+
+/// A transaction on a leaderboard.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct RankedTransaction {
+    /// The height of the block it's in.
+    pub height: u64,
+    pub txid: String,
+    pub size: u32,
+    /// The fee, known only when ranking by them and never for a coinbase.
+    pub fee: Option<u64>,
+}
+
+/// The blocks and transactions from height `from` to `to` ranked highest, first to last.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Leaderboard {
+    pub by: Ranking,
+    /// Whether the lowest ranked were kept instead of the highest.
+    pub smallest: bool,
+    pub from: u64,
+    pub to: u64,
+    pub blocks: Vec<RankedBlock>,
+    pub transactions: Vec<RankedTransaction>,
+}
+
+/// An item kept on a leaderboard, ordered so the heap's greatest is the first to drop: the
+/// lowest key, then the latest added.
+struct Entry<T> {
+    key: u64,
+    sequence: u64,
+    item: T,
+}
+impl<T> Ord for Entry<T> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .key
+            .cmp(&self.key)
+            .then(self.sequence.cmp(&other.sequence))
+    }
+}
+impl<T> PartialOrd for Entry<T> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+impl<T> PartialEq for Entry<T> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+impl<T> Eq for Entry<T> {}
+
+/// The `limit` items with the highest keys of those offered, earlier ones winning ties.
+struct Top<T> {
+    limit: usize,
+    sequence: u64,
+    heap: BinaryHeap<Entry<T>>,
+}
+impl<T> Top<T> {
+    fn new(limit: usize) -> Self {
+        Self {
+            limit,
+            sequence: 0,
+            heap: BinaryHeap::with_capacity(limit + 1),
+        }
+    }
+    /// Offer `item`, built only when `key` makes the board.
+    fn offer(&mut self, key: u64, item: impl FnOnce() -> T) {
+        self.sequence += 1;
+        let makes_it =
+            self.heap.len() < self.limit || self.heap.peek().is_some_and(|lowest| lowest.key < key);
+        if makes_it {
+            self.heap.push(Entry {
+                key,
+                sequence: self.sequence,
+                item: item(),
+            });
+            if self.heap.len() > self.limit {
+                self.heap.pop();
+            }
+        }
+    }
+    /// The items kept, highest first.
+    fn finish(self) -> Vec<T> {
+        self.heap
+            .into_sorted_vec()
+            .into_iter()
+            .map(|entry| entry.item)
+            .collect()
+    }
+}
+
+/// Ranks blocks and their transactions one block at a time, keeping only the leaders.
+pub struct LeaderboardCollector {
+    by: Ranking,
+    smallest: bool,
+    blocks: Top<RankedBlock>,
+    transactions: Top<RankedTransaction>,
+}
+impl LeaderboardCollector {
+    /// A collector keeping the `limit` blocks and transactions ranked highest `by`, or
+    /// lowest when `smallest`.
+    pub fn new(by: Ranking, limit: usize, smallest: bool) -> Self {
+        Self {
+            by,
+            smallest,
+            blocks: Top::new(limit),
+            transactions: Top::new(limit),
+        }
+    }
+    /// The key `value` is ranked by, flipped when the lowest are kept.
+    fn key(&self, value: u64) -> u64 {
+        match self.smallest {
+            true => u64::MAX - value,
+            false => value,
+        }
+    }
+    /// Rank `block`, the block at `height`, and its transactions. Those without fees aren't
+    /// ranked by them.
+    pub fn add(&mut self, height: u64, block: &Block) {
+        let value = match self.by {
+            Ranking::Size => Some(u64::from(block.total_size())),
+            Ranking::TxCount => Some(block.tx_count()),
+            Ranking::Fee => block.total_fees(),
+        };
+        if let Some(value) = value {
+            let key = self.key(value);
+            self.blocks.offer(key, || RankedBlock {
+                height,
+                hash: block.block_header().block_hash(),
+                size: block.total_size(),
+                tx_count: block.tx_count(),
+                fees: block.total_fees(),
+            });
+        }
+
+        for tx in block.transactions() {
+            let value = match self.by {
+                Ranking::Size => Some(u64::from(tx.total_size())),
+                Ranking::TxCount => None,
+                Ranking::Fee => tx.fee(),
+            };
+            if let Some(value) = value {
+                let key = self.key(value);
+                self.transactions.offer(key, || RankedTransaction {
+                    height,
+                    txid: to_display_hex(&tx.txid()),
+                    size: tx.total_size(),
+                    fee: tx.fee(),
+                });
+            }
+        }
+    }
+    /// The leaders of the blocks added, labelled as the range `from` to `to`.
+    pub fn finish(self, from: u64, to: u64) -> Leaderboard {
+        Leaderboard {
+            by: self.by,
+            smallest: self.smallest,
+            from,
+            to,
+            blocks: self.blocks.finish(),
+            transactions: self.transactions.finish(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LeaderboardCollector, Ranking};
+    use crate::util::fixtures::{block, BLOCK_1_HEX, GENESIS_BLOCK_HEX};
+
+    /// Checks blocks are ranked by size either way, ties going to the lower height, that
+    /// transactions aren't ranked by a count, and that without fees nothing is ranked by them.
+    #[test]
+    fn test_leaderboard() {
+        let blocks = [GENESIS_BLOCK_HEX, BLOCK_1_HEX, GENESIS_BLOCK_HEX].map(block);
+        let leaderboard = |by: Ranking, limit: usize, smallest: bool| {
+            let mut collector = LeaderboardCollector::new(by, limit, smallest);
+            for (height, block) in blocks.iter().enumerate() {
+                collector.add(height as u64, block);
+            }
+            collector.finish(0, 2)
+        };
+
+        let biggest = leaderboard(Ranking::Size, 2, false);
+        assert_eq!(
+            biggest
+                .blocks
+                .iter()
+                .map(|b| (b.height, b.size))
+                .collect::<Vec<_>>(),
+            [(0, 285), (2, 285)]
+        );
+        assert_eq!(biggest.transactions[0].height, 0);
+        assert_eq!(biggest.transactions[0].fee, None);
+        let smallest = leaderboard(Ranking::Size, 1, true);
+        assert_eq!(smallest.blocks[0].height, 1);
+
+        let by_count = leaderboard(Ranking::TxCount, 5, false);
+        assert_eq!(by_count.blocks.len(), 3);
+        assert!(by_count.transactions.is_empty());
+        let by_fee = leaderboard(Ranking::Fee, 5, false);
+        assert!(by_fee.blocks.is_empty() && by_fee.transactions.is_empty());
+
+        assert!("weight".parse::<Ranking>().is_err());
+    }
+}
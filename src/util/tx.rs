@@ -0,0 +1,793 @@
+//! Data structures for decoding the transactions contained in a [Block](crate::util::types::Block).
+use crate::util::{
+    constant::{
+        Network, LOCK_TIME, OUTPOINT_TXID, OUTPOINT_VOUT, SEGWIT_FLAG, SEGWIT_MARKER, SEQUENCE,
+        TX_OUT_VALUE, TX_VERSION, WITNESS_SCALE_FACTOR,
+    },
+    display::{format_amount, format_size, options},
+    error::ParseError,
+    hash::{sha256d, to_display_hex},
+    input::InputItem,
+    script::Script,
+    taproot::TaprootSpend,
+    timelock::{signals_rbf, LockTime, RelativeLockTime, SEQUENCE_FINAL},
+    types::{ByteCursor, CompactSize},
+};
+use colored::*;
+use hex::ToHex;
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use std::io::Read;
+
+/// A reference to an output of a previous transaction.
+#[derive(Debug)]
+pub struct OutPoint {
+    txid: Vec<u8>,
+    vout: Vec<u8>,
+}
+impl OutPoint {
+    pub fn from_raw_bytes<R: Read>(cursor: &mut ByteCursor<R>) -> Result<Self, ParseError> {
+        let txid = cursor.take(OUTPOINT_TXID, "outpoint txid")?;
+        let vout = cursor.take(OUTPOINT_VOUT, "outpoint vout")?;
+
+        Ok(Self { txid, vout })
+    }
+    /// The txid in human readable (reversed) byte order.
+    pub fn txid(&self) -> String {
+        to_display_hex(&self.txid)
+    }
+    pub fn vout(&self) -> u32 {
+        u32::from_le_bytes(
+            self.vout
+                .clone()
+                .try_into()
+                .expect("expected 4 bytes for vout"),
+        )
+    }
+}
+impl OutPoint {
+    /// Whether this is the all zero txid and max vout spent by coinbase inputs.
+    pub fn is_null(&self) -> bool {
+        self.txid.iter().all(|byte| *byte == 0) && self.vout() == u32::MAX
+    }
+    pub fn raw_bytes(&self) -> Vec<u8> {
+        [self.txid.as_slice(), &self.vout].concat()
+    }
+}
+impl std::fmt::Display for OutPoint {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}:{}", self.txid(), self.vout())
+    }
+}
+
+impl Serialize for OutPoint {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("OutPoint", 2)?;
+        state.serialize_field("txid", &self.txid())?;
+        state.serialize_field("vout", &self.vout())?;
+        state.end()
+    }
+}
+
+#[derive(Debug)]
+pub struct TxIn {
+    previous_output: OutPoint,
+    script_sig: Vec<u8>,
+    sequence: Vec<u8>,
+    witness: Vec<Vec<u8>>,
+    spent: Option<TxOut>,
+}
+impl TxIn {
+    pub fn from_raw_bytes<R: Read>(cursor: &mut ByteCursor<R>) -> Result<Self, ParseError> {
+        let previous_output = OutPoint::from_raw_bytes(cursor)?;
+        let script_len = CompactSize::length_from_raw_bytes(cursor, "script_sig")?;
+        let script_sig = cursor.take(script_len, "script_sig")?;
+        let sequence = cursor.take(SEQUENCE, "sequence")?;
+
+        Ok(Self {
+            previous_output,
+            script_sig,
+            sequence,
+            witness: Vec::new(),
+            spent: None,
+        })
+    }
+    /// Read the witness stack for this input, which is serialized
+    /// separately from the input itself, after all of the outputs.
+    fn witness_from_raw_bytes<R: Read>(
+        &mut self,
+        cursor: &mut ByteCursor<R>,
+    ) -> Result<(), ParseError> {
+        self.witness = (0..CompactSize::from_raw_bytes(cursor)?.to_u64())
+            .map(|_| {
+                let item_len = CompactSize::length_from_raw_bytes(cursor, "witness item")?;
+                cursor.take(item_len, "witness item")
+            })
+            .collect::<Result<Vec<Vec<u8>>, ParseError>>()?;
+        Ok(())
+    }
+    pub fn previous_output(&self) -> &OutPoint {
+        &self.previous_output
+    }
+    pub fn witness(&self) -> &[Vec<u8>] {
+        &self.witness
+    }
+    /// The output the input spends, known only once its transaction has been applied to a
+    /// [UtxoSet](crate::chain::utxo::UtxoSet) or read with its block's undo data.
+    pub fn spent(&self) -> Option<&TxOut> {
+        self.spent.as_ref()
+    }
+    /// The witness decoded as a taproot key path or script path spend, if it looks like one.
+    pub fn taproot_spend(&self) -> Option<TaprootSpend<'_>> {
+        TaprootSpend::from_witness(&self.witness)
+    }
+    /// The signatures, keys and redeem script the scriptSig pushes, or none if it isn't
+    /// recognised, as for a coinbase.
+    pub fn script_sig_items(&self) -> Vec<InputItem<'_>> {
+        match self.previous_output.is_null() {
+            true => Vec::new(),
+            false => InputItem::from_script_sig(&self.script_sig),
+        }
+    }
+    /// The signatures, keys and witness script of the witness, or none if it isn't
+    /// recognised or is a taproot spend, which [TxIn::taproot_spend] decodes instead.
+    pub fn witness_items(&self) -> Vec<InputItem<'_>> {
+        match self.taproot_spend() {
+            Some(_) => Vec::new(),
+            None => InputItem::from_witness(&self.witness),
+        }
+    }
+    /// The serialized input, excluding its witness.
+    pub fn raw_bytes(&self) -> Vec<u8> {
+        [
+            self.previous_output.raw_bytes().as_slice(),
+            &CompactSize::encode(self.script_sig.len() as u64),
+            &self.script_sig,
+            &self.sequence,
+        ]
+        .concat()
+    }
+    /// The serialized size of the input, excluding its witness.
+    pub fn size(&self) -> u32 {
+        OUTPOINT_TXID
+            + OUTPOINT_VOUT
+            + CompactSize::encoded_len(self.script_sig.len() as u64)
+            + self.script_sig.len() as u32
+            + SEQUENCE
+    }
+    /// The serialized witness stack.
+    pub fn witness_raw_bytes(&self) -> Vec<u8> {
+        let mut raw_bytes = CompactSize::encode(self.witness.len() as u64);
+        for item in &self.witness {
+            raw_bytes.extend(CompactSize::encode(item.len() as u64));
+            raw_bytes.extend(item);
+        }
+        raw_bytes
+    }
+    /// The serialized size of the witness stack.
+    pub fn witness_size(&self) -> u32 {
+        CompactSize::encoded_len(self.witness.len() as u64)
+            + self
+                .witness
+                .iter()
+                .map(|item| CompactSize::encoded_len(item.len() as u64) + item.len() as u32)
+                .sum::<u32>()
+    }
+    pub fn script_sig_bytes(&self) -> &[u8] {
+        &self.script_sig
+    }
+    pub fn script_sig(&self) -> String {
+        self.script_sig.encode_hex::<String>()
+    }
+    /// The scriptSig disassembled, or `None` for a coinbase, whose script is never run.
+    pub fn script_sig_asm(&self) -> Option<String> {
+        (!self.previous_output.is_null()).then(|| Script::new(&self.script_sig).asm())
+    }
+    pub fn sequence(&self) -> u32 {
+        u32::from_le_bytes(
+            self.sequence
+                .clone()
+                .try_into()
+                .expect("expected 4 bytes for sequence"),
+        )
+    }
+    /// Whether the input opts its transaction in to replacement by fee (BIP125).
+    pub fn signals_rbf(&self) -> bool {
+        signals_rbf(self.sequence())
+    }
+    /// The relative lock time (BIP68) of the input, in a transaction of `version`.
+    pub fn relative_lock_time(&self, version: u32) -> Option<RelativeLockTime> {
+        RelativeLockTime::from_sequence(self.sequence(), version)
+    }
+    /// What the sequence number means in a transaction of `version`, such as
+    /// "signals RBF, relative timelock 144 blocks (BIP68)", or `None` for a final input.
+    fn sequence_notes(&self, version: u32) -> Option<String> {
+        let mut notes = Vec::new();
+        if self.signals_rbf() {
+            notes.push("signals RBF".to_string());
+        }
+        if let Some(relative_lock_time) = self.relative_lock_time(version) {
+            notes.push(format!("relative timelock {relative_lock_time} (BIP68)"));
+        }
+        (!notes.is_empty()).then(|| notes.join(", "))
+    }
+}
+impl std::fmt::Display for TxIn {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Prev Output           : {}\nScript Sig            : {}",
+            self.previous_output,
+            self.script_sig(),
+        )?;
+        if let Some(spent) = &self.spent {
+            write!(
+                f,
+                "\nSpent Value           : {}\nSpent Script Pubkey   : {}",
+                format_amount(spent.value()),
+                spent.script_pubkey()
+            )?;
+            if let Some(address) = spent.address() {
+                write!(f, "\nSpent Address         : {address}")?;
+            }
+        }
+        if let Some(asm) = self
+            .script_sig_asm()
+            .filter(|asm| options().verbosity >= 1 && !asm.is_empty())
+        {
+            write!(f, "\nScript Sig Asm        : {asm}")?;
+        }
+        write!(f, "\nSequence              : {}", self.sequence())?;
+        if !self.witness.is_empty() {
+            write!(
+                f,
+                "\nWitness               : {}",
+                self.witness
+                    .iter()
+                    .map(hex::encode)
+                    .collect::<Vec<String>>()
+                    .join(" ")
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for TxIn {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("TxIn", 9)?;
+        state.serialize_field("previous_output", &self.previous_output)?;
+        state.serialize_field("spent", &self.spent)?;
+        state.serialize_field("script_sig", &self.script_sig())?;
+        state.serialize_field("script_sig_asm", &self.script_sig_asm())?;
+        state.serialize_field("sequence", &self.sequence())?;
+        state.serialize_field(
+            "witness",
+            &self
+                .witness
+                .iter()
+                .map(hex::encode)
+                .collect::<Vec<String>>(),
+        )?;
+        state.serialize_field("taproot_spend", &self.taproot_spend())?;
+        state.serialize_field("script_sig_items", &self.script_sig_items())?;
+        state.serialize_field("witness_items", &self.witness_items())?;
+        state.end()
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TxOut {
+    value: Vec<u8>,
+    script_pubkey: Vec<u8>,
+}
+impl TxOut {
+    /// An output paying `value` satoshis to `script_pubkey`, as when describing the output
+    /// an input spends for its sighash.
+    pub fn new(value: u64, script_pubkey: Vec<u8>) -> Self {
+        Self {
+            value: value.to_le_bytes().to_vec(),
+            script_pubkey,
+        }
+    }
+    pub fn from_raw_bytes<R: Read>(cursor: &mut ByteCursor<R>) -> Result<Self, ParseError> {
+        let value = cursor.take(TX_OUT_VALUE, "output value")?;
+        let script_len = CompactSize::length_from_raw_bytes(cursor, "script_pubkey")?;
+        let script_pubkey = cursor.take(script_len, "script_pubkey")?;
+
+        Ok(Self {
+            value,
+            script_pubkey,
+        })
+    }
+    /// The output value in satoshis.
+    pub fn value(&self) -> u64 {
+        u64::from_le_bytes(
+            self.value
+                .clone()
+                .try_into()
+                .expect("expected 8 bytes for value"),
+        )
+    }
+    pub fn script_pubkey(&self) -> String {
+        self.script_pubkey.encode_hex::<String>()
+    }
+    pub fn script_pubkey_bytes(&self) -> &[u8] {
+        &self.script_pubkey
+    }
+    pub fn script(&self) -> Script<'_> {
+        Script::new(&self.script_pubkey)
+    }
+    /// The address the output pays to, on the network set in the display options.
+    pub fn address(&self) -> Option<String> {
+        self.script()
+            .address(options().network.unwrap_or(Network::Mainnet))
+    }
+    pub fn raw_bytes(&self) -> Vec<u8> {
+        [
+            self.value.as_slice(),
+            &CompactSize::encode(self.script_pubkey.len() as u64),
+            &self.script_pubkey,
+        ]
+        .concat()
+    }
+    /// The serialized size of the output.
+    pub fn size(&self) -> u32 {
+        TX_OUT_VALUE
+            + CompactSize::encoded_len(self.script_pubkey.len() as u64)
+            + self.script_pubkey.len() as u32
+    }
+}
+impl std::fmt::Display for TxOut {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Value                 : {}\nScript Pubkey         : {}",
+            format_amount(self.value()),
+            self.script_pubkey(),
+        )?;
+        if options().verbosity >= 1 {
+            write!(f, "\nScript Pubkey Asm     : {}", self.script().asm())?;
+        }
+        write!(
+            f,
+            "\nScript Type           : {}",
+            self.script().script_type()
+        )?;
+        if let Some(output_key) = self.script().taproot_output_key() {
+            write!(f, "\nOutput Key            : {}", hex::encode(output_key))?;
+        }
+        if let Some(address) = self.address() {
+            write!(f, "\nAddress               : {address}")?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for TxOut {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("TxOut", 5)?;
+        state.serialize_field("value", &self.value())?;
+        state.serialize_field("script_pubkey", &self.script_pubkey())?;
+        state.serialize_field("script_pubkey_asm", &self.script().asm())?;
+        state.serialize_field("script_type", self.script().script_type().name())?;
+        state.serialize_field("address", &self.address())?;
+        state.end()
+    }
+}
+
+#[derive(Debug)]
+pub struct Transaction {
+    version: Vec<u8>,
+    segwit: bool,
+    inputs: Vec<TxIn>,
+    outputs: Vec<TxOut>,
+    lock_time: Vec<u8>,
+    /// The fee paid, once the values of the outputs it spends are known.
+    fee: Option<u64>,
+}
+impl Transaction {
+    /// Parse a transaction in either the legacy or the BIP144 witness serialization.
+    ///
+    /// A witness serialized transaction has a zero marker byte where the input count
+    /// would be, which can't be mistaken for a legacy transaction since those
+    /// always have at least one input.
+    pub fn from_raw_bytes<R: Read>(cursor: &mut ByteCursor<R>) -> Result<Self, ParseError> {
+        let version = cursor.take(TX_VERSION, "tx version")?;
+        let mut input_count = CompactSize::from_raw_bytes(cursor)?.to_u64();
+        let segwit = input_count == u64::from(SEGWIT_MARKER);
+        if segwit {
+            let offset = cursor.position();
+            let flag = cursor.take(1, "segwit flag")?[0];
+            if flag != SEGWIT_FLAG {
+                return Err(ParseError::BadSegwitFlag { flag, offset });
+            }
+            input_count = CompactSize::from_raw_bytes(cursor)?.to_u64();
+        }
+        let mut inputs = (0..input_count)
+            .map(|_| TxIn::from_raw_bytes(cursor))
+            .collect::<Result<Vec<TxIn>, ParseError>>()?;
+        let outputs = (0..CompactSize::from_raw_bytes(cursor)?.to_u64())
+            .map(|_| TxOut::from_raw_bytes(cursor))
+            .collect::<Result<Vec<TxOut>, ParseError>>()?;
+        if segwit {
+            for input in inputs.iter_mut() {
+                input.witness_from_raw_bytes(cursor)?;
+            }
+        }
+        let lock_time = cursor.take(LOCK_TIME, "lock time")?;
+
+        Ok(Self {
+            version,
+            segwit,
+            inputs,
+            outputs,
+            lock_time,
+            fee: None,
+        })
+    }
+    /// Whether this is a coinbase, the transaction with a single input
+    /// spending the null outpoint that creates a block's new coins.
+    pub fn is_coinbase(&self) -> bool {
+        matches!(self.inputs.as_slice(), [input] if input.previous_output.is_null())
+    }
+    /// Whether the transaction was witness serialized.
+    pub fn is_segwit(&self) -> bool {
+        self.segwit
+    }
+    /// The legacy serialization of the transaction, without any witness data.
+    pub fn stripped_raw_bytes(&self) -> Vec<u8> {
+        let mut raw_bytes = self.version.clone();
+        raw_bytes.extend(CompactSize::encode(self.inputs.len() as u64));
+        raw_bytes.extend(self.inputs.iter().flat_map(TxIn::raw_bytes));
+        raw_bytes.extend(CompactSize::encode(self.outputs.len() as u64));
+        raw_bytes.extend(self.outputs.iter().flat_map(TxOut::raw_bytes));
+        raw_bytes.extend(&self.lock_time);
+        raw_bytes
+    }
+    /// The full serialization, including the segwit marker, flag and witnesses when present.
+    pub fn raw_bytes(&self) -> Vec<u8> {
+        if !self.segwit {
+            return self.stripped_raw_bytes();
+        }
+        let mut raw_bytes = self.version.clone();
+        raw_bytes.extend([SEGWIT_MARKER, SEGWIT_FLAG]);
+        raw_bytes.extend(CompactSize::encode(self.inputs.len() as u64));
+        raw_bytes.extend(self.inputs.iter().flat_map(TxIn::raw_bytes));
+        raw_bytes.extend(CompactSize::encode(self.outputs.len() as u64));
+        raw_bytes.extend(self.outputs.iter().flat_map(TxOut::raw_bytes));
+        raw_bytes.extend(self.inputs.iter().flat_map(TxIn::witness_raw_bytes));
+        raw_bytes.extend(&self.lock_time);
+        raw_bytes
+    }
+    /// The transaction id, the double SHA256 of the stripped serialization, in serialized byte order.
+    pub fn txid(&self) -> [u8; 32] {
+        sha256d(&self.stripped_raw_bytes())
+    }
+    /// The witness transaction id (BIP141), the double SHA256 of the full serialization
+    /// including any witness data, in serialized byte order. The same as the txid when
+    /// there's no witness data.
+    ///
+    /// The witness merkle tree counts a coinbase's as all zeros rather than this.
+    pub fn wtxid(&self) -> [u8; 32] {
+        sha256d(&self.raw_bytes())
+    }
+    /// The size of the transaction in bytes with its witness data removed.
+    pub fn stripped_size(&self) -> u32 {
+        TX_VERSION
+            + CompactSize::encoded_len(self.inputs.len() as u64)
+            + self.inputs.iter().map(TxIn::size).sum::<u32>()
+            + CompactSize::encoded_len(self.outputs.len() as u64)
+            + self.outputs.iter().map(TxOut::size).sum::<u32>()
+            + LOCK_TIME
+    }
+    /// The serialized size of the transaction in bytes, including witness data.
+    pub fn total_size(&self) -> u32 {
+        let witness_size = if self.segwit {
+            // The marker and flag bytes, then a stack per input.
+            2 + self.inputs.iter().map(TxIn::witness_size).sum::<u32>()
+        } else {
+            0
+        };
+        self.stripped_size() + witness_size
+    }
+    /// The BIP141 weight, counting non-witness bytes four times and witness bytes once.
+    pub fn weight(&self) -> u32 {
+        self.stripped_size() * (WITNESS_SCALE_FACTOR - 1) + self.total_size()
+    }
+    /// The virtual size in vbytes, the weight divided by four and rounded up.
+    pub fn vsize(&self) -> u32 {
+        self.weight().div_ceil(WITNESS_SCALE_FACTOR)
+    }
+    pub fn version(&self) -> u32 {
+        u32::from_le_bytes(
+            self.version
+                .clone()
+                .try_into()
+                .expect("expected 4 bytes for tx version"),
+        )
+    }
+    /// The fee in satoshis, known only once the outputs the transaction spends are, as
+    /// with [TxIn::spent], and never for a coinbase.
+    pub fn fee(&self) -> Option<u64> {
+        self.fee
+    }
+    /// Record `prevouts`, the outputs spent by each input in order, and the fee they leave.
+    pub(crate) fn set_spent(&mut self, prevouts: &[TxOut]) {
+        if self.is_coinbase() {
+            return;
+        }
+        for (input, prevout) in self.inputs.iter_mut().zip(prevouts) {
+            input.spent = Some(prevout.clone());
+        }
+        let created = self.outputs.iter().map(TxOut::value).sum::<u64>();
+        let spent = prevouts.iter().map(TxOut::value).sum::<u64>();
+        self.fee = Some(spent.saturating_sub(created));
+    }
+    pub fn inputs(&self) -> &[TxIn] {
+        &self.inputs
+    }
+    pub fn outputs(&self) -> &[TxOut] {
+        &self.outputs
+    }
+    pub fn lock_time(&self) -> u32 {
+        u32::from_le_bytes(
+            self.lock_time
+                .clone()
+                .try_into()
+                .expect("expected 4 bytes for lock time"),
+        )
+    }
+    /// Whether the lock time is a block height or a time.
+    pub fn absolute_lock_time(&self) -> LockTime {
+        LockTime::from_consensus(self.lock_time())
+    }
+    /// Whether the lock time applies, which it only does when an input isn't final.
+    pub fn is_lock_time_enforced(&self) -> bool {
+        self.inputs
+            .iter()
+            .any(|input| input.sequence() != SEQUENCE_FINAL)
+    }
+    /// Whether any input opts the transaction in to replacement by fee (BIP125).
+    pub fn signals_rbf(&self) -> bool {
+        self.inputs.iter().any(TxIn::signals_rbf)
+    }
+}
+impl std::fmt::Display for Transaction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        writeln!(f, "Version               : {}", self.version())?;
+        writeln!(
+            f,
+            "Size                  : {} ({} stripped)",
+            format_size(self.total_size().into()),
+            format_size(self.stripped_size().into())
+        )?;
+        writeln!(
+            f,
+            "Weight                : {} WU ({} vB)",
+            self.weight(),
+            self.vsize()
+        )?;
+        if let Some(fee) = self.fee {
+            writeln!(
+                f,
+                "Fee                   : {} ({:.2} sat/vB)",
+                format_amount(fee),
+                fee as f64 / f64::from(self.vsize())
+            )?;
+        }
+        let verbose = options().verbosity >= 1;
+        for (index, input) in self.inputs().iter().enumerate() {
+            writeln!(f, "{}\n{}", format!("Input {index}:").yellow(), input)?;
+            if let Some(notes) = input.sequence_notes(self.version()).filter(|_| verbose) {
+                writeln!(f, "Sequence Notes        : {notes}")?;
+            }
+            if let Some(spend) = input.taproot_spend().filter(|_| verbose) {
+                writeln!(f, "{spend}")?;
+            }
+            if verbose {
+                for item in input.script_sig_items() {
+                    writeln!(f, "{item}")?;
+                }
+                for item in input.witness_items() {
+                    writeln!(f, "{item}")?;
+                }
+            }
+        }
+        for (index, output) in self.outputs().iter().enumerate() {
+            writeln!(f, "{}\n{}", format!("Output {index}:").yellow(), output)?;
+        }
+        write!(f, "Lock Time             : {}", self.lock_time())?;
+        let lock_time = self.absolute_lock_time();
+        if verbose && lock_time != LockTime::None {
+            write!(f, " ({lock_time}")?;
+            if !self.is_lock_time_enforced() {
+                write!(f, ", not enforced as every input is final")?;
+            }
+            write!(f, ")")?;
+        }
+        if options().verbosity >= 3 {
+            write!(
+                f,
+                "\nRaw                   : {}",
+                hex::encode(self.raw_bytes())
+            )?;
+        }
+        Ok(())
+    }
+}
+
+impl Serialize for Transaction {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Transaction", 16)?;
+        state.serialize_field("txid", &to_display_hex(&self.txid()))?;
+        state.serialize_field("wtxid", &to_display_hex(&self.wtxid()))?;
+        state.serialize_field("version", &self.version())?;
+        state.serialize_field("segwit", &self.segwit)?;
+        state.serialize_field("total_size", &self.total_size())?;
+        state.serialize_field("stripped_size", &self.stripped_size())?;
+        state.serialize_field("weight", &self.weight())?;
+        state.serialize_field("vsize", &self.vsize())?;
+        state.serialize_field("fee", &self.fee)?;
+        state.serialize_field("inputs", &self.inputs)?;
+        state.serialize_field("outputs", &self.outputs)?;
+        state.serialize_field("lock_time", &self.lock_time())?;
+        state.serialize_field("lock_time_type", self.absolute_lock_time().kind())?;
+        state.serialize_field("lock_time_enforced", &self.is_lock_time_enforced())?;
+        state.serialize_field("signals_rbf", &self.signals_rbf())?;
+        state.serialize_field(
+            "relative_lock_times",
+            &self
+                .inputs
+                .iter()
+                .map(|input| input.relative_lock_time(self.version()))
+                .collect::<Vec<_>>(),
+        )?;
+        state.end()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::util::{
+        fixtures::{arb_transaction, GENESIS_BLOCK_HEX},
+        hash::{sha256d, to_display_hex},
+        timelock::LockTime,
+        types::ByteCursor,
+        view::TransactionView,
+    };
+    use proptest::prelude::*;
+
+    proptest! {
+        /// Checks that parsing then serializing a transaction gives back its bytes, that
+        /// those parse to the same transaction, and that its view agrees.
+        #[test]
+        fn test_transaction_round_trip(raw_bytes in arb_transaction()) {
+            let tx = super::Transaction::from_raw_bytes(&mut ByteCursor::new(raw_bytes.as_slice()))
+                .unwrap();
+            prop_assert_eq!(tx.raw_bytes(), raw_bytes.as_slice());
+            prop_assert_eq!(tx.total_size(), raw_bytes.len() as u32);
+
+            let reparsed =
+                super::Transaction::from_raw_bytes(&mut ByteCursor::new(tx.raw_bytes().as_slice()))
+                    .unwrap();
+            prop_assert_eq!(reparsed.raw_bytes(), tx.raw_bytes());
+            prop_assert_eq!(reparsed.txid(), tx.txid());
+
+            let view = TransactionView::from_slice(&raw_bytes).unwrap();
+            prop_assert_eq!(view.raw_bytes(), raw_bytes.as_slice());
+            prop_assert_eq!(view.txid(), tx.txid());
+            prop_assert_eq!(view.wtxid(), tx.wtxid());
+        }
+    }
+
+    /// Checks that the genesis coinbase decodes into its known inputs and outputs.
+    #[test]
+    fn test_transaction() {
+        // The coinbase follows the 80 byte header and 1 byte tx count.
+        let raw_bytes = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        let mut cursor = ByteCursor::new(&raw_bytes[81..]);
+
+        let tx = super::Transaction::from_raw_bytes(&mut cursor).unwrap();
+
+        assert!(cursor.is_empty().unwrap());
+        assert_eq!(tx.version(), 1);
+        assert_eq!(tx.inputs().len(), 1);
+        assert_eq!(tx.inputs()[0].previous_output.vout(), u32::MAX);
+        assert_eq!(tx.inputs()[0].script_sig.len(), 77);
+        assert_eq!(tx.outputs().len(), 1);
+        assert_eq!(tx.outputs()[0].value(), 5_000_000_000);
+        assert_eq!(tx.lock_time(), 0);
+        assert_eq!(tx.absolute_lock_time(), LockTime::None);
+        assert!(!tx.is_lock_time_enforced());
+        assert!(!tx.signals_rbf());
+        assert!(!tx.is_segwit());
+        assert!(tx.is_coinbase());
+        assert_eq!(tx.total_size(), 204);
+        assert_eq!(tx.stripped_size(), 204);
+        assert_eq!(tx.weight(), 816);
+        assert_eq!(tx.vsize(), 204);
+        assert_eq!(tx.stripped_raw_bytes(), raw_bytes[81..]);
+        assert_eq!(
+            to_display_hex(&tx.txid()),
+            "4a5e1e4baab89f3a32518a88c31bc87f618f76673e2cc77ab2127b7afdeda33b"
+        );
+        assert_eq!(tx.wtxid(), tx.txid());
+    }
+
+    /// Checks that a witness serialized transaction is detected and its witness stack parsed.
+    #[test]
+    fn test_segwit_transaction() {
+        let raw_bytes = hex::decode(
+            [
+                "02000000",
+                "0001",
+                "01",
+                "11".repeat(32).as_str(),
+                "00000000",
+                "00",
+                "ffffffff",
+                "01",
+                "e803000000000000",
+                "16",
+                "0014",
+                "22".repeat(20).as_str(),
+                "02",
+                "01aa",
+                "02bbcc",
+                "00000000",
+            ]
+            .concat(),
+        )
+        .unwrap();
+        let mut cursor = ByteCursor::new(raw_bytes.as_slice());
+
+        let tx = super::Transaction::from_raw_bytes(&mut cursor).unwrap();
+
+        assert!(cursor.is_empty().unwrap());
+        assert!(tx.is_segwit());
+        assert_eq!(tx.inputs()[0].witness(), [vec![0xaa], vec![0xbb, 0xcc]]);
+        assert_eq!(tx.outputs()[0].value(), 1000);
+        assert_eq!(tx.stripped_size(), 82);
+        assert_eq!(tx.total_size(), raw_bytes.len() as u32);
+        assert_eq!(tx.weight(), 336);
+        assert_eq!(tx.vsize(), 84);
+        assert_eq!(tx.raw_bytes(), raw_bytes);
+        assert_eq!(tx.txid(), sha256d(&tx.stripped_raw_bytes()));
+        assert_eq!(tx.wtxid(), sha256d(&raw_bytes));
+        assert_ne!(tx.wtxid(), tx.txid());
+    }
+
+    /// Checks the lock time and sequence annotations of a version 2 transaction
+    /// locked to block 740000, whose input signals RBF with a 144 block relative timelock.
+    #[test]
+    fn test_timelocked_transaction() {
+        let raw_bytes = hex::decode(
+            [
+                "02000000",
+                "01",
+                "11".repeat(32).as_str(),
+                "00000000",
+                "00",
+                "90000000",
+                "01",
+                "e803000000000000",
+                "01",
+                "51",
+                "a04a0b00",
+            ]
+            .concat(),
+        )
+        .unwrap();
+        let tx =
+            super::Transaction::from_raw_bytes(&mut ByteCursor::new(raw_bytes.as_slice())).unwrap();
+
+        assert_eq!(tx.absolute_lock_time(), LockTime::Height(740_000));
+        assert!(tx.is_lock_time_enforced());
+        assert!(tx.signals_rbf());
+        assert_eq!(
+            tx.inputs()[0].sequence_notes(tx.version()).unwrap(),
+            "signals RBF, relative timelock 144 blocks (BIP68)"
+        );
+        assert_eq!(tx.inputs()[0].sequence_notes(1).unwrap(), "signals RBF");
+    }
+}
@@ -1,63 +1,294 @@
 //! Data structures for collecting, validating and converting bitcoin blockchain data into human readable output.
-use crate::util::constant::{
-    BLOCK_HEADER, BLOCK_HEADER_VERSION, BLOCK_SIZE, MAGIC_BYTES, MAINNET_HEX, MERKLE_ROOT_HASH,
-    NONCE, PREVIOUS_BLOCK_HEADER_HASH, TARGET, TX_COUNT, UNIX_EPOCH_TIME,
+use crate::{
+    chain::{Neighbour, Neighbours},
+    util::{
+        coinbase::{block_subsidy, BlockReward, Coinbase},
+        constant::{
+            Network, BLOCK_HEADER, BLOCK_HEADER_VERSION, BLOCK_SIZE, MAGIC_BYTES, MAX_TARGET_BITS,
+            MERKLE_ROOT_HASH, NONCE, PREVIOUS_BLOCK_HEADER_HASH, TARGET, TARGET_SPACING,
+            UNIX_EPOCH_TIME, WITNESS_SCALE_FACTOR,
+        },
+        display::{
+            format_amount, format_difficulty, format_hashrate, format_size, format_timestamp,
+            format_utc, options,
+        },
+        error::{ErrorKind, ParseError},
+        hash::{merkle_root, sha256d, to_display_hex},
+        tx::{Transaction, TxOut},
+        versionbits::{deployment, signaling_bits},
+    },
 };
 use anyhow::Result;
 use colored::*;
 use hex::ToHex;
+use serde::{ser::SerializeStruct, Serialize, Serializer};
+use std::io::{BufRead, BufReader, Read, Seek};
+
+/// A sequential reader over raw blockchain bytes.
+///
+/// Wraps any [Read] source in a [BufReader] so blocks can be parsed
+/// straight from disk in the order they were written.
+#[derive(Debug)]
+pub struct ByteCursor<R: Read> {
+    reader: BufReader<R>,
+    position: u64,
+}
+impl<R: Read> ByteCursor<R> {
+    pub fn new(reader: R) -> Self {
+        Self::starting_at(reader, 0)
+    }
+    /// Wrap a source that has already been advanced `position` bytes.
+    pub fn starting_at(reader: R, position: u64) -> Self {
+        Self {
+            reader: BufReader::new(reader),
+            position,
+        }
+    }
+    /// Take the next `n` bytes of `field`, in the order they appear in the source.
+    ///
+    /// The buffer grows as bytes arrive rather than up front, so a corrupt
+    /// length can't ask for more memory than the source actually holds.
+    pub fn take(&mut self, n: u32, field: &'static str) -> Result<Vec<u8>, ParseError> {
+        let offset = self.position;
+        let bytes = self.take_up_to(n)?;
+        if bytes.len() < n as usize {
+            return Err(ParseError::UnexpectedEof { field, offset });
+        }
+        tracing::debug!(field, offset, "parsed {}", hex::encode(&bytes));
+        Ok(bytes)
+    }
+    /// Take up to the next `n` bytes, fewer only when the source runs out first.
+    pub fn take_up_to(&mut self, n: u32) -> Result<Vec<u8>, ParseError> {
+        let offset = self.position;
+        let mut bytes = Vec::new();
+        (&mut self.reader)
+            .take(u64::from(n))
+            .read_to_end(&mut bytes)
+            .map_err(|source| ParseError::Io { source, offset })?;
+        self.position += bytes.len() as u64;
+        Ok(bytes)
+    }
+    /// Skip bytes for as long as `skip` returns true given each byte's offset and value,
+    /// returning how many were skipped.
+    pub fn skip_while(&mut self, mut skip: impl FnMut(u64, u8) -> bool) -> Result<u64, ParseError> {
+        let start = self.position;
+        loop {
+            let offset = self.position;
+            let buf = self
+                .reader
+                .fill_buf()
+                .map_err(|source| ParseError::Io { source, offset })?;
+            if buf.is_empty() {
+                break;
+            }
+            let n = buf
+                .iter()
+                .enumerate()
+                .take_while(|(i, byte)| skip(offset + *i as u64, **byte))
+                .count();
+            let done = n < buf.len();
+            self.reader.consume(n);
+            self.position += n as u64;
+            if done {
+                break;
+            }
+        }
+        Ok(self.position - start)
+    }
+    /// The offset of the next byte within the source.
+    pub fn position(&self) -> u64 {
+        self.position
+    }
+    /// Whether the source has no bytes left to read.
+    pub fn is_empty(&mut self) -> std::io::Result<bool> {
+        Ok(self.reader.fill_buf()?.is_empty())
+    }
+}
+impl<R: Read + Seek> ByteCursor<R> {
+    /// Skip the next `n` bytes by seeking past them rather than reading them.
+    ///
+    /// Seeking past the end of the source isn't an error, so the cursor is left empty
+    /// instead when fewer than `n` bytes remain.
+    pub fn skip(&mut self, n: u64) -> Result<(), ParseError> {
+        let offset = self.position;
+        let n = i64::try_from(n).map_err(|_| ParseError::UnexpectedEof {
+            field: "skipped bytes",
+            offset,
+        })?;
+        self.reader
+            .seek_relative(n)
+            .map_err(|source| ParseError::Io { source, offset })?;
+        self.position += n as u64;
+        Ok(())
+    }
+}
+
+/// A CompactSize variable length integer.
+///
+/// The first byte is either the value itself, or a marker
+/// for a 2, 4 or 8 byte little endian value that follows it.
+#[derive(Debug)]
+pub struct CompactSize {
+    raw_bytes: Vec<u8>,
+}
+impl CompactSize {
+    pub fn from_raw_bytes<R: Read>(cursor: &mut ByteCursor<R>) -> Result<Self, ParseError> {
+        let mut raw_bytes = cursor.take(1, "compact size")?;
+        let len = match raw_bytes[0] {
+            0xfd => 2,
+            0xfe => 4,
+            0xff => 8,
+            _ => 0,
+        };
+        raw_bytes.extend(cursor.take(len, "compact size")?);
+
+        Ok(Self { raw_bytes })
+    }
+    pub fn to_u64(&self) -> u64 {
+        match self.raw_bytes.as_slice() {
+            [value] => u64::from(*value),
+            [_, value @ ..] => value
+                .iter()
+                .rev()
+                .fold(0, |value, byte| (value << 8) | u64::from(*byte)),
+            [] => unreachable!("a compact size always has a first byte"),
+        }
+    }
+    /// Read a CompactSize giving the length in bytes of `field`, which must fit in a `u32`
+    /// for the bytes to be taken.
+    pub fn length_from_raw_bytes<R: Read>(
+        cursor: &mut ByteCursor<R>,
+        field: &'static str,
+    ) -> Result<u32, ParseError> {
+        let offset = cursor.position();
+        let length = Self::from_raw_bytes(cursor)?.to_u64();
+        u32::try_from(length).map_err(|_| ParseError::BadLength {
+            field,
+            length,
+            offset,
+        })
+    }
+    /// The bytes the value was parsed from, which needn't be its shortest form.
+    pub fn raw_bytes(&self) -> Vec<u8> {
+        self.raw_bytes.clone()
+    }
+    /// Encode `value` in its shortest form.
+    pub fn encode(value: u64) -> Vec<u8> {
+        match Self::encoded_len(value) {
+            1 => vec![value as u8],
+            3 => [[0xfd].as_slice(), &(value as u16).to_le_bytes()].concat(),
+            5 => [[0xfe].as_slice(), &(value as u32).to_le_bytes()].concat(),
+            _ => [[0xff].as_slice(), &value.to_le_bytes()].concat(),
+        }
+    }
+    /// The number of bytes needed to encode `value`.
+    pub fn encoded_len(value: u64) -> u32 {
+        match value {
+            0..=0xfc => 1,
+            0xfd..=0xffff => 3,
+            0x10000..=0xffff_ffff => 5,
+            _ => 9,
+        }
+    }
+}
+
+/// Where a block record starts within a chain of blk files.
+#[derive(Debug, Clone, Copy, Ord, PartialOrd, Eq, PartialEq, Hash)]
+pub struct BlockLocation {
+    /// The position of the file in its [FileChain](crate::reader::FileChain),
+    /// which is the blk file number when reading a data directory.
+    pub file: usize,
+    /// The byte offset of the record's magic bytes within the file.
+    pub offset: u64,
+}
 
 #[derive(Debug, Ord, PartialOrd, Eq, PartialEq)]
-pub(crate) struct BlockInfo {
-    pub(crate) height: u64,
+pub struct BlockInfo {
+    pub height: u64,
     magic_bytes: Vec<u8>,
     size: Vec<u8>,
+    block_hash: Option<[u8; 32]>,
+    location: Option<BlockLocation>,
+    neighbours: Option<Neighbours>,
 }
 impl BlockInfo {
-    pub(crate) fn new(height: u64, magic_bytes: Vec<u8>, size: Vec<u8>) -> Self {
+    pub fn new(height: u64, magic_bytes: Vec<u8>, size: Vec<u8>) -> Self {
         Self {
             height,
             magic_bytes,
             size,
+            block_hash: None,
+            location: None,
+            neighbours: None,
         }
     }
-    /// Get the size of a [Block] as a `u32`.
-    ///
-    /// Formats the size in bytes to little endian order
-    /// then converts to hexcode before parsing as `u32`.
-    pub(crate) fn size_as_u32(&self) -> u32 {
-        u32::from_str_radix(
+    /// Attach where the record was read from.
+    pub fn with_location(mut self, location: BlockLocation) -> Self {
+        self.location = Some(location);
+        self
+    }
+    pub fn location(&self) -> Option<BlockLocation> {
+        self.location
+    }
+    /// Attach the hash of the [Block] this info describes,
+    /// which is only known once the block header has been parsed.
+    pub fn with_block_hash(mut self, block_hash: [u8; 32]) -> Self {
+        self.block_hash = Some(block_hash);
+        self
+    }
+    /// Attach the blocks either side of this one on the best chain, which are only
+    /// known once the chain has been linked.
+    pub fn with_neighbours(mut self, neighbours: Neighbours) -> Self {
+        self.neighbours = Some(neighbours);
+        self
+    }
+    pub fn neighbours(&self) -> Option<Neighbours> {
+        self.neighbours
+    }
+    /// Get the size of a [Block] as a `u32`, stored little endian like every number in a record.
+    pub fn size_as_u32(&self) -> u32 {
+        u32::from_le_bytes(
             self.size
                 .clone()
-                .into_iter()
-                .rev()
-                .collect::<Vec<u8>>()
-                .encode_hex::<String>()
-                .as_str(),
-            16,
+                .try_into()
+                .expect("expected 4 bytes for size"),
         )
-        .expect("unable to convert hex to u32")
     }
-    pub(crate) fn network_hex(&self) -> String {
+    pub fn magic_bytes(&self) -> &[u8] {
+        &self.magic_bytes
+    }
+    pub fn network_hex(&self) -> String {
         self.magic_bytes.encode_hex::<String>()
     }
-    pub(crate) fn validate_network(&self) -> Result<()> {
-        if self.network_hex().as_str() != MAINNET_HEX {
-            anyhow::bail!("network validation failed")
+    /// Identify the network from the magic bytes.
+    pub fn network(&self) -> Result<Network> {
+        Network::from_magic_bytes(&self.magic_bytes)
+            .ok_or_else(|| anyhow::anyhow!("unknown network magic bytes {}", self.network_hex()))
+    }
+    pub fn validate_network(&self, network: Network) -> Result<()> {
+        if self.magic_bytes != network.magic_bytes() {
+            return Err(ErrorKind::WrongNetwork.error(format!(
+                "network validation failed: expected {network} magic bytes {}, found {}",
+                hex::encode(network.magic_bytes()),
+                self.network_hex()
+            )));
         }
         Ok(())
     }
-    pub(crate) fn from_raw_bytes(raw_bytes: &mut Vec<u8>, height: u64) -> Self {
-        let magic_bytes = (0..MAGIC_BYTES)
-            .map(|_| raw_bytes.pop().expect("expected a value for magic_bytes"))
-            .collect::<Vec<u8>>();
-        let size: Vec<u8> = (0..BLOCK_SIZE)
-            .map(|_| raw_bytes.pop().expect("expected a value for size"))
-            .collect::<Vec<u8>>();
+    pub fn from_raw_bytes<R: Read>(
+        cursor: &mut ByteCursor<R>,
+        height: u64,
+    ) -> Result<Self, ParseError> {
+        let magic_bytes = cursor.take(MAGIC_BYTES, "magic_bytes")?;
+        let size = cursor.take(BLOCK_SIZE, "size")?;
 
-        BlockInfo::new(height, magic_bytes, size)
+        Ok(BlockInfo::new(height, magic_bytes, size))
     }
-    pub(crate) fn log(&self) {
+    /// The magic bytes and size that frame the record, as stored in blk files.
+    pub fn raw_bytes(&self) -> Vec<u8> {
+        [self.magic_bytes.as_slice(), &self.size].concat()
+    }
+    pub fn log(&self) {
         println!("{}", self)
     }
 }
@@ -65,48 +296,297 @@ impl std::fmt::Display for BlockInfo {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{}\n\nBlock at Height       : {}\nNetwork               : {}\nBlock Size            : {}\n",
+            "{}\n\nBlock at Height       : {}\n",
             "> BlockInfo".green(),
             self.height,
+        )?;
+        if let Some(block_hash) = self.block_hash {
+            writeln!(f, "Block Hash            : {}", to_display_hex(&block_hash))?;
+        }
+        if let Some(neighbours) = self.neighbours {
+            let describe = |neighbour: Option<Neighbour>, missing: &str| {
+                neighbour.map_or(missing.to_string(), |neighbour| {
+                    format!("{} ({})", neighbour.height, to_display_hex(&neighbour.hash))
+                })
+            };
+            writeln!(
+                f,
+                "Previous Block        : {}\nNext Block            : {}",
+                describe(
+                    neighbours.previous,
+                    match self.height {
+                        0 => "none, the genesis block has no parent",
+                        _ => "none, its parent isn't on the best chain",
+                    }
+                ),
+                describe(
+                    neighbours.next,
+                    "none, no block on the best chain builds on it"
+                ),
+            )?;
+        }
+        writeln!(
+            f,
+            "Network               : {} ({})\nBlock Size            : {}",
+            self.network()
+                .map(|network| network.name())
+                .unwrap_or("unknown"),
             self.network_hex(),
-            self.size_as_u32()
+            format_size(self.size_as_u32().into())
+        )
+    }
+}
+
+impl Serialize for BlockInfo {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("BlockInfo", 7)?;
+        state.serialize_field("height", &self.height)?;
+        state.serialize_field(
+            "block_hash",
+            &self.block_hash.map(|hash| to_display_hex(&hash)),
+        )?;
+        state.serialize_field(
+            "network",
+            &self.network().ok().map(|network| network.name()),
+        )?;
+        state.serialize_field("magic_bytes", &self.network_hex())?;
+        state.serialize_field("size", &self.size_as_u32())?;
+        state.serialize_field(
+            "previous_block",
+            &self.neighbours.and_then(|neighbours| neighbours.previous),
+        )?;
+        state.serialize_field(
+            "next_block",
+            &self.neighbours.and_then(|neighbours| neighbours.next),
+        )?;
+        state.end()
+    }
+}
+
+/// Where the value of a block's transactions came from and went, and the coins, unspent
+/// outputs, they spent and created.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct ValueFlow {
+    /// The value of every output spent by the block's inputs.
+    pub input_value: u64,
+    /// The value of every output, the coinbase's included.
+    pub output_value: u64,
+    pub fees: u64,
+    /// The outputs spent, one for each input that isn't a coinbase's.
+    pub coins_spent: u64,
+    /// The outputs created that can ever be spent, so join the utxo set.
+    pub coins_created: u64,
+}
+// The fees are shown with the block's other totals, so only what's new is here.
+impl std::fmt::Display for ValueFlow {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Input Value           : {}\nOutput Value          : {}\nCoins Spent           : {}\nCoins Created         : {}",
+            format_amount(self.input_value),
+            format_amount(self.output_value),
+            self.coins_spent,
+            self.coins_created,
         )
     }
 }
 
 #[derive(Debug)]
-pub(crate) struct Block {
+pub struct Block {
     block_header: BlockHeader,
-    tx_count: Vec<u8>,
-    tx_data: Vec<u8>,
+    tx_count: CompactSize,
+    transactions: Vec<Transaction>,
+    /// The fees paid by every transaction together, once they're known.
+    total_fees: Option<u64>,
+    /// The height of the block on the best chain, when it was found there.
+    height: Option<u64>,
 }
 impl Block {
-    pub(crate) fn new(block_header: BlockHeader, tx_count: Vec<u8>, tx_data: Vec<u8>) -> Self {
+    pub fn new(
+        block_header: BlockHeader,
+        tx_count: CompactSize,
+        transactions: Vec<Transaction>,
+    ) -> Self {
         Self {
             block_header,
             tx_count,
-            tx_data,
+            transactions,
+            total_fees: None,
+            height: None,
         }
     }
-    pub(crate) fn from_raw_bytes(raw_bytes: &mut Vec<u8>, block_size: u32) -> Self {
-        let mut raw_block_header = (0..BLOCK_HEADER)
-            .map(|_| raw_bytes.pop().expect("expected a value for block_header"))
-            .collect::<Vec<u8>>();
-        let block_header = BlockHeader::from_raw_bytes(&mut raw_block_header);
-        let tx_count = (0..TX_COUNT)
-            .map(|_| raw_bytes.pop().expect("expected a value for tx_count"))
-            .collect::<Vec<u8>>();
-        let tx_data = (0..block_size - (BLOCK_HEADER + TX_COUNT))
-            .map(|_| raw_bytes.pop().expect("expected a value for tx_data"))
-            .collect::<Vec<u8>>();
+    pub fn from_raw_bytes<R: Read>(cursor: &mut ByteCursor<R>) -> Result<Self, ParseError> {
+        let block_header = BlockHeader::from_raw_bytes(cursor)?;
+        let tx_count = CompactSize::from_raw_bytes(cursor)?;
+        let transactions = (0..tx_count.to_u64())
+            .map(|_| Transaction::from_raw_bytes(cursor))
+            .collect::<Result<Vec<Transaction>, ParseError>>()?;
 
-        Block::new(block_header, tx_count, tx_data)
+        Ok(Block::new(block_header, tx_count, transactions))
+    }
+    pub fn block_header(&self) -> &BlockHeader {
+        &self.block_header
+    }
+    pub fn tx_count(&self) -> u64 {
+        self.tx_count.to_u64()
+    }
+    /// The fees paid by every transaction together, known only once the block has been
+    /// applied to a [UtxoSet](crate::chain::utxo::UtxoSet).
+    pub fn total_fees(&self) -> Option<u64> {
+        self.total_fees
+    }
+    /// Record the outputs each transaction spent, in order and none for the coinbase, filling
+    /// in the fee of each and the block's total.
+    pub fn set_spent(&mut self, spent: &[Vec<TxOut>]) {
+        for (tx, prevouts) in self.transactions.iter_mut().zip(spent) {
+            tx.set_spent(prevouts);
+        }
+        self.total_fees = Some(self.transactions.iter().filter_map(Transaction::fee).sum());
+    }
+    /// The value the block's transactions spent and created, known only once the outputs
+    /// they spend are, as with [Block::total_fees].
+    pub fn value_flow(&self) -> Option<ValueFlow> {
+        let fees = self.total_fees?;
+        let spending = self.transactions.iter().filter(|tx| !tx.is_coinbase());
+        let outputs = self.transactions.iter().flat_map(Transaction::outputs);
+        Some(ValueFlow {
+            input_value: spending
+                .clone()
+                .flat_map(Transaction::inputs)
+                .filter_map(|input| input.spent())
+                .map(TxOut::value)
+                .sum(),
+            output_value: outputs.clone().map(TxOut::value).sum(),
+            fees,
+            coins_spent: spending.map(|tx| tx.inputs().len() as u64).sum(),
+            coins_created: outputs
+                .filter(|output| !output.script().is_unspendable())
+                .count() as u64,
+        })
     }
-    fn tx_count(&self) -> u32 {
-        u32::from_str_radix(self.tx_count.clone().encode_hex::<String>().as_str(), 16)
-            .expect("unable to convert hex to u32")
+    /// Label the block with its `height` on the best chain.
+    pub fn with_height(mut self, height: u64) -> Self {
+        self.height = Some(height);
+        self
     }
-    pub(crate) fn log(&self) {
+    /// The height of the block, as labelled with [Block::with_height] or otherwise as its
+    /// coinbase commits to (BIP34).
+    pub fn height(&self) -> Option<u64> {
+        self.height.or_else(|| self.coinbase()?.bip34_height())
+    }
+    /// What the coinbase was allowed to claim at the block's height on `network` and what
+    /// it claimed, `None` when the height or coinbase isn't known.
+    pub fn reward(&self, network: Network) -> Option<BlockReward> {
+        let height = self.height()?;
+        Some(BlockReward {
+            height,
+            subsidy: block_subsidy(height, network),
+            claimed: self.coinbase()?.reward(),
+            fees: self.total_fees,
+        })
+    }
+    pub fn coinbase(&self) -> Option<Coinbase<'_>> {
+        Coinbase::from_block(self)
+    }
+    /// Compute the merkle root of the block's transactions from their txids.
+    pub fn merkle_root(&self) -> Option<[u8; 32]> {
+        merkle_root(self.transactions.iter().map(Transaction::txid).collect())
+    }
+    /// Check that the transactions parsed are the ones the header commits to.
+    pub fn verify_merkle_root(&self) -> Result<()> {
+        let computed = self
+            .merkle_root()
+            .ok_or_else(|| anyhow::anyhow!("merkle root verification failed: no transactions"))?;
+        if computed != self.block_header.merkle_root() {
+            anyhow::bail!(
+                "merkle root verification failed: computed {}, header has {}",
+                to_display_hex(&computed),
+                self.block_header.merkle_root_hash()
+            )
+        }
+        Ok(())
+    }
+    /// The root of the merkle tree of the block's wtxids, with the coinbase's counted as
+    /// all zeros, or `None` for a block without transactions.
+    pub fn witness_merkle_root(&self) -> Option<[u8; 32]> {
+        let coinbase = std::iter::once([0; 32]);
+        let rest = self.transactions.iter().skip(1).map(Transaction::wtxid);
+        merkle_root(coinbase.chain(rest).take(self.transactions.len()).collect())
+    }
+    /// Check the witness data parsed is what the coinbase commits to (BIP141): the double
+    /// SHA256 of the witness merkle root and the witness reserved value.
+    ///
+    /// A block without a commitment passes as long as none of its transactions has
+    /// witness data, like every block from before segwit.
+    pub fn verify_witness_commitment(&self) -> Result<()> {
+        let fail =
+            |reason: String| anyhow::anyhow!("witness commitment verification failed: {reason}");
+        let coinbase = self
+            .coinbase()
+            .ok_or_else(|| fail("no coinbase".to_string()))?;
+        let Some(commitment) = coinbase.witness_commitment() else {
+            if let Some(index) = self.transactions.iter().position(Transaction::is_segwit) {
+                return Err(fail(format!(
+                    "transaction {index} has witness data but the coinbase has no commitment"
+                )));
+            }
+            return Ok(());
+        };
+        let reserved_value = coinbase.witness_reserved_value().ok_or_else(|| {
+            fail("the coinbase witness isn't a single 32 byte reserved value".to_string())
+        })?;
+        let witness_root = self
+            .witness_merkle_root()
+            .expect("expected transactions in a block with a coinbase");
+        let computed = sha256d(&[witness_root, reserved_value].concat());
+        if computed != commitment {
+            return Err(fail(format!(
+                "computed {}, coinbase commits to {}",
+                hex::encode(computed),
+                hex::encode(commitment)
+            )));
+        }
+        Ok(())
+    }
+    /// The full serialization of the block, as stored in blk files after the magic bytes and size.
+    pub fn raw_bytes(&self) -> Vec<u8> {
+        let mut raw_bytes = self.block_header.raw_bytes();
+        raw_bytes.extend(CompactSize::encode(self.tx_count()));
+        raw_bytes.extend(self.transactions.iter().flat_map(Transaction::raw_bytes));
+        raw_bytes
+    }
+    /// The serialized size of the block in bytes, including witness data.
+    pub fn total_size(&self) -> u32 {
+        BLOCK_HEADER
+            + CompactSize::encoded_len(self.tx_count())
+            + self
+                .transactions
+                .iter()
+                .map(Transaction::total_size)
+                .sum::<u32>()
+    }
+    /// The size of the block in bytes with all witness data removed.
+    pub fn stripped_size(&self) -> u32 {
+        BLOCK_HEADER
+            + CompactSize::encoded_len(self.tx_count())
+            + self
+                .transactions
+                .iter()
+                .map(Transaction::stripped_size)
+                .sum::<u32>()
+    }
+    /// The BIP141 block weight, limited to 4,000,000 weight units by consensus.
+    pub fn weight(&self) -> u32 {
+        self.stripped_size() * (WITNESS_SCALE_FACTOR - 1) + self.total_size()
+    }
+    /// The virtual size in vbytes, the weight divided by four and rounded up.
+    pub fn vsize(&self) -> u32 {
+        self.weight().div_ceil(WITNESS_SCALE_FACTOR)
+    }
+    pub fn transactions(&self) -> &[Transaction] {
+        &self.transactions
+    }
+    pub fn log(&self) {
         println!("{}", self)
     }
 }
@@ -114,23 +594,110 @@ impl std::fmt::Display for Block {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
         write!(
             f,
-            "{}\n\n{}\nVersion               : {}\nPrev BlockHeader Hash : {}\nMerkle Root Hash      : {}\nUnix Epoch Time       : {}\nTarget                : {}\nNonce                 : {}\n\n{}\nTX Count              : {}\nTX Data               : {:?}",
+            "{}\n\n{}\n{}",
             "> Block".green(),
             "BlockHeader:".cyan(),
-            self.block_header.version(),
-            self.block_header.previous_block_header_hash(),
-            self.block_header.merkle_root_hash(),
-            self.block_header.unix_epoch_time(),
-            self.block_header.target(),
-            self.block_header.nonce(),
-            "Transactions:".cyan(),
-            self.tx_count(),
-            self.tx_data,
-        )
+            self.block_header
+        )?;
+        // The genesis coinbase message is the point of looking at the genesis block,
+        // so it's shown at every verbosity rather than only with the coinbase.
+        if let Some(message) = self
+            .block_header
+            .is_genesis()
+            .then(|| self.coinbase().and_then(|coinbase| coinbase.miner_tag()))
+            .flatten()
+        {
+            write!(f, "\nGenesis Message       : \"{message}\"")?;
+        }
+        // Coins left unclaimed are lost for good, which is rare enough to always point out.
+        let reward = self.reward(options().network.unwrap_or(Network::Mainnet));
+        if let Some(reward) = reward.filter(|reward| reward.unclaimed() > 0) {
+            write!(
+                f,
+                "\n{}",
+                format!(
+                    "Unclaimed             : {}, the coinbase claims less than allowed",
+                    format_amount(reward.unclaimed())
+                )
+                .yellow()
+            )?;
+        }
+        if options().show_miner {
+            let miner = self.coinbase().and_then(|coinbase| coinbase.miner());
+            write!(
+                f,
+                "\nMiner                 : {}",
+                miner.unwrap_or("unknown")
+            )?;
+        }
+        let verbosity = options().verbosity;
+        if verbosity >= 3 {
+            write!(f, "\n\n{}", "Raw Header Fields:".cyan())?;
+            for (name, bytes) in self.block_header.raw_fields() {
+                write!(f, "\n{name:<22}: {}", hex::encode(bytes))?;
+            }
+        }
+        if verbosity >= 1 {
+            write!(
+                f,
+                "\n\n{}\nTX Count              : {}\nTotal Size            : {}\nStripped Size         : {}\nWeight                : {} WU\nVirtual Size          : {} vB",
+                "Transactions:".cyan(),
+                self.tx_count(),
+                format_size(self.total_size().into()),
+                format_size(self.stripped_size().into()),
+                self.weight(),
+                self.vsize(),
+            )?;
+            if let Some(total_fees) = self.total_fees {
+                write!(f, "\nTotal Fees            : {}", format_amount(total_fees))?;
+            }
+            if let Some(flow) = self.value_flow() {
+                write!(f, "\n\n{}\n{flow}", "Value Flow:".cyan())?;
+            }
+            if let Some(coinbase) = self.coinbase() {
+                write!(f, "\n\n{coinbase}")?;
+            }
+            if let Some(reward) = reward {
+                write!(f, "\n{reward}")?;
+            }
+        }
+        if verbosity >= 2 {
+            for (index, tx) in self.transactions().iter().enumerate() {
+                write!(
+                    f,
+                    "\n\n{}\nTXID                  : {}\nWTXID                 : {}\n{}",
+                    format!("Transaction {index}:").cyan(),
+                    to_display_hex(&tx.txid()),
+                    to_display_hex(&tx.wtxid()),
+                    tx
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
+impl Serialize for Block {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("Block", 11)?;
+        state.serialize_field("block_header", &self.block_header)?;
+        state.serialize_field("tx_count", &self.tx_count())?;
+        state.serialize_field("total_size", &self.total_size())?;
+        state.serialize_field("stripped_size", &self.stripped_size())?;
+        state.serialize_field("weight", &self.weight())?;
+        state.serialize_field("vsize", &self.vsize())?;
+        state.serialize_field("total_fees", &self.total_fees)?;
+        state.serialize_field("value_flow", &self.value_flow())?;
+        state.serialize_field("coinbase", &self.coinbase())?;
+        state.serialize_field(
+            "reward",
+            &self.reward(options().network.unwrap_or(Network::Mainnet)),
+        )?;
+        state.serialize_field("transactions", &self.transactions)?;
+        state.end()
     }
 }
 #[derive(Debug)]
-pub(crate) struct BlockHeader {
+pub struct BlockHeader {
     version: Vec<u8>,
     previous_block_header_hash: Vec<u8>,
     merkle_root_hash: Vec<u8>,
@@ -139,7 +706,7 @@ pub(crate) struct BlockHeader {
     nonce: Vec<u8>,
 }
 impl BlockHeader {
-    fn new(
+    pub fn new(
         version: Vec<u8>,
         previous_block_header_hash: Vec<u8>,
         merkle_root_hash: Vec<u8>,
@@ -156,92 +723,604 @@ impl BlockHeader {
             nonce,
         }
     }
-    fn from_raw_bytes(raw_block_header: &mut Vec<u8>) -> Self {
-        let version = (0..BLOCK_HEADER_VERSION)
-            .map(|_| {
-                raw_block_header
-                    .pop()
-                    .expect("expected a value for version")
-            })
-            .collect::<Vec<u8>>();
-        let previous_block_header_hash = (0..PREVIOUS_BLOCK_HEADER_HASH)
-            .map(|_| {
-                raw_block_header
-                    .pop()
-                    .expect("expected a value for previous hash")
-            })
-            .collect::<Vec<u8>>();
-        let merkle_root_hash = (0..MERKLE_ROOT_HASH)
-            .map(|_| {
-                raw_block_header
-                    .pop()
-                    .expect("expected a value for merkle hash")
-            })
-            .collect::<Vec<u8>>();
-        let unix_epoch_time = (0..UNIX_EPOCH_TIME)
-            .map(|_| {
-                raw_block_header
-                    .pop()
-                    .expect("expected a value for unix epoch time")
-            })
-            .collect::<Vec<u8>>();
-        let target = (0..TARGET)
-            .map(|_| raw_block_header.pop().expect("expected a value for target"))
-            .collect::<Vec<u8>>();
-        let nonce = (0..NONCE)
-            .map(|_| raw_block_header.pop().expect("expected a value for nonce"))
-            .collect::<Vec<u8>>();
-
-        BlockHeader::new(
+    pub fn from_raw_bytes<R: Read>(cursor: &mut ByteCursor<R>) -> Result<Self, ParseError> {
+        let version = cursor.take(BLOCK_HEADER_VERSION, "version")?;
+        let previous_block_header_hash =
+            cursor.take(PREVIOUS_BLOCK_HEADER_HASH, "previous hash")?;
+        let merkle_root_hash = cursor.take(MERKLE_ROOT_HASH, "merkle hash")?;
+        let unix_epoch_time = cursor.take(UNIX_EPOCH_TIME, "unix epoch time")?;
+        let target = cursor.take(TARGET, "target")?;
+        let nonce = cursor.take(NONCE, "nonce")?;
+
+        Ok(BlockHeader::new(
             version,
             previous_block_header_hash,
             merkle_root_hash,
             unix_epoch_time,
             target,
             nonce,
-        )
+        ))
+    }
+    /// The serialized 80 byte header, the preimage of the block hash.
+    pub fn raw_bytes(&self) -> Vec<u8> {
+        [
+            self.version.as_slice(),
+            &self.previous_block_header_hash,
+            &self.merkle_root_hash,
+            &self.unix_epoch_time,
+            &self.target,
+            &self.nonce,
+        ]
+        .concat()
     }
-    fn version(&self) -> u32 {
-        u32::from_str_radix(self.version.clone().encode_hex::<String>().as_str(), 16)
-            .expect("unable to convert hex to u32")
+    /// Each field of the header by name, as the bytes appear in the serialization.
+    pub fn raw_fields(&self) -> [(&'static str, &[u8]); 6] {
+        [
+            ("Version", &self.version),
+            ("Prev BlockHeader Hash", &self.previous_block_header_hash),
+            ("Merkle Root Hash", &self.merkle_root_hash),
+            ("Unix Epoch Time", &self.unix_epoch_time),
+            ("Target", &self.target),
+            ("Nonce", &self.nonce),
+        ]
     }
-    fn previous_block_header_hash(&self) -> String {
+    /// The double SHA256 of the header in serialized byte order.
+    pub fn hash(&self) -> [u8; 32] {
+        sha256d(&self.raw_bytes())
+    }
+    /// The hash of the parent block in serialized byte order.
+    pub fn previous_hash(&self) -> [u8; 32] {
         self.previous_block_header_hash
             .clone()
-            .encode_hex::<String>()
+            .try_into()
+            .expect("expected 32 bytes for previous hash")
+    }
+    /// The merkle root in serialized byte order.
+    pub fn merkle_root(&self) -> [u8; 32] {
+        self.merkle_root_hash
+            .clone()
+            .try_into()
+            .expect("expected 32 bytes for merkle root")
     }
-    fn merkle_root_hash(&self) -> String {
-        self.merkle_root_hash.clone().encode_hex::<String>()
+    /// The block hash in human readable order.
+    pub fn block_hash(&self) -> String {
+        to_display_hex(&self.hash())
     }
-    fn unix_epoch_time(&self) -> u32 {
-        u32::from_str_radix(
-            self.unix_epoch_time.clone().encode_hex::<String>().as_str(),
-            16,
+    pub fn version(&self) -> u32 {
+        u32::from_le_bytes(
+            self.version
+                .clone()
+                .try_into()
+                .expect("expected 4 bytes for version"),
         )
-        .expect("unable to convert hex to u32")
     }
-    fn target(&self) -> u32 {
-        u32::from_str_radix(self.target.clone().encode_hex::<String>().as_str(), 16)
-            .expect("unable to convert hex to u32")
+    /// The BIP9 bits the version signals, or `None` when it doesn't use version bits.
+    pub fn signaling_bits(&self) -> Option<Vec<u8>> {
+        signaling_bits(self.version())
+    }
+    /// The names of the known deployments the block signaled for on `network`.
+    pub fn signaled_deployments(&self, network: Network) -> Vec<&'static str> {
+        self.signaling_bits()
+            .unwrap_or_default()
+            .into_iter()
+            .filter_map(|bit| deployment(bit, self.unix_epoch_time(), network))
+            .map(|deployment| deployment.name)
+            .collect()
+    }
+    /// The version in decimal and hex, followed by what its signaling bits stand for.
+    pub(crate) fn version_summary(&self) -> String {
+        let version = self.version();
+        let mut summary = format!("{version} ({version:#010x})");
+        if let Some(bits) = self.signaling_bits() {
+            let network = options().network.unwrap_or(Network::Mainnet);
+            let signals = bits
+                .into_iter()
+                .map(
+                    |bit| match deployment(bit, self.unix_epoch_time(), network) {
+                        Some(deployment) => format!("{} (bit {bit})", deployment.name),
+                        None => format!("bit {bit}"),
+                    },
+                )
+                .collect::<Vec<String>>();
+            match signals.is_empty() {
+                true => summary.push_str(", signaling nothing"),
+                false => summary.push_str(&format!(", signaling {}", signals.join(", "))),
+            }
+        }
+        summary
+    }
+    pub fn previous_block_header_hash(&self) -> String {
+        to_display_hex(&self.previous_block_header_hash)
     }
-    fn nonce(&self) -> u32 {
-        u32::from_str_radix(self.nonce.clone().encode_hex::<String>().as_str(), 16)
-            .expect("unable to convert hex to u32")
+    /// Whether this is a genesis block, the only kind whose previous hash is all zeros.
+    pub fn is_genesis(&self) -> bool {
+        self.previous_block_header_hash
+            .iter()
+            .all(|byte| *byte == 0)
+    }
+    pub fn merkle_root_hash(&self) -> String {
+        to_display_hex(&self.merkle_root_hash)
+    }
+    /// The block timestamp, in seconds since the unix epoch.
+    pub fn unix_epoch_time(&self) -> u32 {
+        u32::from_le_bytes(
+            self.unix_epoch_time
+                .clone()
+                .try_into()
+                .expect("expected 4 bytes for unix epoch time"),
+        )
+    }
+    /// The compact encoded target (nBits) the block hash must not exceed.
+    pub fn target(&self) -> u32 {
+        u32::from_le_bytes(
+            self.target
+                .clone()
+                .try_into()
+                .expect("expected 4 bytes for target"),
+        )
+    }
+    /// Expand the compact target into the full 256 bit target, in big endian order.
+    ///
+    /// The top byte of nBits is a base 256 exponent and the lower three bytes
+    /// a mantissa, so the target is `mantissa * 256^(exponent - 3)`.
+    pub fn expanded_target(&self) -> Result<[u8; 32]> {
+        let bits = self.target();
+        let exponent = (bits >> 24) as i32;
+        let mantissa = bits & 0x007f_ffff;
+        if bits & 0x0080_0000 != 0 && mantissa != 0 {
+            anyhow::bail!("target {bits:#010x} is negative")
+        }
+        let mut target = [0; 32];
+        for (index, byte) in mantissa.to_be_bytes()[1..].iter().enumerate() {
+            // The power of 256 this mantissa byte is multiplied by.
+            let power = exponent - 1 - index as i32;
+            if *byte == 0 || power < 0 {
+                continue;
+            }
+            if power >= 32 {
+                anyhow::bail!("target {bits:#010x} overflows 256 bits")
+            }
+            target[31 - power as usize] = *byte;
+        }
+        Ok(target)
+    }
+    /// The expected number of hashes to find a block meeting the target, `2^256 / (target + 1)`,
+    /// which summed along a branch is its chainwork. Zero for a target that doesn't expand or
+    /// is zero, as Bitcoin Core counts it, and saturating at `u128::MAX`, which no target a
+    /// real block has met comes near.
+    pub fn work(&self) -> u128 {
+        let Ok(target) = self.expanded_target() else {
+            return 0;
+        };
+        let halves = |bytes: [u8; 32]| {
+            let (high, low) = bytes.split_at(16);
+            (
+                u128::from_be_bytes(high.try_into().expect("expected 16 bytes")),
+                u128::from_be_bytes(low.try_into().expect("expected 16 bytes")),
+            )
+        };
+        let (high, low) = halves(target);
+        if (high, low) == (0, 0) {
+            return 0;
+        }
+        // 2^256 doesn't fit in 256 bits, so as Bitcoin Core does, divide its complement
+        // `2^256 - 1 - target` by `target + 1` and add the one that was taken away.
+        let divisor = match low.checked_add(1) {
+            Some(low) => (high, low),
+            None => match high.checked_add(1) {
+                Some(high) => (high, 0),
+                None => return 1,
+            },
+        };
+        if divisor.0 == 0 {
+            return u128::MAX;
+        }
+        let dividend = halves(target.map(|byte| !byte));
+        let (mut remainder, mut quotient) = ((0u128, 0u128), 0u128);
+        for bit in (0..256).rev() {
+            let next = match bit >= 128 {
+                true => (dividend.0 >> (bit - 128)) & 1,
+                false => (dividend.1 >> bit) & 1,
+            };
+            // Shifting can carry out of 256 bits, but then the remainder is past the divisor
+            // and subtracting it brings the remainder back below it, wrapping to the right value.
+            let carry = remainder.0 >> 127 == 1;
+            remainder = (
+                remainder.0 << 1 | remainder.1 >> 127,
+                remainder.1 << 1 | next,
+            );
+            if carry || remainder >= divisor {
+                let (low, borrow) = remainder.1.overflowing_sub(divisor.1);
+                remainder = (
+                    remainder
+                        .0
+                        .wrapping_sub(divisor.0)
+                        .wrapping_sub(u128::from(borrow)),
+                    low,
+                );
+                // The divisor is at least 2^128, so the quotient fits in its lower half.
+                quotient |= 1 << bit;
+            }
+        }
+        quotient.saturating_add(1)
+    }
+    /// How many times harder the target is to meet than the genesis target.
+    ///
+    /// Computed directly from the compact forms, as Bitcoin Core does:
+    /// the ratio of the mantissas scaled by the difference in exponents.
+    pub fn difficulty(&self) -> f64 {
+        let bits = self.target();
+        let max_exponent = (MAX_TARGET_BITS >> 24) as i32;
+        let max_mantissa = f64::from(MAX_TARGET_BITS & 0x00ff_ffff);
+        let exponent = (bits >> 24) as i32;
+        let mantissa = f64::from(bits & 0x00ff_ffff);
+        max_mantissa / mantissa * 256f64.powi(max_exponent - exponent)
+    }
+    /// The hashes per second expected to find a block at this difficulty every ten minutes.
+    pub fn hashrate(&self) -> f64 {
+        self.difficulty() * 2f64.powi(32) / f64::from(TARGET_SPACING)
+    }
+    /// Check that the block hash meets the difficulty target it claims.
+    pub fn validate_pow(&self) -> Result<()> {
+        let mut hash = self.hash();
+        hash.reverse();
+        if hash > self.expanded_target()? {
+            anyhow::bail!(
+                "proof of work validation failed: block {} is above its target {:#010x}",
+                self.block_hash(),
+                self.target()
+            )
+        }
+        Ok(())
+    }
+    pub fn nonce(&self) -> u32 {
+        u32::from_le_bytes(
+            self.nonce
+                .clone()
+                .try_into()
+                .expect("expected 4 bytes for nonce"),
+        )
+    }
+}
+impl std::fmt::Display for BlockHeader {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "Block Hash            : {}\nVersion               : {}\nPrev BlockHeader Hash : {}\nMerkle Root Hash      : {}\nUnix Epoch Time       : {}\nTarget                : {:#010x}\nTarget Hash           : {}\nDifficulty            : {}\nNonce                 : {}",
+            self.block_hash(),
+            self.version_summary(),
+            match self.is_genesis() {
+                true => "none, all zeros by design as the genesis block has no parent"
+                    .to_string(),
+                false => self.previous_block_header_hash(),
+            },
+            self.merkle_root_hash(),
+            format_timestamp(self.unix_epoch_time()),
+            self.target(),
+            self.expanded_target()
+                .map_or_else(|e| e.to_string(), hex::encode),
+            match options().human {
+                true => format!(
+                    "{} ({} expected hashrate)",
+                    format_difficulty(self.difficulty()),
+                    format_hashrate(self.hashrate())
+                ),
+                false => format_difficulty(self.difficulty()),
+            },
+            self.nonce(),
+        )
+    }
+}
+
+impl Serialize for BlockHeader {
+    fn serialize<S: Serializer>(&self, serializer: S) -> std::result::Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("BlockHeader", 12)?;
+        state.serialize_field("block_hash", &self.block_hash())?;
+        state.serialize_field("version", &self.version())?;
+        state.serialize_field("signaling_bits", &self.signaling_bits())?;
+        state.serialize_field(
+            "signaled_deployments",
+            &self.signaled_deployments(options().network.unwrap_or(Network::Mainnet)),
+        )?;
+        state.serialize_field(
+            "previous_block_header_hash",
+            &self.previous_block_header_hash(),
+        )?;
+        state.serialize_field("merkle_root_hash", &self.merkle_root_hash())?;
+        state.serialize_field("unix_epoch_time", &self.unix_epoch_time())?;
+        state.serialize_field("time", &format_utc(self.unix_epoch_time()))?;
+        state.serialize_field("target", &self.target())?;
+        state.serialize_field("target_hash", &self.expanded_target().ok().map(hex::encode))?;
+        state.serialize_field("difficulty", &self.difficulty())?;
+        state.serialize_field("nonce", &self.nonce())?;
+        state.end()
     }
 }
 
 #[cfg(test)]
 mod tests {
+    use crate::util::{
+        fixtures::{self, arb_block, GENESIS_BLOCK_HEX},
+        view::BlockView,
+    };
+    use proptest::prelude::*;
+
+    proptest! {
+        /// Checks that parsing then serializing a block gives back its bytes, that those
+        /// parse to the same block, and that its view agrees.
+        #[test]
+        fn test_block_round_trip(raw_bytes in arb_block()) {
+            let mut cursor = super::ByteCursor::new(raw_bytes.as_slice());
+            let block = super::Block::from_raw_bytes(&mut cursor).unwrap();
+            prop_assert!(cursor.is_empty().unwrap());
+            prop_assert_eq!(block.raw_bytes(), raw_bytes.as_slice());
+            prop_assert_eq!(block.total_size(), raw_bytes.len() as u32);
+
+            let reparsed =
+                super::Block::from_raw_bytes(&mut super::ByteCursor::new(block.raw_bytes().as_slice()))
+                    .unwrap();
+            prop_assert_eq!(reparsed.raw_bytes(), block.raw_bytes());
+
+            let view = BlockView::from_slice(&raw_bytes).unwrap();
+            prop_assert_eq!(view.raw_bytes(), raw_bytes.as_slice());
+            prop_assert_eq!(view.block_header().hash(), block.block_header().hash());
+            prop_assert_eq!(view.to_owned().raw_bytes(), block.raw_bytes());
+        }
+
+        /// Checks that a compact size encodes to the shortest form, which decodes back to it.
+        #[test]
+        fn test_compact_size_round_trip(value in any::<u64>()) {
+            let raw_bytes = super::CompactSize::encode(value);
+            let compact_size =
+                super::CompactSize::from_raw_bytes(&mut super::ByteCursor::new(raw_bytes.as_slice()))
+                    .unwrap();
+            prop_assert_eq!(compact_size.to_u64(), value);
+            prop_assert_eq!(compact_size.raw_bytes(), raw_bytes);
+        }
+
+        /// Checks that a record's magic bytes and size serialize back to what they parsed from.
+        #[test]
+        fn test_block_info_round_trip(size in any::<u32>()) {
+            let raw_bytes = [[0xf9, 0xbe, 0xb4, 0xd9], size.to_le_bytes()].concat();
+            let block_info = super::BlockInfo::from_raw_bytes(
+                &mut super::ByteCursor::new(raw_bytes.as_slice()),
+                0,
+            )
+            .unwrap();
+            prop_assert_eq!(block_info.raw_bytes(), raw_bytes);
+        }
+    }
+
     /// Checks that the network is valid and the block size is accurate.
     #[test]
     fn test_block_info() {
         // the first 8 bytes of blk00000
-        let mut raw_bytes: Vec<u8> = vec![249, 190, 180, 217, 29, 1, 0, 0];
-        raw_bytes.reverse();
+        let raw_bytes: Vec<u8> = vec![249, 190, 180, 217, 29, 1, 0, 0];
+        let mut cursor = super::ByteCursor::new(raw_bytes.as_slice());
 
-        let block_info = super::BlockInfo::from_raw_bytes(&mut raw_bytes, 0);
+        let block_info = super::BlockInfo::from_raw_bytes(&mut cursor, 0).unwrap();
 
-        assert!(block_info.validate_network().is_ok());
+        assert_eq!(block_info.network().unwrap(), super::Network::Mainnet);
+        assert!(block_info.validate_network(super::Network::Mainnet).is_ok());
+        assert!(block_info
+            .validate_network(super::Network::Regtest)
+            .is_err());
         assert_eq!(block_info.size_as_u32(), 285);
+        assert!(cursor.is_empty().unwrap());
+    }
+
+    /// Checks the numeric header fields against the genesis block's known values.
+    #[test]
+    fn test_header_fields() {
+        let raw_bytes = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        let mut cursor = super::ByteCursor::new(&raw_bytes[..80]);
+        let header = super::BlockHeader::from_raw_bytes(&mut cursor).unwrap();
+
+        assert_eq!(header.version(), 1);
+        assert_eq!(header.signaling_bits(), None);
+        assert_eq!(header.version_summary(), "1 (0x00000001)");
+        assert_eq!(header.unix_epoch_time(), 1_231_006_505);
+        assert_eq!(header.target(), 0x1d00_ffff);
+        assert_eq!(header.nonce(), 2_083_236_893);
+    }
+
+    /// Checks each CompactSize width.
+    #[test]
+    fn test_compact_size() {
+        for (raw_bytes, value) in [
+            (vec![0xfc], 0xfc),
+            (vec![0xfd, 0x01, 0x02], 0x0201),
+            (vec![0xfe, 0x01, 0x02, 0x03, 0x04], 0x04030201),
+            (vec![0xff, 1, 0, 0, 0, 0, 0, 0, 1], 0x0100000000000001),
+        ] {
+            let mut cursor = super::ByteCursor::new(raw_bytes.as_slice());
+            assert_eq!(
+                super::CompactSize::from_raw_bytes(&mut cursor)
+                    .unwrap()
+                    .to_u64(),
+                value
+            );
+            assert!(cursor.is_empty().unwrap());
+        }
+    }
+
+    /// Checks the witness commitment of a segwit block is recomputed from its wtxids and the
+    /// reserved value, and that tampering with either side is caught.
+    #[test]
+    fn test_witness_commitment() {
+        let segwit_tx = [
+            "02000000",
+            "0001",
+            "01",
+            "11".repeat(32).as_str(),
+            "00000000",
+            "00",
+            "ffffffff",
+            "01",
+            "e803000000000000",
+            "16",
+            "0014",
+            "22".repeat(20).as_str(),
+            "02",
+            "01aa",
+            "02bbcc",
+            "00000000",
+        ]
+        .concat();
+        let block = |commitment: Option<[u8; 32]>| {
+            let commitment_output = commitment.map_or(String::new(), |commitment| {
+                [
+                    "0000000000000000",
+                    "26",
+                    "6a24aa21a9ed",
+                    &hex::encode(commitment),
+                ]
+                .concat()
+            });
+            fixtures::block(
+                &[
+                    "00".repeat(80).as_str(),
+                    "02",
+                    // The coinbase, witness serialized with an all zero reserved value.
+                    "01000000",
+                    "0001",
+                    "01",
+                    "00".repeat(32).as_str(),
+                    "ffffffff",
+                    "02",
+                    "5100",
+                    "ffffffff",
+                    match commitment {
+                        Some(_) => "02",
+                        None => "01",
+                    },
+                    "00f2052a01000000",
+                    "01",
+                    "51",
+                    &commitment_output,
+                    "01",
+                    "20",
+                    "00".repeat(32).as_str(),
+                    "00000000",
+                    &segwit_tx,
+                ]
+                .concat(),
+            )
+        };
+
+        let witness_root = block(None).witness_merkle_root().unwrap();
+        let commitment = super::sha256d(&[witness_root, [0; 32]].concat());
+        let committed = block(Some(commitment));
+        assert_eq!(
+            committed.coinbase().unwrap().witness_commitment(),
+            Some(commitment)
+        );
+        assert_eq!(
+            committed.coinbase().unwrap().witness_reserved_value(),
+            Some([0; 32])
+        );
+        assert!(committed.verify_witness_commitment().is_ok());
+        assert!(block(Some([0xff; 32])).verify_witness_commitment().is_err());
+        assert!(block(None).verify_witness_commitment().is_err());
+
+        let genesis = fixtures::block(GENESIS_BLOCK_HEX);
+        assert!(genesis.verify_witness_commitment().is_ok());
+    }
+
+    /// Checks the genesis block parses completely and hashes to its well known hash.
+    #[test]
+    fn test_block_hash() {
+        let raw_bytes = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        let mut cursor = super::ByteCursor::new(raw_bytes.as_slice());
+
+        let block = super::Block::from_raw_bytes(&mut cursor).unwrap();
+
+        assert!(cursor.is_empty().unwrap());
+        assert!(block.verify_merkle_root().is_ok());
+        assert_eq!(block.total_size(), 285);
+        assert_eq!(block.stripped_size(), 285);
+        assert_eq!(block.weight(), 1140);
+        assert_eq!(block.vsize(), 285);
+        assert_eq!(block.block_header().raw_bytes(), raw_bytes[..80]);
+        assert_eq!(block.raw_bytes(), raw_bytes);
+        assert!(block.block_header().is_genesis());
+        assert!(block.to_string().contains(
+            "Genesis Message       : \"The Times 03/Jan/2009 Chancellor on brink of second bailout for banks\""
+        ));
+        assert_eq!(
+            block.block_header().block_hash(),
+            "000000000019d6689c085ae165831e934ff763ae46a2a6c172b3f1b60a8ce26f"
+        );
+        let json = serde_json::to_value(&block).unwrap();
+        assert_eq!(
+            json["block_header"]["block_hash"],
+            block.block_header().block_hash()
+        );
+        assert_eq!(
+            json["transactions"][0]["outputs"][0]["value"],
+            5_000_000_000u64
+        );
+    }
+
+    /// Checks the genesis target expands correctly and the header meets it.
+    #[test]
+    fn test_validate_pow() {
+        let raw_bytes = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        let mut cursor = super::ByteCursor::new(&raw_bytes[..80]);
+        let header = super::BlockHeader::from_raw_bytes(&mut cursor).unwrap();
+
+        assert_eq!(header.target(), 0x1d00ffff);
+        assert_eq!(
+            hex::encode(header.expanded_target().unwrap()),
+            "00000000ffff0000000000000000000000000000000000000000000000000000"
+        );
+        assert!(header.validate_pow().is_ok());
+        assert_eq!(header.difficulty(), 1.0);
+
+        let mut raw_bytes = raw_bytes[..80].to_vec();
+        raw_bytes[79] ^= 1;
+        let mut cursor = super::ByteCursor::new(raw_bytes.as_slice());
+        assert!(super::BlockHeader::from_raw_bytes(&mut cursor)
+            .unwrap()
+            .validate_pow()
+            .is_err());
+    }
+
+    /// Checks difficulty against the worked example from the bitcoin wiki.
+    #[test]
+    fn test_difficulty() {
+        let mut raw_bytes = hex::decode(GENESIS_BLOCK_HEX).unwrap()[..80].to_vec();
+        raw_bytes[72..76].copy_from_slice(&0x1b0404cbu32.to_le_bytes());
+        let header =
+            super::BlockHeader::from_raw_bytes(&mut super::ByteCursor::new(raw_bytes.as_slice()))
+                .unwrap();
+
+        assert!((header.difficulty() - 16307.420938523983).abs() < 1e-9);
+        assert_eq!(
+            hex::encode(header.expanded_target().unwrap()),
+            "00000000000404cb000000000000000000000000000000000000000000000000"
+        );
+    }
+
+    /// Checks the work of a block against the chainwork Bitcoin Core gives the genesis and
+    /// regtest targets, and that targets past 256 bits or of zero count as no work.
+    #[test]
+    fn test_block_work() {
+        let raw_bytes = hex::decode(GENESIS_BLOCK_HEX).unwrap()[..80].to_vec();
+        let work = |bits: u32| {
+            let mut raw_bytes = raw_bytes.clone();
+            raw_bytes[72..76].copy_from_slice(&bits.to_le_bytes());
+            super::BlockHeader::from_raw_bytes(&mut super::ByteCursor::new(raw_bytes.as_slice()))
+                .unwrap()
+                .work()
+        };
+
+        assert_eq!(work(0x1d00ffff), 0x1_0001_0001);
+        assert_eq!(work(0x207fffff), 2);
+        assert_eq!(work(0x1b0404cb), 0x3fb3_ab76_4c00);
+        assert_eq!(work(0x117fffff), 0x0200_0004_0000_0800_0010_0000_2000_003f);
+        assert_eq!(work(0x03000001), u128::MAX);
+        assert_eq!(work(0x2200ffff), 0);
+        assert_eq!(work(0x1d000000), 0);
     }
 }
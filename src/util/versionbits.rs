@@ -0,0 +1,92 @@
+//! Decoding the soft fork signaling bits of a block header's version (BIP9).
+//!
+//! A version whose top three bits are `001` sets one of its low 29 bits for each
+//! deployment the miner is ready for. Bits are reused once a deployment's window
+//! closes, so a bit is only named while its deployment could be signaled for.
+use crate::util::constant::Network;
+
+/// The top three bits of a version that uses BIP9 signaling.
+const TOP_BITS: u32 = 0x2000_0000;
+const TOP_MASK: u32 = 0xe000_0000;
+/// The bits miners are free to roll as extra nonce space (BIP320), never used for signaling.
+const VERSION_ROLLING_MASK: u32 = 0x1fff_e000;
+
+/// A soft fork deployed through version bits signaling.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Deployment {
+    pub name: &'static str,
+    pub bit: u8,
+    /// The unix time signaling started, compared to the median time past.
+    pub start_time: u32,
+    /// The unix time after which signaling for the deployment stopped counting.
+    pub timeout: u32,
+}
+
+/// The mainnet deployments that activated through BIP9 or Speedy Trial.
+pub const DEPLOYMENTS: [Deployment; 3] = [
+    Deployment {
+        name: "CSV",
+        bit: 0,
+        start_time: 1_462_060_800,
+        timeout: 1_493_596_800,
+    },
+    Deployment {
+        name: "SegWit",
+        bit: 1,
+        start_time: 1_479_168_000,
+        timeout: 1_510_704_000,
+    },
+    Deployment {
+        name: "Taproot",
+        bit: 2,
+        start_time: 1_619_222_400,
+        timeout: 1_628_640_000,
+    },
+];
+
+/// The bits a version signals, lowest first, or `None` when it doesn't use BIP9 signaling.
+/// Version rolling bits are left out, as they say nothing about the miner's readiness.
+pub fn signaling_bits(version: u32) -> Option<Vec<u8>> {
+    if version & TOP_MASK != TOP_BITS {
+        return None;
+    }
+    let bits = version & !TOP_MASK & !VERSION_ROLLING_MASK;
+    Some((0..29).filter(|bit| bits & (1 << bit) != 0).collect())
+}
+
+/// The deployment that `bit` signaled for in a block with `time` on `network`,
+/// if it's a known one. Only mainnet's windows are known.
+pub fn deployment(bit: u8, time: u32, network: Network) -> Option<&'static Deployment> {
+    if network != Network::Mainnet {
+        return None;
+    }
+    DEPLOYMENTS.iter().find(|deployment| {
+        deployment.bit == bit && (deployment.start_time..deployment.timeout).contains(&time)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{deployment, signaling_bits};
+    use crate::Network;
+
+    /// Checks signaling bits against versions seen on mainnet.
+    #[test]
+    fn test_signaling_bits() {
+        assert_eq!(signaling_bits(1), None);
+        assert_eq!(signaling_bits(0x2000_0000), Some(vec![]));
+        assert_eq!(signaling_bits(0x2000_0003), Some(vec![0, 1]));
+        // A Taproot signaling block that also rolled its version.
+        assert_eq!(signaling_bits(0x2fff_e004), Some(vec![2]));
+
+        // Block 481824, the first with SegWit active, from August 2017.
+        let time = 1_503_539_857;
+        assert_eq!(
+            deployment(1, time, Network::Mainnet).unwrap().name,
+            "SegWit"
+        );
+        assert_eq!(deployment(0, time, Network::Mainnet), None);
+        assert_eq!(deployment(1, time, Network::Testnet3), None);
+        assert_eq!(deployment(1, 1_700_000_000, Network::Mainnet), None);
+    }
+}
@@ -0,0 +1,494 @@
+//! Borrowed views of blocks and their transactions, parsed in place from a byte slice.
+//!
+//! The owned [Block] copies every field into its own buffer, dozens of small allocations
+//! per block. A view instead checks the structure once and keeps only slices of the bytes
+//! it was parsed from, reading fields out of them on demand, which suits scans that look at
+//! each block once. [BlockView::to_owned] converts a view when something needs to be kept.
+use crate::util::{
+    constant::{
+        BLOCK_HEADER, LOCK_TIME, OUTPOINT_TXID, OUTPOINT_VOUT, SEGWIT_FLAG, SEGWIT_MARKER,
+        SEQUENCE, TX_OUT_VALUE, TX_VERSION, WITNESS_SCALE_FACTOR,
+    },
+    error::ParseError,
+    hash::sha256d,
+    types::{Block, BlockHeader, ByteCursor},
+};
+use sha2::{Digest, Sha256};
+
+/// Reads fields out of a slice, reporting offsets relative to `base`.
+#[derive(Debug, Clone)]
+struct SliceCursor<'a> {
+    bytes: &'a [u8],
+    position: usize,
+    base: u64,
+}
+impl<'a> SliceCursor<'a> {
+    fn new(bytes: &'a [u8], base: u64) -> Self {
+        Self {
+            bytes,
+            position: 0,
+            base,
+        }
+    }
+    fn offset(&self) -> u64 {
+        self.base + self.position as u64
+    }
+    fn take(&mut self, n: usize, field: &'static str) -> Result<&'a [u8], ParseError> {
+        let bytes = self
+            .position
+            .checked_add(n)
+            .and_then(|end| self.bytes.get(self.position..end))
+            .ok_or(ParseError::UnexpectedEof {
+                field,
+                offset: self.offset(),
+            })?;
+        self.position += n;
+        Ok(bytes)
+    }
+    fn compact_size(&mut self) -> Result<u64, ParseError> {
+        let len = match self.take(1, "compact size")?[0] {
+            0xfd => 2,
+            0xfe => 4,
+            0xff => 8,
+            value => return Ok(u64::from(value)),
+        };
+        let value = self.take(len, "compact size")?;
+        Ok(value
+            .iter()
+            .rev()
+            .fold(0, |value, byte| (value << 8) | u64::from(*byte)))
+    }
+    /// Skip a compact size prefixed run of bytes.
+    fn skip_prefixed(&mut self, field: &'static str) -> Result<&'a [u8], ParseError> {
+        let len = self.compact_size()?;
+        let len = usize::try_from(len).map_err(|_| ParseError::UnexpectedEof {
+            field,
+            offset: self.offset(),
+        })?;
+        self.take(len, field)
+    }
+}
+
+fn u32_at(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes(
+        bytes[at..at + 4]
+            .try_into()
+            .expect("expected 4 bytes for a u32"),
+    )
+}
+
+/// An 80 byte block header, borrowed.
+#[derive(Debug, Clone, Copy)]
+pub struct HeaderView<'a> {
+    raw: &'a [u8],
+}
+impl<'a> HeaderView<'a> {
+    /// View the header at the start of `raw`, which must be at least 80 bytes.
+    pub fn from_slice(raw: &'a [u8]) -> Result<Self, ParseError> {
+        Self::parse(&mut SliceCursor::new(raw, 0))
+    }
+    fn parse(cursor: &mut SliceCursor<'a>) -> Result<Self, ParseError> {
+        Ok(Self {
+            raw: cursor.take(BLOCK_HEADER as usize, "block header")?,
+        })
+    }
+    pub fn raw_bytes(&self) -> &'a [u8] {
+        self.raw
+    }
+    /// The block hash in serialized byte order.
+    pub fn hash(&self) -> [u8; 32] {
+        sha256d(self.raw)
+    }
+    pub fn version(&self) -> u32 {
+        u32_at(self.raw, 0)
+    }
+    /// The hash of the parent block in serialized byte order.
+    pub fn previous_hash(&self) -> [u8; 32] {
+        self.raw[4..36]
+            .try_into()
+            .expect("expected 32 bytes for previous block header hash")
+    }
+    /// The merkle root in serialized byte order.
+    pub fn merkle_root(&self) -> [u8; 32] {
+        self.raw[36..68]
+            .try_into()
+            .expect("expected 32 bytes for merkle root hash")
+    }
+    pub fn unix_epoch_time(&self) -> u32 {
+        u32_at(self.raw, 68)
+    }
+    /// The compact difficulty target, the header's bits.
+    pub fn target(&self) -> u32 {
+        u32_at(self.raw, 72)
+    }
+    pub fn nonce(&self) -> u32 {
+        u32_at(self.raw, 76)
+    }
+    /// Copy the header into an owned [BlockHeader].
+    pub fn to_owned(&self) -> BlockHeader {
+        BlockHeader::from_raw_bytes(&mut ByteCursor::new(self.raw))
+            .expect("expected a view to hold a whole header")
+    }
+}
+
+/// A transaction input, borrowed.
+#[derive(Debug, Clone, Copy)]
+pub struct TxInView<'a> {
+    raw: &'a [u8],
+}
+impl<'a> TxInView<'a> {
+    /// The txid of the output spent, in serialized byte order.
+    pub fn previous_txid(&self) -> &'a [u8] {
+        &self.raw[..OUTPOINT_TXID as usize]
+    }
+    pub fn previous_vout(&self) -> u32 {
+        u32_at(self.raw, OUTPOINT_TXID as usize)
+    }
+    pub fn script_sig(&self) -> &'a [u8] {
+        let mut cursor = SliceCursor::new(&self.raw[(OUTPOINT_TXID + OUTPOINT_VOUT) as usize..], 0);
+        cursor
+            .skip_prefixed("script_sig")
+            .expect("expected a view to hold a whole input")
+    }
+    pub fn sequence(&self) -> u32 {
+        u32_at(self.raw, self.raw.len() - SEQUENCE as usize)
+    }
+}
+
+/// A transaction output, borrowed.
+#[derive(Debug, Clone, Copy)]
+pub struct TxOutView<'a> {
+    raw: &'a [u8],
+}
+impl<'a> TxOutView<'a> {
+    /// The value in satoshis.
+    pub fn value(&self) -> u64 {
+        u64::from_le_bytes(
+            self.raw[..TX_OUT_VALUE as usize]
+                .try_into()
+                .expect("expected 8 bytes for output value"),
+        )
+    }
+    pub fn script_pubkey(&self) -> &'a [u8] {
+        let mut cursor = SliceCursor::new(&self.raw[TX_OUT_VALUE as usize..], 0);
+        cursor
+            .skip_prefixed("script_pubkey")
+            .expect("expected a view to hold a whole output")
+    }
+}
+
+/// A transaction in either serialization, borrowed.
+///
+/// Kept as the slices the legacy serialization is made of, so the txid can be
+/// hashed without copying the witness data out first.
+#[derive(Debug, Clone, Copy)]
+pub struct TransactionView<'a> {
+    raw: &'a [u8],
+    segwit: bool,
+    input_count: u64,
+    /// The inputs, each serialized as in the legacy form, without their count.
+    inputs: &'a [u8],
+    output_count: u64,
+    /// The outputs, without their count.
+    outputs: &'a [u8],
+    /// From the input count through the outputs, as in the legacy serialization.
+    body: &'a [u8],
+}
+impl<'a> TransactionView<'a> {
+    /// View the transaction at the start of `raw`, as [Transaction](crate::Transaction)
+    /// parses one.
+    pub fn from_slice(raw: &'a [u8]) -> Result<Self, ParseError> {
+        Self::parse(&mut SliceCursor::new(raw, 0))
+    }
+    fn parse(cursor: &mut SliceCursor<'a>) -> Result<Self, ParseError> {
+        let start = cursor.position;
+        cursor.take(TX_VERSION as usize, "tx version")?;
+        let mut body_start = cursor.position;
+        let mut input_count = cursor.compact_size()?;
+        let segwit = input_count == u64::from(SEGWIT_MARKER);
+        if segwit {
+            let offset = cursor.offset();
+            let flag = cursor.take(1, "segwit flag")?[0];
+            if flag != SEGWIT_FLAG {
+                return Err(ParseError::BadSegwitFlag { flag, offset });
+            }
+            body_start = cursor.position;
+            input_count = cursor.compact_size()?;
+        }
+        let inputs_start = cursor.position;
+        for _ in 0..input_count {
+            cursor.take((OUTPOINT_TXID + OUTPOINT_VOUT) as usize, "outpoint")?;
+            cursor.skip_prefixed("script_sig")?;
+            cursor.take(SEQUENCE as usize, "sequence")?;
+        }
+        let inputs = &cursor.bytes[inputs_start..cursor.position];
+        let output_count = cursor.compact_size()?;
+        let outputs_start = cursor.position;
+        for _ in 0..output_count {
+            cursor.take(TX_OUT_VALUE as usize, "output value")?;
+            cursor.skip_prefixed("script_pubkey")?;
+        }
+        let outputs = &cursor.bytes[outputs_start..cursor.position];
+        let body = &cursor.bytes[body_start..cursor.position];
+        if segwit {
+            for _ in 0..input_count {
+                for _ in 0..cursor.compact_size()? {
+                    cursor.skip_prefixed("witness item")?;
+                }
+            }
+        }
+        cursor.take(LOCK_TIME as usize, "lock time")?;
+
+        Ok(Self {
+            raw: &cursor.bytes[start..cursor.position],
+            segwit,
+            input_count,
+            inputs,
+            output_count,
+            outputs,
+            body,
+        })
+    }
+    /// The full serialization, as it was parsed.
+    pub fn raw_bytes(&self) -> &'a [u8] {
+        self.raw
+    }
+    pub fn version(&self) -> u32 {
+        u32_at(self.raw, 0)
+    }
+    pub fn lock_time(&self) -> u32 {
+        u32_at(self.raw, self.raw.len() - LOCK_TIME as usize)
+    }
+    /// Whether the transaction was witness serialized.
+    pub fn is_segwit(&self) -> bool {
+        self.segwit
+    }
+    /// Whether this is a coinbase, with a single input spending the null outpoint.
+    pub fn is_coinbase(&self) -> bool {
+        let mut inputs = self.inputs();
+        match (inputs.next(), inputs.next()) {
+            (Some(input), None) => {
+                input.previous_txid() == [0; 32] && input.previous_vout() == u32::MAX
+            }
+            _ => false,
+        }
+    }
+    pub fn input_count(&self) -> u64 {
+        self.input_count
+    }
+    pub fn output_count(&self) -> u64 {
+        self.output_count
+    }
+    pub fn inputs(&self) -> impl Iterator<Item = TxInView<'a>> {
+        let mut cursor = SliceCursor::new(self.inputs, 0);
+        (0..self.input_count).map(move |_| {
+            let start = cursor.position;
+            cursor
+                .take((OUTPOINT_TXID + OUTPOINT_VOUT) as usize, "outpoint")
+                .and_then(|_| cursor.skip_prefixed("script_sig"))
+                .and_then(|_| cursor.take(SEQUENCE as usize, "sequence"))
+                .expect("expected a view to hold whole inputs");
+            TxInView {
+                raw: &cursor.bytes[start..cursor.position],
+            }
+        })
+    }
+    pub fn outputs(&self) -> impl Iterator<Item = TxOutView<'a>> {
+        let mut cursor = SliceCursor::new(self.outputs, 0);
+        (0..self.output_count).map(move |_| {
+            let start = cursor.position;
+            cursor
+                .take(TX_OUT_VALUE as usize, "output value")
+                .and_then(|_| cursor.skip_prefixed("script_pubkey"))
+                .expect("expected a view to hold whole outputs");
+            TxOutView {
+                raw: &cursor.bytes[start..cursor.position],
+            }
+        })
+    }
+    /// The transaction id, hashing the legacy serialization piece by piece.
+    pub fn txid(&self) -> [u8; 32] {
+        let first = Sha256::new()
+            .chain_update(&self.raw[..TX_VERSION as usize])
+            .chain_update(self.body)
+            .chain_update(&self.raw[self.raw.len() - LOCK_TIME as usize..])
+            .finalize();
+        Sha256::digest(first).into()
+    }
+    /// The witness transaction id, as [Transaction::wtxid](crate::Transaction::wtxid).
+    pub fn wtxid(&self) -> [u8; 32] {
+        sha256d(self.raw)
+    }
+    /// The serialized size of the transaction in bytes, including witness data.
+    pub fn total_size(&self) -> u32 {
+        self.raw.len() as u32
+    }
+    /// The size of the transaction in bytes with its witness data removed.
+    pub fn stripped_size(&self) -> u32 {
+        TX_VERSION + self.body.len() as u32 + LOCK_TIME
+    }
+    /// The BIP141 weight, counting non-witness bytes four times and witness bytes once.
+    pub fn weight(&self) -> u32 {
+        self.stripped_size() * (WITNESS_SCALE_FACTOR - 1) + self.total_size()
+    }
+}
+
+/// A block, borrowed from the bytes of its record.
+#[derive(Debug, Clone, Copy)]
+pub struct BlockView<'a> {
+    raw: &'a [u8],
+    header: HeaderView<'a>,
+    tx_count: u64,
+    /// Every transaction, without their count.
+    transactions: &'a [u8],
+}
+impl<'a> BlockView<'a> {
+    /// View the block serialized at the start of `raw`, checking every transaction parses.
+    pub fn from_slice(raw: &'a [u8]) -> Result<Self, ParseError> {
+        Self::parse_at(raw, 0)
+    }
+    /// As [Self::from_slice], with errors reporting offsets as if `raw` started `base` bytes in.
+    pub(crate) fn parse_at(raw: &'a [u8], base: u64) -> Result<Self, ParseError> {
+        let mut cursor = SliceCursor::new(raw, base);
+        let header = HeaderView::parse(&mut cursor)?;
+        let tx_count = cursor.compact_size()?;
+        let transactions_start = cursor.position;
+        for _ in 0..tx_count {
+            TransactionView::parse(&mut cursor)?;
+        }
+        Ok(Self {
+            raw: &raw[..cursor.position],
+            header,
+            tx_count,
+            transactions: &raw[transactions_start..cursor.position],
+        })
+    }
+    /// The serialized block, as many bytes of the slice as it was parsed from.
+    pub fn raw_bytes(&self) -> &'a [u8] {
+        self.raw
+    }
+    pub fn block_header(&self) -> HeaderView<'a> {
+        self.header
+    }
+    pub fn tx_count(&self) -> u64 {
+        self.tx_count
+    }
+    pub fn transactions(&self) -> impl Iterator<Item = TransactionView<'a>> {
+        let mut cursor = SliceCursor::new(self.transactions, 0);
+        (0..self.tx_count).map(move |_| {
+            TransactionView::parse(&mut cursor).expect("expected a view to hold whole transactions")
+        })
+    }
+    /// The serialized size of the block in bytes, including witness data.
+    pub fn total_size(&self) -> u32 {
+        self.raw.len() as u32
+    }
+    /// Parse the block into an owned [Block].
+    pub fn to_owned(&self) -> Block {
+        Block::from_raw_bytes(&mut ByteCursor::new(self.raw))
+            .expect("expected a view to hold a whole block")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{BlockView, TransactionView};
+    use crate::util::{
+        error::ParseError,
+        fixtures::{BLOCK_1_HEX, GENESIS_BLOCK_HEX},
+        tx::Transaction,
+        types::ByteCursor,
+    };
+
+    /// Checks a view reads the same fields as the owned block it converts to,
+    /// and reports where a truncated block runs out.
+    #[test]
+    fn test_block_view() {
+        for block_hex in [GENESIS_BLOCK_HEX, BLOCK_1_HEX] {
+            let raw_bytes = hex::decode(block_hex).unwrap();
+            let view = BlockView::from_slice(&raw_bytes).unwrap();
+            let block = view.to_owned();
+
+            let header = view.block_header();
+            assert_eq!(header.hash(), block.block_header().hash());
+            assert_eq!(header.previous_hash(), block.block_header().previous_hash());
+            assert_eq!(header.merkle_root(), block.block_header().merkle_root());
+            assert_eq!(
+                header.unix_epoch_time(),
+                block.block_header().unix_epoch_time()
+            );
+            assert_eq!(header.target(), block.block_header().target());
+            assert_eq!(header.nonce(), block.block_header().nonce());
+            assert_eq!(view.total_size(), block.total_size());
+            assert_eq!(view.tx_count(), block.tx_count());
+            for (tx, owned) in view.transactions().zip(block.transactions()) {
+                assert_eq!(tx.txid(), owned.txid());
+                assert_eq!(tx.raw_bytes(), owned.raw_bytes());
+                assert_eq!(tx.is_coinbase(), owned.is_coinbase());
+                assert_eq!(tx.weight(), owned.weight());
+                let values = tx
+                    .outputs()
+                    .map(|output| output.value())
+                    .collect::<Vec<_>>();
+                assert_eq!(values, [5_000_000_000]);
+                let input = tx.inputs().next().unwrap();
+                assert_eq!(input.sequence(), owned.inputs()[0].sequence());
+                assert_eq!(input.script_sig(), owned.inputs()[0].script_sig_bytes());
+            }
+        }
+
+        // Cut off after the genesis coinbase's script_sig, which runs to byte 200.
+        let raw_bytes = hex::decode(GENESIS_BLOCK_HEX).unwrap();
+        assert!(matches!(
+            BlockView::parse_at(&raw_bytes[..200], 8),
+            Err(ParseError::UnexpectedEof {
+                field: "sequence",
+                offset: 208,
+            })
+        ));
+    }
+
+    /// Checks a witness serialized transaction hashes to the txid of its legacy form.
+    #[test]
+    fn test_transaction_view() {
+        let raw_bytes = hex::decode(
+            [
+                "02000000",
+                "0001",
+                "01",
+                "11".repeat(32).as_str(),
+                "00000000",
+                "00",
+                "ffffffff",
+                "01",
+                "e803000000000000",
+                "16",
+                "0014",
+                "22".repeat(20).as_str(),
+                "02",
+                "01aa",
+                "02bbcc",
+                "00000000",
+            ]
+            .concat(),
+        )
+        .unwrap();
+        let view = TransactionView::from_slice(&raw_bytes).unwrap();
+        let owned =
+            Transaction::from_raw_bytes(&mut ByteCursor::new(raw_bytes.as_slice())).unwrap();
+
+        assert!(view.is_segwit());
+        assert!(!view.is_coinbase());
+        assert_eq!(view.txid(), owned.txid());
+        assert_eq!(view.stripped_size(), 82);
+        assert_eq!(view.weight(), 336);
+        assert_eq!(view.version(), 2);
+        let output = view.outputs().next().unwrap();
+        assert_eq!(output.value(), 1000);
+        assert_eq!(
+            output.script_pubkey(),
+            owned.outputs()[0].script_pubkey_bytes()
+        );
+        assert_eq!(view.inputs().next().unwrap().previous_txid(), [0x11; 32]);
+    }
+}
@@ -0,0 +1,62 @@
+//! Bindings for decoding raw blocks, headers and transactions from JavaScript, for block
+//! explorers to decode client side with the same parser as the CLI.
+//!
+//! Built with `wasm-pack build --features wasm`, each function takes the serialized bytes
+//! as a `Uint8Array` and returns an object of the shape `decode --format json` prints,
+//! throwing when the bytes don't parse exactly. Only the parsing in [util](crate::util) is
+//! used, never the blk file reader, so nothing touches a filesystem.
+use crate::util::{
+    decode::whole,
+    display,
+    hash::to_display_hex,
+    types::{Block, BlockHeader},
+};
+use crate::{Network, Transaction};
+use serde::Serialize;
+use wasm_bindgen::prelude::*;
+
+/// Convert `value` to a plain JavaScript object, as `JSON.parse` would give for its JSON.
+fn to_js(value: &impl Serialize) -> Result<JsValue, JsError> {
+    value
+        .serialize(&serde_wasm_bindgen::Serializer::json_compatible())
+        .map_err(|e| JsError::new(&format!("failed to convert to a JavaScript value: {e}")))
+}
+
+fn js_error(error: anyhow::Error) -> JsError {
+    JsError::new(&format!("{error:#}"))
+}
+
+/// Select the network addresses and block rewards are given for, mainnet until set, by the
+/// same names `--network` takes.
+#[wasm_bindgen(js_name = setNetwork)]
+pub fn set_network(network: &str) -> Result<(), JsError> {
+    display::set_network(network.parse::<Network>().map_err(js_error)?);
+    Ok(())
+}
+
+/// Decode a serialized block.
+#[wasm_bindgen(js_name = parseBlock)]
+pub fn parse_block(bytes: &[u8]) -> Result<JsValue, JsError> {
+    to_js(&whole(bytes, Block::from_raw_bytes).map_err(js_error)?)
+}
+
+/// Decode a serialized 80 byte block header.
+#[wasm_bindgen(js_name = parseHeader)]
+pub fn parse_header(bytes: &[u8]) -> Result<JsValue, JsError> {
+    to_js(&whole(bytes, BlockHeader::from_raw_bytes).map_err(js_error)?)
+}
+
+/// Decode a serialized transaction, legacy or witness, along with its txid.
+#[wasm_bindgen(js_name = parseTransaction)]
+pub fn parse_transaction(bytes: &[u8]) -> Result<JsValue, JsError> {
+    #[derive(Serialize)]
+    struct Output<'a> {
+        txid: String,
+        transaction: &'a Transaction,
+    }
+    let tx = whole(bytes, Transaction::from_raw_bytes).map_err(js_error)?;
+    to_js(&Output {
+        txid: to_display_hex(&tx.txid()),
+        transaction: &tx,
+    })
+}
@@ -0,0 +1,243 @@
+//! Subscribing to the notifications a Bitcoin Core node publishes over ZeroMQ, such as the
+//! `rawblock` ones sent with `-zmqpubrawblock` as each block is connected.
+//!
+//! Just enough of ZMTP 3.0 is spoken to be a SUB socket with the NULL mechanism: a 64 byte
+//! greeting each way, a READY command each way naming the socket types, then a message
+//! subscribing to a topic. Every notification is a message of three frames: the topic, the
+//! body and a four byte little endian sequence number. Each frame starts with a flags byte,
+//! for more frames following, a long size and a command, then its size in one byte or in
+//! eight big endian ones when long.
+use crate::util::{
+    error::ErrorKind,
+    types::{Block, ByteCursor},
+};
+use anyhow::{bail, Result};
+use std::{
+    io::{Read, Write},
+    net::{TcpStream, ToSocketAddrs},
+    time::Duration,
+};
+
+/// The topic of the notifications carrying each block connected, serialized whole.
+pub const RAWBLOCK: &str = "rawblock";
+/// The flag of a frame followed by more of the same message.
+const MORE: u8 = 0x01;
+/// The flag of a frame with an eight byte size.
+const LONG: u8 = 0x02;
+/// The flag of a frame holding a command rather than part of a message.
+const COMMAND: u8 = 0x04;
+/// The largest frame accepted, matching the largest payload taken from a P2P peer.
+const MAX_FRAME: u64 = 32 * 1024 * 1024;
+/// How long to wait connecting and on the handshake, though not on notifications, which
+/// only come as often as blocks are found.
+const TIMEOUT: Duration = Duration::from_secs(30);
+
+/// A notification published by the node.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Notification {
+    pub topic: String,
+    pub body: Vec<u8>,
+    /// The number of notifications of the topic published before this one, for noticing
+    /// any missed.
+    pub sequence: Option<u32>,
+}
+impl Notification {
+    /// The block a `rawblock` notification carries.
+    pub fn block(&self) -> Result<Block> {
+        if self.topic != RAWBLOCK {
+            bail!("failed to parse a block from a {} notification", self.topic)
+        }
+        Ok(Block::from_raw_bytes(&mut ByteCursor::new(
+            self.body.as_slice(),
+        ))?)
+    }
+}
+
+/// A connection to a node's ZMQ publisher, subscribed to a topic.
+#[derive(Debug)]
+pub struct Subscriber {
+    stream: TcpStream,
+}
+impl Subscriber {
+    /// Connect to the publisher at `address`, such as 127.0.0.1:28332 for a node run with
+    /// `-zmqpubrawblock=tcp://127.0.0.1:28332`, and subscribe to `topic`.
+    pub fn connect(address: impl ToSocketAddrs, topic: &str) -> Result<Self> {
+        let address = address
+            .to_socket_addrs()
+            .map_err(|e| anyhow::anyhow!("failed to resolve the publisher address: {e}"))?
+            .next()
+            .ok_or_else(|| anyhow::anyhow!("failed to resolve the publisher address"))?;
+        let stream = TcpStream::connect_timeout(&address, TIMEOUT)
+            .map_err(|e| ErrorKind::Io.error(format!("failed to connect to {address}: {e}")))?;
+        stream.set_read_timeout(Some(TIMEOUT))?;
+        stream.set_write_timeout(Some(TIMEOUT))?;
+
+        let mut subscriber = Self { stream };
+        subscriber.handshake("SUB")?;
+        subscriber.write_frame(0, &[&[1], topic.as_bytes()].concat())?;
+        subscriber.stream.set_read_timeout(None)?;
+        Ok(subscriber)
+    }
+    /// Exchange greetings and READY commands, announcing `socket_type`.
+    fn handshake(&mut self, socket_type: &str) -> Result<()> {
+        let mut greeting = [0; 64];
+        greeting[0] = 0xff;
+        greeting[9] = 0x7f;
+        greeting[10] = 3;
+        greeting[12..16].copy_from_slice(b"NULL");
+        self.stream
+            .write_all(&greeting)
+            .map_err(|e| ErrorKind::Io.error(format!("failed to greet the publisher: {e}")))?;
+        let mut peer = [0; 64];
+        self.stream.read_exact(&mut peer).map_err(|e| {
+            ErrorKind::Io.error(format!("failed to read the publisher's greeting: {e}"))
+        })?;
+        if peer[0] != 0xff || peer[9] != 0x7f || peer[10] < 3 {
+            bail!("failed to connect to the publisher, it doesn't speak ZMTP 3")
+        }
+        if !peer[12..32].starts_with(b"NULL\0") {
+            bail!(
+                "failed to connect to the publisher, it wants a security mechanism other than NULL"
+            )
+        }
+
+        let property = |name: &str, value: &str| {
+            [
+                &[name.len() as u8],
+                name.as_bytes(),
+                &(value.len() as u32).to_be_bytes(),
+                value.as_bytes(),
+            ]
+            .concat()
+        };
+        let ready = [
+            b"\x05READY".as_slice(),
+            &property("Socket-Type", socket_type),
+        ]
+        .concat();
+        self.write_frame(COMMAND, &ready)?;
+        let (flags, command) = self.read_frame()?;
+        let name = command
+            .get(1..1 + usize::from(*command.first().unwrap_or(&0)))
+            .unwrap_or_default();
+        if flags & COMMAND == 0 || name != b"READY" {
+            let reason = String::from_utf8_lossy(command.get(7..).unwrap_or_default()).into_owned();
+            bail!("failed to connect to the publisher, it refused the handshake: {reason}")
+        }
+        Ok(())
+    }
+    /// Wait for the next notification.
+    pub fn receive(&mut self) -> Result<Notification> {
+        let mut frames = Vec::new();
+        loop {
+            let (flags, body) = self.read_frame()?;
+            // Commands can come between messages, but none need answering.
+            if flags & COMMAND != 0 {
+                continue;
+            }
+            frames.push(body);
+            if flags & MORE == 0 {
+                break;
+            }
+        }
+        let mut frames = frames.into_iter();
+        let topic = String::from_utf8_lossy(&frames.next().unwrap_or_default()).into_owned();
+        let body = frames.next().unwrap_or_default();
+        let sequence = frames
+            .next()
+            .and_then(|sequence| Some(u32::from_le_bytes(sequence.try_into().ok()?)));
+        Ok(Notification {
+            topic,
+            body,
+            sequence,
+        })
+    }
+    fn write_frame(&mut self, flags: u8, body: &[u8]) -> Result<()> {
+        let frame = match u8::try_from(body.len()) {
+            Ok(len) => [&[flags, len], body].concat(),
+            Err(_) => [
+                &[flags | LONG],
+                (body.len() as u64).to_be_bytes().as_slice(),
+                body,
+            ]
+            .concat(),
+        };
+        self.stream
+            .write_all(&frame)
+            .map_err(|e| ErrorKind::Io.error(format!("failed to write to the publisher: {e}")))
+    }
+    fn read_frame(&mut self) -> Result<(u8, Vec<u8>)> {
+        let read_error = |e: std::io::Error| match e.kind() {
+            std::io::ErrorKind::UnexpectedEof => {
+                ErrorKind::Io.error("failed to read from the publisher, it closed the connection")
+            }
+            _ => ErrorKind::Io.error(format!("failed to read from the publisher: {e}")),
+        };
+        let mut flags = [0];
+        self.stream.read_exact(&mut flags).map_err(read_error)?;
+        let len = match flags[0] & LONG {
+            0 => {
+                let mut len = [0];
+                self.stream.read_exact(&mut len).map_err(read_error)?;
+                u64::from(len[0])
+            }
+            _ => {
+                let mut len = [0; 8];
+                self.stream.read_exact(&mut len).map_err(read_error)?;
+                u64::from_be_bytes(len)
+            }
+        };
+        if len > MAX_FRAME {
+            bail!("failed to read from the publisher, its {len} byte frame is too large")
+        }
+        let mut body = vec![0; len as usize];
+        self.stream.read_exact(&mut body).map_err(read_error)?;
+        Ok((flags[0], body))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::fixtures::BLOCK_1_HEX;
+    use std::net::TcpListener;
+
+    /// Checks the handshake and subscription against a publisher that sends one block,
+    /// with a command between messages and its body in a long frame.
+    #[test]
+    fn test_subscriber_receive() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let address = listener.local_addr().unwrap();
+        let block_1 = hex::decode(BLOCK_1_HEX).unwrap();
+        let body = block_1.clone();
+        let publisher = std::thread::spawn(move || {
+            let (stream, _) = listener.accept().unwrap();
+            let mut node = Subscriber { stream };
+            node.handshake("PUB").unwrap();
+            let (_, subscription) = node.read_frame().unwrap();
+            node.write_frame(COMMAND, b"\x04PING\x00\x00").unwrap();
+            node.write_frame(MORE, RAWBLOCK.as_bytes()).unwrap();
+            let long = [
+                [MORE | LONG].as_slice(),
+                &(body.len() as u64).to_be_bytes(),
+                &body,
+            ]
+            .concat();
+            node.stream.write_all(&long).unwrap();
+            node.write_frame(0, &7u32.to_le_bytes()).unwrap();
+            subscription
+        });
+
+        let mut subscriber = Subscriber::connect(address, RAWBLOCK).unwrap();
+        let notification = subscriber.receive().unwrap();
+        let subscription = publisher.join().unwrap();
+
+        assert_eq!(
+            subscription,
+            [b"\x01".as_slice(), RAWBLOCK.as_bytes()].concat()
+        );
+        assert_eq!(notification.topic, RAWBLOCK);
+        assert_eq!(notification.sequence, Some(7));
+        assert_eq!(notification.block().unwrap().raw_bytes(), block_1);
+    }
+}